@@ -63,7 +63,7 @@ void main() {
         multiple: 1,
         addend: 1.0,
     };
-    let pipeline = Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &spec_consts).unwrap());
+    let pipeline = Arc::new(ComputePipeline::new(device.clone(), &shader.main_entry_point(), &spec_consts, None).unwrap());
 
     let data_buffer = {
         let data_iter = (0 .. 65536u32).map(|n| n);
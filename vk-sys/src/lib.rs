@@ -434,6 +434,7 @@ pub const IMAGE_LAYOUT_TRANSFER_SRC_OPTIMAL: u32 = 6;
 pub const IMAGE_LAYOUT_TRANSFER_DST_OPTIMAL: u32 = 7;
 pub const IMAGE_LAYOUT_PREINITIALIZED: u32 = 8;
 pub const IMAGE_LAYOUT_PRESENT_SRC_KHR: u32 = 1000001002;
+pub const IMAGE_LAYOUT_SHARED_PRESENT_KHR: u32 = 1000111000;
 
 pub type ImageViewType = u32;
 pub const IMAGE_VIEW_TYPE_1D: u32 = 0;
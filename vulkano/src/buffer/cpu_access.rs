@@ -55,6 +55,9 @@ use memory::pool::MemoryPool;
 use memory::pool::MemoryPoolAlloc;
 use memory::pool::PotentialDedicatedAllocation;
 use memory::pool::StdMemoryPoolAlloc;
+use pipeline::input_assembly::Index;
+use pipeline::input_assembly::IndexBounds;
+use pipeline::input_assembly::KnownIndices;
 use sync::AccessError;
 use sync::Sharing;
 
@@ -433,6 +436,24 @@ unsafe impl<T: ?Sized, A> TypedBufferAccess for CpuAccessibleBuffer<T, A>
     type Content = T;
 }
 
+/// The bounds are computed from the content of the buffer when `index_bounds` is called, which
+/// for `draw_indexed_checked` is when the draw command is recorded. Writes made from the CPU
+/// afterwards, before the command buffer is executed, aren't covered by the check.
+///
+/// Returns `None` if the buffer is currently locked for writing by the CPU or by the GPU.
+unsafe impl<I, A> KnownIndices for CpuAccessibleBuffer<[I], A>
+    where I: Index + 'static,
+          A: MemoryPoolAlloc
+{
+    #[inline]
+    fn index_bounds(&self) -> Option<IndexBounds> {
+        match self.read() {
+            Ok(content) => Some(IndexBounds::from_indices(content.iter())),
+            Err(_) => None,
+        }
+    }
+}
+
 unsafe impl<T: ?Sized, A> DeviceOwned for CpuAccessibleBuffer<T, A> {
     #[inline]
     fn device(&self) -> &Arc<Device> {
@@ -572,6 +593,7 @@ impl fmt::Display for WriteLockError {
 #[cfg(test)]
 mod tests {
     use buffer::{BufferUsage, CpuAccessibleBuffer};
+    use pipeline::input_assembly::KnownIndices;
 
     #[test]
     fn create_empty_buffer() {
@@ -581,4 +603,22 @@ mod tests {
 
         let _ = CpuAccessibleBuffer::from_data(device, BufferUsage::all(), EMPTY.iter());
     }
+
+    #[test]
+    fn index_bounds() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let buffer = CpuAccessibleBuffer::from_iter(device,
+                                                    BufferUsage::index_buffer(),
+                                                    [3u32, 9, 1].iter().cloned())
+            .unwrap();
+        assert_eq!(buffer.index_bounds().unwrap().max_vertex_index(false), Some(9));
+
+        // The bounds follow the writes made before the draw command is recorded.
+        buffer.write().unwrap()[0] = 12;
+        assert_eq!(buffer.index_bounds().unwrap().max_vertex_index(false), Some(12));
+
+        let _lock = buffer.write().unwrap();
+        assert!(buffer.index_bounds().is_none());
+    }
 }
@@ -19,6 +19,7 @@
 //!
 
 use smallvec::SmallVec;
+use std::any::TypeId;
 use std::marker::PhantomData;
 use std::mem;
 use std::sync::Arc;
@@ -51,6 +52,9 @@ use memory::pool::MemoryPool;
 use memory::pool::MemoryPoolAlloc;
 use memory::pool::PotentialDedicatedAllocation;
 use memory::pool::StdMemoryPoolAlloc;
+use pipeline::input_assembly::Index;
+use pipeline::input_assembly::IndexBounds;
+use pipeline::input_assembly::KnownIndices;
 use sync::AccessError;
 use sync::NowFuture;
 use sync::Sharing;
@@ -71,6 +75,9 @@ pub struct ImmutableBuffer<T: ?Sized, A = PotentialDedicatedAllocation<StdMemory
     // Queue families allowed to access this buffer.
     queue_families: SmallVec<[u32; 4]>,
 
+    // Bounds of the indices, if the buffer was created from an iterator of indices.
+    index_bounds: Option<IndexBounds>,
+
     // Necessary to have the appropriate template parameter.
     marker: PhantomData<Box<T>>,
 }
@@ -113,6 +120,17 @@ impl<T: ?Sized> ImmutableBuffer<T> {
                   DeviceMemoryAllocError>
         where B: BufferAccess + TypedBufferAccess<Content = T> + 'static + Clone + Send + Sync,
               T: 'static + Send + Sync
+    {
+        ImmutableBuffer::from_buffer_impl(source, usage, queue, None)
+    }
+
+    // Internal implementation of `from_buffer`, which also sets the bounds of the indices.
+    fn from_buffer_impl<B>(
+        source: B, usage: BufferUsage, queue: Arc<Queue>, index_bounds: Option<IndexBounds>)
+        -> Result<(Arc<ImmutableBuffer<T>>, ImmutableBufferFromBufferFuture),
+                  DeviceMemoryAllocError>
+        where B: BufferAccess + TypedBufferAccess<Content = T> + 'static + Clone + Send + Sync,
+              T: 'static + Send + Sync
     {
         unsafe {
             // We automatically set `transfer_destination` to true in order to avoid annoying errors.
@@ -121,10 +139,17 @@ impl<T: ?Sized> ImmutableBuffer<T> {
                 ..usage
             };
 
-            let (buffer, init) = ImmutableBuffer::raw(source.device().clone(),
-                                                      source.size(),
-                                                      actual_usage,
-                                                      source.device().active_queue_families())?;
+            let queue_families = source
+                .device()
+                .active_queue_families()
+                .into_iter()
+                .map(|f| f.id())
+                .collect();
+            let (buffer, init) = ImmutableBuffer::raw_impl(source.device().clone(),
+                                                           source.size(),
+                                                           actual_usage,
+                                                           queue_families,
+                                                           index_bounds)?;
 
             let cb = AutoCommandBufferBuilder::new(source.device().clone(),
                                                    queue.family())?
@@ -171,6 +196,11 @@ impl<T> ImmutableBuffer<T> {
 }
 
 impl<T> ImmutableBuffer<[T]> {
+    /// Builds an `ImmutableBuffer` from an iterator of elements.
+    ///
+    /// If the elements are `u16` or `u32`, the bounds of their values are computed while the
+    /// data is being written, so that the buffer can be used as an index buffer with
+    /// `AutoCommandBufferBuilder::draw_indexed_checked`.
     pub fn from_iter<D>(
         data: D, usage: BufferUsage, queue: Arc<Queue>)
         -> Result<(Arc<ImmutableBuffer<[T]>>, ImmutableBufferFromBufferFuture),
@@ -178,10 +208,51 @@ impl<T> ImmutableBuffer<[T]> {
         where D: ExactSizeIterator<Item = T>,
               T: 'static + Send + Sync + Sized
     {
-        let source = CpuAccessibleBuffer::from_iter(queue.device().clone(),
-                                                    BufferUsage::transfer_source(),
-                                                    data)?;
-        ImmutableBuffer::from_buffer(source, usage, queue)
+        // `from_iter` is also used for vertex data, so it can't require `T: Index`. Instead we
+        // check whether `T` is one of the index types.
+        let is_u16 = TypeId::of::<T>() == TypeId::of::<u16>();
+        let is_u32 = TypeId::of::<T>() == TypeId::of::<u32>();
+
+        let mut bounds = IndexBounds::default();
+        let source = {
+            let data = data.inspect(|element| unsafe {
+                let element = element as *const T;
+                if is_u16 {
+                    bounds.add(&*(element as *const u16));
+                } else if is_u32 {
+                    bounds.add(&*(element as *const u32));
+                }
+            });
+            CpuAccessibleBuffer::from_iter(queue.device().clone(),
+                                           BufferUsage::transfer_source(),
+                                           data)?
+        };
+
+        let index_bounds = if is_u16 || is_u32 { Some(bounds) } else { None };
+        ImmutableBuffer::from_buffer_impl(source, usage, queue, index_bounds)
+    }
+
+    /// Builds an `ImmutableBuffer` from an iterator of indices.
+    ///
+    /// This is the same as `from_iter`, except that the bounds of the indices are computed for
+    /// any type that implements `Index`. This allows the buffer to be used with
+    /// `AutoCommandBufferBuilder::draw_indexed_checked`.
+    pub fn from_indices<D>(
+        data: D, usage: BufferUsage, queue: Arc<Queue>)
+        -> Result<(Arc<ImmutableBuffer<[T]>>, ImmutableBufferFromBufferFuture),
+                  DeviceMemoryAllocError>
+        where D: ExactSizeIterator<Item = T>,
+              T: Index + 'static + Send + Sync + Sized
+    {
+        let mut bounds = IndexBounds::default();
+        let source = {
+            let data = data.inspect(|index| bounds.add(index));
+            CpuAccessibleBuffer::from_iter(queue.device().clone(),
+                                           BufferUsage::transfer_source(),
+                                           data)?
+        };
+
+        ImmutableBuffer::from_buffer_impl(source, usage, queue, Some(bounds))
     }
 
     /// Builds a new buffer with uninitialized data. Can be used for arrays.
@@ -236,13 +307,14 @@ impl<T: ?Sized> ImmutableBuffer<T> {
         where I: IntoIterator<Item = QueueFamily<'a>>
     {
         let queue_families = queue_families.into_iter().map(|f| f.id()).collect();
-        ImmutableBuffer::raw_impl(device, size, usage, queue_families)
+        ImmutableBuffer::raw_impl(device, size, usage, queue_families, None)
     }
 
     // Internal implementation of `raw`. This is separated from `raw` so that it doesn't need to be
     // inlined.
     unsafe fn raw_impl(
-        device: Arc<Device>, size: usize, usage: BufferUsage, queue_families: SmallVec<[u32; 4]>)
+        device: Arc<Device>, size: usize, usage: BufferUsage, queue_families: SmallVec<[u32; 4]>,
+        index_bounds: Option<IndexBounds>)
         -> Result<(Arc<ImmutableBuffer<T>>, ImmutableBufferInitialization<T>),
                   DeviceMemoryAllocError> {
         let (buffer, mem_reqs) = {
@@ -278,6 +350,7 @@ impl<T: ?Sized> ImmutableBuffer<T> {
                                      memory: mem,
                                      queue_families: queue_families,
                                      initialized: AtomicBool::new(false),
+                                     index_bounds: index_bounds,
                                      marker: PhantomData,
                                  });
 
@@ -368,6 +441,15 @@ unsafe impl<T: ?Sized, A> TypedBufferAccess for ImmutableBuffer<T, A> {
     type Content = T;
 }
 
+unsafe impl<I, A> KnownIndices for ImmutableBuffer<[I], A>
+    where I: Index
+{
+    #[inline]
+    fn index_bounds(&self) -> Option<IndexBounds> {
+        self.index_bounds
+    }
+}
+
 unsafe impl<T: ?Sized, A> DeviceOwned for ImmutableBuffer<T, A> {
     #[inline]
     fn device(&self) -> &Arc<Device> {
@@ -460,6 +542,7 @@ mod tests {
     use buffer::immutable::ImmutableBuffer;
     use command_buffer::AutoCommandBufferBuilder;
     use command_buffer::CommandBuffer;
+    use pipeline::input_assembly::KnownIndices;
     use sync::GpuFuture;
 
     #[test]
@@ -518,6 +601,47 @@ mod tests {
         }
     }
 
+    #[test]
+    fn from_indices_bounds() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let (buffer, _) = ImmutableBuffer::from_indices([4u16, 0xffff, 2].iter().cloned(),
+                                                     BufferUsage::index_buffer(),
+                                                     queue.clone())
+            .unwrap();
+        let bounds = buffer.index_bounds().unwrap();
+        assert_eq!(bounds.max_vertex_index(true), Some(4));
+        assert_eq!(bounds.max_vertex_index(false), Some(0xffff));
+
+    }
+
+    #[test]
+    fn from_iter_bounds() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let (buffer, _) = ImmutableBuffer::from_iter(0 .. 512u32,
+                                                     BufferUsage::index_buffer(),
+                                                     queue.clone())
+            .unwrap();
+        let bounds = buffer.index_bounds().unwrap();
+        assert_eq!(bounds.max_vertex_index(false), Some(511));
+
+        let (buffer, _) = ImmutableBuffer::from_iter([4u16, 0xffff, 2].iter().cloned(),
+                                                     BufferUsage::index_buffer(),
+                                                     queue.clone())
+            .unwrap();
+        let bounds = buffer.index_bounds().unwrap();
+        assert_eq!(bounds.max_vertex_index(true), Some(4));
+        assert_eq!(bounds.max_vertex_index(false), Some(0xffff));
+
+        // Elements that aren't indices don't have bounds.
+        let (buffer, _) = ImmutableBuffer::from_iter([4i32, 2].iter().cloned(),
+                                                     BufferUsage::vertex_buffer(),
+                                                     queue.clone())
+            .unwrap();
+        assert!(buffer.index_bounds.is_none());
+    }
+
     #[test]
     fn writing_forbidden() {
         let (device, queue) = gfx_dev_and_queue!();
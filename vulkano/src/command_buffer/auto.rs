@@ -7,28 +7,45 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use smallvec::SmallVec;
 use std::error;
 use std::fmt;
 use std::iter;
 use std::mem;
+use std::ops::Range;
 use std::slice;
 use std::sync::Arc;
 use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering;
 
 use OomError;
+use SafeDeref;
+use VulkanObject;
 use buffer::BufferAccess;
+use buffer::BufferSlice;
 use buffer::TypedBufferAccess;
+use command_buffer::BufferImageCopy;
+use command_buffer::ClearAttachment;
+use command_buffer::ClearRect;
 use command_buffer::CommandBuffer;
 use command_buffer::CommandBufferExecError;
+use command_buffer::DispatchIndirectCommand;
 use command_buffer::DrawIndirectCommand;
 use command_buffer::DrawIndexedIndirectCommand;
 use command_buffer::DynamicState;
+use command_buffer::DynamicStencilValue;
+use command_buffer::ImageCopy;
+use command_buffer::SecondaryCommandBuffer;
+use command_buffer::SecondaryResourceUsage;
 use command_buffer::StateCacher;
 use command_buffer::StateCacherOutcome;
+use command_buffer::pool::CommandPool;
+use command_buffer::pool::CommandPoolAlloc;
 use command_buffer::pool::CommandPoolBuilderAlloc;
 use command_buffer::pool::standard::StandardCommandPoolAlloc;
 use command_buffer::pool::standard::StandardCommandPoolBuilder;
+use command_buffer::synced::BarrierReport;
 use command_buffer::synced::SyncCommandBuffer;
 use command_buffer::synced::SyncCommandBufferBuilder;
 use command_buffer::synced::SyncCommandBufferBuilderError;
@@ -37,12 +54,16 @@ use command_buffer::sys::Kind;
 use command_buffer::sys::KindOcclusionQuery;
 use command_buffer::sys::KindSecondaryRenderPass;
 use command_buffer::sys::UnsafeCommandBuffer;
+use command_buffer::sys::UnsafeCommandBufferBuilder;
 use command_buffer::sys::UnsafeCommandBufferBuilderBufferImageCopy;
 use command_buffer::sys::UnsafeCommandBufferBuilderColorImageClear;
+use command_buffer::sys::UnsafeCommandBufferBuilderDepthStencilImageClear;
 use command_buffer::sys::UnsafeCommandBufferBuilderImageAspect;
 use command_buffer::sys::UnsafeCommandBufferBuilderImageBlit;
 use command_buffer::sys::UnsafeCommandBufferBuilderImageCopy;
+use command_buffer::sys::UnsafeCommandBufferBuilderImageResolve;
 use command_buffer::validity::*;
+use descriptor::descriptor_set::DescriptorSetWithOffsets;
 use descriptor::descriptor_set::DescriptorSetsCollection;
 use descriptor::pipeline_layout::PipelineLayoutAbstract;
 use device::Device;
@@ -67,14 +88,24 @@ use image::ImageLayout;
 use instance::QueueFamily;
 use pipeline::ComputePipelineAbstract;
 use pipeline::GraphicsPipelineAbstract;
+use pipeline::depth_stencil::StencilFaceFlags;
 use pipeline::input_assembly::Index;
+use pipeline::input_assembly::IndexBounds;
+use pipeline::input_assembly::KnownIndices;
 use pipeline::vertex::VertexSource;
 use query::QueryPipelineStatisticFlags;
+use query::QueryPoolAbstract;
+use query::QueryResultElement;
+use query::QueryResultFlags;
+use query::QueryType;
 use sampler::Filter;
 use sync::AccessCheckError;
 use sync::AccessFlagBits;
+use sync::Event;
 use sync::GpuFuture;
+use sync::OwnershipTransfer;
 use sync::PipelineStages;
+use vk;
 
 /// Note that command buffers allocated from the default command pool (`Arc<StandardCommandPool>`)
 /// don't implement the `Send` and `Sync` traits. If you use this pool, then the
@@ -84,6 +115,9 @@ pub struct AutoCommandBufferBuilder<P = StandardCommandPoolBuilder> {
     inner: SyncCommandBufferBuilder<P>,
     state_cacher: StateCacher,
 
+    // Id of the queue family of the command buffer.
+    queue_family: u32,
+
     // True if the queue family supports graphics operations.
     graphics_allowed: bool,
 
@@ -92,20 +126,44 @@ pub struct AutoCommandBufferBuilder<P = StandardCommandPoolBuilder> {
 
     debug_utils_allowed: bool,
 
+    // Queries that have been begun but not ended yet, with the type of their query pool, the
+    // index of the subpass they were begun in if they were begun inside of a render pass, and
+    // whether they are precise.
+    active_queries: Vec<(vk::QueryPool, u32, QueryType, Option<u32>, bool)>,
+
+    // Ranges of queries that have been reset by this command buffer and haven't been begun or
+    // written to since then. A query must be in one of these ranges in order to be used.
+    reset_queries: Vec<(vk::QueryPool, Range<u32>)>,
+
     // If we're inside a render pass, contains the render pass and the subpass index.
-    render_pass: Option<(Box<RenderPassAbstract>, u32)>,
+    render_pass: Option<(Arc<RenderPassAbstract + Send + Sync>, u32)>,
+
+    // If we're inside a render pass and the framebuffer is known, contains its dimensions.
+    framebuffer_dimensions: Option<[u32; 3]>,
 
     // True if we are a secondary command buffer.
     secondary_cb: bool,
 
+    // If we are a secondary command buffer, contains how the queries are inherited from the
+    // primary command buffer.
+    inherited_queries: Option<(KindOcclusionQuery, QueryPipelineStatisticFlags)>,
+
     // True if we're in a subpass that only allows executing secondary command buffers. False if
     // we're in a subpass that only allows inline commands. Irrelevant if not in a subpass.
     subpass_secondary: bool,
 
+    // Kind passed when creating the command buffer. Used when the command buffer is recorded
+    // again.
+    kind: ErasedKind,
+
     // Flags passed when creating the command buffer.
     flags: Flags,
 }
 
+// Kind of a command buffer, with the types of the render pass and of the framebuffer erased.
+type ErasedKind = Kind<Arc<RenderPassAbstract + Send + Sync>,
+                       Arc<FramebufferAbstract + Send + Sync>>;
+
 impl AutoCommandBufferBuilder<StandardCommandPoolBuilder> {
     #[inline]
     pub fn new(device: Arc<Device>, queue_family: QueueFamily)
@@ -361,44 +419,115 @@ impl AutoCommandBufferBuilder<StandardCommandPoolBuilder> {
         AutoCommandBufferBuilder::with_flags(device, queue_family, kind, Flags::SimultaneousUse)
     }
 
-    // Actual constructor. Private.
-    fn with_flags<R, F>(device: Arc<Device>, queue_family: QueueFamily, kind: Kind<R, F>,
-                        flags: Flags)
-                        -> Result<AutoCommandBufferBuilder<StandardCommandPoolBuilder>, OomError>
+    // Actual constructor.
+    pub(crate) fn with_flags<R, F>(device: Arc<Device>, queue_family: QueueFamily,
+                                   kind: Kind<R, F>, flags: Flags)
+                                   -> Result<AutoCommandBufferBuilder<StandardCommandPoolBuilder>,
+                                             OomError>
         where R: RenderPassAbstract + Clone + Send + Sync + 'static,
-              F: FramebufferAbstract
+              F: FramebufferAbstract + Send + Sync + 'static
     {
-        unsafe {
-            let (secondary_cb, render_pass) = match kind {
-                Kind::Primary => (false, None),
-                Kind::Secondary { render_pass: Some(ref sec), .. } => {
-                    let render_pass = sec.subpass.render_pass().clone();
+        let secondary = match kind {
+            Kind::Primary => false,
+            Kind::Secondary { .. } => true,
+        };
+
+        let pool = Device::standard_command_pool(&device, queue_family);
+        let alloc = pool.alloc(secondary, 1)?
+            .next()
+            .expect("Requested one command buffer from the command pool, but got zero.");
+
+        unsafe { AutoCommandBufferBuilder::from_alloc(alloc, kind, flags) }
+    }
+}
+
+impl<P> AutoCommandBufferBuilder<P> {
+    // Starts recording into a command buffer that has already been allocated with `kind`.
+    unsafe fn from_alloc<R, F>(alloc: P, kind: Kind<R, F>, flags: Flags)
+                               -> Result<AutoCommandBufferBuilder<P>, OomError>
+        where P: CommandPoolBuilderAlloc,
+              R: RenderPassAbstract + Clone + Send + Sync + 'static,
+              F: FramebufferAbstract + Send + Sync + 'static
+    {
+        let device = alloc.device().clone();
+        let queue_family = device
+            .physical_device()
+            .queue_family_by_id(alloc.queue_family().id())
+            .unwrap();
+
+        // The kind is kept so that the command buffer can be recorded again with the same kind.
+        let kind: ErasedKind = match kind {
+            Kind::Primary => Kind::Primary,
+            Kind::Secondary {
+                render_pass,
+                occlusion_query,
+                query_statistics_flags,
+            } => {
+                let render_pass = render_pass.map(|sec| {
                     let index = sec.subpass.index();
-                    (true, Some((Box::new(render_pass) as Box<_>, index)))
-                },
-                Kind::Secondary { render_pass: None, .. } => (true, None),
-            };
+                    let render_pass = Arc::new(sec.subpass.render_pass().clone()) as Arc<_>;
+                    KindSecondaryRenderPass {
+                        subpass: Subpass::from(render_pass, index).unwrap(),
+                        framebuffer: sec.framebuffer.map(|fb| Arc::new(fb) as Arc<_>),
+                    }
+                });
 
-            let pool = Device::standard_command_pool(&device, queue_family);
-            let inner = SyncCommandBufferBuilder::new(&pool, kind, flags);
-            let state_cacher = StateCacher::new();
+                Kind::Secondary {
+                    render_pass: render_pass,
+                    occlusion_query: occlusion_query,
+                    query_statistics_flags: query_statistics_flags,
+                }
+            },
+        };
 
-            let graphics_allowed = queue_family.supports_graphics();
-            let compute_allowed = queue_family.supports_compute();
-            let debug_utils_allowed = device.instance().loaded_extensions().ext_debug_utils;
+        let (render_pass, framebuffer_dimensions, inherited_queries) = match kind {
+            Kind::Primary => (None, None, None),
+            Kind::Secondary {
+                ref render_pass,
+                occlusion_query,
+                query_statistics_flags,
+            } => {
+                let (render_pass, dimensions) = match *render_pass {
+                    Some(ref sec) => {
+                        let render_pass = sec.subpass.render_pass().clone();
+                        let index = sec.subpass.index();
+                        let dimensions = sec.framebuffer.as_ref().map(|fb| fb.dimensions());
+                        (Some((render_pass, index)), dimensions)
+                    },
+                    None => (None, None),
+                };
+                (render_pass, dimensions, Some((occlusion_query, query_statistics_flags)))
+            },
+        };
 
-            Ok(AutoCommandBufferBuilder {
-                   inner: inner?,
-                   state_cacher,
-                   graphics_allowed,
-                   compute_allowed,
-                   debug_utils_allowed,
-                   render_pass,
-                   secondary_cb,
-                   subpass_secondary: false,
-                   flags,
-               })
-        }
+        let secondary_cb = inherited_queries.is_some();
+        let inside_render_pass = render_pass.is_some();
+        let cmd = UnsafeCommandBufferBuilder::already_allocated(alloc, kind.clone(), flags)?;
+        let inner = SyncCommandBufferBuilder::from_unsafe_cmd(cmd, secondary_cb,
+                                                              inside_render_pass);
+        let state_cacher = StateCacher::new();
+
+        let graphics_allowed = queue_family.supports_graphics();
+        let compute_allowed = queue_family.supports_compute();
+        let debug_utils_allowed = device.instance().loaded_extensions().ext_debug_utils;
+
+        Ok(AutoCommandBufferBuilder {
+               inner,
+               state_cacher,
+               queue_family: queue_family.id(),
+               graphics_allowed,
+               compute_allowed,
+               debug_utils_allowed,
+               active_queries: Vec::new(),
+               reset_queries: Vec::new(),
+               render_pass,
+               framebuffer_dimensions,
+               secondary_cb,
+               inherited_queries,
+               subpass_secondary: false,
+               kind,
+               flags,
+           })
     }
 }
 
@@ -445,6 +574,14 @@ impl<P> AutoCommandBufferBuilder<P> {
             return Err(AutoCommandBufferBuilderContextError::WrongSubpassIndex);
         }
 
+        if !RenderPassCompatible::is_subpass_compatible_with(pipeline,
+                                                             pipeline.subpass_index(),
+                                                             &local_render_pass.0,
+                                                             local_render_pass.1)
+        {
+            return Err(AutoCommandBufferBuilderContextError::IncompatibleSubpass);
+        }
+
         if !RenderPassCompatible::is_compatible_with(pipeline, &local_render_pass.0) {
             return Err(AutoCommandBufferBuilderContextError::IncompatibleRenderPass);
         }
@@ -461,6 +598,10 @@ impl<P> AutoCommandBufferBuilder<P> {
             return Err(AutoCommandBufferBuilderContextError::ForbiddenInsideRenderPass.into());
         }
 
+        if !self.active_queries.is_empty() {
+            return Err(AutoCommandBufferBuilderContextError::QueryIsActive.into());
+        }
+
         let submit_state = match self.flags {
             Flags::None => {
                 SubmitState::ExclusiveUse { in_use: AtomicBool::new(false) }
@@ -473,12 +614,151 @@ impl<P> AutoCommandBufferBuilder<P> {
             },
         };
 
+        let render_pass = if self.secondary_cb { self.render_pass } else { None };
+
         Ok(AutoCommandBuffer {
                inner: self.inner.build()?,
                submit_state,
+               secondary_cb: self.secondary_cb,
+               render_pass,
+               kind: self.kind,
+               flags: self.flags,
+               num_submissions: AtomicUsize::new(0),
            })
     }
 
+    /// Enables the barrier report of the command buffer, which can then be obtained with
+    /// `AutoCommandBuffer::barrier_report()`.
+    ///
+    /// The report is disabled by default, because recording it has a cost. It only contains the
+    /// commands that are added after this method has been called. See `BarrierReport`.
+    #[inline]
+    pub fn enable_barrier_report(mut self) -> Self {
+        self.inner.enable_barrier_report();
+        self
+    }
+
+    /// Adds a command that acquires the ownership of a buffer from another queue family.
+    ///
+    /// The buffer must have been released to the queue family of this command buffer with
+    /// `release_buffer_ownership` by a command buffer of `source_family`, and this command buffer
+    /// must be executed after it. Resources that are created with the exclusive sharing mode must
+    /// be transferred this way before they can be used by another queue family.
+    ///
+    /// > **Note**: `then_execute` automatically acquires the resources released to the queue
+    /// > family of the command buffer by the previous future. Acquiring a buffer explicitly is
+    /// > only needed if the release isn't part of the future the command buffer is executed
+    /// > after.
+    #[inline]
+    pub fn acquire_buffer_ownership<B>(mut self, buffer: B, source_family: QueueFamily)
+                                       -> Result<Self, OwnershipTransferError>
+        where B: BufferAccess + Send + Sync + 'static
+    {
+        unsafe {
+            let destination_family = self.queue_family;
+            self.ensure_ownership_transfer(source_family, false)?;
+            self.inner
+                .acquire_buffer_ownership(buffer, source_family.id(), destination_family)?;
+            Ok(self)
+        }
+    }
+
+    /// Adds a command that acquires the ownership of an image from another queue family.
+    ///
+    /// The image must have been released to the queue family of this command buffer with
+    /// `release_image_ownership` by a command buffer of `source_family`, and this command buffer
+    /// must be executed after it. The image stays in the layout it was released in, which is the
+    /// initial layout requirement of the image.
+    ///
+    /// > **Note**: `then_execute` automatically acquires the resources released to the queue
+    /// > family of the command buffer by the previous future. Acquiring an image explicitly is
+    /// > only needed if the release isn't part of the future the command buffer is executed
+    /// > after.
+    #[inline]
+    pub fn acquire_image_ownership<I>(mut self, image: I, source_family: QueueFamily)
+                                      -> Result<Self, OwnershipTransferError>
+        where I: ImageAccess + Send + Sync + 'static
+    {
+        unsafe {
+            let destination_family = self.queue_family;
+            self.ensure_ownership_transfer(source_family, false)?;
+            let layout = image.initial_layout_requirement();
+            self.inner.acquire_image_ownership(image,
+                                               layout,
+                                               source_family.id(),
+                                               destination_family)?;
+            Ok(self)
+        }
+    }
+
+    /// Adds a command that begins a query.
+    ///
+    /// The query pool must be an occlusion or a pipeline statistics query pool. `precise` can only
+    /// be true for occlusion queries, and requires the `occlusion_query_precise` feature.
+    ///
+    /// The query must have been reset with `reset_query_pool` earlier in this command buffer, and
+    /// must not have been begun or written to since then. Use `begin_query_assume_reset` if the
+    /// query was reset somewhere else, for example by a command buffer submitted earlier. If the
+    /// query is begun inside of a render pass, it must be ended in the same subpass.
+    #[inline]
+    pub fn begin_query<Q>(self, query_pool: Q, query: u32, precise: bool)
+                          -> Result<Self, BeginQueryError>
+        where Q: QueryPoolAbstract + Send + Sync + 'static
+    {
+        self.begin_query_impl(query_pool, query, precise, false)
+    }
+
+    /// Same as `begin_query`, except that the query doesn't need to have been reset earlier in
+    /// this command buffer.
+    ///
+    /// # Safety
+    ///
+    /// - The query must have been reset before this command is executed, for example by a
+    ///   command buffer submitted earlier, and must not have been begun or written to since then.
+    ///
+    #[inline]
+    pub unsafe fn begin_query_assume_reset<Q>(self, query_pool: Q, query: u32, precise: bool)
+                                              -> Result<Self, BeginQueryError>
+        where Q: QueryPoolAbstract + Send + Sync + 'static
+    {
+        self.begin_query_impl(query_pool, query, precise, true)
+    }
+
+    // Implementation of `begin_query` and `begin_query_assume_reset`.
+    fn begin_query_impl<Q>(mut self, query_pool: Q, query: u32, precise: bool,
+                           assume_reset: bool)
+                           -> Result<Self, BeginQueryError>
+        where Q: QueryPoolAbstract + Send + Sync + 'static
+    {
+        check_begin_query(self.device(), &query_pool, query, precise)?;
+
+        let ty = query_pool.inner().ty();
+        let allowed = match ty {
+            QueryType::PipelineStatistics(_) => self.graphics_allowed || self.compute_allowed,
+            _ => self.graphics_allowed,
+        };
+        if !allowed {
+            return Err(AutoCommandBufferBuilderContextError::NotSupportedByQueueFamily.into());
+        }
+
+        let pool = query_pool.inner().internal_object();
+        if self.active_queries
+            .iter()
+            .any(|&(p, i, ref t, _, _)| (p == pool && i == query) || t.same_kind(&ty))
+        {
+            return Err(AutoCommandBufferBuilderContextError::QueryIsActive.into());
+        }
+
+        self.use_reset_query(pool, query, assume_reset)?;
+
+        unsafe {
+            self.inner.begin_query(query_pool, query, precise);
+        }
+        let subpass = self.render_pass.as_ref().map(|&(_, index)| index);
+        self.active_queries.push((pool, query, ty, subpass, precise));
+        Ok(self)
+    }
+
     /// Adds a command that enters a render pass.
     ///
     /// If `secondary` is true, then you will only be able to add secondary command buffers while
@@ -563,7 +843,8 @@ impl<P> AutoCommandBufferBuilder<P> {
             };
             self.inner
                 .begin_render_pass(framebuffer.clone(), contents, clear_values)?;
-            self.render_pass = Some((Box::new(framebuffer) as Box<_>, 0));
+            self.framebuffer_dimensions = Some(framebuffer.dimensions());
+            self.render_pass = Some((Arc::new(framebuffer) as Arc<_>, 0));
             self.subpass_secondary = secondary;
             Ok(self)
         }
@@ -619,11 +900,15 @@ impl<P> AutoCommandBufferBuilder<P> {
     /// If `layer_count` is greater than 1, the copy will happen between each individual layer as
     /// if they were separate images.
     ///
+    /// The source is used in the `TransferSrcOptimal` layout and the destination in the
+    /// `TransferDstOptimal` layout. Use `copy_image_with_layouts` to choose other layouts.
+    ///
     /// # Panic
     ///
     /// - Panics if the source or the destination was not created with `device`.
     ///
-    pub fn copy_image<S, D>(mut self, source: S, source_offset: [i32; 3],
+    #[inline]
+    pub fn copy_image<S, D>(self, source: S, source_offset: [i32; 3],
                             source_base_array_layer: u32, source_mip_level: u32,
                             destination: D, destination_offset: [i32; 3],
                             destination_base_array_layer: u32, destination_mip_level: u32,
@@ -631,10 +916,55 @@ impl<P> AutoCommandBufferBuilder<P> {
                             -> Result<Self, CopyImageError>
         where S: ImageAccess + Send + Sync + 'static,
               D: ImageAccess + Send + Sync + 'static
+    {
+        self.copy_image_with_layouts(source,
+                                     ImageLayout::TransferSrcOptimal,
+                                     source_offset,
+                                     source_base_array_layer,
+                                     source_mip_level,
+                                     destination,
+                                     ImageLayout::TransferDstOptimal,
+                                     destination_offset,
+                                     destination_base_array_layer,
+                                     destination_mip_level,
+                                     extent,
+                                     layer_count)
+    }
+
+    /// Same as `copy_image`, but lets you choose the layouts that the source and the destination
+    /// are in during the copy.
+    ///
+    /// The source can be in the `TransferSrcOptimal` layout and the destination in the
+    /// `TransferDstOptimal` layout. Both can also be in the `General` layout, or in the
+    /// `SharedPresent` layout if the `khr_shared_presentable_image` extension is enabled. Using
+    /// the layout that the image is already in avoids a layout transition.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the source or the destination was not created with `device`.
+    ///
+    pub fn copy_image_with_layouts<S, D>(mut self, source: S, source_layout: ImageLayout,
+                                         source_offset: [i32; 3], source_base_array_layer: u32,
+                                         source_mip_level: u32, destination: D,
+                                         destination_layout: ImageLayout,
+                                         destination_offset: [i32; 3],
+                                         destination_base_array_layer: u32,
+                                         destination_mip_level: u32, extent: [u32; 3],
+                                         layer_count: u32)
+                                         -> Result<Self, CopyImageError>
+        where S: ImageAccess + Send + Sync + 'static,
+              D: ImageAccess + Send + Sync + 'static
     {
         unsafe {
             self.ensure_outside_render_pass()?;
 
+            check_transfer_image_layout(self.device(),
+                                        source_layout,
+                                        CheckTransferImageLayoutTy::Source)?;
+            check_transfer_image_layout(self.device(),
+                                        destination_layout,
+                                        CheckTransferImageLayoutTy::Destination)?;
+
             check_copy_image(self.device(),
                              &source,
                              source_offset,
@@ -648,8 +978,7 @@ impl<P> AutoCommandBufferBuilder<P> {
                              layer_count)?;
 
             let copy = UnsafeCommandBufferBuilderImageCopy {
-                // TODO: Allowing choosing a subset of the image aspects, but note that if color
-                // is included, neither depth nor stencil may.
+                // Use `copy_image_regions` to copy a subset of the aspects.
                 aspect: UnsafeCommandBufferBuilderImageAspect {
                     color: source.has_color(),
                     depth: !source.has_color() &&
@@ -667,16 +996,101 @@ impl<P> AutoCommandBufferBuilder<P> {
                 extent,
             };
 
-            // TODO: Allow choosing layouts, but note that only Transfer*Optimal and General are
-            // valid.
             self.inner
-                .copy_image(source, ImageLayout::TransferSrcOptimal,
-                            destination, ImageLayout::TransferDstOptimal,
+                .copy_image(source, source_layout, destination, destination_layout,
                             iter::once(copy))?;
             Ok(self)
         }
     }
 
+    /// Adds a command that copies a list of regions of an image to another image.
+    ///
+    /// Contrary to `copy_image`, each region chooses which aspect of the images it copies, and
+    /// regions can copy different mipmap levels and array layers in a single command. The same
+    /// restrictions as `copy_image` apply to each region, and the source and the destination
+    /// must both have the aspect that the region copies.
+    ///
+    /// Does nothing if `regions` is empty.
+    ///
+    /// The source is used in the `TransferSrcOptimal` layout and the destination in the
+    /// `TransferDstOptimal` layout. Use `copy_image_regions_with_layouts` to choose other layouts.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the source or the destination was not created with `device`.
+    ///
+    #[inline]
+    pub fn copy_image_regions<S, D, R>(self, source: S, destination: D, regions: R)
+                                       -> Result<Self, CopyImageError>
+        where S: ImageAccess + Send + Sync + 'static,
+              D: ImageAccess + Send + Sync + 'static,
+              R: IntoIterator<Item = ImageCopy>
+    {
+        self.copy_image_regions_with_layouts(source,
+                                             ImageLayout::TransferSrcOptimal,
+                                             destination,
+                                             ImageLayout::TransferDstOptimal,
+                                             regions)
+    }
+
+    /// Same as `copy_image_regions`, but lets you choose the layouts that the source and the
+    /// destination are in during the copy.
+    ///
+    /// See `copy_image_with_layouts` for the layouts that are allowed.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the source or the destination was not created with `device`.
+    ///
+    pub fn copy_image_regions_with_layouts<S, D, R>(mut self, source: S,
+                                                    source_layout: ImageLayout, destination: D,
+                                                    destination_layout: ImageLayout, regions: R)
+                                                    -> Result<Self, CopyImageError>
+        where S: ImageAccess + Send + Sync + 'static,
+              D: ImageAccess + Send + Sync + 'static,
+              R: IntoIterator<Item = ImageCopy>
+    {
+        unsafe {
+            self.ensure_outside_render_pass()?;
+
+            check_transfer_image_layout(self.device(),
+                                        source_layout,
+                                        CheckTransferImageLayoutTy::Source)?;
+            check_transfer_image_layout(self.device(),
+                                        destination_layout,
+                                        CheckTransferImageLayoutTy::Destination)?;
+
+            let regions: Vec<ImageCopy> = regions.into_iter().collect();
+            if regions.is_empty() {
+                return Ok(self);
+            }
+
+            check_copy_image_regions(self.device(), &source, &destination, &regions)?;
+
+            let copies = regions
+                .into_iter()
+                .map(|region| {
+                    UnsafeCommandBufferBuilderImageCopy {
+                        aspect: region.aspect.to_unsafe(),
+                        source_mip_level: region.source_mip_level,
+                        destination_mip_level: region.destination_mip_level,
+                        source_base_array_layer: region.source_base_array_layer,
+                        destination_base_array_layer: region.destination_base_array_layer,
+                        layer_count: region.layer_count,
+                        source_offset: region.source_offset,
+                        destination_offset: region.destination_offset,
+                        extent: region.extent,
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            self.inner
+                .copy_image(source, source_layout, destination, destination_layout,
+                            copies.into_iter())?;
+            Ok(self)
+        }
+    }
+
     /// Adds a command that blits an image to another.
     ///
     /// A *blit* is similar to an image copy operation, except that the portion of the image that
@@ -704,11 +1118,15 @@ impl<P> AutoCommandBufferBuilder<P> {
     /// If `layer_count` is greater than 1, the blit will happen between each individual layer as
     /// if they were separate images.
     ///
+    /// The source is used in the `TransferSrcOptimal` layout and the destination in the
+    /// `TransferDstOptimal` layout. Use `blit_image_with_layouts` to choose other layouts.
+    ///
     /// # Panic
     ///
     /// - Panics if the source or the destination was not created with `device`.
     ///
-    pub fn blit_image<S, D>(mut self, source: S, source_top_left: [i32; 3],
+    #[inline]
+    pub fn blit_image<S, D>(self, source: S, source_top_left: [i32; 3],
                             source_bottom_right: [i32; 3], source_base_array_layer: u32,
                             source_mip_level: u32, destination: D, destination_top_left: [i32; 3],
                             destination_bottom_right: [i32; 3], destination_base_array_layer: u32,
@@ -716,6 +1134,45 @@ impl<P> AutoCommandBufferBuilder<P> {
                             -> Result<Self, BlitImageError>
         where S: ImageAccess + Send + Sync + 'static,
               D: ImageAccess + Send + Sync + 'static
+    {
+        self.blit_image_with_layouts(source,
+                                     ImageLayout::TransferSrcOptimal,
+                                     source_top_left,
+                                     source_bottom_right,
+                                     source_base_array_layer,
+                                     source_mip_level,
+                                     destination,
+                                     ImageLayout::TransferDstOptimal,
+                                     destination_top_left,
+                                     destination_bottom_right,
+                                     destination_base_array_layer,
+                                     destination_mip_level,
+                                     layer_count,
+                                     filter)
+    }
+
+    /// Same as `blit_image`, but lets you choose the layouts that the source and the destination
+    /// are in during the blit.
+    ///
+    /// See `copy_image_with_layouts` for the layouts that are allowed.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the source or the destination was not created with `device`.
+    ///
+    pub fn blit_image_with_layouts<S, D>(mut self, source: S, source_layout: ImageLayout,
+                                         source_top_left: [i32; 3],
+                                         source_bottom_right: [i32; 3],
+                                         source_base_array_layer: u32, source_mip_level: u32,
+                                         destination: D, destination_layout: ImageLayout,
+                                         destination_top_left: [i32; 3],
+                                         destination_bottom_right: [i32; 3],
+                                         destination_base_array_layer: u32,
+                                         destination_mip_level: u32, layer_count: u32,
+                                         filter: Filter)
+                                         -> Result<Self, BlitImageError>
+        where S: ImageAccess + Send + Sync + 'static,
+              D: ImageAccess + Send + Sync + 'static
     {
         unsafe {
             if !self.graphics_allowed {
@@ -724,6 +1181,13 @@ impl<P> AutoCommandBufferBuilder<P> {
 
             self.ensure_outside_render_pass()?;
 
+            check_transfer_image_layout(self.device(),
+                                        source_layout,
+                                        CheckTransferImageLayoutTy::Source)?;
+            check_transfer_image_layout(self.device(),
+                                        destination_layout,
+                                        CheckTransferImageLayoutTy::Destination)?;
+
             check_blit_image(self.device(),
                              &source,
                              source_top_left,
@@ -762,15 +1226,56 @@ impl<P> AutoCommandBufferBuilder<P> {
 
             self.inner
                 .blit_image(source,
-                            ImageLayout::TransferSrcOptimal,
-                            destination, // TODO: let choose layout
-                            ImageLayout::TransferDstOptimal,
+                            source_layout,
+                            destination,
+                            destination_layout,
                             iter::once(blit),
                             filter)?;
             Ok(self)
         }
     }
 
+    /// Adds a command that clears regions of attachments of the current subpass.
+    ///
+    /// Can only be used inside a subpass that allows inline commands. Each rect is checked
+    /// against the dimensions of the framebuffer, if it is known.
+    pub fn clear_attachments<A, R>(mut self, attachments: A, rects: R)
+                                   -> Result<Self, ClearAttachmentsError>
+        where A: IntoIterator<Item = ClearAttachment>,
+              R: IntoIterator<Item = ClearRect>
+    {
+        unsafe {
+            if !self.graphics_allowed {
+                return Err(AutoCommandBufferBuilderContextError::NotSupportedByQueueFamily.into());
+            }
+
+            let attachments: SmallVec<[_; 4]> = attachments.into_iter().collect();
+            let rects: SmallVec<[_; 4]> = rects.into_iter().collect();
+
+            match self.render_pass {
+                Some((ref render_pass, subpass)) => {
+                    if self.subpass_secondary {
+                        return Err(AutoCommandBufferBuilderContextError::WrongSubpassType.into());
+                    }
+
+                    check_clear_attachments(&**render_pass,
+                                            subpass,
+                                            self.framebuffer_dimensions,
+                                            &attachments,
+                                            &rects)?;
+                },
+                None => {
+                    return Err(AutoCommandBufferBuilderContextError::ForbiddenOutsideRenderPass
+                                   .into());
+                },
+            }
+
+            self.inner
+                .clear_attachments(attachments.into_iter(), rects.into_iter());
+            Ok(self)
+        }
+    }
+
     /// Adds a command that clears all the layers and mipmap levels of a color image with a
     /// specific value.
     ///
@@ -794,10 +1299,37 @@ impl<P> AutoCommandBufferBuilder<P> {
     ///
     /// - Panics if `color` is not a color value.
     ///
-    pub fn clear_color_image_dimensions<I>(mut self, image: I, first_layer: u32, num_layers: u32,
+    #[inline]
+    pub fn clear_color_image_dimensions<I>(self, image: I, first_layer: u32, num_layers: u32,
                                            first_mipmap: u32, num_mipmaps: u32, color: ClearValue)
                                            -> Result<Self, ClearColorImageError>
         where I: ImageAccess + Send + Sync + 'static
+    {
+        self.clear_color_image_dimensions_with_layout(image,
+                                                      ImageLayout::TransferDstOptimal,
+                                                      first_layer,
+                                                      num_layers,
+                                                      first_mipmap,
+                                                      num_mipmaps,
+                                                      color)
+    }
+
+    /// Same as `clear_color_image_dimensions`, but lets you choose the layout that the image is
+    /// in during the clear.
+    ///
+    /// The image can be in the `TransferDstOptimal` or `General` layout, or in the
+    /// `SharedPresent` layout if the `khr_shared_presentable_image` extension is enabled.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `color` is not a color value.
+    ///
+    pub fn clear_color_image_dimensions_with_layout<I>(mut self, image: I, layout: ImageLayout,
+                                                       first_layer: u32, num_layers: u32,
+                                                       first_mipmap: u32, num_mipmaps: u32,
+                                                       color: ClearValue)
+                                                       -> Result<Self, ClearColorImageError>
+        where I: ImageAccess + Send + Sync + 'static
     {
         unsafe {
             if !self.graphics_allowed && !self.compute_allowed {
@@ -805,6 +1337,9 @@ impl<P> AutoCommandBufferBuilder<P> {
             }
 
             self.ensure_outside_render_pass()?;
+            check_transfer_image_layout(self.device(),
+                                        layout,
+                                        CheckTransferImageLayoutTy::Destination)?;
             check_clear_color_image(self.device(),
                                     &image,
                                     first_layer,
@@ -826,12 +1361,103 @@ impl<P> AutoCommandBufferBuilder<P> {
                 layer_count: num_layers,
             };
 
-            // TODO: let choose layout
             self.inner
-                .clear_color_image(image,
-                                   ImageLayout::TransferDstOptimal,
-                                   color,
-                                   iter::once(region))?;
+                .clear_color_image(image, layout, color, iter::once(region))?;
+            Ok(self)
+        }
+    }
+
+    /// Adds a command that clears all the layers and mipmap levels of a depth and/or stencil
+    /// image with a specific value.
+    ///
+    /// The aspects that are cleared depend on `clear_value`. A `ClearValue::Depth` only clears
+    /// the depth aspect, a `ClearValue::Stencil` only clears the stencil aspect, and a
+    /// `ClearValue::DepthStencil` clears both.
+    pub fn clear_depth_stencil_image<I>(self, image: I, clear_value: ClearValue)
+                                        -> Result<Self, ClearDepthStencilImageError>
+        where I: ImageAccess + Send + Sync + 'static
+    {
+        let layers = image.dimensions().array_layers();
+        let levels = image.mipmap_levels();
+
+        self.clear_depth_stencil_image_dimensions(image, 0, layers, 0, levels, clear_value)
+    }
+
+    /// Adds a command that clears a depth and/or stencil image with a specific value.
+    ///
+    /// See `clear_depth_stencil_image` for how the aspects to clear are chosen.
+    #[inline]
+    pub fn clear_depth_stencil_image_dimensions<I>(self, image: I, first_layer: u32,
+                                                   num_layers: u32, first_mipmap: u32,
+                                                   num_mipmaps: u32, clear_value: ClearValue)
+                                                   -> Result<Self, ClearDepthStencilImageError>
+        where I: ImageAccess + Send + Sync + 'static
+    {
+        self.clear_depth_stencil_image_dimensions_with_layout(image,
+                                                              ImageLayout::TransferDstOptimal,
+                                                              first_layer,
+                                                              num_layers,
+                                                              first_mipmap,
+                                                              num_mipmaps,
+                                                              clear_value)
+    }
+
+    /// Same as `clear_depth_stencil_image_dimensions`, but lets you choose the layout that the
+    /// image is in during the clear.
+    ///
+    /// See `clear_color_image_dimensions_with_layout` for the layouts that are allowed.
+    pub fn clear_depth_stencil_image_dimensions_with_layout<I>(
+        mut self, image: I, layout: ImageLayout, first_layer: u32, num_layers: u32,
+        first_mipmap: u32, num_mipmaps: u32, clear_value: ClearValue)
+        -> Result<Self, ClearDepthStencilImageError>
+        where I: ImageAccess + Send + Sync + 'static
+    {
+        unsafe {
+            if !self.graphics_allowed {
+                return Err(AutoCommandBufferBuilderContextError::NotSupportedByQueueFamily.into());
+            }
+
+            self.ensure_outside_render_pass()?;
+            check_transfer_image_layout(self.device(),
+                                        layout,
+                                        CheckTransferImageLayoutTy::Destination)?;
+            check_clear_depth_stencil_image(self.device(),
+                                            &image,
+                                            first_layer,
+                                            num_layers,
+                                            first_mipmap,
+                                            num_mipmaps,
+                                            clear_value)?;
+
+            let aspect = match clear_value {
+                ClearValue::Depth(_) => UnsafeCommandBufferBuilderImageAspect {
+                    color: false,
+                    depth: true,
+                    stencil: false,
+                },
+                ClearValue::Stencil(_) => UnsafeCommandBufferBuilderImageAspect {
+                    color: false,
+                    depth: false,
+                    stencil: true,
+                },
+                ClearValue::DepthStencil(_) => UnsafeCommandBufferBuilderImageAspect {
+                    color: false,
+                    depth: true,
+                    stencil: true,
+                },
+                _ => unreachable!(),
+            };
+
+            let region = UnsafeCommandBufferBuilderDepthStencilImageClear {
+                aspect: aspect,
+                base_mip_level: first_mipmap,
+                level_count: num_mipmaps,
+                base_array_layer: first_layer,
+                layer_count: num_layers,
+            };
+
+            self.inner
+                .clear_depth_stencil_image(image, layout, clear_value, iter::once(region))?;
             Ok(self)
         }
     }
@@ -870,17 +1496,45 @@ impl<P> AutoCommandBufferBuilder<P> {
     }
 
     /// Adds a command that copies from a buffer to an image.
-    pub fn copy_buffer_to_image_dimensions<S, D, Px>(mut self, source: S, destination: D,
+    #[inline]
+    pub fn copy_buffer_to_image_dimensions<S, D, Px>(self, source: S, destination: D,
                                                      offset: [u32; 3], size: [u32; 3],
                                                      first_layer: u32, num_layers: u32, mipmap: u32)
                                                      -> Result<Self, CopyBufferImageError>
         where S: TypedBufferAccess<Content = [Px]> + Send + Sync + 'static,
               D: ImageAccess + Send + Sync + 'static,
               Format: AcceptsPixels<Px>
+    {
+        self.copy_buffer_to_image_dimensions_with_layout(source,
+                                                         destination,
+                                                         ImageLayout::TransferDstOptimal,
+                                                         offset,
+                                                         size,
+                                                         first_layer,
+                                                         num_layers,
+                                                         mipmap)
+    }
+
+    /// Same as `copy_buffer_to_image_dimensions`, but lets you choose the layout that the
+    /// destination is in during the copy.
+    ///
+    /// The destination can be in the `TransferDstOptimal` or `General` layout, or in the
+    /// `SharedPresent` layout if the `khr_shared_presentable_image` extension is enabled.
+    pub fn copy_buffer_to_image_dimensions_with_layout<S, D, Px>(
+        mut self, source: S, destination: D, destination_layout: ImageLayout, offset: [u32; 3],
+        size: [u32; 3], first_layer: u32, num_layers: u32, mipmap: u32)
+        -> Result<Self, CopyBufferImageError>
+        where S: TypedBufferAccess<Content = [Px]> + Send + Sync + 'static,
+              D: ImageAccess + Send + Sync + 'static,
+              Format: AcceptsPixels<Px>
     {
         unsafe {
             self.ensure_outside_render_pass()?;
 
+            check_transfer_image_layout(self.device(),
+                                        destination_layout,
+                                        CheckTransferImageLayoutTy::Destination)?;
+
             check_copy_buffer_image(self.device(),
                                     &source,
                                     &destination,
@@ -895,14 +1549,10 @@ impl<P> AutoCommandBufferBuilder<P> {
                 buffer_offset: 0,
                 buffer_row_length: 0,
                 buffer_image_height: 0,
-                image_aspect: if destination.has_color() {
-                    UnsafeCommandBufferBuilderImageAspect {
-                        color: true,
-                        depth: false,
-                        stencil: false,
-                    }
-                } else {
-                    unimplemented!()
+                image_aspect: UnsafeCommandBufferBuilderImageAspect {
+                    color: destination.has_color(),
+                    depth: destination.has_depth(),
+                    stencil: destination.has_stencil(),
                 },
                 image_mip_level: mipmap,
                 image_base_array_layer: first_layer,
@@ -912,39 +1562,127 @@ impl<P> AutoCommandBufferBuilder<P> {
             };
 
             self.inner
-                .copy_buffer_to_image(source,
-                                      destination,
-                                      ImageLayout::TransferDstOptimal, // TODO: let choose layout
-                                      iter::once(copy))?;
+                .copy_buffer_to_image(source, destination, destination_layout, iter::once(copy))?;
             Ok(self)
         }
     }
 
-    /// Adds a command that copies from an image to a buffer.
-    pub fn copy_image_to_buffer<S, D, Px>(self, source: S, destination: D)
-                                          -> Result<Self, CopyBufferImageError>
-        where S: ImageAccess + Send + Sync + 'static,
-              D: TypedBufferAccess<Content = [Px]> + Send + Sync + 'static,
-              Format: AcceptsPixels<Px>
+    /// Adds a command that copies a list of regions of a buffer to an image.
+    ///
+    /// Each region chooses which aspect of the image it writes, and regions can write different
+    /// mipmap levels and array layers in a single command. This can be used for example to
+    /// upload all the mipmaps of a texture at once, or to write only the stencil aspect of a
+    /// depth-stencil image. The offsets and lengths in the buffer are expressed in number of
+    /// elements of the buffer, and the type of the elements must match the aspect. See
+    /// `BufferImageCopy`.
+    ///
+    /// Does nothing if `regions` is empty.
+    ///
+    /// The destination is used in the `TransferDstOptimal` layout. Use
+    /// `copy_buffer_to_image_regions_with_layout` to choose another layout.
+    #[inline]
+    pub fn copy_buffer_to_image_regions<S, D, Px, R>(self, source: S, destination: D, regions: R)
+                                                     -> Result<Self, CopyBufferImageError>
+        where S: TypedBufferAccess<Content = [Px]> + Send + Sync + 'static,
+              D: ImageAccess + Send + Sync + 'static,
+              R: IntoIterator<Item = BufferImageCopy>
     {
-        self.ensure_outside_render_pass()?;
-
-        let dims = source.dimensions().width_height_depth();
-        self.copy_image_to_buffer_dimensions(source, destination, [0, 0, 0], dims, 0, 1, 0)
+        self.copy_buffer_to_image_regions_with_layout(source,
+                                                      destination,
+                                                      ImageLayout::TransferDstOptimal,
+                                                      regions)
     }
 
-    /// Adds a command that copies from an image to a buffer.
-    pub fn copy_image_to_buffer_dimensions<S, D, Px>(mut self, source: S, destination: D,
-                                                     offset: [u32; 3], size: [u32; 3],
+    /// Same as `copy_buffer_to_image_regions`, but lets you choose the layout that the
+    /// destination is in during the copy.
+    ///
+    /// See `copy_buffer_to_image_dimensions_with_layout` for the layouts that are allowed.
+    pub fn copy_buffer_to_image_regions_with_layout<S, D, Px, R>(
+        mut self, source: S, destination: D, destination_layout: ImageLayout, regions: R)
+        -> Result<Self, CopyBufferImageError>
+        where S: TypedBufferAccess<Content = [Px]> + Send + Sync + 'static,
+              D: ImageAccess + Send + Sync + 'static,
+              R: IntoIterator<Item = BufferImageCopy>
+    {
+        unsafe {
+            self.ensure_outside_render_pass()?;
+
+            check_transfer_image_layout(self.device(),
+                                        destination_layout,
+                                        CheckTransferImageLayoutTy::Destination)?;
+
+            let regions: Vec<BufferImageCopy> = regions.into_iter().collect();
+            if regions.is_empty() {
+                return Ok(self);
+            }
+
+            check_copy_buffer_image_regions(self.device(),
+                                            &source,
+                                            &destination,
+                                            CheckCopyBufferImageTy::BufferToImage,
+                                            &regions)?;
+
+            let copies = buffer_image_copies::<Px>(regions);
+            self.inner
+                .copy_buffer_to_image(source, destination, destination_layout,
+                                      copies.into_iter())?;
+            Ok(self)
+        }
+    }
+
+    /// Adds a command that copies from an image to a buffer.
+    pub fn copy_image_to_buffer<S, D, Px>(self, source: S, destination: D)
+                                          -> Result<Self, CopyBufferImageError>
+        where S: ImageAccess + Send + Sync + 'static,
+              D: TypedBufferAccess<Content = [Px]> + Send + Sync + 'static,
+              Format: AcceptsPixels<Px>
+    {
+        self.ensure_outside_render_pass()?;
+
+        let dims = source.dimensions().width_height_depth();
+        self.copy_image_to_buffer_dimensions(source, destination, [0, 0, 0], dims, 0, 1, 0)
+    }
+
+    /// Adds a command that copies from an image to a buffer.
+    #[inline]
+    pub fn copy_image_to_buffer_dimensions<S, D, Px>(self, source: S, destination: D,
+                                                     offset: [u32; 3], size: [u32; 3],
                                                      first_layer: u32, num_layers: u32, mipmap: u32)
                                                      -> Result<Self, CopyBufferImageError>
         where S: ImageAccess + Send + Sync + 'static,
               D: TypedBufferAccess<Content = [Px]> + Send + Sync + 'static,
               Format: AcceptsPixels<Px>
+    {
+        self.copy_image_to_buffer_dimensions_with_layout(source,
+                                                         ImageLayout::TransferSrcOptimal,
+                                                         destination,
+                                                         offset,
+                                                         size,
+                                                         first_layer,
+                                                         num_layers,
+                                                         mipmap)
+    }
+
+    /// Same as `copy_image_to_buffer_dimensions`, but lets you choose the layout that the source
+    /// is in during the copy.
+    ///
+    /// The source can be in the `TransferSrcOptimal` or `General` layout, or in the
+    /// `SharedPresent` layout if the `khr_shared_presentable_image` extension is enabled.
+    pub fn copy_image_to_buffer_dimensions_with_layout<S, D, Px>(
+        mut self, source: S, source_layout: ImageLayout, destination: D, offset: [u32; 3],
+        size: [u32; 3], first_layer: u32, num_layers: u32, mipmap: u32)
+        -> Result<Self, CopyBufferImageError>
+        where S: ImageAccess + Send + Sync + 'static,
+              D: TypedBufferAccess<Content = [Px]> + Send + Sync + 'static,
+              Format: AcceptsPixels<Px>
     {
         unsafe {
             self.ensure_outside_render_pass()?;
 
+            check_transfer_image_layout(self.device(),
+                                        source_layout,
+                                        CheckTransferImageLayoutTy::Source)?;
+
             check_copy_buffer_image(self.device(),
                                     &destination,
                                     &source,
@@ -972,10 +1710,104 @@ impl<P> AutoCommandBufferBuilder<P> {
             };
 
             self.inner
-                .copy_image_to_buffer(source,
-                                      ImageLayout::TransferSrcOptimal,
-                                      destination, // TODO: let choose layout
-                                      iter::once(copy))?;
+                .copy_image_to_buffer(source, source_layout, destination, iter::once(copy))?;
+            Ok(self)
+        }
+    }
+
+    /// Adds a command that copies a list of regions of an image to a buffer.
+    ///
+    /// Each region chooses which aspect of the image it reads, and regions can read different
+    /// mipmap levels and array layers in a single command. This can be used for example to read
+    /// back only the stencil aspect of a depth-stencil image. The offsets and lengths in the
+    /// buffer are expressed in number of elements of the buffer, and the type of the elements
+    /// must match the aspect. See `BufferImageCopy`.
+    ///
+    /// Does nothing if `regions` is empty.
+    ///
+    /// The source is used in the `TransferSrcOptimal` layout. Use
+    /// `copy_image_to_buffer_regions_with_layout` to choose another layout.
+    #[inline]
+    pub fn copy_image_to_buffer_regions<S, D, Px, R>(self, source: S, destination: D, regions: R)
+                                                     -> Result<Self, CopyBufferImageError>
+        where S: ImageAccess + Send + Sync + 'static,
+              D: TypedBufferAccess<Content = [Px]> + Send + Sync + 'static,
+              R: IntoIterator<Item = BufferImageCopy>
+    {
+        self.copy_image_to_buffer_regions_with_layout(source,
+                                                      ImageLayout::TransferSrcOptimal,
+                                                      destination,
+                                                      regions)
+    }
+
+    /// Same as `copy_image_to_buffer_regions`, but lets you choose the layout that the source
+    /// is in during the copy.
+    ///
+    /// See `copy_image_to_buffer_dimensions_with_layout` for the layouts that are allowed.
+    pub fn copy_image_to_buffer_regions_with_layout<S, D, Px, R>(
+        mut self, source: S, source_layout: ImageLayout, destination: D, regions: R)
+        -> Result<Self, CopyBufferImageError>
+        where S: ImageAccess + Send + Sync + 'static,
+              D: TypedBufferAccess<Content = [Px]> + Send + Sync + 'static,
+              R: IntoIterator<Item = BufferImageCopy>
+    {
+        unsafe {
+            self.ensure_outside_render_pass()?;
+
+            check_transfer_image_layout(self.device(),
+                                        source_layout,
+                                        CheckTransferImageLayoutTy::Source)?;
+
+            let regions: Vec<BufferImageCopy> = regions.into_iter().collect();
+            if regions.is_empty() {
+                return Ok(self);
+            }
+
+            check_copy_buffer_image_regions(self.device(),
+                                            &destination,
+                                            &source,
+                                            CheckCopyBufferImageTy::ImageToBuffer,
+                                            &regions)?;
+
+            let copies = buffer_image_copies::<Px>(regions);
+            self.inner
+                .copy_image_to_buffer(source, source_layout, destination, copies.into_iter())?;
+            Ok(self)
+        }
+    }
+
+    /// Adds a command that copies the results of a range of queries to a buffer.
+    ///
+    /// The results are tightly packed in the buffer, and are written as 64 bits values if the
+    /// content of the buffer is `[u64]`, or as 32 bits values if it is `[u32]`. If
+    /// `flags.with_availability` is true, an additional value is written after the result of each
+    /// query.
+    #[inline]
+    pub fn copy_query_pool_results<Q, D, T>(mut self, query_pool: Q, first_query: u32,
+                                            query_count: u32, destination: D,
+                                            flags: QueryResultFlags)
+                                            -> Result<Self, CopyQueryPoolResultsError>
+        where Q: QueryPoolAbstract + Send + Sync + 'static,
+              D: TypedBufferAccess<Content = [T]> + Send + Sync + 'static,
+              T: QueryResultElement
+    {
+        unsafe {
+            self.ensure_outside_render_pass()?;
+            let stride = check_copy_query_pool_results(self.device(),
+                                                       &query_pool,
+                                                       first_query,
+                                                       query_count,
+                                                       &destination,
+                                                       flags)?
+                .stride;
+            self.ensure_queries_inactive(&query_pool, first_query, query_count)?;
+
+            self.inner.copy_query_pool_results(query_pool,
+                                               first_query,
+                                               query_count,
+                                               destination,
+                                               stride,
+                                               flags)?;
             Ok(self)
         }
     }
@@ -994,6 +1826,8 @@ impl<P> AutoCommandBufferBuilder<P> {
             self.ensure_outside_render_pass()?;
             check_push_constants_validity(&pipeline, &constants)?;
             check_descriptor_sets_validity(&pipeline, &sets)?;
+            let sets = sets.into_vec();
+            check_dynamic_offsets(self.device(), &sets)?;
             check_dispatch(pipeline.device(), dimensions)?;
 
             if let StateCacherOutcome::NeedChange =
@@ -1014,6 +1848,53 @@ impl<P> AutoCommandBufferBuilder<P> {
         }
     }
 
+    /// Performs a dispatch whose dimensions are read from `indirect_buffer` when the command is
+    /// executed.
+    ///
+    /// Only the first element of `indirect_buffer` is used. To use another element, wrap the
+    /// buffer in a `vulkano::buffer::BufferSlice`.
+    #[inline]
+    pub fn dispatch_indirect<Cp, S, Pc, Inb>(mut self, indirect_buffer: Inb, pipeline: Cp,
+                                             sets: S, constants: Pc)
+                                             -> Result<Self, DispatchIndirectError>
+        where Cp: ComputePipelineAbstract + Send + Sync + 'static + Clone, // TODO: meh for Clone
+              S: DescriptorSetsCollection,
+              Inb: BufferAccess
+                       + TypedBufferAccess<Content = [DispatchIndirectCommand]>
+                       + Send
+                       + Sync
+                       + 'static
+    {
+        unsafe {
+            if !self.compute_allowed {
+                return Err(AutoCommandBufferBuilderContextError::NotSupportedByQueueFamily.into());
+            }
+
+            self.ensure_outside_render_pass()?;
+            check_push_constants_validity(&pipeline, &constants)?;
+            check_descriptor_sets_validity(&pipeline, &sets)?;
+            let sets = sets.into_vec();
+            check_dynamic_offsets(self.device(), &sets)?;
+            check_indirect_buffer(self.device(), &indirect_buffer)?;
+
+            if let StateCacherOutcome::NeedChange =
+                self.state_cacher.bind_compute_pipeline(&pipeline)
+            {
+                self.inner.bind_pipeline_compute(pipeline.clone());
+            }
+
+            push_constants(&mut self.inner, pipeline.clone(), constants);
+            descriptor_sets(&mut self.inner,
+                            &mut self.state_cacher,
+                            false,
+                            pipeline.clone(),
+                            sets)?;
+
+            self.inner.dispatch_indirect(indirect_buffer)?;
+            Ok(self)
+        }
+    }
+
     /// Draw once, using the `vertex_buffer`.
     ///
     /// To use only some data in the buffer, wrap it in a `vulkano::buffer::BufferSlice`.
@@ -1025,12 +1906,12 @@ impl<P> AutoCommandBufferBuilder<P> {
               S: DescriptorSetsCollection
     {
         unsafe {
-            // TODO: must check that pipeline is compatible with render pass
-
             self.ensure_inside_render_pass_inline(&pipeline)?;
             check_dynamic_state_validity(&pipeline, dynamic)?;
             check_push_constants_validity(&pipeline, &constants)?;
             check_descriptor_sets_validity(&pipeline, &sets)?;
+            let sets = sets.into_vec();
+            check_dynamic_offsets(self.device(), &sets)?;
             let vb_infos = check_vertex_buffers(&pipeline, vertex_buffer)?;
 
             if let StateCacherOutcome::NeedChange =
@@ -1065,8 +1946,12 @@ impl<P> AutoCommandBufferBuilder<P> {
     /// Draw once, using the `vertex_buffer` and the `index_buffer`.
     ///
     /// To use only some data in a buffer, wrap it in a `vulkano::buffer::BufferSlice`.
+    ///
+    /// The values of the indices aren't checked, and an index that is out of range of the
+    /// vertex buffers results in undefined behavior on the GPU. Use `draw_indexed_checked` in
+    /// order to check them if the content of the index buffer is known.
     #[inline]
-    pub fn draw_indexed<V, Gp, S, Pc, Ib, I>(mut self, pipeline: Gp, dynamic: &DynamicState,
+    pub fn draw_indexed<V, Gp, S, Pc, Ib, I>(self, pipeline: Gp, dynamic: &DynamicState,
                                              vertex_buffer: V, index_buffer: Ib, sets: S, constants: Pc)
                                              -> Result<Self, DrawIndexedError>
         where Gp: GraphicsPipelineAbstract + VertexSource<V> + Send + Sync + 'static + Clone, // TODO: meh for Clone
@@ -1074,15 +1959,75 @@ impl<P> AutoCommandBufferBuilder<P> {
               Ib: BufferAccess + TypedBufferAccess<Content = [I]> + Send + Sync + 'static,
               I: Index + 'static
     {
-        unsafe {
-            // TODO: must check that pipeline is compatible with render pass
+        self.draw_indexed_impl(pipeline, dynamic, vertex_buffer, index_buffer, sets, constants,
+                               None)
+    }
+
+    /// Same as `draw_indexed`, but also checks that every index is within range of the vertex
+    /// buffers and doesn't exceed the largest index value supported by the device.
+    ///
+    /// This requires the content of the index buffer to be known by the CPU, which is the case
+    /// for an `ImmutableBuffer` of `u16` or `u32` created with `from_iter`, an `ImmutableBuffer`
+    /// created with `from_indices`, or a `CpuAccessibleBuffer`. The indices are only checked
+    /// against the vertex buffers whose input rate is per-vertex, if there is any.
+    ///
+    /// The content of a `CpuAccessibleBuffer` is read when this function is called. Writes made
+    /// from the CPU afterwards, before the command buffer is executed, aren't checked.
+    #[inline]
+    pub fn draw_indexed_checked<V, Gp, S, Pc, Ib, I>(self, pipeline: Gp, dynamic: &DynamicState,
+                                                     vertex_buffer: V, index_buffer: Ib, sets: S,
+                                                     constants: Pc)
+                                                     -> Result<Self, DrawIndexedError>
+        where Gp: GraphicsPipelineAbstract + VertexSource<V> + Send + Sync + 'static + Clone, // TODO: meh for Clone
+              S: DescriptorSetsCollection,
+              Ib: BufferAccess
+                      + TypedBufferAccess<Content = [I]>
+                      + KnownIndices
+                      + Send
+                      + Sync
+                      + 'static,
+              I: Index + 'static
+    {
+        let index_bounds = match index_buffer.index_bounds() {
+            Some(bounds) => bounds,
+            None => return Err(CheckIndexRangeError::UnknownIndices.into()),
+        };
 
+        self.draw_indexed_impl(pipeline, dynamic, vertex_buffer, index_buffer, sets, constants,
+                               Some(index_bounds))
+    }
+
+    // Implementation of `draw_indexed` and `draw_indexed_checked`. The indices are checked
+    // against the vertex buffers if `index_bounds` is `Some`.
+    fn draw_indexed_impl<V, Gp, S, Pc, Ib, I>(mut self, pipeline: Gp, dynamic: &DynamicState,
+                                              vertex_buffer: V, index_buffer: Ib, sets: S,
+                                              constants: Pc, index_bounds: Option<IndexBounds>)
+                                              -> Result<Self, DrawIndexedError>
+        where Gp: GraphicsPipelineAbstract + VertexSource<V> + Send + Sync + 'static + Clone,
+              S: DescriptorSetsCollection,
+              Ib: BufferAccess + TypedBufferAccess<Content = [I]> + Send + Sync + 'static,
+              I: Index + 'static
+    {
+        unsafe {
             self.ensure_inside_render_pass_inline(&pipeline)?;
             let ib_infos = check_index_buffer(self.device(), &index_buffer)?;
             check_dynamic_state_validity(&pipeline, dynamic)?;
             check_push_constants_validity(&pipeline, &constants)?;
             check_descriptor_sets_validity(&pipeline, &sets)?;
+            let sets = sets.into_vec();
+            check_dynamic_offsets(self.device(), &sets)?;
             let vb_infos = check_vertex_buffers(&pipeline, vertex_buffer)?;
+            if let Some(ref index_bounds) = index_bounds {
+                let vertex_count = if pipeline.has_per_vertex_input() {
+                    Some(vb_infos.vertex_count)
+                } else {
+                    None
+                };
+                check_index_range(self.device(),
+                                  index_bounds,
+                                  pipeline.has_primitive_restart(),
+                                  vertex_count)?;
+            }
 
             if let StateCacherOutcome::NeedChange =
                 self.state_cacher.bind_graphics_pipeline(&pipeline)
@@ -1108,7 +2053,6 @@ impl<P> AutoCommandBufferBuilder<P> {
             vertex_buffers(&mut self.inner,
                            &mut self.state_cacher,
                            vb_infos.vertex_buffers)?;
-            // TODO: how to handle an index out of range of the vertex buffers?
 
             debug_assert!(self.graphics_allowed);
 
@@ -1139,12 +2083,12 @@ impl<P> AutoCommandBufferBuilder<P> {
                       + 'static
     {
         unsafe {
-            // TODO: must check that pipeline is compatible with render pass
-
             self.ensure_inside_render_pass_inline(&pipeline)?;
             check_dynamic_state_validity(&pipeline, dynamic)?;
             check_push_constants_validity(&pipeline, &constants)?;
             check_descriptor_sets_validity(&pipeline, &sets)?;
+            let sets = sets.into_vec();
+            check_dynamic_offsets(self.device(), &sets)?;
             let vb_infos = check_vertex_buffers(&pipeline, vertex_buffer)?;
 
             let draw_count = indirect_buffer.len() as u32;
@@ -1197,13 +2141,13 @@ impl<P> AutoCommandBufferBuilder<P> {
               I: Index + 'static
     {
         unsafe {
-            // TODO: must check that pipeline is compatible with render pass
-
             self.ensure_inside_render_pass_inline(&pipeline)?;
             let ib_infos = check_index_buffer(self.device(), &index_buffer)?;
             check_dynamic_state_validity(&pipeline, dynamic)?;
             check_push_constants_validity(&pipeline, &constants)?;
             check_descriptor_sets_validity(&pipeline, &sets)?;
+            let sets = sets.into_vec();
+            check_dynamic_offsets(self.device(), &sets)?;
             let vb_infos = check_vertex_buffers(&pipeline, vertex_buffer)?;
 
             let draw_count = indirect_buffer.len() as u32;
@@ -1267,32 +2211,147 @@ impl<P> AutoCommandBufferBuilder<P> {
                 },
             }
 
+            self.ensure_no_query_active_in_subpass()?;
+
             debug_assert!(self.graphics_allowed);
 
             self.inner.end_render_pass();
             self.render_pass = None;
+            self.framebuffer_dimensions = None;
             Ok(self)
         }
     }
 
-    /// Adds a command that executes a secondary command buffer.
+    /// Adds a command that ends a query that was previously begun with `begin_query`.
     ///
-    /// **This function is unsafe for now because safety checks and synchronization are not
-    /// implemented.**
-    // TODO: implement correctly
-    pub unsafe fn execute_commands<C>(mut self, command_buffer: C)
-                                      -> Result<Self, ExecuteCommandsError>
-        where C: CommandBuffer + Send + Sync + 'static
+    /// If the query was begun inside of a render pass, it must be ended in the same subpass.
+    /// Otherwise it must be ended outside of a render pass.
+    #[inline]
+    pub fn end_query<Q>(mut self, query_pool: Q, query: u32)
+                        -> Result<Self, AutoCommandBufferBuilderContextError>
+        where Q: QueryPoolAbstract + Send + Sync + 'static
     {
-        {
-            let mut builder = self.inner.execute_commands();
-            builder.add(command_buffer);
-            builder.submit()?;
+        unsafe {
+            let pool = query_pool.inner().internal_object();
+            let position = match self.active_queries
+                .iter()
+                .position(|&(p, i, _, _, _)| p == pool && i == query) {
+                Some(p) => p,
+                None => return Err(AutoCommandBufferBuilderContextError::QueryNotActive),
+            };
+
+            let subpass = self.render_pass.as_ref().map(|&(_, index)| index);
+            if self.active_queries[position].3 != subpass {
+                return Err(AutoCommandBufferBuilderContextError::WrongQuerySubpass);
+            }
+
+            self.inner.end_query(query_pool, query);
+            self.active_queries.remove(position);
+            Ok(self)
         }
+    }
 
-        self.state_cacher.invalidate();
+    /// Adds a command that executes a secondary command buffer.
+    ///
+    /// If called inside of a render pass, the subpass must have been entered with `secondary` set
+    /// to true, and the secondary command buffer must continue the current subpass of a render
+    /// pass compatible with the current one. If called outside of a render pass, the secondary
+    /// command buffer must not continue a render pass.
+    ///
+    /// If queries are active, the secondary command buffer must have been created with the flags
+    /// that allow it to inherit them.
+    ///
+    /// A secondary command buffer created with the "one time submit" flag can only be executed
+    /// once. A secondary command buffer created without the "simultaneous use" flag can only be
+    /// recorded in one command buffer at a time, and prevents this command buffer from being
+    /// executed multiple times simultaneously.
+    #[inline]
+    pub fn execute_commands<C>(self, command_buffer: C) -> Result<Self, ExecuteCommandsError>
+        where C: CommandBuffer + SecondaryCommandBuffer + Send + Sync + 'static
+    {
+        self.execute_commands_multiple(iter::once(command_buffer))
+    }
 
-        Ok(self)
+    /// Adds a command that executes multiple secondary command buffers, in the order in which
+    /// they are produced by the iterator.
+    ///
+    /// The same requirements as `execute_commands` apply to each command buffer. Executing them
+    /// with a single command is cheaper than calling `execute_commands` for each of them, but no
+    /// pipeline barrier can be inserted between them. Therefore they must not access the same
+    /// resource if one of them writes it.
+    ///
+    /// Does nothing if the iterator is empty.
+    pub fn execute_commands_multiple<I, C>(mut self, command_buffers: I)
+                                           -> Result<Self, ExecuteCommandsError>
+        where I: IntoIterator<Item = C>,
+              C: CommandBuffer + SecondaryCommandBuffer + Send + Sync + 'static
+    {
+        unsafe {
+            if self.secondary_cb {
+                return Err(AutoCommandBufferBuilderContextError::ForbiddenInSecondary.into());
+            }
+
+            let command_buffers: Vec<C> = command_buffers.into_iter().collect();
+            if command_buffers.is_empty() {
+                return Ok(self);
+            }
+
+            for command_buffer in &command_buffers {
+                match (self.render_pass.as_ref(), command_buffer.render_pass()) {
+                    (Some(&(ref render_pass, subpass)), Some((cb_render_pass, cb_subpass))) => {
+                        self.ensure_inside_render_pass_secondary()?;
+
+                        if cb_subpass != subpass {
+                            return Err(AutoCommandBufferBuilderContextError::WrongSubpassIndex
+                                           .into());
+                        }
+
+                        if !RenderPassCompatible::is_compatible_with(cb_render_pass, render_pass) {
+                            return Err(AutoCommandBufferBuilderContextError::IncompatibleRenderPass
+                                           .into());
+                        }
+                    },
+                    (Some(_), None) => {
+                        return Err(AutoCommandBufferBuilderContextError::ForbiddenInsideRenderPass
+                                       .into());
+                    },
+                    (None, Some(_)) => {
+                        return Err(AutoCommandBufferBuilderContextError::ForbiddenOutsideRenderPass
+                                       .into());
+                    },
+                    (None, None) => (),
+                }
+
+                self.ensure_queries_inherited(command_buffer)?;
+            }
+
+            for (num, command_buffer) in command_buffers.iter().enumerate() {
+                if let Err(err) = command_buffer.lock_record() {
+                    for command_buffer in &command_buffers[.. num] {
+                        command_buffer.unlock_record();
+                    }
+                    return Err(err.into());
+                }
+            }
+
+            if command_buffers.iter().any(|cb| !cb.simultaneous_use()) &&
+                self.flags == Flags::SimultaneousUse
+            {
+                self.flags = Flags::None;
+            }
+
+            {
+                let mut builder = self.inner.execute_commands();
+                for command_buffer in command_buffers {
+                    builder.add(command_buffer);
+                }
+                builder.submit()?;
+            }
+
+            self.state_cacher.invalidate();
+
+            Ok(self)
+        }
     }
 
     /// Adds a command that writes the content of a buffer.
@@ -1326,6 +2385,8 @@ impl<P> AutoCommandBufferBuilder<P> {
                 return Err(AutoCommandBufferBuilderContextError::ForbiddenInSecondary);
             }
 
+            self.ensure_no_query_active_in_subpass()?;
+
             match self.render_pass {
                 None => {
                     return Err(AutoCommandBufferBuilderContextError::ForbiddenOutsideRenderPass);
@@ -1356,68 +2417,525 @@ impl<P> AutoCommandBufferBuilder<P> {
         }
     }
 
-    /// Adds a command that writes data to a buffer.
+    /// Adds a command that releases the ownership of a buffer to another queue family.
     ///
-    /// If `data` is larger than the buffer, only the part of `data` that fits is written. If the
-    /// buffer is larger than `data`, only the start of the buffer is written.
-    // TODO: allow unsized values
+    /// After the command buffer has been executed, the buffer can no longer be used by the queue
+    /// family of this command buffer. A command buffer of `destination_family` must acquire it
+    /// before using it, which `then_execute` does automatically when it is executed after this
+    /// command buffer.
     #[inline]
-    pub fn update_buffer<B, D>(mut self, buffer: B, data: D) -> Result<Self, UpdateBufferError>
-        where B: TypedBufferAccess<Content = D> + Send + Sync + 'static,
-              D: Send + Sync + 'static
+    pub fn release_buffer_ownership<B>(mut self, buffer: B, destination_family: QueueFamily)
+                                       -> Result<Self, OwnershipTransferError>
+        where B: BufferAccess + Send + Sync + 'static
     {
         unsafe {
-            self.ensure_outside_render_pass()?;
-            check_update_buffer(self.device(), &buffer, &data)?;
-
-            let size_of_data = mem::size_of_val(&data);
-            if buffer.size() >= size_of_data {
-                self.inner.update_buffer(buffer, data);
-            } else {
-                unimplemented!() // TODO:
-                //self.inner.update_buffer(buffer.slice(0 .. size_of_data), data);
-            }
-
+            let source_family = self.queue_family;
+            self.ensure_ownership_transfer(destination_family, true)?;
+            self.inner
+                .release_buffer_ownership(buffer, source_family, destination_family.id())?;
             Ok(self)
         }
     }
-}
 
-unsafe impl<P> DeviceOwned for AutoCommandBufferBuilder<P> {
+    /// Adds a command that releases the ownership of an image to another queue family.
+    ///
+    /// The image is transitioned to its final layout requirement before being released. After
+    /// the command buffer has been executed, the image can no longer be used by the queue family
+    /// of this command buffer. A command buffer of `destination_family` must acquire it before
+    /// using it, which `then_execute` does automatically when it is executed after this command
+    /// buffer.
     #[inline]
-    fn device(&self) -> &Arc<Device> {
-        self.inner.device()
+    pub fn release_image_ownership<I>(mut self, image: I, destination_family: QueueFamily)
+                                      -> Result<Self, OwnershipTransferError>
+        where I: ImageAccess + Send + Sync + 'static
+    {
+        unsafe {
+            let source_family = self.queue_family;
+            self.ensure_ownership_transfer(destination_family, true)?;
+            let layout = image.final_layout_requirement();
+            self.inner.release_image_ownership(image,
+                                               layout,
+                                               source_family,
+                                               destination_family.id())?;
+            Ok(self)
+        }
     }
-}
 
-// Shortcut function to set the push constants.
-unsafe fn push_constants<P, Pl, Pc>(destination: &mut SyncCommandBufferBuilder<P>, pipeline: Pl,
-                                    push_constants: Pc)
-    where Pl: PipelineLayoutAbstract + Send + Sync + Clone + 'static
-{
-    for num_range in 0 .. pipeline.num_push_constants_ranges() {
-        let range = match pipeline.push_constants_range(num_range) {
-            Some(r) => r,
-            None => continue,
-        };
+    /// Adds a command that resets an event to the unsignaled state, once all previous commands
+    /// have reached `stages`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the event was not created with the same device as this command buffer.
+    ///
+    #[inline]
+    pub fn reset_event(mut self, event: Arc<Event>, stages: PipelineStages)
+                       -> Result<Self, ResetEventError> {
+        unsafe {
+            if !self.graphics_allowed && !self.compute_allowed {
+                return Err(AutoCommandBufferBuilderContextError::NotSupportedByQueueFamily.into());
+            }
 
-        debug_assert_eq!(range.offset % 4, 0);
-        debug_assert_eq!(range.size % 4, 0);
+            self.ensure_outside_render_pass()?;
+            check_event_command(self.device(), &event, stages)?;
+            self.inner.reset_event(event, stages);
+            Ok(self)
+        }
+    }
 
-        let data = slice::from_raw_parts((&push_constants as *const Pc as *const u8)
-                                             .offset(range.offset as isize),
-                                         range.size as usize);
+    /// Adds a command that resets a range of queries of a query pool.
+    ///
+    /// Queries must be reset before they can be begun or written to.
+    #[inline]
+    pub fn reset_query_pool<Q>(mut self, query_pool: Q, first_query: u32, query_count: u32)
+                               -> Result<Self, ResetQueryPoolError>
+        where Q: QueryPoolAbstract + Send + Sync + 'static
+    {
+        unsafe {
+            self.ensure_outside_render_pass()?;
+            check_reset_query_pool(self.device(), &query_pool, first_query, query_count)?;
+            self.ensure_queries_inactive(&query_pool, first_query, query_count)?;
 
-        destination.push_constants::<_, [u8]>(pipeline.clone(),
-                                              range.stages,
-                                              range.offset as u32,
-                                              range.size as u32,
-                                              data);
+            let pool = query_pool.inner().internal_object();
+            self.inner.reset_query_pool(query_pool, first_query, query_count);
+            self.reset_queries
+                .push((pool, first_query .. first_query + query_count));
+            Ok(self)
+        }
     }
-}
 
-// Shortcut function to change the state of the pipeline.
-unsafe fn set_state<P>(destination: &mut SyncCommandBufferBuilder<P>, dynamic: &DynamicState) {
+    /// Adds a command that resolves a multisampled image into a single-sampled image.
+    ///
+    /// The samples of each pixel of the source are combined into one value, which is written to
+    /// the destination. Both images must have the same color format, the source must be
+    /// multisampled and the destination must not be.
+    ///
+    /// If `layer_count` is greater than 1, the resolve will happen between each individual layer
+    /// as if they were separate images.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the source or the destination was not created with `device`.
+    ///
+    pub fn resolve_image<S, D>(mut self, source: S, source_offset: [i32; 3],
+                               source_base_array_layer: u32, source_mip_level: u32,
+                               destination: D, destination_offset: [i32; 3],
+                               destination_base_array_layer: u32, destination_mip_level: u32,
+                               extent: [u32; 3], layer_count: u32)
+                               -> Result<Self, ResolveImageError>
+        where S: ImageAccess + Send + Sync + 'static,
+              D: ImageAccess + Send + Sync + 'static
+    {
+        unsafe {
+            if !self.graphics_allowed {
+                return Err(AutoCommandBufferBuilderContextError::NotSupportedByQueueFamily.into());
+            }
+
+            self.ensure_outside_render_pass()?;
+
+            check_resolve_image(self.device(),
+                                &source,
+                                source_offset,
+                                source_base_array_layer,
+                                source_mip_level,
+                                &destination,
+                                destination_offset,
+                                destination_base_array_layer,
+                                destination_mip_level,
+                                extent,
+                                layer_count)?;
+
+            let resolve = UnsafeCommandBufferBuilderImageResolve {
+                source_mip_level,
+                destination_mip_level,
+                source_base_array_layer,
+                destination_base_array_layer,
+                layer_count,
+                source_offset,
+                destination_offset,
+                extent,
+            };
+
+            self.inner
+                .resolve_image(source, ImageLayout::TransferSrcOptimal,
+                               destination, ImageLayout::TransferDstOptimal,
+                               iter::once(resolve))?;
+            Ok(self)
+        }
+    }
+
+    /// Adds a command that sets an event to the signaled state, once all previous commands have
+    /// reached `stages`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the event was not created with the same device as this command buffer.
+    ///
+    #[inline]
+    pub fn set_event(mut self, event: Arc<Event>, stages: PipelineStages)
+                     -> Result<Self, SetEventError> {
+        unsafe {
+            if !self.graphics_allowed && !self.compute_allowed {
+                return Err(AutoCommandBufferBuilderContextError::NotSupportedByQueueFamily.into());
+            }
+
+            self.ensure_outside_render_pass()?;
+            check_event_command(self.device(), &event, stages)?;
+            self.inner.set_event(event, stages);
+            Ok(self)
+        }
+    }
+
+    /// Adds a command that sets an event to the signaled state, once all previous commands have
+    /// reached `stages`, and uses it as the first half of a split barrier.
+    ///
+    /// When a later command needs a barrier that only waits upon commands recorded before this
+    /// one, the builder waits on `event` instead of adding a full pipeline barrier. This lets the
+    /// commands recorded in-between overlap with the previous ones. The event is waited upon at
+    /// most once.
+    ///
+    /// # Safety
+    ///
+    /// The event must not be set, reset or waited upon anywhere else until this command buffer
+    /// has finished executing, and it must be reset before this command buffer is submitted
+    /// again.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the event was not created with the same device as this command buffer.
+    ///
+    #[inline]
+    pub unsafe fn signal_split_barrier(mut self, event: Arc<Event>, stages: PipelineStages)
+                                       -> Result<Self, SetEventError> {
+        if !self.graphics_allowed && !self.compute_allowed {
+            return Err(AutoCommandBufferBuilderContextError::NotSupportedByQueueFamily.into());
+        }
+
+        self.ensure_outside_render_pass()?;
+        check_event_command(self.device(), &event, stages)?;
+        self.inner.signal_split_barrier(event, stages);
+        Ok(self)
+    }
+
+    /// Adds a command that writes data to a buffer.
+    ///
+    /// If `data` is larger than the buffer, only the part of `data` that fits is written. If the
+    /// buffer is larger than `data`, only the start of the buffer is written.
+    ///
+    /// The data must not be larger than 65536 bytes, and its size must be a multiple of 4. Use
+    /// `update_buffer_slice` to write an array or to write at an offset within the buffer.
+    #[inline]
+    pub fn update_buffer<B, D>(mut self, buffer: B, data: D) -> Result<Self, UpdateBufferError>
+        where B: TypedBufferAccess<Content = D> + Send + Sync + 'static,
+              D: Send + Sync + 'static
+    {
+        unsafe {
+            self.ensure_outside_render_pass()?;
+            check_update_buffer(self.device(), &buffer, &data)?;
+
+            let size_of_data = mem::size_of_val(&data);
+            if buffer.size() > size_of_data {
+                let buffer = BufferSlice::from_typed_buffer_access(buffer)
+                    .reinterpret::<[u8]>()
+                    .slice(0 .. size_of_data)
+                    .unwrap();
+                self.inner.update_buffer(buffer, Box::new(data));
+            } else {
+                self.inner.update_buffer(buffer, Box::new(data));
+            }
+
+            Ok(self)
+        }
+    }
+
+    /// Adds a command that writes the elements of `data` to an array buffer, starting at the
+    /// element at index `first_element`.
+    ///
+    /// Since `buffer` can itself be a `BufferSlice`, this can be used to update a small range of
+    /// a large buffer. The data can be anything that derefs to a slice and can be kept alive
+    /// until the command buffer is built, such as a `Box<[T]>` or an `Arc<[T]>`.
+    ///
+    /// The data must not be larger than 65536 bytes, and both its size and its offset within the
+    /// buffer must be multiples of 4.
+    pub fn update_buffer_slice<B, T, Dd>(mut self, buffer: B, first_element: usize, data: Dd)
+                                         -> Result<Self, UpdateBufferError>
+        where B: TypedBufferAccess<Content = [T]> + Send + Sync + 'static,
+              T: Send + Sync + 'static,
+              Dd: SafeDeref<Target = [T]> + Send + Sync + 'static
+    {
+        unsafe {
+            self.ensure_outside_render_pass()?;
+            check_update_buffer_slice(self.device(), &buffer, first_element, &*data)?;
+
+            let buffer = BufferSlice::from_typed_buffer_access(buffer)
+                .slice(first_element .. first_element + data.len())
+                .unwrap();
+            self.inner.update_buffer(buffer, data);
+
+            Ok(self)
+        }
+    }
+
+    /// Adds a command that waits for all the events of `events` to be signaled, then makes the
+    /// memory accesses of `source_access` in `source_stages` available to the accesses of
+    /// `destination_access` in `destination_stages`.
+    ///
+    /// The automatic synchronization of the builder is not affected by this command: any barrier
+    /// that is needed by the resources used in the command buffer is still added.
+    ///
+    /// # Safety
+    ///
+    /// Each event must be signaled, either by a previous `set_event` command or from the host,
+    /// before the command executes. Waiting for an event that is never signaled stalls the queue.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if one of the events was not created with the same device as this command
+    ///   buffer.
+    ///
+    pub unsafe fn wait_events<I>(mut self, events: I, source_stages: PipelineStages,
+                                 source_access: AccessFlagBits,
+                                 destination_stages: PipelineStages,
+                                 destination_access: AccessFlagBits)
+                                 -> Result<Self, WaitEventsError>
+        where I: IntoIterator<Item = Arc<Event>>
+    {
+        if !self.graphics_allowed && !self.compute_allowed {
+            return Err(AutoCommandBufferBuilderContextError::NotSupportedByQueueFamily.into());
+        }
+
+        self.ensure_outside_render_pass()?;
+
+        let events = events.into_iter().collect::<SmallVec<[_; 4]>>();
+        check_wait_events(self.device(),
+                          events.iter().map(|e| &**e),
+                          source_stages,
+                          source_access,
+                          destination_stages,
+                          destination_access)?;
+
+        self.inner.wait_events(events,
+                               source_stages,
+                               source_access,
+                               destination_stages,
+                               destination_access);
+        Ok(self)
+    }
+
+    /// Adds a command that writes a timestamp to a query of a timestamp query pool, once all
+    /// previous commands have reached `stage`.
+    ///
+    /// `stage` must contain exactly one pipeline stage.
+    ///
+    /// The query must have been reset with `reset_query_pool` earlier in this command buffer, and
+    /// must not have been written to since then. Use `write_timestamp_assume_reset` if the query
+    /// was reset somewhere else, for example by a command buffer submitted earlier.
+    #[inline]
+    pub fn write_timestamp<Q>(self, query_pool: Q, query: u32, stage: PipelineStages)
+                              -> Result<Self, WriteTimestampError>
+        where Q: QueryPoolAbstract + Send + Sync + 'static
+    {
+        self.write_timestamp_impl(query_pool, query, stage, false)
+    }
+
+    /// Same as `write_timestamp`, except that the query doesn't need to have been reset earlier
+    /// in this command buffer.
+    ///
+    /// # Safety
+    ///
+    /// - The query must have been reset before this command is executed, for example by a
+    ///   command buffer submitted earlier, and must not have been written to since then.
+    ///
+    #[inline]
+    pub unsafe fn write_timestamp_assume_reset<Q>(self, query_pool: Q, query: u32,
+                                                  stage: PipelineStages)
+                                                  -> Result<Self, WriteTimestampError>
+        where Q: QueryPoolAbstract + Send + Sync + 'static
+    {
+        self.write_timestamp_impl(query_pool, query, stage, true)
+    }
+
+    // Implementation of `write_timestamp` and `write_timestamp_assume_reset`.
+    fn write_timestamp_impl<Q>(mut self, query_pool: Q, query: u32, stage: PipelineStages,
+                               assume_reset: bool)
+                               -> Result<Self, WriteTimestampError>
+        where Q: QueryPoolAbstract + Send + Sync + 'static
+    {
+        {
+            let queue_family = self.device()
+                .physical_device()
+                .queue_family_by_id(self.queue_family)
+                .unwrap();
+            check_write_timestamp(self.device(), queue_family, &query_pool, query, stage)?;
+        }
+
+        let pool = query_pool.inner().internal_object();
+        self.use_reset_query(pool, query, assume_reset)?;
+
+        unsafe {
+            self.inner.write_timestamp(query_pool, query, stage);
+        }
+        Ok(self)
+    }
+
+    // Checks whether the ownership of a resource can be transferred between the queue family of
+    // the command buffer and `other_family`. `release` is true if the command buffer releases the
+    // resource, and false if it acquires it.
+    fn ensure_ownership_transfer(&self, other_family: QueueFamily, release: bool)
+                                 -> Result<(), OwnershipTransferError> {
+        // The transfers of a secondary command buffer wouldn't be known by the futures that
+        // execute its primary command buffer.
+        if self.secondary_cb {
+            return Err(AutoCommandBufferBuilderContextError::ForbiddenInSecondary.into());
+        }
+
+        self.ensure_outside_render_pass()?;
+
+        let physical_device = self.device().physical_device();
+        let own_family = physical_device.queue_family_by_id(self.queue_family).unwrap();
+        if release {
+            check_queue_family_ownership_transfer(self.device(), own_family, other_family)?;
+        } else {
+            check_queue_family_ownership_transfer(self.device(), other_family, own_family)?;
+        }
+
+        Ok(())
+    }
+
+    // Returns an error if any of the queries in the range are active.
+    #[inline]
+    fn ensure_queries_inactive<Q>(&self, query_pool: &Q, first_query: u32, query_count: u32)
+                                  -> Result<(), AutoCommandBufferBuilderContextError>
+        where Q: ?Sized + QueryPoolAbstract
+    {
+        let pool = query_pool.inner().internal_object();
+        let range = first_query .. first_query + query_count;
+
+        if self.active_queries
+            .iter()
+            .any(|&(p, i, _, _, _)| p == pool && i >= range.start && i < range.end)
+        {
+            return Err(AutoCommandBufferBuilderContextError::QueryIsActive);
+        }
+
+        Ok(())
+    }
+
+    // Returns an error if the secondary command buffer doesn't inherit one of the active queries.
+    fn ensure_queries_inherited<C>(&self, command_buffer: &C)
+                                   -> Result<(), AutoCommandBufferBuilderContextError>
+        where C: ?Sized + SecondaryCommandBuffer
+    {
+        let (occlusion_query, statistics_flags) = command_buffer.inherited_queries();
+
+        for &(_, _, ref ty, _, precise) in &self.active_queries {
+            let inherited = match *ty {
+                QueryType::Occlusion => {
+                    match occlusion_query {
+                        KindOcclusionQuery::Allowed { control_precise_allowed } => {
+                            control_precise_allowed || !precise
+                        },
+                        KindOcclusionQuery::Forbidden => false,
+                    }
+                },
+                QueryType::PipelineStatistics(flags) => {
+                    let active: vk::QueryPipelineStatisticFlags = flags.into();
+                    let inherited: vk::QueryPipelineStatisticFlags = statistics_flags.into();
+                    active & !inherited == 0
+                },
+                QueryType::Timestamp => true,
+            };
+
+            if !inherited {
+                return Err(AutoCommandBufferBuilderContextError::QueryNotInherited);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Returns an error if a query that was begun in the current subpass is still active.
+    fn ensure_no_query_active_in_subpass(&self)
+                                         -> Result<(), AutoCommandBufferBuilderContextError> {
+        let subpass = self.render_pass.as_ref().map(|&(_, index)| index);
+
+        if subpass.is_some() && self.active_queries.iter().any(|&(_, _, _, s, _)| s == subpass) {
+            return Err(AutoCommandBufferBuilderContextError::QueryIsActive);
+        }
+
+        Ok(())
+    }
+
+    // Marks a query as used, so that it can't be used again before it is reset. Returns an error
+    // if the query hasn't been reset by this command buffer since it was last used, unless
+    // `assume_reset` is true.
+    fn use_reset_query(&mut self, pool: vk::QueryPool, query: u32, assume_reset: bool)
+                       -> Result<(), AutoCommandBufferBuilderContextError> {
+        if !assume_reset &&
+            !self.reset_queries
+                .iter()
+                .any(|&(p, ref r)| p == pool && r.start <= query && query < r.end)
+        {
+            return Err(AutoCommandBufferBuilderContextError::QueryNotReset);
+        }
+
+        // Ranges can overlap if the same queries were reset multiple times, so the query is
+        // removed from all of them.
+        let mut remaining = Vec::with_capacity(self.reset_queries.len() + 1);
+        for (p, range) in self.reset_queries.drain(..) {
+            if p != pool || query < range.start || query >= range.end {
+                remaining.push((p, range));
+                continue;
+            }
+
+            if range.start < query {
+                remaining.push((p, range.start .. query));
+            }
+            if query + 1 < range.end {
+                remaining.push((p, query + 1 .. range.end));
+            }
+        }
+        self.reset_queries = remaining;
+
+        Ok(())
+    }
+}
+
+unsafe impl<P> DeviceOwned for AutoCommandBufferBuilder<P> {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.inner.device()
+    }
+}
+
+// Shortcut function to set the push constants.
+unsafe fn push_constants<P, Pl, Pc>(destination: &mut SyncCommandBufferBuilder<P>, pipeline: Pl,
+                                    push_constants: Pc)
+    where Pl: PipelineLayoutAbstract + Send + Sync + Clone + 'static
+{
+    for num_range in 0 .. pipeline.num_push_constants_ranges() {
+        let range = match pipeline.push_constants_range(num_range) {
+            Some(r) => r,
+            None => continue,
+        };
+
+        debug_assert_eq!(range.offset % 4, 0);
+        debug_assert_eq!(range.size % 4, 0);
+
+        let data = slice::from_raw_parts((&push_constants as *const Pc as *const u8)
+                                             .offset(range.offset as isize),
+                                         range.size as usize);
+
+        destination.push_constants::<_, [u8]>(pipeline.clone(),
+                                              range.stages,
+                                              range.offset as u32,
+                                              range.size as u32,
+                                              data);
+    }
+}
+
+// Shortcut function to change the state of the pipeline.
+unsafe fn set_state<P>(destination: &mut SyncCommandBufferBuilder<P>, dynamic: &DynamicState) {
     if let Some(line_width) = dynamic.line_width {
         destination.set_line_width(line_width);
     }
@@ -1429,6 +2947,50 @@ unsafe fn set_state<P>(destination: &mut SyncCommandBufferBuilder<P>, dynamic: &
     if let Some(ref scissors) = dynamic.scissors {
         destination.set_scissor(0, scissors.iter().cloned().collect::<Vec<_>>().into_iter()); // TODO: don't collect
     }
+
+    if let Some(depth_bias) = dynamic.depth_bias {
+        destination.set_depth_bias(depth_bias.constant_factor, depth_bias.clamp,
+                                   depth_bias.slope_factor);
+    }
+
+    if let Some(ref depth_bounds) = dynamic.depth_bounds {
+        destination.set_depth_bounds(depth_bounds.start, depth_bounds.end);
+    }
+
+    if let Some(compare_mask) = dynamic.compare_mask {
+        for (face, value) in stencil_faces(compare_mask) {
+            destination.set_stencil_compare_mask(face, value);
+        }
+    }
+
+    if let Some(write_mask) = dynamic.write_mask {
+        for (face, value) in stencil_faces(write_mask) {
+            destination.set_stencil_write_mask(face, value);
+        }
+    }
+
+    if let Some(reference) = dynamic.reference {
+        for (face, value) in stencil_faces(reference) {
+            destination.set_stencil_reference(face, value);
+        }
+    }
+
+    if let Some(blend_constants) = dynamic.blend_constants {
+        destination.set_blend_constants(blend_constants);
+    }
+}
+
+// Returns the stencil faces to set a dynamic stencil value for. A single command is enough if
+// both faces share the same value.
+fn stencil_faces(value: DynamicStencilValue) -> SmallVec<[(StencilFaceFlags, u32); 2]> {
+    let mut out = SmallVec::new();
+    if value.front == value.back {
+        out.push((StencilFaceFlags::StencilFrontAndBack, value.front));
+    } else {
+        out.push((StencilFaceFlags::StencilFaceFrontBit, value.front));
+        out.push((StencilFaceFlags::StencilFaceBackBit, value.back));
+    }
+    out
 }
 
 // Shortcut function to bind vertex buffers.
@@ -1462,19 +3024,16 @@ unsafe fn vertex_buffers<P>(destination: &mut SyncCommandBufferBuilder<P>,
     Ok(())
 }
 
-unsafe fn descriptor_sets<P, Pl, S>(destination: &mut SyncCommandBufferBuilder<P>,
-                                    state_cacher: &mut StateCacher, gfx: bool, pipeline: Pl,
-                                    sets: S)
-                                    -> Result<(), SyncCommandBufferBuilderError>
-    where Pl: PipelineLayoutAbstract + Send + Sync + Clone + 'static,
-          S: DescriptorSetsCollection
+unsafe fn descriptor_sets<P, Pl>(destination: &mut SyncCommandBufferBuilder<P>,
+                                state_cacher: &mut StateCacher, gfx: bool, pipeline: Pl,
+                                sets: Vec<DescriptorSetWithOffsets>)
+                                -> Result<(), SyncCommandBufferBuilderError>
+    where Pl: PipelineLayoutAbstract + Send + Sync + Clone + 'static
 {
-    let sets = sets.into_vec();
-
     let first_binding = {
         let mut compare = state_cacher.bind_descriptor_sets(gfx);
         for set in sets.iter() {
-            compare.add(set);
+            compare.add(set.descriptor_set(), set.dynamic_offsets());
         }
         compare.compare()
     };
@@ -1485,19 +3044,65 @@ unsafe fn descriptor_sets<P, Pl, S>(destination: &mut SyncCommandBufferBuilder<P
     };
 
     let mut sets_binder = destination.bind_descriptor_sets();
+    let mut dynamic_offsets = Vec::new();
     for set in sets.into_iter().skip(first_binding as usize) {
+        let (set, offsets) = set.into_tuple();
         sets_binder.add(set);
+        dynamic_offsets.extend(offsets);
     }
     sets_binder
-        .submit(gfx, pipeline.clone(), first_binding, iter::empty())?;
+        .submit(gfx, pipeline.clone(), first_binding, dynamic_offsets.into_iter())?;
     Ok(())
 }
 
+// Turns regions of a copy between a buffer whose elements are `Px` and an image into the regions
+// of the unsafe command.
+fn buffer_image_copies<Px>(regions: Vec<BufferImageCopy>)
+                           -> Vec<UnsafeCommandBufferBuilderBufferImageCopy> {
+    regions
+        .into_iter()
+        .map(|region| {
+            UnsafeCommandBufferBuilderBufferImageCopy {
+                buffer_offset: region.buffer_offset * mem::size_of::<Px>(),
+                buffer_row_length: region.buffer_row_length,
+                buffer_image_height: region.buffer_image_height,
+                image_aspect: region.image_aspect.to_unsafe(),
+                image_mip_level: region.image_mip_level,
+                image_base_array_layer: region.image_base_array_layer,
+                image_layer_count: region.image_layer_count,
+                image_offset: [region.image_offset[0] as i32,
+                               region.image_offset[1] as i32,
+                               region.image_offset[2] as i32],
+                image_extent: region.image_extent,
+            }
+        })
+        .collect()
+}
+
 pub struct AutoCommandBuffer<P = StandardCommandPoolAlloc> {
     inner: SyncCommandBuffer<P>,
 
     // Tracks usage of the command buffer on the GPU.
     submit_state: SubmitState,
+
+    // True if we are a secondary command buffer.
+    secondary_cb: bool,
+
+    // If we are a secondary command buffer that continues a render pass, contains the render
+    // pass and the subpass index.
+    render_pass: Option<(Arc<RenderPassAbstract + Send + Sync>, u32)>,
+
+    // Kind passed when creating the command buffer. Used when the command buffer is recorded
+    // again.
+    kind: ErasedKind,
+
+    // Flags passed when creating the command buffer. Used when the command buffer is recorded
+    // again.
+    flags: Flags,
+
+    // Number of calls to `lock_submit` and `lock_record` that haven't been matched by a call to
+    // `unlock` or `unlock_record` yet. The command buffer can't be reset while this is not zero.
+    num_submissions: AtomicUsize,
 }
 
 // Whether the command buffer can be submitted.
@@ -1521,6 +3126,55 @@ enum SubmitState {
     },
 }
 
+impl<P> AutoCommandBuffer<P> {
+    /// Returns the list of the commands of the command buffer, with the pipeline barriers and
+    /// layout transitions that were automatically inserted between them.
+    ///
+    /// This can be used to find out which commands cause barriers. See `BarrierReport`.
+    ///
+    /// Returns `None` if `enable_barrier_report` wasn't called on the builder.
+    #[inline]
+    pub fn barrier_report(&self) -> Option<&BarrierReport> {
+        self.inner.barrier_report()
+    }
+
+    /// Resets the command buffer so that it can be recorded again, reusing its allocation
+    /// instead of allocating a new command buffer from the pool.
+    ///
+    /// The returned builder has the same kind, including the framebuffer of a secondary command
+    /// buffer, and the same flags as the builder that built this command buffer. The barrier
+    /// report is enabled if it was enabled before. The commands are erased and the resources
+    /// they use are released.
+    ///
+    /// Returns an error if the command buffer is still in use by the GPU, in other words if a
+    /// future that executes it hasn't finished yet, or if the pool doesn't allow recording the
+    /// command buffer again. The command buffers of the standard command pool can only be recorded
+    /// again from the thread that allocated them.
+    pub fn reset(self) -> Result<AutoCommandBufferBuilder<P::Builder>, ResetCommandBufferError>
+        where P: CommandPoolAlloc
+    {
+        if self.num_submissions.load(Ordering::SeqCst) != 0 {
+            return Err(ResetCommandBufferError::InUse);
+        }
+
+        let barrier_report = self.inner.barrier_report().is_some();
+        // Safe because we checked above that the GPU is no longer using the command buffer.
+        let alloc = match unsafe { self.inner.into_alloc().into_builder() } {
+            Ok(alloc) => alloc,
+            Err(_) => return Err(ResetCommandBufferError::NotResettable),
+        };
+
+        let builder = unsafe {
+            AutoCommandBufferBuilder::from_alloc(alloc, self.kind, self.flags)?
+        };
+        if barrier_report {
+            Ok(builder.enable_barrier_report())
+        } else {
+            Ok(builder)
+        }
+    }
+}
+
 unsafe impl<P> CommandBuffer for AutoCommandBuffer<P> {
     type PoolAlloc = P;
 
@@ -1548,7 +3202,10 @@ unsafe impl<P> CommandBuffer for AutoCommandBuffer<P> {
         };
 
         let err = match self.inner.lock_submit(future, queue) {
-            Ok(()) => return Ok(()),
+            Ok(()) => {
+                self.num_submissions.fetch_add(1, Ordering::SeqCst);
+                return Ok(());
+            },
             Err(err) => err,
         };
 
@@ -1571,6 +3228,9 @@ unsafe impl<P> CommandBuffer for AutoCommandBuffer<P> {
         // Because of panic safety, we unlock the inner command buffer first.
         self.inner.unlock();
 
+        let old_val = self.num_submissions.fetch_sub(1, Ordering::SeqCst);
+        debug_assert_ne!(old_val, 0);
+
         match self.submit_state {
             SubmitState::OneTime { ref already_submitted } => {
                 debug_assert!(already_submitted.load(Ordering::SeqCst));
@@ -1597,6 +3257,108 @@ unsafe impl<P> CommandBuffer for AutoCommandBuffer<P> {
         self.inner
             .check_image_access(image, layout, exclusive, queue)
     }
+
+    #[inline]
+    fn ownership_releases(&self) -> Vec<OwnershipTransfer> {
+        self.inner.ownership_releases()
+    }
+
+    #[inline]
+    fn ownership_acquires(&self) -> Vec<OwnershipTransfer> {
+        self.inner.ownership_acquires()
+    }
+}
+
+unsafe impl<P> SecondaryCommandBuffer for AutoCommandBuffer<P> {
+    #[inline]
+    fn lock_record(&self) -> Result<(), CommandBufferExecError> {
+        if !self.secondary_cb {
+            return Err(CommandBufferExecError::NotSecondary);
+        }
+
+        match self.submit_state {
+            SubmitState::OneTime { ref already_submitted } => {
+                let was_already_submitted = already_submitted.swap(true, Ordering::SeqCst);
+                if was_already_submitted {
+                    return Err(CommandBufferExecError::OneTimeSubmitAlreadySubmitted);
+                }
+            },
+            SubmitState::ExclusiveUse { ref in_use } => {
+                let already_in_use = in_use.swap(true, Ordering::SeqCst);
+                if already_in_use {
+                    return Err(CommandBufferExecError::ExclusiveAlreadyInUse);
+                }
+            },
+            SubmitState::Concurrent => (),
+        };
+
+        self.num_submissions.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    #[inline]
+    unsafe fn unlock_record(&self) {
+        let old_val = self.num_submissions.fetch_sub(1, Ordering::SeqCst);
+        debug_assert_ne!(old_val, 0);
+
+        match self.submit_state {
+            SubmitState::OneTime { ref already_submitted } => {
+                debug_assert!(already_submitted.load(Ordering::SeqCst));
+            },
+            SubmitState::ExclusiveUse { ref in_use } => {
+                let old_val = in_use.swap(false, Ordering::SeqCst);
+                debug_assert!(old_val);
+            },
+            SubmitState::Concurrent => (),
+        };
+    }
+
+    #[inline]
+    fn simultaneous_use(&self) -> bool {
+        match self.submit_state {
+            SubmitState::Concurrent => true,
+            _ => false,
+        }
+    }
+
+    #[inline]
+    fn render_pass(&self) -> Option<(&RenderPassAbstract, u32)> {
+        self.render_pass
+            .as_ref()
+            .map(|&(ref rp, index)| (&**rp as &RenderPassAbstract, index))
+    }
+
+    #[inline]
+    fn inherited_queries(&self) -> (KindOcclusionQuery, QueryPipelineStatisticFlags) {
+        match self.kind {
+            Kind::Primary => (KindOcclusionQuery::Forbidden, QueryPipelineStatisticFlags::none()),
+            Kind::Secondary {
+                occlusion_query,
+                query_statistics_flags,
+                ..
+            } => (occlusion_query, query_statistics_flags),
+        }
+    }
+
+    #[inline]
+    fn num_buffers(&self) -> usize {
+        self.inner.num_buffers()
+    }
+
+    #[inline]
+    fn buffer(&self, index: usize) -> Option<(&BufferAccess, SecondaryResourceUsage)> {
+        self.inner.buffer(index)
+    }
+
+    #[inline]
+    fn num_images(&self) -> usize {
+        self.inner.num_images()
+    }
+
+    #[inline]
+    fn image(&self, index: usize) -> Option<(&ImageAccess, SecondaryResourceUsage)> {
+        self.inner.image(index)
+    }
 }
 
 unsafe impl<P> DeviceOwned for AutoCommandBuffer<P> {
@@ -1668,18 +3430,39 @@ err_gen!(BeginRenderPassError {
 err_gen!(CopyImageError {
              AutoCommandBufferBuilderContextError,
              CheckCopyImageError,
+             CheckTransferImageLayoutError,
              SyncCommandBufferBuilderError,
          });
 
 err_gen!(BlitImageError {
              AutoCommandBufferBuilderContextError,
              CheckBlitImageError,
+             CheckTransferImageLayoutError,
              SyncCommandBufferBuilderError,
          });
 
+err_gen!(ClearAttachmentsError {
+             AutoCommandBufferBuilderContextError,
+             CheckClearAttachmentsError,
+         });
+
 err_gen!(ClearColorImageError {
              AutoCommandBufferBuilderContextError,
              CheckClearColorImageError,
+             CheckTransferImageLayoutError,
+             SyncCommandBufferBuilderError,
+         });
+
+err_gen!(ClearDepthStencilImageError {
+             AutoCommandBufferBuilderContextError,
+             CheckClearDepthStencilImageError,
+             CheckTransferImageLayoutError,
+             SyncCommandBufferBuilderError,
+         });
+
+err_gen!(ResolveImageError {
+             AutoCommandBufferBuilderContextError,
+             CheckResolveImageError,
              SyncCommandBufferBuilderError,
          });
 
@@ -1692,6 +3475,7 @@ err_gen!(CopyBufferError {
 err_gen!(CopyBufferImageError {
              AutoCommandBufferBuilderContextError,
              CheckCopyBufferImageError,
+             CheckTransferImageLayoutError,
              SyncCommandBufferBuilderError,
          });
 
@@ -1704,15 +3488,26 @@ err_gen!(DispatchError {
              AutoCommandBufferBuilderContextError,
              CheckPushConstantsValidityError,
              CheckDescriptorSetsValidityError,
+             CheckDynamicOffsetsError,
              CheckDispatchError,
              SyncCommandBufferBuilderError,
          });
 
+err_gen!(DispatchIndirectError {
+             AutoCommandBufferBuilderContextError,
+             CheckPushConstantsValidityError,
+             CheckDescriptorSetsValidityError,
+             CheckDynamicOffsetsError,
+             CheckIndirectBufferError,
+             SyncCommandBufferBuilderError,
+         });
+
 err_gen!(DrawError {
              AutoCommandBufferBuilderContextError,
              CheckDynamicStateValidityError,
              CheckPushConstantsValidityError,
              CheckDescriptorSetsValidityError,
+             CheckDynamicOffsetsError,
              CheckVertexBufferError,
              SyncCommandBufferBuilderError,
          });
@@ -1722,8 +3517,10 @@ err_gen!(DrawIndexedError {
              CheckDynamicStateValidityError,
              CheckPushConstantsValidityError,
              CheckDescriptorSetsValidityError,
+             CheckDynamicOffsetsError,
              CheckVertexBufferError,
              CheckIndexBufferError,
+             CheckIndexRangeError,
              SyncCommandBufferBuilderError,
          });
 
@@ -1732,6 +3529,7 @@ err_gen!(DrawIndirectError {
              CheckDynamicStateValidityError,
              CheckPushConstantsValidityError,
              CheckDescriptorSetsValidityError,
+             CheckDynamicOffsetsError,
              CheckVertexBufferError,
              SyncCommandBufferBuilderError,
          });
@@ -1741,6 +3539,7 @@ err_gen!(DrawIndexedIndirectError {
              CheckDynamicStateValidityError,
              CheckPushConstantsValidityError,
              CheckDescriptorSetsValidityError,
+             CheckDynamicOffsetsError,
              CheckVertexBufferError,
              CheckIndexBufferError,
              SyncCommandBufferBuilderError,
@@ -1748,6 +3547,7 @@ err_gen!(DrawIndexedIndirectError {
 
 err_gen!(ExecuteCommandsError {
              AutoCommandBufferBuilderContextError,
+             CommandBufferExecError,
              SyncCommandBufferBuilderError,
          });
 
@@ -1756,6 +3556,48 @@ err_gen!(UpdateBufferError {
              CheckUpdateBufferError,
          });
 
+err_gen!(BeginQueryError {
+             AutoCommandBufferBuilderContextError,
+             CheckBeginQueryError,
+         });
+
+err_gen!(WriteTimestampError {
+             AutoCommandBufferBuilderContextError,
+             CheckWriteTimestampError,
+         });
+
+err_gen!(ResetEventError {
+             AutoCommandBufferBuilderContextError,
+             CheckEventCommandError,
+         });
+
+err_gen!(ResetQueryPoolError {
+             AutoCommandBufferBuilderContextError,
+             CheckResetQueryPoolError,
+         });
+
+err_gen!(CopyQueryPoolResultsError {
+             AutoCommandBufferBuilderContextError,
+             CheckCopyQueryPoolResultsError,
+             SyncCommandBufferBuilderError,
+         });
+
+err_gen!(SetEventError {
+             AutoCommandBufferBuilderContextError,
+             CheckEventCommandError,
+         });
+
+err_gen!(WaitEventsError {
+             AutoCommandBufferBuilderContextError,
+             CheckWaitEventsError,
+         });
+
+err_gen!(OwnershipTransferError {
+             AutoCommandBufferBuilderContextError,
+             CheckQueueFamilyOwnershipTransferError,
+             SyncCommandBufferBuilderError,
+         });
+
 #[derive(Debug, Copy, Clone)]
 pub enum AutoCommandBufferBuilderContextError {
     /// Operation forbidden in a secondary command buffer.
@@ -1780,9 +3622,27 @@ pub enum AutoCommandBufferBuilderContextError {
     /// Tried to use a graphics pipeline whose subpass index didn't match the current subpass
     /// index.
     WrongSubpassIndex,
+    /// Tried to use a graphics pipeline whose subpass is incompatible with the current subpass,
+    /// because their attachment references don't match.
+    IncompatibleSubpass,
     /// Tried to use a graphics pipeline whose render pass is incompatible with the current render
     /// pass.
     IncompatibleRenderPass,
+    /// Tried to begin a query while it or another query of the same type is already active, to
+    /// reset or copy the results of an active query, to end a subpass while a query begun in it is
+    /// active, or to build a command buffer with active queries.
+    QueryIsActive,
+    /// Tried to end a query that is not active.
+    QueryNotActive,
+    /// Tried to begin or write to a query that hasn't been reset by this command buffer since it
+    /// was last used.
+    QueryNotReset,
+    /// Tried to end a query in another subpass than the one it was begun in, or inside of a render
+    /// pass while it was begun outside of it.
+    WrongQuerySubpass,
+    /// Tried to execute a secondary command buffer while a query is active, but the secondary
+    /// command buffer wasn't created with the flags that allow it to inherit that query.
+    QueryNotInherited,
 }
 
 impl error::Error for AutoCommandBufferBuilderContextError {
@@ -1814,10 +3674,34 @@ impl error::Error for AutoCommandBufferBuilderContextError {
                 "tried to use a graphics pipeline whose subpass index didn't match the current \
                  subpass index"
             },
+            AutoCommandBufferBuilderContextError::IncompatibleSubpass => {
+                "tried to use a graphics pipeline whose subpass is incompatible with the current \
+                 subpass, because their attachment references don't match"
+            },
             AutoCommandBufferBuilderContextError::IncompatibleRenderPass => {
                 "tried to use a graphics pipeline whose render pass is incompatible with the \
                  current render pass"
             },
+            AutoCommandBufferBuilderContextError::QueryIsActive => {
+                "tried to begin a query while it or another query of the same type is already \
+                 active, to reset or copy the results of an active query, to end a subpass while \
+                 a query begun in it is active, or to build a command buffer with active queries"
+            },
+            AutoCommandBufferBuilderContextError::QueryNotActive => {
+                "tried to end a query that is not active"
+            },
+            AutoCommandBufferBuilderContextError::QueryNotReset => {
+                "tried to begin or write to a query that hasn't been reset by this command buffer \
+                 since it was last used"
+            },
+            AutoCommandBufferBuilderContextError::WrongQuerySubpass => {
+                "tried to end a query in another subpass than the one it was begun in, or inside \
+                 of a render pass while it was begun outside of it"
+            },
+            AutoCommandBufferBuilderContextError::QueryNotInherited => {
+                "tried to execute a secondary command buffer that doesn't inherit a query that is \
+                 active"
+            },
         }
     }
 }
@@ -1828,3 +3712,506 @@ impl fmt::Display for AutoCommandBufferBuilderContextError {
         write!(fmt, "{}", error::Error::description(self))
     }
 }
+
+/// Error that can happen when resetting an `AutoCommandBuffer`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ResetCommandBufferError {
+    /// The command buffer is still in use by the GPU.
+    InUse,
+    /// The command pool doesn't allow recording the command buffer again, for example because it
+    /// is used from another thread.
+    NotResettable,
+    /// Not enough memory to start recording the command buffer again.
+    OomError(OomError),
+}
+
+impl error::Error for ResetCommandBufferError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            ResetCommandBufferError::InUse => {
+                "the command buffer is still in use by the GPU"
+            },
+            ResetCommandBufferError::NotResettable => {
+                "the command pool doesn't allow recording the command buffer again"
+            },
+            ResetCommandBufferError::OomError(_) => {
+                "not enough memory to start recording the command buffer again"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            ResetCommandBufferError::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ResetCommandBufferError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<OomError> for ResetCommandBufferError {
+    #[inline]
+    fn from(err: OomError) -> ResetCommandBufferError {
+        ResetCommandBufferError::OomError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use buffer::ImmutableBuffer;
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::AutoCommandBufferBuilderContextError;
+    use command_buffer::BeginQueryError;
+    use command_buffer::CommandBufferExecError;
+    use command_buffer::CommandBuffer;
+    use command_buffer::DynamicState;
+    use command_buffer::ExecuteCommandsError;
+    use command_buffer::ResetCommandBufferError;
+    use command_buffer::SecondaryCommandBuffer;
+    use command_buffer::WriteTimestampError;
+    use command_buffer::sys::Flags;
+    use command_buffer::sys::Kind;
+    use command_buffer::sys::KindOcclusionQuery;
+    use command_buffer::sys::KindSecondaryRenderPass;
+    use device::DeviceOwned;
+    use framebuffer::EmptySinglePassRenderPassDesc;
+    use framebuffer::Framebuffer;
+    use framebuffer::RenderPassDesc;
+    use framebuffer::Subpass;
+    use pipeline::GraphicsPipelineAbstract;
+    use pipeline::vertex::BufferlessVertices;
+    use query::QueryPipelineStatisticFlags;
+    use query::QueryType;
+    use query::UnsafeQueryPool;
+    use sync::PipelineStages;
+    use VulkanObject;
+
+    #[test]
+    fn execute_primary() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let primary = AutoCommandBufferBuilder::primary(device.clone(), queue.family())
+            .unwrap()
+            .build()
+            .unwrap();
+
+        match AutoCommandBufferBuilder::primary(device, queue.family())
+            .unwrap()
+            .execute_commands(primary)
+        {
+            Err(ExecuteCommandsError::CommandBufferExecError(
+                    CommandBufferExecError::NotSecondary)) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn secondary_one_time_submit() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let secondary =
+            AutoCommandBufferBuilder::secondary_compute_one_time_submit(device.clone(),
+                                                                        queue.family())
+                .unwrap()
+                .build()
+                .unwrap();
+        let secondary = Arc::new(secondary);
+
+        let builder = AutoCommandBufferBuilder::primary(device, queue.family())
+            .unwrap()
+            .execute_commands(secondary.clone())
+            .unwrap();
+
+        match builder.execute_commands(secondary) {
+            Err(ExecuteCommandsError::CommandBufferExecError(
+                    CommandBufferExecError::OneTimeSubmitAlreadySubmitted)) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn secondary_exclusive_use() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let secondary = AutoCommandBufferBuilder::secondary_compute(device.clone(),
+                                                                    queue.family())
+            .unwrap()
+            .build()
+            .unwrap();
+        let secondary = Arc::new(secondary);
+
+        let primary = AutoCommandBufferBuilder::primary(device.clone(), queue.family())
+            .unwrap()
+            .execute_commands(secondary.clone())
+            .unwrap()
+            .build()
+            .unwrap();
+
+        match AutoCommandBufferBuilder::primary(device.clone(), queue.family())
+            .unwrap()
+            .execute_commands(secondary.clone())
+        {
+            Err(ExecuteCommandsError::CommandBufferExecError(
+                    CommandBufferExecError::ExclusiveAlreadyInUse)) => (),
+            _ => panic!(),
+        }
+
+        // Destroying the primary command buffer releases the secondary command buffer.
+        drop(primary);
+        AutoCommandBufferBuilder::primary(device, queue.family())
+            .unwrap()
+            .execute_commands(secondary)
+            .unwrap();
+    }
+
+    #[test]
+    fn execute_multiple_unlocks_on_error() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let secondary = AutoCommandBufferBuilder::secondary_compute(device.clone(),
+                                                                    queue.family())
+            .unwrap()
+            .build()
+            .unwrap();
+        let secondary = Arc::new(secondary);
+
+        match AutoCommandBufferBuilder::primary(device.clone(), queue.family())
+            .unwrap()
+            .execute_commands_multiple(vec![secondary.clone(), secondary.clone()])
+        {
+            Err(ExecuteCommandsError::CommandBufferExecError(
+                    CommandBufferExecError::ExclusiveAlreadyInUse)) => (),
+            _ => panic!(),
+        }
+
+        // The first command buffer of the list has been released.
+        AutoCommandBufferBuilder::primary(device, queue.family())
+            .unwrap()
+            .execute_commands(secondary)
+            .unwrap();
+    }
+
+    #[test]
+    fn secondary_resources() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let buffer = CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(), 0u32)
+            .unwrap();
+
+        let secondary = AutoCommandBufferBuilder::secondary_compute(device, queue.family())
+            .unwrap()
+            .fill_buffer(buffer, 0)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        assert_eq!(secondary.num_buffers(), 1);
+        assert_eq!(secondary.num_images(), 0);
+        let (_, usage) = secondary.buffer(0).unwrap();
+        assert!(usage.exclusive);
+        assert!(usage.stages.transfer);
+        assert!(usage.access.transfer_write);
+        assert!(secondary.buffer(1).is_none());
+    }
+
+    #[test]
+    fn reset_reuses_command_buffer() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let buffer = CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(), 0u32)
+            .unwrap();
+
+        let cb = AutoCommandBufferBuilder::primary_one_time_submit(device, queue.family())
+            .unwrap()
+            .fill_buffer(buffer.clone(), 0)
+            .unwrap()
+            .build()
+            .unwrap();
+        let raw = cb.inner().internal_object();
+
+        let cb = cb.reset()
+            .unwrap()
+            .fill_buffer(buffer, 1)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(raw, cb.inner().internal_object());
+    }
+
+    #[test]
+    fn reset_keeps_kind() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let render_pass = Arc::new(EmptySinglePassRenderPassDesc
+                                       .build_render_pass(device.clone())
+                                       .unwrap());
+        let framebuffer = Arc::new(Framebuffer::with_dimensions(render_pass.clone(), [64, 64, 1])
+                                       .build()
+                                       .unwrap());
+        let kind = Kind::Secondary {
+            render_pass: Some(KindSecondaryRenderPass {
+                                  subpass: Subpass::from(render_pass, 0).unwrap(),
+                                  framebuffer: Some(framebuffer),
+                              }),
+            occlusion_query: KindOcclusionQuery::Forbidden,
+            query_statistics_flags: QueryPipelineStatisticFlags::none(),
+        };
+
+        let builder = AutoCommandBufferBuilder::with_flags(device, queue.family(), kind,
+                                                           Flags::None)
+            .unwrap();
+        assert_eq!(builder.framebuffer_dimensions, Some([64, 64, 1]));
+
+        let builder = builder.build().unwrap().reset().unwrap();
+        assert_eq!(builder.framebuffer_dimensions, Some([64, 64, 1]));
+        match builder.kind {
+            Kind::Secondary { render_pass: Some(ref sec), .. } => {
+                assert_eq!(sec.subpass.index(), 0);
+                assert!(sec.framebuffer.is_some());
+            },
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn reset_secondary_in_use() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let secondary = AutoCommandBufferBuilder::secondary_compute(device.clone(),
+                                                                    queue.family())
+            .unwrap()
+            .build()
+            .unwrap();
+
+        secondary.lock_record().unwrap();
+        match secondary.reset() {
+            Err(ResetCommandBufferError::InUse) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn query_requires_reset() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let pool = Arc::new(UnsafeQueryPool::new(device.clone(), QueryType::Occlusion, 4).unwrap());
+
+        match AutoCommandBufferBuilder::primary(device.clone(), queue.family())
+            .unwrap()
+            .begin_query(pool.clone(), 0, false)
+        {
+            Err(BeginQueryError::AutoCommandBufferBuilderContextError(
+                    AutoCommandBufferBuilderContextError::QueryNotReset)) => (),
+            _ => panic!(),
+        }
+
+        let builder = AutoCommandBufferBuilder::primary(device, queue.family())
+            .unwrap()
+            .reset_query_pool(pool.clone(), 0, 4)
+            .unwrap()
+            .begin_query(pool.clone(), 1, false)
+            .unwrap()
+            .end_query(pool.clone(), 1)
+            .unwrap();
+
+        // The query has been used since it was reset.
+        let builder = match builder.begin_query(pool.clone(), 1, false) {
+            Err(BeginQueryError::AutoCommandBufferBuilderContextError(
+                    AutoCommandBufferBuilderContextError::QueryNotReset)) => {
+                AutoCommandBufferBuilder::primary(pool.device().clone(), queue.family())
+                    .unwrap()
+                    .reset_query_pool(pool.clone(), 0, 4)
+                    .unwrap()
+                    .begin_query(pool.clone(), 1, false)
+                    .unwrap()
+                    .end_query(pool.clone(), 1)
+                    .unwrap()
+            },
+            _ => panic!(),
+        };
+
+        // The other queries of the range are still available.
+        builder
+            .begin_query(pool.clone(), 0, false)
+            .unwrap()
+            .end_query(pool.clone(), 0)
+            .unwrap()
+            .begin_query(pool.clone(), 2, false)
+            .unwrap()
+            .end_query(pool, 2)
+            .unwrap()
+            .build()
+            .unwrap();
+    }
+
+    #[test]
+    fn query_reset_elsewhere() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let pool = Arc::new(UnsafeQueryPool::new(device.clone(), QueryType::Occlusion, 1).unwrap());
+
+        // The pool is reset by a command buffer that is submitted before the one that uses it.
+        let _reset = AutoCommandBufferBuilder::primary(device.clone(), queue.family())
+            .unwrap()
+            .reset_query_pool(pool.clone(), 0, 1)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let builder = unsafe {
+            AutoCommandBufferBuilder::primary(device, queue.family())
+                .unwrap()
+                .begin_query_assume_reset(pool.clone(), 0, false)
+                .unwrap()
+                .end_query(pool.clone(), 0)
+                .unwrap()
+        };
+
+        // The query has been used since it was reset.
+        match builder.begin_query(pool, 0, false) {
+            Err(BeginQueryError::AutoCommandBufferBuilderContextError(
+                    AutoCommandBufferBuilderContextError::QueryNotReset)) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn write_timestamp_requires_reset() {
+        let (device, queue) = gfx_dev_and_queue!();
+        if queue.family().timestamp_valid_bits().is_none() {
+            return;
+        }
+
+        let pool = Arc::new(UnsafeQueryPool::new(device.clone(), QueryType::Timestamp, 2).unwrap());
+        let stage = PipelineStages {
+            bottom_of_pipe: true,
+            ..PipelineStages::none()
+        };
+
+        let builder = AutoCommandBufferBuilder::primary(device, queue.family())
+            .unwrap()
+            .reset_query_pool(pool.clone(), 0, 2)
+            .unwrap()
+            .write_timestamp(pool.clone(), 0, stage)
+            .unwrap();
+
+        match builder.write_timestamp(pool, 0, stage) {
+            Err(WriteTimestampError::AutoCommandBufferBuilderContextError(
+                    AutoCommandBufferBuilderContextError::QueryNotReset)) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn execute_secondary_without_inherited_query() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let pool = Arc::new(UnsafeQueryPool::new(device.clone(), QueryType::Occlusion, 1).unwrap());
+
+        let secondary = AutoCommandBufferBuilder::secondary_compute(device.clone(),
+                                                                    queue.family())
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let builder = AutoCommandBufferBuilder::primary(device, queue.family())
+            .unwrap()
+            .reset_query_pool(pool.clone(), 0, 1)
+            .unwrap()
+            .begin_query(pool, 0, false)
+            .unwrap();
+
+        match builder.execute_commands(secondary) {
+            Err(ExecuteCommandsError::AutoCommandBufferBuilderContextError(
+                    AutoCommandBufferBuilderContextError::QueryNotInherited)) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn query_active_at_end_of_subpass() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let pool = Arc::new(UnsafeQueryPool::new(device.clone(), QueryType::Occlusion, 1).unwrap());
+
+        let render_pass = Arc::new(EmptySinglePassRenderPassDesc
+                                       .build_render_pass(device.clone())
+                                       .unwrap());
+        let framebuffer = Arc::new(Framebuffer::with_dimensions(render_pass, [64, 64, 1])
+                                       .build()
+                                       .unwrap());
+
+        let builder = AutoCommandBufferBuilder::primary(device.clone(), queue.family())
+            .unwrap()
+            .reset_query_pool(pool.clone(), 0, 1)
+            .unwrap()
+            .begin_render_pass(framebuffer.clone(), false, Vec::new())
+            .unwrap()
+            .begin_query(pool.clone(), 0, false)
+            .unwrap();
+
+        match builder.end_render_pass() {
+            Err(AutoCommandBufferBuilderContextError::QueryIsActive) => (),
+            _ => panic!(),
+        }
+
+        // A query begun outside of the render pass can't be ended inside of it.
+        let builder = AutoCommandBufferBuilder::primary(device, queue.family())
+            .unwrap()
+            .reset_query_pool(pool.clone(), 0, 1)
+            .unwrap()
+            .begin_query(pool.clone(), 0, false)
+            .unwrap()
+            .begin_render_pass(framebuffer, false, Vec::new())
+            .unwrap();
+
+        match builder.end_query(pool, 0) {
+            Err(AutoCommandBufferBuilderContextError::WrongQuerySubpass) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn draw_indexed_checked_without_vertex_buffers() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let render_pass = Arc::new(EmptySinglePassRenderPassDesc
+                                       .build_render_pass(device.clone())
+                                       .unwrap());
+        let framebuffer = Arc::new(Framebuffer::with_dimensions(render_pass.clone(), [64, 64, 1])
+                                       .build()
+                                       .unwrap());
+        let pipeline = Arc::new(empty_gfx_pipeline!(device, render_pass).unwrap());
+        assert!(!pipeline.has_per_vertex_input());
+
+        // The indices aren't used to read any vertex buffer, so they don't have to be less than
+        // the number of vertices.
+        let (indices, _) = ImmutableBuffer::from_indices([0u32, 5].iter().cloned(),
+                                                         BufferUsage::index_buffer(),
+                                                         queue.clone())
+            .unwrap();
+
+        AutoCommandBufferBuilder::primary(device, queue.family())
+            .unwrap()
+            .begin_render_pass(framebuffer, false, Vec::new())
+            .unwrap()
+            .draw_indexed_checked(pipeline,
+                                  &DynamicState::none(),
+                                  BufferlessVertices {
+                                      vertices: 1,
+                                      instances: 1,
+                                  },
+                                  indices,
+                                  (),
+                                  ())
+            .unwrap();
+    }
+}
@@ -30,6 +30,7 @@
 //!
 //! - Building a command buffer is a single-threaded operation, but by using secondary command
 //!   buffers you can build multiple secondary command buffers in multiple threads simultaneously.
+//!   The `ParallelSecondaryRecorder` helps with doing this for the draw commands of a subpass.
 //! - Secondary command buffers can be kept alive between frames. When you always repeat the same
 //!   operations, it might be a good idea to build a secondary command buffer once at
 //!   initialization and then reuse it afterwards.
@@ -76,27 +77,49 @@
 pub use self::auto::AutoCommandBuffer;
 pub use self::auto::AutoCommandBufferBuilder;
 pub use self::auto::AutoCommandBufferBuilderContextError;
+pub use self::auto::BeginQueryError;
 pub use self::auto::BeginRenderPassError;
 pub use self::auto::BlitImageError;
 pub use self::auto::BuildError;
+pub use self::auto::ClearAttachmentsError;
 pub use self::auto::ClearColorImageError;
+pub use self::auto::ClearDepthStencilImageError;
 pub use self::auto::CopyBufferError;
 pub use self::auto::CopyBufferImageError;
+pub use self::auto::CopyQueryPoolResultsError;
 pub use self::auto::DispatchError;
+pub use self::auto::DispatchIndirectError;
 pub use self::auto::DrawError;
 pub use self::auto::DrawIndexedError;
 pub use self::auto::DrawIndirectError;
 pub use self::auto::ExecuteCommandsError;
 pub use self::auto::FillBufferError;
+pub use self::auto::OwnershipTransferError;
+pub use self::auto::ResetCommandBufferError;
+pub use self::auto::ResetEventError;
+pub use self::auto::ResetQueryPoolError;
+pub use self::auto::ResolveImageError;
+pub use self::auto::SetEventError;
 pub use self::auto::UpdateBufferError;
+pub use self::auto::WaitEventsError;
+pub use self::auto::WriteTimestampError;
+pub use self::parallel::ParallelSecondaryRecorder;
 pub use self::state_cacher::StateCacher;
 pub use self::state_cacher::StateCacherOutcome;
 pub use self::traits::CommandBuffer;
 pub use self::traits::CommandBufferExecError;
 pub use self::traits::CommandBufferExecFuture;
+pub use self::traits::SecondaryCommandBuffer;
+pub use self::traits::SecondaryResourceUsage;
 
+use command_buffer::sys::UnsafeCommandBufferBuilderImageAspect;
+use format::ClearValue;
+use format::Format;
+use format::FormatTy;
+use pipeline::raster::DepthBias;
 use pipeline::viewport::Scissor;
 use pipeline::viewport::Viewport;
+use std::ops::Range;
 
 pub mod pool;
 pub mod submit;
@@ -105,6 +128,7 @@ pub mod sys;
 pub mod validity;
 
 mod auto;
+mod parallel;
 mod state_cacher;
 mod traits;
 
@@ -142,7 +166,14 @@ pub struct DynamicState {
     pub line_width: Option<f32>,
     pub viewports: Option<Vec<Viewport>>,
     pub scissors: Option<Vec<Scissor>>,
-    // TODO: missing fields
+    /// Requires the `depth_bias_clamp` feature if the clamp is different from `0.0`.
+    pub depth_bias: Option<DepthBias>,
+    /// Values must be between 0.0 and 1.0.
+    pub depth_bounds: Option<Range<f32>>,
+    pub compare_mask: Option<DynamicStencilValue>,
+    pub write_mask: Option<DynamicStencilValue>,
+    pub reference: Option<DynamicStencilValue>,
+    pub blend_constants: Option<[f32; 4]>,
 }
 
 impl DynamicState {
@@ -152,6 +183,12 @@ impl DynamicState {
             line_width: None,
             viewports: None,
             scissors: None,
+            depth_bias: None,
+            depth_bounds: None,
+            compare_mask: None,
+            write_mask: None,
+            reference: None,
+            blend_constants: None,
         }
     }
 }
@@ -162,3 +199,142 @@ impl Default for DynamicState {
         DynamicState::none()
     }
 }
+
+/// An attachment of the current subpass to clear with `clear_attachments`.
+#[derive(Debug, Copy, Clone)]
+pub enum ClearAttachment {
+    /// Clears the color attachment at the given index in the list of color attachments of the
+    /// current subpass. The value must be `Float`, `Int` or `Uint` depending on the format.
+    Color(ClearValue, u32),
+    /// Clears the depth aspect of the depth-stencil attachment of the current subpass.
+    Depth(f32),
+    /// Clears the stencil aspect of the depth-stencil attachment of the current subpass.
+    Stencil(u32),
+    /// Clears both aspects of the depth-stencil attachment of the current subpass.
+    DepthStencil((f32, u32)),
+}
+
+/// A region of the framebuffer to clear with `clear_attachments`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ClearRect {
+    /// Top-left corner of the region, in pixels.
+    pub rect_offset: [u32; 2],
+    /// Width and height of the region, in pixels.
+    pub rect_extent: [u32; 2],
+    /// First framebuffer layer to clear.
+    pub base_array_layer: u32,
+    /// Number of framebuffer layers to clear.
+    pub layer_count: u32,
+}
+
+/// Dynamic value of a stencil state, for the front and back faces.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DynamicStencilValue {
+    pub front: u32,
+    pub back: u32,
+}
+
+impl DynamicStencilValue {
+    /// Builds a `DynamicStencilValue` that uses the same value for both faces.
+    #[inline]
+    pub fn both(value: u32) -> DynamicStencilValue {
+        DynamicStencilValue {
+            front: value,
+            back: value,
+        }
+    }
+}
+
+/// Aspect of an image that a copy command reads or writes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ImageAspect {
+    /// The color of an image whose format is a color or compressed format.
+    Color,
+    /// The depth of an image whose format is a depth or depth-stencil format.
+    Depth,
+    /// The stencil of an image whose format is a stencil or depth-stencil format.
+    Stencil,
+    /// Both the depth and the stencil of an image whose format is a depth-stencil format. Only
+    /// allowed when copying between two images.
+    DepthStencil,
+}
+
+impl ImageAspect {
+    /// Returns true if images of the given format have this aspect.
+    #[inline]
+    pub fn is_in_format(&self, format: Format) -> bool {
+        match (*self, format.ty()) {
+            (ImageAspect::Color, FormatTy::Float) => true,
+            (ImageAspect::Color, FormatTy::Uint) => true,
+            (ImageAspect::Color, FormatTy::Sint) => true,
+            (ImageAspect::Color, FormatTy::Compressed) => true,
+            (ImageAspect::Depth, FormatTy::Depth) => true,
+            (ImageAspect::Depth, FormatTy::DepthStencil) => true,
+            (ImageAspect::Stencil, FormatTy::Stencil) => true,
+            (ImageAspect::Stencil, FormatTy::DepthStencil) => true,
+            (ImageAspect::DepthStencil, FormatTy::DepthStencil) => true,
+            _ => false,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn to_unsafe(&self) -> UnsafeCommandBufferBuilderImageAspect {
+        UnsafeCommandBufferBuilderImageAspect {
+            color: *self == ImageAspect::Color,
+            depth: *self == ImageAspect::Depth || *self == ImageAspect::DepthStencil,
+            stencil: *self == ImageAspect::Stencil || *self == ImageAspect::DepthStencil,
+        }
+    }
+}
+
+/// A region to copy with `copy_image_regions`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ImageCopy {
+    /// The aspect to copy. The source and the destination must both have it.
+    pub aspect: ImageAspect,
+    /// Mipmap level of the source to copy from.
+    pub source_mip_level: u32,
+    /// First array layer of the source to copy from.
+    pub source_base_array_layer: u32,
+    /// Coordinates of the first texel of the source to copy from.
+    pub source_offset: [i32; 3],
+    /// Mipmap level of the destination to copy to.
+    pub destination_mip_level: u32,
+    /// First array layer of the destination to copy to.
+    pub destination_base_array_layer: u32,
+    /// Coordinates of the first texel of the destination to copy to.
+    pub destination_offset: [i32; 3],
+    /// Size of the region, in texels.
+    pub extent: [u32; 3],
+    /// Number of array layers to copy.
+    pub layer_count: u32,
+}
+
+/// A region to copy with `copy_buffer_to_image_regions` or `copy_image_to_buffer_regions`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BufferImageCopy {
+    /// Offset of the first texel in the buffer, in number of elements of the buffer.
+    pub buffer_offset: usize,
+    /// Number of texels between the start of two consecutive rows in the buffer, or 0 if the
+    /// rows are tightly packed.
+    pub buffer_row_length: u32,
+    /// Number of rows between the start of two consecutive slices or array layers in the
+    /// buffer, or 0 if they are tightly packed.
+    pub buffer_image_height: u32,
+    /// The aspect of the image to copy. Can't be `DepthStencil`.
+    ///
+    /// When copying the depth aspect, each texel is 2 bytes in the buffer for the `D16` formats
+    /// and 4 bytes for the `D24` and `D32` formats. When copying the stencil aspect, each texel
+    /// is 1 byte in the buffer.
+    pub image_aspect: ImageAspect,
+    /// Mipmap level of the image.
+    pub image_mip_level: u32,
+    /// First array layer of the image.
+    pub image_base_array_layer: u32,
+    /// Number of array layers to copy.
+    pub image_layer_count: u32,
+    /// Coordinates of the first texel of the image.
+    pub image_offset: [u32; 3],
+    /// Size of the region of the image, in texels.
+    pub image_extent: [u32; 3],
+}
@@ -0,0 +1,287 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::mem;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use OomError;
+use VulkanObject;
+use command_buffer::AutoCommandBuffer;
+use command_buffer::AutoCommandBufferBuilder;
+use command_buffer::ExecuteCommandsError;
+use command_buffer::sys::Flags;
+use command_buffer::sys::Kind;
+use command_buffer::sys::KindOcclusionQuery;
+use command_buffer::sys::KindSecondaryRenderPass;
+use device::Device;
+use device::DeviceOwned;
+use framebuffer::EmptySinglePassRenderPassDesc;
+use framebuffer::Framebuffer;
+use framebuffer::RenderPass;
+use framebuffer::RenderPassAbstract;
+use framebuffer::Subpass;
+use instance::QueueFamily;
+use query::QueryPipelineStatisticFlags;
+
+/// Helper for recording secondary graphics command buffers for the same subpass from multiple
+/// threads, and executing all of them at once from a primary command buffer.
+///
+/// Call `builder()` from each worker thread in order to start recording a secondary command
+/// buffer that continues the subpass. The command buffer is allocated from the
+/// `StandardCommandPool` of the device, which uses a separate Vulkan pool for each thread.
+/// Once the command buffer is built, pass it to `push()` along with its position in the final
+/// order. The recorder can be shared between threads, for example with an `Arc`.
+///
+/// Once all the worker threads are done, call `execute()` on the primary command buffer builder.
+/// This adds all the command buffers that have been pushed in a single `vkCmdExecuteCommands`
+/// command, sorted by their position. The primary command buffer must be inside the subpass,
+/// which must have been entered with `secondary` set to true.
+///
+/// Since no pipeline barrier can be inserted between the command buffers, they must not access
+/// the same resource if one of them writes it.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::thread;
+/// use vulkano::command_buffer::ParallelSecondaryRecorder;
+/// use vulkano::command_buffer::sys::Flags;
+/// # use vulkano::command_buffer::AutoCommandBufferBuilder;
+/// # use vulkano::framebuffer::RenderPassAbstract;
+/// # use vulkano::framebuffer::Subpass;
+/// # let device: Arc<vulkano::device::Device> = return;
+/// # let queue: Arc<vulkano::device::Queue> = return;
+/// # let subpass: Subpass<Arc<RenderPassAbstract + Send + Sync>> = return;
+/// # let primary: AutoCommandBufferBuilder = return;
+///
+/// let recorder = Arc::new(ParallelSecondaryRecorder::new(device.clone(), queue.family(),
+///                                                        subpass, Flags::OneTimeSubmit));
+///
+/// let threads = (0 .. 4).map(|num| {
+///     let recorder = recorder.clone();
+///     thread::spawn(move || {
+///         let builder = recorder.builder().unwrap();
+///         // Add draw commands here.
+///         recorder.push(num, builder.build().unwrap());
+///     })
+/// }).collect::<Vec<_>>();
+///
+/// for thread in threads {
+///     thread.join().unwrap();
+/// }
+///
+/// let primary = recorder.execute(primary).unwrap();
+/// ```
+pub struct ParallelSecondaryRecorder<R> {
+    // The device.
+    device: Arc<Device>,
+    // Identifier of the queue family of the command buffers.
+    queue_family: u32,
+    // The subpass that the command buffers continue.
+    subpass: Subpass<R>,
+    // Flags of the command buffers.
+    flags: Flags,
+    // How the queries are inherited from the primary command buffer.
+    occlusion_query: KindOcclusionQuery,
+    query_statistics_flags: QueryPipelineStatisticFlags,
+    // The command buffers that have been pushed and haven't been executed yet, with their
+    // position.
+    command_buffers: Mutex<Vec<(usize, AutoCommandBuffer)>>,
+}
+
+impl<R> ParallelSecondaryRecorder<R>
+    where R: RenderPassAbstract + Clone + Send + Sync + 'static
+{
+    /// Builds a new recorder for command buffers that continue `subpass`.
+    ///
+    /// The command buffers don't inherit any query. Use `with_inherited_queries` to change that.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the device and the queue family don't belong to the same physical device.
+    ///
+    pub fn new(device: Arc<Device>, queue_family: QueueFamily, subpass: Subpass<R>, flags: Flags)
+               -> ParallelSecondaryRecorder<R> {
+        assert_eq!(device.physical_device().internal_object(),
+                   queue_family.physical_device().internal_object());
+
+        ParallelSecondaryRecorder {
+            device: device,
+            queue_family: queue_family.id(),
+            subpass: subpass,
+            flags: flags,
+            occlusion_query: KindOcclusionQuery::Forbidden,
+            query_statistics_flags: QueryPipelineStatisticFlags::none(),
+            command_buffers: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Sets how the queries of the primary command buffer are inherited by the command buffers.
+    #[inline]
+    pub fn with_inherited_queries(mut self, occlusion_query: KindOcclusionQuery,
+                                  query_statistics_flags: QueryPipelineStatisticFlags)
+                                  -> ParallelSecondaryRecorder<R> {
+        self.occlusion_query = occlusion_query;
+        self.query_statistics_flags = query_statistics_flags;
+        self
+    }
+
+    /// Returns the subpass that the command buffers continue.
+    #[inline]
+    pub fn subpass(&self) -> &Subpass<R> {
+        &self.subpass
+    }
+
+    /// Starts building a secondary graphics command buffer that continues the subpass.
+    ///
+    /// The command buffer is allocated from the pool of the current thread. The builder can't
+    /// be moved to another thread, but the command buffer can once it is built.
+    pub fn builder(&self) -> Result<AutoCommandBufferBuilder, OomError> {
+        let kind = Kind::Secondary {
+            render_pass: Some(KindSecondaryRenderPass {
+                                  subpass: self.subpass.clone(),
+                                  framebuffer:
+                                      None::<Framebuffer<RenderPass<EmptySinglePassRenderPassDesc>,
+                                                         ()>>,
+                              }),
+            occlusion_query: self.occlusion_query,
+            query_statistics_flags: self.query_statistics_flags,
+        };
+
+        let queue_family = self.device
+            .physical_device()
+            .queue_family_by_id(self.queue_family)
+            .unwrap();
+
+        AutoCommandBufferBuilder::with_flags(self.device.clone(), queue_family, kind, self.flags)
+    }
+
+    /// Adds a finished command buffer to the list of command buffers to execute.
+    ///
+    /// The command buffers are executed in increasing order of `position`. Command buffers that
+    /// have the same position are executed in the order in which they were pushed.
+    #[inline]
+    pub fn push(&self, position: usize, command_buffer: AutoCommandBuffer) {
+        self.command_buffers
+            .lock()
+            .unwrap()
+            .push((position, command_buffer));
+    }
+
+    /// Returns the number of command buffers that have been pushed and haven't been executed
+    /// yet.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.command_buffers.lock().unwrap().len()
+    }
+
+    /// Returns true if no command buffer has been pushed since the last call to `execute`.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.command_buffers.lock().unwrap().is_empty()
+    }
+
+    /// Adds a command that executes all the command buffers that have been pushed to
+    /// `primary`, in the order of their position.
+    ///
+    /// The list of command buffers is emptied, so the recorder can be used again afterwards, for
+    /// example for the next frame. If an error is returned, the command buffers are destroyed.
+    pub fn execute<P>(&self, primary: AutoCommandBufferBuilder<P>)
+                      -> Result<AutoCommandBufferBuilder<P>, ExecuteCommandsError> {
+        let mut command_buffers = mem::replace(&mut *self.command_buffers.lock().unwrap(),
+                                               Vec::new());
+        command_buffers.sort_by_key(|&(position, _)| position);
+
+        primary.execute_commands_multiple(command_buffers.into_iter().map(|(_, cb)| cb))
+    }
+}
+
+unsafe impl<R> DeviceOwned for ParallelSecondaryRecorder<R> {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::ParallelSecondaryRecorder;
+    use command_buffer::sys::Flags;
+    use framebuffer::EmptySinglePassRenderPassDesc;
+    use framebuffer::Framebuffer;
+    use framebuffer::RenderPassDesc;
+    use framebuffer::Subpass;
+
+    #[test]
+    fn record_in_parallel() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let render_pass = Arc::new(EmptySinglePassRenderPassDesc
+                                       .build_render_pass(device.clone())
+                                       .unwrap());
+        let framebuffer = Arc::new(Framebuffer::with_dimensions(render_pass.clone(), [64, 64, 1])
+                                       .build()
+                                       .unwrap());
+
+        let recorder = Arc::new(ParallelSecondaryRecorder::new(device.clone(),
+                                                               queue.family(),
+                                                               Subpass::from(render_pass, 0)
+                                                                   .unwrap(),
+                                                               Flags::OneTimeSubmit));
+
+        let threads = (0 .. 4)
+            .map(|num| {
+                let recorder = recorder.clone();
+                thread::spawn(move || {
+                                  let cb = recorder.builder().unwrap().build().unwrap();
+                                  recorder.push(num, cb);
+                              })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(recorder.len(), 4);
+
+        let primary = AutoCommandBufferBuilder::primary_one_time_submit(device, queue.family())
+            .unwrap()
+            .begin_render_pass(framebuffer, true, Vec::new())
+            .unwrap();
+        let primary = recorder.execute(primary).unwrap();
+        assert!(recorder.is_empty());
+
+        primary.end_render_pass().unwrap().build().unwrap();
+    }
+
+    #[test]
+    fn execute_outside_render_pass() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let render_pass = Arc::new(EmptySinglePassRenderPassDesc
+                                       .build_render_pass(device.clone())
+                                       .unwrap());
+
+        let recorder = ParallelSecondaryRecorder::new(device.clone(),
+                                                      queue.family(),
+                                                      Subpass::from(render_pass, 0).unwrap(),
+                                                      Flags::None);
+        let cb = recorder.builder().unwrap().build().unwrap();
+        recorder.push(0, cb);
+
+        let primary = AutoCommandBufferBuilder::primary(device, queue.family()).unwrap();
+        assert!(recorder.execute(primary).is_err());
+        assert!(recorder.is_empty());
+    }
+}
@@ -94,10 +94,29 @@ pub unsafe trait CommandPoolBuilderAlloc: DeviceOwned {
 ///
 /// See `CommandPool` for information about safety.
 ///
-pub unsafe trait CommandPoolAlloc: DeviceOwned {
+pub unsafe trait CommandPoolAlloc: DeviceOwned + Sized {
+    /// Return type of `into_builder`.
+    type Builder: CommandPoolBuilderAlloc<Alloc = Self>;
+
     /// Returns the internal object that contains the command buffer.
     fn inner(&self) -> &UnsafeCommandPoolAlloc;
 
+    /// Turns this command buffer back into a builder, so that it can be recorded again.
+    ///
+    /// Returns `Err` with the command buffer if it can't be recorded again, for example because
+    /// the pool can't be used from the current thread.
+    ///
+    /// The implementation must make sure that the pool was created with the
+    /// reset-command-buffer flag, since the command buffer is implicitly reset when it starts
+    /// being recorded again.
+    ///
+    /// # Safety
+    ///
+    /// - The command buffer must no longer be in use by the GPU, in other words all the
+    ///   submissions that execute it must have finished.
+    ///
+    unsafe fn into_builder(self) -> Result<Self::Builder, Self>;
+
     /// Returns the queue family that the pool targets.
     fn queue_family(&self) -> QueueFamily;
 }
@@ -9,6 +9,7 @@
 
 use crossbeam::queue::MsQueue;
 use fnv::FnvHashMap;
+use std::cell::RefCell;
 use std::collections::hash_map::Entry;
 use std::marker::PhantomData;
 use std::mem::ManuallyDrop;
@@ -16,12 +17,15 @@ use std::ptr;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::sync::Weak;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
 use std::thread;
 use std::vec::IntoIter as VecIntoIter;
 
 use command_buffer::pool::CommandPool;
 use command_buffer::pool::CommandPoolAlloc;
 use command_buffer::pool::CommandPoolBuilderAlloc;
+use command_buffer::pool::CommandPoolTrimError;
 use command_buffer::pool::UnsafeCommandPool;
 use command_buffer::pool::UnsafeCommandPoolAlloc;
 use instance::QueueFamily;
@@ -36,9 +40,15 @@ use device::DeviceOwned;
 /// It is guaranteed that the allocated command buffers keep the `Arc<StandardCommandPool>` alive.
 /// This is desirable so that we can store a `Weak<StandardCommandPool>`.
 ///
-/// Will use one Vulkan pool per thread in order to avoid locking. Will try to reuse command
+/// Will use one Vulkan pool per thread in order to avoid locking. The pool of the current thread
+/// is found through a thread-local cache, so that allocating doesn't need to lock anything that
+/// is shared between threads once a thread has used the pool. Will try to reuse command
 /// buffers. Command buffers can't be moved between threads during the building process, but
 /// finished command buffers can.
+///
+/// The pool of a thread is destroyed once the thread has exited and all the command buffers
+/// allocated from it have been destroyed. Call `trim` to free the memory of the pool of the
+/// current thread while it is still in use.
 pub struct StandardCommandPool {
     // The device.
     device: Arc<Device>,
@@ -46,8 +56,11 @@ pub struct StandardCommandPool {
     // Identifier of the queue family.
     queue_family: u32,
 
+    // Unique identifier of this pool. Used as a key in the thread-local cache.
+    id: usize,
+
     // For each thread, we store thread-specific info.
-    per_thread: Mutex<FnvHashMap<thread::ThreadId, Weak<StandardCommandPoolPerThread>>>,
+    per_thread: Mutex<FnvHashMap<thread::ThreadId, Arc<StandardCommandPoolPerThread>>>,
 }
 
 unsafe impl Send for StandardCommandPool {
@@ -56,12 +69,45 @@ unsafe impl Sync for StandardCommandPool {
 }
 
 struct StandardCommandPoolPerThread {
+    // The thread this pool belongs to.
+    thread: thread::ThreadId,
     // The Vulkan pool of this thread.
     pool: Mutex<UnsafeCommandPool>,
     // List of existing primary command buffers that are available for reuse.
     available_primary_command_buffers: MsQueue<UnsafeCommandPoolAlloc>,
     // List of existing secondary command buffers that are available for reuse.
     available_secondary_command_buffers: MsQueue<UnsafeCommandPoolAlloc>,
+    // Number of command buffers allocated from `pool` that haven't been freed.
+    num_allocated: AtomicUsize,
+    // Number of command buffers in the lists of command buffers available for reuse. Always
+    // incremented before pushing to a list, so that it never underflows.
+    num_free: AtomicUsize,
+}
+
+// Identifier of the next `StandardCommandPool` to be created.
+static NEXT_POOL_ID: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    // Pools of the current thread, indexed by the id of the `StandardCommandPool` they belong to.
+    static THREAD_POOLS: RefCell<ThreadPools> = RefCell::new(ThreadPools {
+        pools: FnvHashMap::default(),
+    });
+}
+
+// Pools of the current thread. Unregisters them from their `StandardCommandPool` when the thread
+// exits.
+struct ThreadPools {
+    pools: FnvHashMap<usize, (Weak<StandardCommandPool>, Weak<StandardCommandPoolPerThread>)>,
+}
+
+impl Drop for ThreadPools {
+    fn drop(&mut self) {
+        for &(ref parent, ref per_thread) in self.pools.values() {
+            if let (Some(parent), Some(per_thread)) = (parent.upgrade(), per_thread.upgrade()) {
+                parent.per_thread.lock().unwrap().remove(&per_thread.thread);
+            }
+        }
+    }
 }
 
 impl StandardCommandPool {
@@ -78,41 +124,146 @@ impl StandardCommandPool {
         StandardCommandPool {
             device: device,
             queue_family: queue_family.id(),
+            id: NEXT_POOL_ID.fetch_add(1, Ordering::Relaxed),
             per_thread: Mutex::new(Default::default()),
         }
     }
+
+    /// Frees the command buffers of the current thread that are waiting to be reused, then gives
+    /// the unused memory of the Vulkan pool of the current thread back to the system.
+    ///
+    /// Only the pool of the current thread is trimmed, because a Vulkan pool can't be used by
+    /// multiple threads at the same time. Command buffers that are being built or that are alive
+    /// are not affected.
+    ///
+    /// Giving back the memory requires the `VK_KHR_maintenance1` extension. If it isn't enabled,
+    /// the command buffers are still freed but an error is returned. Since this operation is
+    /// purely an optimization it is legitimate to simply ignore the error.
+    pub fn trim(&self) -> Result<(), CommandPoolTrimError> {
+        let per_thread = match self.per_thread.lock().unwrap().get(&thread::current().id()) {
+            Some(per_thread) => per_thread.clone(),
+            None => return Ok(()),
+        };
+
+        let pool = per_thread.pool.lock().unwrap();
+
+        for list in &[&per_thread.available_primary_command_buffers,
+                      &per_thread.available_secondary_command_buffers]
+        {
+            let mut command_buffers = Vec::new();
+            while let Some(cmd) = list.try_pop() {
+                command_buffers.push(cmd);
+            }
+
+            if command_buffers.is_empty() {
+                continue;
+            }
+
+            per_thread
+                .num_free
+                .fetch_sub(command_buffers.len(), Ordering::SeqCst);
+            per_thread
+                .num_allocated
+                .fetch_sub(command_buffers.len(), Ordering::SeqCst);
+
+            // Safe because the command buffers available for reuse are no longer in use.
+            unsafe {
+                pool.free_command_buffers(command_buffers.into_iter());
+            }
+        }
+
+        pool.trim()
+    }
+
+    /// Returns the number of command buffers that have been allocated from the Vulkan pools and
+    /// haven't been freed, whether they are alive or available for reuse.
+    ///
+    /// The pools of the threads that have exited are not counted.
+    pub fn num_allocated_command_buffers(&self) -> usize {
+        self.per_thread
+            .lock()
+            .unwrap()
+            .values()
+            .map(|per_thread| per_thread.num_allocated.load(Ordering::SeqCst))
+            .sum()
+    }
+
+    /// Returns the number of command buffers that are available for reuse.
+    ///
+    /// The pools of the threads that have exited are not counted.
+    pub fn num_free_command_buffers(&self) -> usize {
+        self.per_thread
+            .lock()
+            .unwrap()
+            .values()
+            .map(|per_thread| per_thread.num_free.load(Ordering::SeqCst))
+            .sum()
+    }
 }
 
-unsafe impl CommandPool for Arc<StandardCommandPool> {
-    type Iter = VecIntoIter<StandardCommandPoolBuilder>;
-    type Builder = StandardCommandPoolBuilder;
-    type Alloc = StandardCommandPoolAlloc;
+// Returns the pool of the current thread, creating it if necessary.
+fn thread_pool(me: &Arc<StandardCommandPool>)
+               -> Result<Arc<StandardCommandPoolPerThread>, OomError> {
+    // Fast path: the pool is in the thread-local cache. `try_with` fails if we're called while
+    // the thread-local cache is being destroyed, in which case we fall back to the slow path.
+    let cached = THREAD_POOLS
+        .try_with(|pools| {
+                      pools
+                          .borrow()
+                          .pools
+                          .get(&me.id)
+                          .and_then(|&(_, ref per_thread)| per_thread.upgrade())
+                  })
+        .unwrap_or(None);
+    if let Some(per_thread) = cached {
+        return Ok(per_thread);
+    }
 
-    fn alloc(&self, secondary: bool, count: u32) -> Result<Self::Iter, OomError> {
-        // Find the correct `StandardCommandPoolPerThread` structure.
-        let mut hashmap = self.per_thread.lock().unwrap();
-        // TODO: meh for iterating everything every time
-        hashmap.retain(|_, w| w.upgrade().is_some());
-
-        // Get an appropriate `Arc<StandardCommandPoolPerThread>`.
-        let per_thread = match hashmap.entry(thread::current().id()) {
-            Entry::Occupied(entry) => {
-                // The `unwrap()` can't fail, since we retained only valid members earlier.
-                entry.get().upgrade().unwrap()
-            },
+    // Slow path: find or create the pool in the map shared between threads.
+    let per_thread = {
+        let mut hashmap = me.per_thread.lock().unwrap();
+        match hashmap.entry(thread::current().id()) {
+            Entry::Occupied(entry) => entry.get().clone(),
             Entry::Vacant(entry) => {
+                // Command buffers need to be individually resettable in order to be reused.
                 let new_pool =
-                    UnsafeCommandPool::new(self.device.clone(), self.queue_family(), false, true)?;
+                    UnsafeCommandPool::new(me.device.clone(), me.queue_family(), false, true)?;
                 let pt = Arc::new(StandardCommandPoolPerThread {
+                                      thread: *entry.key(),
                                       pool: Mutex::new(new_pool),
                                       available_primary_command_buffers: MsQueue::new(),
                                       available_secondary_command_buffers: MsQueue::new(),
+                                      num_allocated: AtomicUsize::new(0),
+                                      num_free: AtomicUsize::new(0),
                                   });
 
-                entry.insert(Arc::downgrade(&pt));
+                entry.insert(pt.clone());
                 pt
             },
-        };
+        }
+    };
+
+    let _ = THREAD_POOLS.try_with(|pools| {
+        let mut pools = pools.borrow_mut();
+        // Remove the entries of the `StandardCommandPool`s that have been destroyed.
+        pools
+            .pools
+            .retain(|_, &mut (ref parent, _)| parent.upgrade().is_some());
+        pools
+            .pools
+            .insert(me.id, (Arc::downgrade(me), Arc::downgrade(&per_thread)));
+    });
+
+    Ok(per_thread)
+}
+
+unsafe impl CommandPool for Arc<StandardCommandPool> {
+    type Iter = VecIntoIter<StandardCommandPoolBuilder>;
+    type Builder = StandardCommandPoolBuilder;
+    type Alloc = StandardCommandPoolAlloc;
+
+    fn alloc(&self, secondary: bool, count: u32) -> Result<Self::Iter, OomError> {
+        let per_thread = thread_pool(self)?;
 
         // The final output.
         let mut output = Vec::with_capacity(count as usize);
@@ -127,6 +278,7 @@ unsafe impl CommandPool for Arc<StandardCommandPool> {
 
             for _ in 0 .. count as usize {
                 if let Some(cmd) = existing.try_pop() {
+                    per_thread.num_free.fetch_sub(1, Ordering::SeqCst);
                     output.push(StandardCommandPoolBuilder {
                                     inner: StandardCommandPoolAlloc {
                                         cmd: ManuallyDrop::new(cmd),
@@ -160,6 +312,8 @@ unsafe impl CommandPool for Arc<StandardCommandPool> {
                                 dummy_avoid_send_sync: PhantomData,
                             });
             }
+
+            per_thread.num_allocated.fetch_add(num_new, Ordering::SeqCst);
         }
 
         // Final output.
@@ -237,11 +391,26 @@ unsafe impl Sync for StandardCommandPoolAlloc {
 }
 
 unsafe impl CommandPoolAlloc for StandardCommandPoolAlloc {
+    type Builder = StandardCommandPoolBuilder;
+
     #[inline]
     fn inner(&self) -> &UnsafeCommandPoolAlloc {
         &*self.cmd
     }
 
+    #[inline]
+    unsafe fn into_builder(self) -> Result<StandardCommandPoolBuilder, StandardCommandPoolAlloc> {
+        // The Vulkan pool can only be used by the thread it belongs to.
+        if self.pool.thread != thread::current().id() {
+            return Err(self);
+        }
+
+        Ok(StandardCommandPoolBuilder {
+               inner: self,
+               dummy_avoid_send_sync: PhantomData,
+           })
+    }
+
     #[inline]
     fn queue_family(&self) -> QueueFamily {
         let queue_family_id = self.pool.pool.lock().unwrap().queue_family().id();
@@ -267,6 +436,7 @@ impl Drop for StandardCommandPoolAlloc {
         // Safe because `self.cmd` is wrapped in a `ManuallyDrop`.
         let cmd: UnsafeCommandPoolAlloc = unsafe { ptr::read(&*self.cmd) };
 
+        self.pool.num_free.fetch_add(1, Ordering::SeqCst);
         if self.secondary {
             self.pool.available_secondary_command_buffers.push(cmd);
         } else {
@@ -279,10 +449,12 @@ impl Drop for StandardCommandPoolAlloc {
 mod tests {
     use VulkanObject;
     use command_buffer::pool::CommandPool;
+    use command_buffer::pool::CommandPoolAlloc;
     use command_buffer::pool::CommandPoolBuilderAlloc;
     use command_buffer::pool::StandardCommandPool;
     use device::Device;
     use std::sync::Arc;
+    use std::thread;
 
     #[test]
     fn reuse_command_buffers() {
@@ -313,4 +485,89 @@ mod tests {
         drop(cb);
         assert!(pool_weak.upgrade().is_none());
     }
+
+    #[test]
+    fn into_builder() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let pool = Device::standard_command_pool(&device, queue.family());
+        let cb = pool.alloc(false, 1).unwrap().next().unwrap();
+        let raw = cb.inner().internal_object();
+
+        let builder = unsafe { cb.into_alloc().into_builder().ok().unwrap() };
+        assert_eq!(raw, builder.inner().internal_object());
+    }
+
+    #[test]
+    fn into_builder_other_thread() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let pool = Device::standard_command_pool(&device, queue.family());
+        let alloc = pool.alloc(false, 1).unwrap().next().unwrap().into_alloc();
+
+        thread::spawn(move || unsafe { assert!(alloc.into_builder().is_err()); })
+            .join()
+            .unwrap();
+    }
+
+    #[test]
+    fn counters() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let pool = Arc::new(StandardCommandPool::new(device, queue.family()));
+        assert_eq!(pool.num_allocated_command_buffers(), 0);
+        assert_eq!(pool.num_free_command_buffers(), 0);
+
+        let cbs = pool.alloc(false, 2).unwrap().collect::<Vec<_>>();
+        assert_eq!(pool.num_allocated_command_buffers(), 2);
+        assert_eq!(pool.num_free_command_buffers(), 0);
+
+        drop(cbs);
+        assert_eq!(pool.num_allocated_command_buffers(), 2);
+        assert_eq!(pool.num_free_command_buffers(), 2);
+
+        let _cb = pool.alloc(false, 1).unwrap().next().unwrap();
+        assert_eq!(pool.num_allocated_command_buffers(), 2);
+        assert_eq!(pool.num_free_command_buffers(), 1);
+    }
+
+    #[test]
+    fn one_pool_per_thread() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let pool = Arc::new(StandardCommandPool::new(device, queue.family()));
+        let cb = pool.alloc(false, 1).unwrap().next().unwrap();
+        let raw = cb.inner().internal_object();
+        drop(cb);
+
+        let pool2 = pool.clone();
+        let raw2 = thread::spawn(move || {
+                                     let cb = pool2.alloc(false, 1).unwrap().next().unwrap();
+                                     cb.inner().internal_object()
+                                 })
+            .join()
+            .unwrap();
+        assert_ne!(raw, raw2);
+
+        // The pool of the other thread is gone since the thread has exited.
+        assert_eq!(pool.num_allocated_command_buffers(), 1);
+    }
+
+    #[test]
+    fn trim() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let pool = Arc::new(StandardCommandPool::new(device, queue.family()));
+        let cbs = pool.alloc(false, 2).unwrap().collect::<Vec<_>>();
+        let cb = pool.alloc(true, 1).unwrap().next().unwrap();
+        drop(cbs);
+
+        // Trimming requires `VK_KHR_maintenance1`, which isn't necessarily enabled.
+        let _ = pool.trim();
+        assert_eq!(pool.num_allocated_command_buffers(), 1);
+        assert_eq!(pool.num_free_command_buffers(), 0);
+
+        drop(cb);
+        assert_eq!(pool.num_free_command_buffers(), 1);
+    }
 }
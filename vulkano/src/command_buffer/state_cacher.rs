@@ -30,10 +30,10 @@ pub struct StateCacher {
     compute_pipeline: vk::Pipeline,
     // The graphics pipeline currently bound. 0 if nothing bound.
     graphics_pipeline: vk::Pipeline,
-    // The descriptor sets for the compute pipeline.
-    compute_descriptor_sets: SmallVec<[vk::DescriptorSet; 12]>,
-    // The descriptor sets for the graphics pipeline.
-    graphics_descriptor_sets: SmallVec<[vk::DescriptorSet; 12]>,
+    // The descriptor sets for the compute pipeline, with their dynamic offsets.
+    compute_descriptor_sets: SmallVec<[(vk::DescriptorSet, Vec<u32>); 12]>,
+    // The descriptor sets for the graphics pipeline, with their dynamic offsets.
+    graphics_descriptor_sets: SmallVec<[(vk::DescriptorSet, Vec<u32>); 12]>,
     // If the user starts comparing descriptor sets, but drops the helper struct in the middle of
     // the processing then we will end up in a weird state. This bool is true when we start
     // comparing sets, and is set to false when we end up comparing. If it was true when we start
@@ -108,6 +108,12 @@ impl StateCacher {
         cmp!(line_width);
         cmp!(viewports);
         cmp!(scissors);
+        cmp!(depth_bias);
+        cmp!(depth_bounds);
+        cmp!(compare_mask);
+        cmp!(write_mask);
+        cmp!(reference);
+        cmp!(blend_constants);
 
         changed
     }
@@ -235,7 +241,7 @@ pub struct StateCacherDescriptorSets<'s> {
     // Reference to the parent's `poisoned_descriptor_sets`.
     poisoned: &'s mut bool,
     // Reference to the descriptor sets list to compare to.
-    state: &'s mut SmallVec<[vk::DescriptorSet; 12]>,
+    state: &'s mut SmallVec<[(vk::DescriptorSet, Vec<u32>); 12]>,
     // Next offset within the list to compare to.
     offset: usize,
     // Contains the return value of `compare`.
@@ -243,23 +249,23 @@ pub struct StateCacherDescriptorSets<'s> {
 }
 
 impl<'s> StateCacherDescriptorSets<'s> {
-    /// Adds a descriptor set to the list to compare.
+    /// Adds a descriptor set to the list to compare, along with its dynamic offsets.
     #[inline]
-    pub fn add<S>(&mut self, set: &S)
+    pub fn add<S>(&mut self, set: &S, dynamic_offsets: &[u32])
         where S: ?Sized + DescriptorSet
     {
         let raw = set.inner().internal_object();
 
         if self.offset < self.state.len() {
-            if self.state[self.offset] == raw {
+            if self.state[self.offset].0 == raw && self.state[self.offset].1 == dynamic_offsets {
                 self.offset += 1;
                 return;
             }
 
-            self.state[self.offset] = raw;
+            self.state[self.offset] = (raw, dynamic_offsets.to_vec());
 
         } else {
-            self.state.push(raw);
+            self.state.push((raw, dynamic_offsets.to_vec()));
         }
 
         if self.found_diff.is_none() {
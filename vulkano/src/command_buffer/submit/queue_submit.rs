@@ -11,6 +11,7 @@ use smallvec::SmallVec;
 use std::error;
 use std::fmt;
 use std::marker::PhantomData;
+use std::mem;
 use std::ptr;
 
 use command_buffer::sys::UnsafeCommandBuffer;
@@ -27,31 +28,95 @@ use check_errors;
 use vk;
 
 /// Prototype for a submission that executes command buffers.
+///
+/// The submission is made of one or more batches, each with its own semaphores to wait upon,
+/// command buffers and semaphores to signal. A semaphore wait only applies to the command buffers
+/// of its own batch, and a semaphore signal only waits for the command buffers of its own batch.
+///
+/// Merging two builders keeps their batches separate, so that the command buffers of one don't
+/// wait upon the semaphores of the other. Adding a semaphore or a command buffer afterwards
+/// collapses all the batches into one, so that the new element is ordered with all of them.
 // TODO: example here
 #[derive(Debug)]
 pub struct SubmitCommandBufferBuilder<'a> {
+    batches: SmallVec<[Batch; 4]>,
+    fence: vk::Fence,
+    marker: PhantomData<&'a ()>,
+}
+
+// A single `VkSubmitInfo` of the submission.
+#[derive(Debug)]
+struct Batch {
     wait_semaphores: SmallVec<[vk::Semaphore; 16]>,
     destination_stages: SmallVec<[vk::PipelineStageFlags; 8]>,
     signal_semaphores: SmallVec<[vk::Semaphore; 16]>,
     command_buffers: SmallVec<[vk::CommandBuffer; 4]>,
-    fence: vk::Fence,
-    marker: PhantomData<&'a ()>,
 }
 
-impl<'a> SubmitCommandBufferBuilder<'a> {
-    /// Builds a new empty `SubmitCommandBufferBuilder`.
+impl Batch {
     #[inline]
-    pub fn new() -> SubmitCommandBufferBuilder<'a> {
-        SubmitCommandBufferBuilder {
+    fn new() -> Batch {
+        Batch {
             wait_semaphores: SmallVec::new(),
             destination_stages: SmallVec::new(),
             signal_semaphores: SmallVec::new(),
             command_buffers: SmallVec::new(),
+        }
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.wait_semaphores.is_empty() && self.signal_semaphores.is_empty() &&
+            self.command_buffers.is_empty()
+    }
+}
+
+impl<'a> SubmitCommandBufferBuilder<'a> {
+    /// Builds a new empty `SubmitCommandBufferBuilder`.
+    #[inline]
+    pub fn new() -> SubmitCommandBufferBuilder<'a> {
+        SubmitCommandBufferBuilder {
+            batches: SmallVec::new(),
             fence: 0,
             marker: PhantomData,
         }
     }
 
+    /// Returns the number of batches of the submission.
+    ///
+    /// This is greater than one only if the builder is the result of `merge` and nothing has been
+    /// added to it since.
+    #[inline]
+    pub fn num_batches(&self) -> usize {
+        self.batches.len()
+    }
+
+    // Returns the batch that new elements are added to.
+    //
+    // If the builder holds the batches of several merged builders, they are collapsed into one
+    // first. A semaphore wait only applies to its own batch, so keeping them separate would let a
+    // new command buffer skip the waits of the other batches, and a new semaphore signal skip
+    // their command buffers.
+    fn current_batch(&mut self) -> &mut Batch {
+        if self.batches.len() >= 2 {
+            let mut batches = mem::replace(&mut self.batches, SmallVec::new()).into_iter();
+            let mut collapsed = batches.next().unwrap();
+            for batch in batches {
+                collapsed.wait_semaphores.extend(batch.wait_semaphores);
+                collapsed.destination_stages.extend(batch.destination_stages);
+                collapsed.signal_semaphores.extend(batch.signal_semaphores);
+                collapsed.command_buffers.extend(batch.command_buffers);
+            }
+            self.batches.push(collapsed);
+        }
+
+        if self.batches.is_empty() {
+            self.batches.push(Batch::new());
+        }
+
+        self.batches.last_mut().unwrap()
+    }
+
     /// Returns true if this builder will signal a fence when submitted.
     ///
     /// # Example
@@ -143,13 +208,16 @@ impl<'a> SubmitCommandBufferBuilder<'a> {
     pub unsafe fn add_wait_semaphore(&mut self, semaphore: &'a Semaphore, stages: PipelineStages) {
         debug_assert!(stages.into_vulkan_bits() != 0);
         // TODO: debug assert that the device supports the stages
-        self.wait_semaphores.push(semaphore.internal_object());
-        self.destination_stages.push(stages.into_vulkan_bits());
+        let batch = self.current_batch();
+        batch.wait_semaphores.push(semaphore.internal_object());
+        batch.destination_stages.push(stages.into_vulkan_bits());
     }
 
     /// Adds a command buffer that is executed as part of this command.
     ///
-    /// The command buffers are submitted in the order in which they are added.
+    /// The command buffers are submitted in the order in which they are added. If the builder is
+    /// the result of `merge`, the command buffer waits upon the semaphores of all the merged
+    /// builders.
     ///
     /// # Safety
     ///
@@ -167,7 +235,9 @@ impl<'a> SubmitCommandBufferBuilder<'a> {
     ///
     #[inline]
     pub unsafe fn add_command_buffer<P>(&mut self, command_buffer: &'a UnsafeCommandBuffer<P>) {
-        self.command_buffers.push(command_buffer.internal_object());
+        self.current_batch()
+            .command_buffers
+            .push(command_buffer.internal_object());
     }
 
     /// Returns the number of semaphores to signal.
@@ -175,11 +245,17 @@ impl<'a> SubmitCommandBufferBuilder<'a> {
     /// In other words, this is the number of times `add_signal_semaphore` has been called.
     #[inline]
     pub fn num_signal_semaphores(&self) -> usize {
-        self.signal_semaphores.len()
+        self.batches
+            .iter()
+            .map(|b| b.signal_semaphores.len())
+            .sum()
     }
 
     /// Adds a semaphore that is going to be signaled at the end of the submission.
     ///
+    /// The semaphore is signaled once all the command buffers of the submission have finished
+    /// executing.
+    ///
     /// # Safety
     ///
     /// - If you submit this builder, the semaphore must be kept alive until you are guaranteed
@@ -192,7 +268,9 @@ impl<'a> SubmitCommandBufferBuilder<'a> {
     ///
     #[inline]
     pub unsafe fn add_signal_semaphore(&mut self, semaphore: &'a Semaphore) {
-        self.signal_semaphores.push(semaphore.internal_object());
+        self.current_batch()
+            .signal_semaphores
+            .push(semaphore.internal_object());
     }
 
     /// Submits the command buffer to the given queue.
@@ -205,39 +283,51 @@ impl<'a> SubmitCommandBufferBuilder<'a> {
             let vk = queue.device().pointers();
             let queue = queue.internal_object_guard();
 
-            debug_assert_eq!(self.wait_semaphores.len(), self.destination_stages.len());
-
-            let batch = vk::SubmitInfo {
-                sType: vk::STRUCTURE_TYPE_SUBMIT_INFO,
-                pNext: ptr::null(),
-                waitSemaphoreCount: self.wait_semaphores.len() as u32,
-                pWaitSemaphores: self.wait_semaphores.as_ptr(),
-                pWaitDstStageMask: self.destination_stages.as_ptr(),
-                commandBufferCount: self.command_buffers.len() as u32,
-                pCommandBuffers: self.command_buffers.as_ptr(),
-                signalSemaphoreCount: self.signal_semaphores.len() as u32,
-                pSignalSemaphores: self.signal_semaphores.as_ptr(),
-            };
-
-            check_errors(vk.QueueSubmit(*queue, 1, &batch, self.fence))?;
+            let batches = self.batches
+                .iter()
+                .map(|batch| {
+                    debug_assert_eq!(batch.wait_semaphores.len(),
+                                     batch.destination_stages.len());
+
+                    vk::SubmitInfo {
+                        sType: vk::STRUCTURE_TYPE_SUBMIT_INFO,
+                        pNext: ptr::null(),
+                        waitSemaphoreCount: batch.wait_semaphores.len() as u32,
+                        pWaitSemaphores: batch.wait_semaphores.as_ptr(),
+                        pWaitDstStageMask: batch.destination_stages.as_ptr(),
+                        commandBufferCount: batch.command_buffers.len() as u32,
+                        pCommandBuffers: batch.command_buffers.as_ptr(),
+                        signalSemaphoreCount: batch.signal_semaphores.len() as u32,
+                        pSignalSemaphores: batch.signal_semaphores.as_ptr(),
+                    }
+                })
+                .collect::<SmallVec<[_; 4]>>();
+
+            check_errors(vk.QueueSubmit(*queue,
+                                        batches.len() as u32,
+                                        batches.as_ptr(),
+                                        self.fence))?;
             Ok(())
         }
     }
 
     /// Merges this builder with another builder.
     ///
+    /// The batches of `other` are submitted after the batches of `self` and are kept separate, so
+    /// that the command buffers of each builder only wait upon the semaphores of that builder.
+    /// Anything that is added to the merged builder afterwards collapses the batches into one, so
+    /// that a command buffer added afterwards waits upon the semaphores of both builders, and a
+    /// semaphore signal added afterwards waits for the command buffers of both builders.
+    ///
     /// # Panic
     ///
     /// Panics if both builders have a fence already set.
-    // TODO: create multiple batches instead
     pub fn merge(mut self, other: Self) -> Self {
         assert!(self.fence == 0 || other.fence == 0,
                 "Can't merge two queue submits that both have a fence");
 
-        self.wait_semaphores.extend(other.wait_semaphores);
-        self.destination_stages.extend(other.destination_stages); // TODO: meh? will be solved if we submit multiple batches
-        self.signal_semaphores.extend(other.signal_semaphores);
-        self.command_buffers.extend(other.command_buffers);
+        self.batches
+            .extend(other.batches.into_iter().filter(|b| !b.is_empty()));
 
         if self.fence == 0 {
             self.fence = other.fence;
@@ -299,6 +389,8 @@ impl From<Error> for SubmitCommandBufferError {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::CommandBuffer;
     use std::time::Duration;
     use sync::Fence;
 
@@ -358,4 +450,128 @@ mod tests {
             });
         }
     }
+
+    // Returns the semaphores that the given command buffer waits upon.
+    fn waits_of<P>(builder: &SubmitCommandBufferBuilder, command_buffer: &UnsafeCommandBuffer<P>)
+                   -> Vec<vk::Semaphore> {
+        let command_buffer = command_buffer.internal_object();
+        let batch = builder
+            .batches
+            .iter()
+            .find(|b| b.command_buffers.contains(&command_buffer))
+            .unwrap();
+        batch.wait_semaphores.iter().cloned().collect()
+    }
+
+    // Returns the command buffers that the given semaphore waits for before being signaled.
+    fn signaled_after(builder: &SubmitCommandBufferBuilder, semaphore: &Semaphore)
+                      -> Vec<vk::CommandBuffer> {
+        let semaphore = semaphore.internal_object();
+        let batch = builder
+            .batches
+            .iter()
+            .find(|b| b.signal_semaphores.contains(&semaphore))
+            .unwrap();
+        batch.command_buffers.iter().cloned().collect()
+    }
+
+    #[test]
+    fn merge_keeps_waits_separate() {
+        unsafe {
+            let (device, queue) = gfx_dev_and_queue!();
+
+            let semaphore1 = Semaphore::alloc(device.clone()).unwrap();
+            let semaphore2 = Semaphore::alloc(device.clone()).unwrap();
+            let stages = PipelineStages {
+                all_commands: true,
+                ..PipelineStages::none()
+            };
+            let cb1 = AutoCommandBufferBuilder::primary(device.clone(), queue.family())
+                .unwrap()
+                .build()
+                .unwrap();
+            let cb2 = AutoCommandBufferBuilder::primary(device.clone(), queue.family())
+                .unwrap()
+                .build()
+                .unwrap();
+
+            let mut builder1 = SubmitCommandBufferBuilder::new();
+            builder1.add_wait_semaphore(&semaphore1, stages);
+            builder1.add_command_buffer(cb1.inner());
+            let mut builder2 = SubmitCommandBufferBuilder::new();
+            builder2.add_wait_semaphore(&semaphore2, stages);
+            builder2.add_command_buffer(cb2.inner());
+
+            let merged = builder1.merge(builder2);
+            assert_eq!(merged.num_batches(), 2);
+            assert_eq!(waits_of(&merged, cb1.inner()), vec![semaphore1.internal_object()]);
+            assert_eq!(waits_of(&merged, cb2.inner()), vec![semaphore2.internal_object()]);
+        }
+    }
+
+    #[test]
+    fn join_then_execute() {
+        unsafe {
+            let (device, queue) = gfx_dev_and_queue!();
+
+            let semaphore1 = Semaphore::alloc(device.clone()).unwrap();
+            let semaphore2 = Semaphore::alloc(device.clone()).unwrap();
+            let stages = PipelineStages {
+                all_commands: true,
+                ..PipelineStages::none()
+            };
+            let cbs = (0 .. 3)
+                .map(|_| {
+                    AutoCommandBufferBuilder::primary(device.clone(), queue.family())
+                        .unwrap()
+                        .build()
+                        .unwrap()
+                })
+                .collect::<Vec<_>>();
+
+            let mut builder1 = SubmitCommandBufferBuilder::new();
+            builder1.add_wait_semaphore(&semaphore1, stages);
+            builder1.add_command_buffer(cbs[0].inner());
+            let mut builder2 = SubmitCommandBufferBuilder::new();
+            builder2.add_wait_semaphore(&semaphore2, stages);
+            builder2.add_command_buffer(cbs[1].inner());
+
+            // The command buffer executed after the join must wait upon the semaphores of both
+            // sides, and the command buffers of both sides must still wait upon their own.
+            let mut merged = builder1.merge(builder2);
+            merged.add_command_buffer(cbs[2].inner());
+            let both = vec![semaphore1.internal_object(), semaphore2.internal_object()];
+            assert_eq!(merged.num_batches(), 1);
+            for cb in &cbs {
+                assert_eq!(waits_of(&merged, cb.inner()), both);
+            }
+        }
+    }
+
+    #[test]
+    fn join_then_signal() {
+        unsafe {
+            let (device, queue) = gfx_dev_and_queue!();
+
+            let semaphore = Semaphore::alloc(device.clone()).unwrap();
+            let cb1 = AutoCommandBufferBuilder::primary(device.clone(), queue.family())
+                .unwrap()
+                .build()
+                .unwrap();
+            let cb2 = AutoCommandBufferBuilder::primary(device.clone(), queue.family())
+                .unwrap()
+                .build()
+                .unwrap();
+
+            let mut builder1 = SubmitCommandBufferBuilder::new();
+            builder1.add_command_buffer(cb1.inner());
+            let mut builder2 = SubmitCommandBufferBuilder::new();
+            builder2.add_command_buffer(cb2.inner());
+
+            let mut merged = builder1.merge(builder2);
+            merged.add_signal_semaphore(&semaphore);
+            assert_eq!(signaled_after(&merged, &semaphore),
+                       vec![cb1.inner().internal_object(), cb2.inner().internal_object()]);
+        }
+    }
 }
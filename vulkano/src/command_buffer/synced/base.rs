@@ -13,15 +13,24 @@ use std::collections::hash_map::Entry;
 use std::error;
 use std::fmt;
 use std::hash::{Hash, Hasher};
+use std::iter;
+use std::mem;
 use std::sync::Arc;
 use std::sync::Mutex;
 
 use OomError;
 use buffer::BufferAccess;
 use command_buffer::CommandBufferExecError;
+use command_buffer::SecondaryResourceUsage;
 use command_buffer::pool::CommandPool;
 use command_buffer::pool::CommandPoolAlloc;
 use command_buffer::pool::CommandPoolBuilderAlloc;
+use command_buffer::synced::BarrierReport;
+use command_buffer::synced::BarrierReportCommand;
+use command_buffer::synced::BarrierReportEntry;
+use command_buffer::synced::InsertedBarrier;
+use command_buffer::synced::InsertedBarrierResource;
+use command_buffer::synced::InsertedBarrierTy;
 use command_buffer::sys::Flags;
 use command_buffer::sys::Kind;
 use command_buffer::sys::UnsafeCommandBuffer;
@@ -37,8 +46,12 @@ use image::ImageLayout;
 use sync::AccessCheckError;
 use sync::AccessError;
 use sync::AccessFlagBits;
+use sync::Event;
 use sync::GpuFuture;
+use sync::OwnershipTransfer;
+use sync::OwnershipTransferResource;
 use sync::PipelineStages;
+use VulkanObject;
 
 /// Wrapper around `UnsafeCommandBufferBuilder` that handles synchronization for you.
 ///
@@ -69,10 +82,28 @@ pub struct SyncCommandBufferBuilder<P> {
     // in `commands`.
     pending_barrier: UnsafeCommandBufferBuilderPipelineBarrier,
 
+    // Information about the commands that `pending_barrier` waits upon.
+    pending_barrier_sources: PendingBarrierSources,
+
+    // Description of the resources in `pending_barrier`, for the barrier report.
+    pending_barrier_report: Vec<InsertedBarrierResource>,
+
+    // List of the commands and barriers that have been sent to `inner` so far. `None` if the
+    // barrier report isn't enabled.
+    barrier_report: Option<BarrierReport>,
+
+    // If a split barrier has been signaled and hasn't been waited upon yet, contains the event
+    // that can be waited upon instead of submitting `pending_barrier` as a full pipeline barrier.
+    split_barrier: Option<SplitBarrier>,
+
     // Stores all the commands that were added to the sync builder. Some of them are maybe not
     // submitted to the inner builder yet. A copy of this `Arc` is stored in each `BuilderKey`.
     commands: Arc<Mutex<Commands<P>>>,
 
+    // Queue family ownership transfers released and acquired by the commands.
+    ownership_releases: Vec<OwnershipTransferRecord>,
+    ownership_acquires: Vec<OwnershipTransferRecord>,
+
     // True if we're a secondary command buffer.
     is_secondary: bool,
 }
@@ -131,6 +162,16 @@ pub struct SyncCommandBufferBuilder<P> {
 // command. If so, it will try to merge this barrier with the prototype and add the command to the
 // queue. If not possible, the queue will be entirely flushed and the command added to a fresh new
 // queue with a fresh new barrier prototype.
+//
+// ## Split barriers
+//
+// A pipeline barrier blocks the queue until the source stages of all the commands before it have
+// finished, even if only some of these commands are actually relevant. If the user signals an
+// event with `signal_split_barrier`, the builder remembers which commands were recorded before
+// the event was set. When the prototype barrier is flushed and all of its source commands are
+// before the event, the barrier is submitted as a `vkCmdWaitEvents` instead. The commands that
+// were recorded between the `vkCmdSetEvent` and the wait can then overlap with the commands
+// before the event. The event is waited upon at most once, after which the builder forgets it.
 
 impl<P> fmt::Debug for SyncCommandBufferBuilder<P> {
     #[inline]
@@ -219,12 +260,39 @@ pub trait Command<P> {
     fn image_name(&self, _num: usize) -> Cow<'static, str> {
         panic!()
     }
+
+    // Returns true if the `num`th buffer or image used by the command conflicts with `buf`.
+    fn conflicts_buffer(&self, resource_ty: KeyTy, num: usize, buf: &BufferAccess) -> bool {
+        match resource_ty {
+            KeyTy::Buffer => self.buffer(num).conflicts_buffer(buf),
+            KeyTy::Image => self.image(num).conflicts_buffer(buf),
+        }
+    }
+
+    // Returns true if the `num`th buffer or image used by the command conflicts with `img`.
+    fn conflicts_image(&self, resource_ty: KeyTy, num: usize, img: &ImageAccess) -> bool {
+        match resource_ty {
+            KeyTy::Buffer => self.buffer(num).conflicts_image(img),
+            KeyTy::Image => self.image(num).conflicts_image(img),
+        }
+    }
+
+    // Returns the Vulkan handle of the `num`th buffer or image used by the command, for the
+    // barrier report.
+    fn resource_handle(&self, resource_ty: KeyTy, num: usize) -> u64 {
+        match resource_ty {
+            KeyTy::Buffer => self.buffer(num).inner().buffer.internal_object(),
+            KeyTy::Image => self.image(num).inner().image.internal_object(),
+        }
+    }
 }
 
 /// Type of resource whose state is to be tracked.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum KeyTy {
+    /// The resource is a buffer, accessed through `BufferAccess`.
     Buffer,
+    /// The resource is an image, accessed through `ImageAccess`.
     Image,
 }
 
@@ -247,7 +315,7 @@ struct BuilderKey<P> {
 impl<P> BuilderKey<P> {
     // Turns this key used by the builder into a key used by the final command buffer.
     // Called when the command buffer is being built.
-    fn into_cb_key(self, final_commands: Arc<Mutex<Vec<Box<FinalCommand + Send + Sync>>>>)
+    fn into_cb_key(self, final_commands: Arc<Vec<Box<FinalCommand + Send + Sync>>>)
                    -> CbKey<'static> {
         CbKey::Command {
             commands: final_commands,
@@ -259,32 +327,16 @@ impl<P> BuilderKey<P> {
 
     #[inline]
     fn conflicts_buffer(&self, commands_lock: &Commands<P>, buf: &BufferAccess) -> bool {
-        // TODO: put the conflicts_* methods directly on the Command trait to avoid an indirect call?
-        match self.resource_ty {
-            KeyTy::Buffer => {
-                let c = &commands_lock.commands[self.command_id];
-                c.buffer(self.resource_index).conflicts_buffer(buf)
-            },
-            KeyTy::Image => {
-                let c = &commands_lock.commands[self.command_id];
-                c.image(self.resource_index).conflicts_buffer(buf)
-            },
-        }
+        commands_lock.commands[self.command_id].conflicts_buffer(self.resource_ty,
+                                                                 self.resource_index,
+                                                                 buf)
     }
 
     #[inline]
     fn conflicts_image(&self, commands_lock: &Commands<P>, img: &ImageAccess) -> bool {
-        // TODO: put the conflicts_* methods directly on the Command trait to avoid an indirect call?
-        match self.resource_ty {
-            KeyTy::Buffer => {
-                let c = &commands_lock.commands[self.command_id];
-                c.buffer(self.resource_index).conflicts_image(img)
-            },
-            KeyTy::Image => {
-                let c = &commands_lock.commands[self.command_id];
-                c.image(self.resource_index).conflicts_image(img)
-            },
-        }
+        commands_lock.commands[self.command_id].conflicts_image(self.resource_ty,
+                                                                self.resource_index,
+                                                                img)
     }
 }
 
@@ -328,14 +380,152 @@ impl<P> Hash for BuilderKey<P> {
     }
 }
 
+// Information about the commands that the pending barrier of the builder waits upon.
+#[derive(Debug, Copy, Clone)]
+struct PendingBarrierSources {
+    // Index of the latest command that is the source of a barrier in the pending barrier.
+    latest_command: Option<usize>,
+    // Union of the source stages of the barriers.
+    stages: PipelineStages,
+    // False if the pending barrier contains a barrier that doesn't originate from a command of
+    // this command buffer, in which case it can't be turned into a wait on an event.
+    splittable: bool,
+}
+
+impl PendingBarrierSources {
+    #[inline]
+    fn new() -> PendingBarrierSources {
+        PendingBarrierSources {
+            latest_command: None,
+            stages: PipelineStages::none(),
+            splittable: true,
+        }
+    }
+}
+
+// Event signaled with `signal_split_barrier`.
+struct SplitBarrier {
+    event: Arc<Event>,
+    // Index of the `vkCmdSetEvent` command in the list of commands.
+    command_id: usize,
+    // Stages that were passed to `vkCmdSetEvent`.
+    stages: PipelineStages,
+}
+
+// Submits the pending barrier to `inner`, either as a pipeline barrier or as a wait on the split
+// barrier event if possible, and resets it.
+//
+// `first_unflushed` is the index of the first command that hasn't been sent to `inner` yet. If
+// the split barrier is waited upon, it is consumed and `split_barrier` is set to `None`.
+//
+// The resources of `pending_report` are moved to a new entry of `report`, if the barrier report
+// is enabled.
+unsafe fn flush_pending_barrier<P>(inner: &mut UnsafeCommandBufferBuilder<P>,
+                                   barrier: &mut UnsafeCommandBufferBuilderPipelineBarrier,
+                                   sources: &mut PendingBarrierSources,
+                                   split_barrier: &mut Option<SplitBarrier>,
+                                   first_unflushed: usize, report: &mut Option<BarrierReport>,
+                                   pending_report: &mut Vec<InsertedBarrierResource>) {
+    let use_split_barrier = match *split_barrier {
+        Some(ref split) => {
+            !barrier.is_empty() && sources.splittable && split.command_id < first_unflushed &&
+                sources.latest_command.map(|c| c < split.command_id).unwrap_or(true) &&
+                (sources.stages | split.stages) == split.stages
+        },
+        None => false,
+    };
+
+    let ty = if use_split_barrier {
+        let split = split_barrier.take().unwrap();
+
+        // The source stages of the wait must be exactly the stages the event was signaled
+        // with. Adding `top_of_pipe` to the destination doesn't add any dependency.
+        barrier.add_execution_dependency(split.stages,
+                                         PipelineStages {
+                                             top_of_pipe: true,
+                                             ..PipelineStages::none()
+                                         },
+                                         true);
+        inner.wait_events(iter::once(&*split.event), barrier);
+        InsertedBarrierTy::WaitEvents
+    } else {
+        inner.pipeline_barrier(barrier);
+        InsertedBarrierTy::PipelineBarrier
+    };
+
+    if let (Some(report), false) = (report.as_mut(), pending_report.is_empty()) {
+        report.entries.push(BarrierReportEntry::Barrier(InsertedBarrier {
+                                                            ty,
+                                                            resources: mem::replace(pending_report,
+                                                                                    Vec::new()),
+                                                        }));
+    }
+
+    *barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+    *sources = PendingBarrierSources::new();
+}
+
+// Adds a command that has been sent to the inner builder to the barrier report, if it is
+// enabled.
+#[inline]
+fn report_command(report: &mut Option<BarrierReport>, index: usize, name: &'static str) {
+    if let Some(ref mut report) = *report {
+        report.entries.push(BarrierReportEntry::Command(BarrierReportCommand { index, name }));
+    }
+}
+
+// Queue family ownership transfer performed by a command. The resource is always the first buffer
+// or image of the command.
+#[derive(Debug, Copy, Clone)]
+struct OwnershipTransferRecord {
+    // Index of the command that performs the transfer.
+    command_id: usize,
+    // Type of the resource.
+    resource_ty: KeyTy,
+    // Id of the queue family that releases the resource.
+    source_family: u32,
+    // Id of the queue family that acquires the resource.
+    destination_family: u32,
+    // Layout of the image during the transfer. `Undefined` for buffers.
+    layout: ImageLayout,
+}
+
+impl OwnershipTransferRecord {
+    // Turns the record into an `OwnershipTransfer` whose resource is taken from `command`.
+    #[inline]
+    fn to_transfer<'a>(&self, command: &'a FinalCommand) -> OwnershipTransfer<'a> {
+        let resource = match self.resource_ty {
+            KeyTy::Buffer => OwnershipTransferResource::Buffer(command.buffer(0)),
+            KeyTy::Image => OwnershipTransferResource::Image(command.image(0), self.layout),
+        };
+
+        OwnershipTransfer {
+            resource,
+            source_family: self.source_family,
+            destination_family: self.destination_family,
+        }
+    }
+}
+
 // State of a resource during the building of the command buffer.
 #[derive(Debug, Clone)]
 struct ResourceState {
+    // Index of the command that last used this resource.
+    last_command: usize,
+    // Type and index of the resource within the command that last used it.
+    last_resource_ty: KeyTy,
+    last_resource_index: usize,
+
     // Stage of the command that last used this resource.
     stages: PipelineStages,
     // Access for the command that last used this resource.
     access: AccessFlagBits,
 
+    // Union of the stages of all the commands that used this resource.
+    all_stages: PipelineStages,
+    // Union of the accesses of all the commands that used this resource.
+    all_access: AccessFlagBits,
+
     // True if the resource was used in exclusive mode at any point during the building of the
     // command buffer. Also true if an image layout transition or queue transfer has been performed.
     exclusive_any: bool,
@@ -349,6 +539,10 @@ struct ResourceState {
 
     // Current layout at this stage of the building.
     current_layout: ImageLayout,
+
+    // If the last command that used this resource released its ownership, contains the id of the
+    // queue family it was released to.
+    released_to: Option<u32>,
 }
 
 impl ResourceState {
@@ -362,6 +556,7 @@ impl ResourceState {
             exclusive: self.exclusive_any,
             initial_layout: self.initial_layout,
             final_layout: self.current_layout,
+            released_to: self.released_to,
         }
     }
 }
@@ -409,15 +604,33 @@ impl<P> SyncCommandBufferBuilder<P> {
             inner: cmd,
             resources: FnvHashMap::default(),
             pending_barrier: UnsafeCommandBufferBuilderPipelineBarrier::new(),
+            pending_barrier_sources: PendingBarrierSources::new(),
+            pending_barrier_report: Vec::new(),
+            barrier_report: None,
+            split_barrier: None,
             commands: Arc::new(Mutex::new(Commands {
                                               first_unflushed: 0,
                                               latest_render_pass_enter,
                                               commands: Vec::new(),
                                           })),
+            ownership_releases: Vec::new(),
+            ownership_acquires: Vec::new(),
             is_secondary,
         }
     }
 
+    /// Enables the barrier report, which records the commands and the barriers inserted between
+    /// them so that it can be obtained with `SyncCommandBuffer::barrier_report()`.
+    ///
+    /// The report is disabled by default, because recording it has a cost. It only contains the
+    /// commands that are added after this method has been called.
+    #[inline]
+    pub fn enable_barrier_report(&mut self) {
+        if self.barrier_report.is_none() {
+            self.barrier_report = Some(BarrierReport::default());
+        }
+    }
+
     // Adds a command to be processed by the builder.
     //
     // After this method has been called, call `prev_cmd_resource` for each buffer or image used
@@ -449,6 +662,67 @@ impl<P> SyncCommandBufferBuilder<P> {
         cmd_lock.latest_render_pass_enter = None;
     }
 
+    // Call this when the previous command signaled `event` as a split barrier.
+    #[inline]
+    pub(super) fn prev_cmd_signaled_split_barrier(&mut self, event: Arc<Event>,
+                                                  stages: PipelineStages) {
+        let command_id = self.commands.lock().unwrap().commands.len() - 1;
+        self.split_barrier = Some(SplitBarrier {
+                                      event,
+                                      command_id,
+                                      stages,
+                                  });
+    }
+
+    // Returns true if a split barrier has been signaled and hasn't been waited upon yet.
+    #[cfg(test)]
+    pub(super) fn has_pending_split_barrier(&self) -> bool {
+        self.split_barrier.is_some()
+    }
+
+    // Call this when the previous command released the ownership of its first buffer or image to
+    // another queue family. `prev_cmd_resource` must have been called for this resource first.
+    pub(super) fn prev_cmd_released_ownership(&mut self, resource_ty: KeyTy, source_family: u32,
+                                              destination_family: u32, layout: ImageLayout) {
+        let record = self.prev_cmd_ownership_transfer(resource_ty, source_family,
+                                                      destination_family, layout);
+
+        let key = BuilderKey {
+            commands: self.commands.clone(),
+            command_id: record.command_id,
+            resource_ty,
+            resource_index: 0,
+        };
+        self.resources.get_mut(&key).unwrap().released_to = Some(destination_family);
+
+        self.ownership_releases.push(record);
+    }
+
+    // Call this when the previous command acquired the ownership of its first buffer or image
+    // from another queue family.
+    pub(super) fn prev_cmd_acquired_ownership(&mut self, resource_ty: KeyTy, source_family: u32,
+                                              destination_family: u32, layout: ImageLayout) {
+        let record = self.prev_cmd_ownership_transfer(resource_ty, source_family,
+                                                      destination_family, layout);
+        self.ownership_acquires.push(record);
+    }
+
+    #[inline]
+    fn prev_cmd_ownership_transfer(&self, resource_ty: KeyTy, source_family: u32,
+                                   destination_family: u32, layout: ImageLayout)
+                                   -> OwnershipTransferRecord {
+        debug_assert_ne!(source_family, destination_family);
+        debug_assert!(resource_ty != KeyTy::Buffer || layout == ImageLayout::Undefined);
+
+        OwnershipTransferRecord {
+            command_id: self.commands.lock().unwrap().commands.len() - 1,
+            resource_ty,
+            source_family,
+            destination_family,
+            layout,
+        }
+    }
+
     // After a command is added to the list of pending commands, this function must be called for
     // each resource used by the command that has just been added.
     // The function will take care of handling the pipeline barrier or flushing.
@@ -493,28 +767,34 @@ impl<P> SyncCommandBufferBuilder<P> {
 
             // Situation where this resource was used before in this command buffer.
             Entry::Occupied(entry) => {
-                // `collision_cmd_id` contains the ID of the command that we are potentially
+                // `collision_cmd_id` contains the ID of the first command that used the resource,
+                // and `last_cmd_id` the ID of the last one, which is the one we are potentially
                 // colliding with.
                 let collision_cmd_id = entry.key().command_id;
-                debug_assert!(collision_cmd_id <= latest_command_id);
+                let last_cmd_id = entry.get().last_command;
+                debug_assert!(collision_cmd_id <= last_cmd_id);
+                debug_assert!(last_cmd_id <= latest_command_id);
 
-                let entry_key_resource_index = entry.key().resource_index;
-                let entry_key_resource_ty = entry.key().resource_ty;
                 let entry = entry.into_mut();
 
                 // Find out if we have a collision with the pending commands.
                 if exclusive || entry.exclusive || entry.current_layout != start_layout {
-                    // Collision found between `latest_command_id` and `collision_cmd_id`.
+                    // Collision found between `latest_command_id` and `last_cmd_id`.
 
                     // We now want to modify the current pipeline barrier in order to handle the
                     // collision. But since the pipeline barrier is going to be submitted before
-                    // the flushed commands, it would be a mistake if `collision_cmd_id` hasn't
-                    // been flushed yet.
-                    if collision_cmd_id >= first_unflushed_cmd_id {
+                    // the flushed commands, it would be a mistake if `last_cmd_id` hasn't been
+                    // flushed yet. Otherwise the collision is merged with the pending barrier.
+                    if last_cmd_id >= first_unflushed_cmd_id {
                         unsafe {
                             // Flush the pending barrier.
-                            self.inner.pipeline_barrier(&self.pending_barrier);
-                            self.pending_barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+                            flush_pending_barrier(&mut self.inner,
+                                                  &mut self.pending_barrier,
+                                                  &mut self.pending_barrier_sources,
+                                                  &mut self.split_barrier,
+                                                  first_unflushed_cmd_id,
+                                                  &mut self.barrier_report,
+                                                  &mut self.pending_barrier_report);
 
                             // Flush the commands if possible, or return an error if not possible.
                             {
@@ -527,17 +807,19 @@ impl<P> SyncCommandBufferBuilder<P> {
                                 } else {
                                     latest_command_id
                                 };
-                                if collision_cmd_id >= end {
-                                    let cmd1 = &commands_lock.commands[collision_cmd_id];
+                                if last_cmd_id >= end {
+                                    let cmd1 = &commands_lock.commands[last_cmd_id];
                                     let cmd2 = &commands_lock.commands[latest_command_id];
+                                    let cmd1_index = entry.last_resource_index;
                                     return Err(SyncCommandBufferBuilderError::Conflict {
                                                    command1_name: cmd1.name(),
-                                                   command1_param: match entry_key_resource_ty {
-                                                       KeyTy::Buffer => cmd1.buffer_name(entry_key_resource_index),
+                                                   command1_param: match entry.last_resource_ty {
+                                                       KeyTy::Buffer =>
+                                                           cmd1.buffer_name(cmd1_index),
                                                        KeyTy::Image =>
-                                                           cmd1.image_name(entry_key_resource_index),
+                                                           cmd1.image_name(cmd1_index),
                                                    },
-                                                   command1_offset: collision_cmd_id,
+                                                   command1_offset: last_cmd_id,
 
                                                    command2_name: cmd2.name(),
                                                    command2_param: match resource_ty {
@@ -549,7 +831,12 @@ impl<P> SyncCommandBufferBuilder<P> {
                                                    command2_offset: latest_command_id,
                                                });
                                 }
-                                for command in &mut commands_lock.commands[start .. end] {
+                                for (index, command) in commands_lock.commands[start .. end]
+                                    .iter_mut()
+                                    .enumerate()
+                                {
+                                    report_command(&mut self.barrier_report, start + index,
+                                                   command.name());
                                     command.send(&mut self.inner);
                                 }
                                 commands_lock.first_unflushed = end;
@@ -559,7 +846,55 @@ impl<P> SyncCommandBufferBuilder<P> {
 
                     // Modify the pipeline barrier to handle the collision.
                     unsafe {
+                        let sources = &mut self.pending_barrier_sources;
+                        sources.latest_command = Some(match sources.latest_command {
+                            Some(c) if c > last_cmd_id => c,
+                            _ => last_cmd_id,
+                        });
+                        sources.stages = sources.stages | entry.stages;
+
+                        // Only the writes of the previous commands need to be made available.
+                        // If there is none and the layout doesn't change, we have a
+                        // write-after-read and an execution dependency is enough.
+                        let source_access = entry.access.writes();
+                        let destination_access = if source_access == AccessFlagBits::none() &&
+                            entry.current_layout == start_layout
+                        {
+                            AccessFlagBits::none()
+                        } else {
+                            access
+                        };
+
                         let commands_lock = self.commands.lock().unwrap();
+
+                        if self.barrier_report.is_some() {
+                            let source_cmd = &commands_lock.commands[last_cmd_id];
+                            let destination_cmd = &commands_lock.commands[latest_command_id];
+                            self.pending_barrier_report.push(InsertedBarrierResource {
+                                resource_ty,
+                                resource_handle: destination_cmd
+                                    .resource_handle(resource_ty, resource_index),
+                                resource_name: match resource_ty {
+                                    KeyTy::Buffer => destination_cmd.buffer_name(resource_index),
+                                    KeyTy::Image => destination_cmd.image_name(resource_index),
+                                },
+                                source_command: Some(BarrierReportCommand {
+                                                         index: last_cmd_id,
+                                                         name: source_cmd.name(),
+                                                     }),
+                                source_stages: entry.stages,
+                                source_access,
+                                destination_command: Some(BarrierReportCommand {
+                                                              index: latest_command_id,
+                                                              name: destination_cmd.name(),
+                                                          }),
+                                destination_stages: stages,
+                                destination_access,
+                                old_layout: entry.current_layout,
+                                new_layout: start_layout,
+                            });
+                        }
+
                         match resource_ty {
                             KeyTy::Buffer => {
                                 let buf = commands_lock.commands[latest_command_id]
@@ -568,9 +903,9 @@ impl<P> SyncCommandBufferBuilder<P> {
                                 let b = &mut self.pending_barrier;
                                 b.add_buffer_memory_barrier(buf,
                                                             entry.stages,
-                                                            entry.access,
+                                                            source_access,
                                                             stages,
-                                                            access,
+                                                            destination_access,
                                                             true,
                                                             None,
                                                             0,
@@ -586,9 +921,9 @@ impl<P> SyncCommandBufferBuilder<P> {
                                                            0 .. img.mipmap_levels(),
                                                            0 .. img.dimensions().array_layers(),
                                                            entry.stages,
-                                                           entry.access,
+                                                           source_access,
                                                            stages,
-                                                           access,
+                                                           destination_access,
                                                            true,
                                                            None,
                                                            entry.current_layout,
@@ -598,10 +933,16 @@ impl<P> SyncCommandBufferBuilder<P> {
                     }
 
                     // Update state.
+                    entry.last_command = latest_command_id;
+                    entry.last_resource_ty = resource_ty;
+                    entry.last_resource_index = resource_index;
                     entry.stages = stages;
                     entry.access = access;
+                    entry.all_stages = entry.all_stages | stages;
+                    entry.all_access = entry.all_access | access;
                     entry.exclusive_any = true;
                     entry.exclusive = exclusive;
+                    entry.released_to = None;
                     if exclusive || end_layout != ImageLayout::Undefined {
                         // Only modify the layout in case of a write, because buffer operations
                         // pass `Undefined` for the layout. While a buffer write *must* set the
@@ -611,10 +952,15 @@ impl<P> SyncCommandBufferBuilder<P> {
 
                 } else {
                     // There is no collision. Simply merge the stages and accesses.
-                    // TODO: what about simplifying the newly-constructed stages/accesses?
-                    //       this would simplify the job of the driver, but is it worth it?
+                    // Note that the stages are not simplified, as removing a stage would also
+                    // remove its memory accesses from the scope of the next barrier.
+                    entry.last_command = latest_command_id;
+                    entry.last_resource_ty = resource_ty;
+                    entry.last_resource_index = resource_index;
                     entry.stages = entry.stages | stages;
                     entry.access = entry.access | access;
+                    entry.all_stages = entry.all_stages | stages;
+                    entry.all_access = entry.all_access | access;
                 }
             },
 
@@ -637,6 +983,23 @@ impl<P> SyncCommandBufferBuilder<P> {
                         actually_exclusive = true;
                         actual_start_layout = initial_layout_requirement;
 
+                        // If the command only reads the image, the next reads in the same layout
+                        // don't cause a barrier, even if they happen in other stages. The
+                        // transition must therefore be visible to all the stages and not only to
+                        // the stages of this command.
+                        let (transition_stages, transition_access) = if exclusive {
+                            (stages, access)
+                        } else {
+                            (PipelineStages {
+                                 all_commands: true,
+                                 ..PipelineStages::none()
+                             },
+                             AccessFlagBits {
+                                 memory_read: true,
+                                 ..AccessFlagBits::none()
+                             })
+                        };
+
                         // Note that we transition from `bottom_of_pipe`, which means that we
                         // wait for all the previous commands to be entirely finished. This is
                         // suboptimal, but:
@@ -647,7 +1010,33 @@ impl<P> SyncCommandBufferBuilder<P> {
                         //   to be merged with an existing barrier. While it may still be
                         //   suboptimal in some cases, in the general situation it will be ok.
                         //
+                        if self.barrier_report.is_some() {
+                            let destination_cmd = &commands_lock.commands[latest_command_id];
+                            self.pending_barrier_report.push(InsertedBarrierResource {
+                                resource_ty,
+                                resource_handle: destination_cmd
+                                    .resource_handle(resource_ty, resource_index),
+                                resource_name: destination_cmd.image_name(resource_index),
+                                source_command: None,
+                                source_stages: PipelineStages {
+                                    bottom_of_pipe: true,
+                                    ..PipelineStages::none()
+                                },
+                                source_access: AccessFlagBits::none(),
+                                destination_command: Some(BarrierReportCommand {
+                                                              index: latest_command_id,
+                                                              name: destination_cmd.name(),
+                                                          }),
+                                destination_stages: transition_stages,
+                                destination_access: transition_access,
+                                old_layout: initial_layout_requirement,
+                                new_layout: start_layout,
+                            });
+                        }
+
                         unsafe {
+                            self.pending_barrier_sources.splittable = false;
+
                             let b = &mut self.pending_barrier;
                             b.add_image_memory_barrier(img,
                                                        0 .. img.mipmap_levels(),
@@ -657,8 +1046,8 @@ impl<P> SyncCommandBufferBuilder<P> {
                                                            ..PipelineStages::none()
                                                        },
                                                        AccessFlagBits::none(),
-                                                       stages,
-                                                       access,
+                                                       transition_stages,
+                                                       transition_access,
                                                        true,
                                                        None,
                                                        initial_layout_requirement,
@@ -667,13 +1056,23 @@ impl<P> SyncCommandBufferBuilder<P> {
                     }
                 }
 
+                // The layout transition is performed by the barrier before the command and is
+                // visible to all the later reads, so the command itself only uses the resource in
+                // the way it requested. This avoids a barrier between two reads of the image in
+                // the same layout.
                 entry.insert(ResourceState {
+                    last_command: latest_command_id,
+                    last_resource_ty: resource_ty,
+                    last_resource_index: resource_index,
                     stages: stages,
                     access: access,
+                    all_stages: stages,
+                    all_access: access,
                     exclusive_any: actually_exclusive,
-                    exclusive: actually_exclusive,
+                    exclusive: exclusive,
                     initial_layout: actual_start_layout,
                     current_layout: end_layout,     // TODO: what if we reach the end with Undefined? that's not correct?
+                    released_to: None,
                 });
             },
         }
@@ -691,52 +1090,125 @@ impl<P> SyncCommandBufferBuilder<P> {
         debug_assert!(commands_lock.latest_render_pass_enter.is_none() ||
                           self.pending_barrier.is_empty());
 
-        // The commands that haven't been sent to the inner command buffer yet need to be sent.
-        unsafe {
-            self.inner.pipeline_barrier(&self.pending_barrier);
-            let f = commands_lock.first_unflushed;
-            for command in &mut commands_lock.commands[f ..] {
-                command.send(&mut self.inner);
-            }
-        }
+        let first_unflushed = commands_lock.first_unflushed;
 
         // Transition images to their desired final layout.
+        //
+        // If the pending barrier isn't empty, the transitions of the images that aren't used by
+        // the commands that haven't been sent yet are merged with it. The other transitions are
+        // submitted as a separate barrier at the end of the command buffer.
+        let mut final_barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+        let mut final_report = Vec::new();
         if !self.is_secondary {
-            unsafe {
-                // TODO: this could be optimized by merging the barrier with the barrier above?
-                let mut barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+            let merge_with_pending = !self.pending_barrier.is_empty();
 
-                for (key, state) in &mut self.resources {
-                    if key.resource_ty != KeyTy::Image {
-                        continue;
-                    }
+            for (key, state) in &mut self.resources {
+                if key.resource_ty != KeyTy::Image {
+                    continue;
+                }
 
-                    let img = commands_lock.commands[key.command_id].image(key.resource_index);
-                    let requested_layout = img.final_layout_requirement();
-                    if requested_layout == state.current_layout {
-                        continue;
-                    }
+                // The image can't be transitioned anymore after it has been released to
+                // another queue family.
+                if state.released_to.is_some() {
+                    continue;
+                }
+
+                let img = commands_lock.commands[key.command_id].image(key.resource_index);
+                let requested_layout = img.final_layout_requirement();
+                if requested_layout == state.current_layout {
+                    continue;
+                }
+
+                let source_access = state.access.writes();
+                let destination_stages = PipelineStages {
+                    top_of_pipe: true,
+                    ..PipelineStages::none()
+                };
+
+                let source_cmd = &commands_lock.commands[state.last_command];
+                let report = InsertedBarrierResource {
+                    resource_ty: KeyTy::Image,
+                    resource_handle: img.inner().image.internal_object(),
+                    resource_name: commands_lock.commands[key.command_id]
+                        .image_name(key.resource_index),
+                    source_command: Some(BarrierReportCommand {
+                                             index: state.last_command,
+                                             name: source_cmd.name(),
+                                         }),
+                    source_stages: state.stages,
+                    source_access,
+                    destination_command: None,
+                    destination_stages,
+                    destination_access: AccessFlagBits::none(),
+                    old_layout: state.current_layout,
+                    new_layout: requested_layout,
+                };
 
+                let (barrier, report_resources) = if merge_with_pending &&
+                    state.last_command < first_unflushed
+                {
+                    let sources = &mut self.pending_barrier_sources;
+                    sources.latest_command = Some(match sources.latest_command {
+                        Some(c) if c > state.last_command => c,
+                        _ => state.last_command,
+                    });
+                    sources.stages = sources.stages | state.stages;
+                    (&mut self.pending_barrier, &mut self.pending_barrier_report)
+                } else {
+                    (&mut final_barrier, &mut final_report)
+                };
+
+                unsafe {
                     barrier.add_image_memory_barrier(img,
                                                      0 .. img.mipmap_levels(),
                                                      0 .. img.dimensions().array_layers(),
                                                      state.stages,
-                                                     state.access,
-                                                     PipelineStages {
-                                                         top_of_pipe: true,
-                                                         ..PipelineStages::none()
-                                                     },
+                                                     source_access,
+                                                     destination_stages,
                                                      AccessFlagBits::none(),
                                                      true,
-                                                     None, // TODO: queue transfers?
+                                                     None,
                                                      state.current_layout,
                                                      requested_layout);
-
-                    state.exclusive_any = true;
-                    state.current_layout = requested_layout;
                 }
+                if self.barrier_report.is_some() {
+                    report_resources.push(report);
+                }
+
+                state.exclusive_any = true;
+                state.current_layout = requested_layout;
+            }
+        }
+
+        // The commands that haven't been sent to the inner command buffer yet need to be sent.
+        unsafe {
+            flush_pending_barrier(&mut self.inner,
+                                  &mut self.pending_barrier,
+                                  &mut self.pending_barrier_sources,
+                                  &mut self.split_barrier,
+                                  first_unflushed,
+                                  &mut self.barrier_report,
+                                  &mut self.pending_barrier_report);
+            for (index, command) in commands_lock.commands[first_unflushed ..]
+                .iter_mut()
+                .enumerate()
+            {
+                report_command(&mut self.barrier_report, first_unflushed + index, command.name());
+                command.send(&mut self.inner);
+            }
+        }
 
-                self.inner.pipeline_barrier(&barrier);
+        if !final_barrier.is_empty() {
+            unsafe {
+                self.inner.pipeline_barrier(&final_barrier);
+            }
+
+            if let Some(ref mut report) = self.barrier_report {
+                let barrier = InsertedBarrier {
+                    ty: InsertedBarrierTy::FinalLayoutTransition,
+                    resources: final_report,
+                };
+                report.entries.push(BarrierReportEntry::Barrier(barrier));
             }
         }
 
@@ -746,9 +1218,27 @@ impl<P> SyncCommandBufferBuilder<P> {
             for command in commands_lock.commands.drain(..) {
                 final_commands.push(command.into_final_command());
             }
-            Arc::new(Mutex::new(final_commands))
+            Arc::new(final_commands)
         };
 
+        // List of the buffers and images used by the command buffer, with the way they are used.
+        let mut buffers = Vec::new();
+        let mut images = Vec::new();
+        for (key, state) in &self.resources {
+            let usage = SecondaryResourceUsage {
+                exclusive: state.exclusive_any,
+                stages: state.all_stages,
+                access: state.all_access,
+                initial_layout: state.initial_layout,
+                final_layout: state.current_layout,
+            };
+
+            match key.resource_ty {
+                KeyTy::Buffer => buffers.push((key.command_id, key.resource_index, usage)),
+                KeyTy::Image => images.push((key.command_id, key.resource_index, usage)),
+            }
+        }
+
         // Build the final resources states.
         let final_resources_states: FnvHashMap<_, _> = {
             self.resources
@@ -762,6 +1252,11 @@ impl<P> SyncCommandBufferBuilder<P> {
         Ok(SyncCommandBuffer {
                inner: self.inner.build()?,
                resources: final_resources_states,
+               buffers,
+               images,
+               ownership_releases: self.ownership_releases,
+               ownership_acquires: self.ownership_acquires,
+               barrier_report: self.barrier_report,
                commands: final_commands,
            })
     }
@@ -783,10 +1278,24 @@ pub struct SyncCommandBuffer<P> {
     // State of all the resources used by this command buffer.
     resources: FnvHashMap<CbKey<'static>, ResourceFinalState>,
 
+    // List of the buffers and images used by the command buffer. Contains the index of the
+    // command that holds the resource, the index of the resource within the command, and the way
+    // the resource is used by the whole command buffer.
+    buffers: Vec<(usize, usize, SecondaryResourceUsage)>,
+    images: Vec<(usize, usize, SecondaryResourceUsage)>,
+
+    // Queue family ownership transfers released and acquired by the command buffer.
+    ownership_releases: Vec<OwnershipTransferRecord>,
+    ownership_acquires: Vec<OwnershipTransferRecord>,
+
+    // The commands and the barriers that were inserted between them, if the barrier report was
+    // enabled.
+    barrier_report: Option<BarrierReport>,
+
     // List of commands used by the command buffer. Used to hold the various resources that are
     // being used. Each element of `resources` has a copy of this `Arc`, but we need to keep one
     // here in case `resources` is empty.
-    commands: Arc<Mutex<Vec<Box<FinalCommand + Send + Sync>>>>,
+    commands: Arc<Vec<Box<FinalCommand + Send + Sync>>>,
 }
 
 // Usage of a resource in a finished command buffer.
@@ -806,6 +1315,10 @@ struct ResourceFinalState {
 
     // Layout the image will be in at the end of the command buffer.
     final_layout: ImageLayout, // TODO: maybe wrap in an Option to mean that the layout doesn't change? because of buffers?
+
+    // If the command buffer releases the ownership of the resource at the end, contains the id
+    // of the queue family it is released to.
+    released_to: Option<u32>,
 }
 
 /// Equivalent to `Command`, but with less methods. Typically contains less things than the
@@ -835,6 +1348,22 @@ pub trait FinalCommand {
     fn image_name(&self, _num: usize) -> Cow<'static, str> {
         panic!()
     }
+
+    // Returns true if the `num`th buffer or image used by the command conflicts with `buf`.
+    fn conflicts_buffer(&self, resource_ty: KeyTy, num: usize, buf: &BufferAccess) -> bool {
+        match resource_ty {
+            KeyTy::Buffer => self.buffer(num).conflicts_buffer(buf),
+            KeyTy::Image => self.image(num).conflicts_buffer(buf),
+        }
+    }
+
+    // Returns true if the `num`th buffer or image used by the command conflicts with `img`.
+    fn conflicts_image(&self, resource_ty: KeyTy, num: usize, img: &ImageAccess) -> bool {
+        match resource_ty {
+            KeyTy::Buffer => self.buffer(num).conflicts_image(img),
+            KeyTy::Image => self.image(num).conflicts_image(img),
+        }
+    }
 }
 
 impl FinalCommand for &'static str {
@@ -855,7 +1384,7 @@ enum CbKey<'a> {
     // The resource is held in the list of commands.
     Command {
         // Same `Arc` as in the `SyncCommandBufferBuilder`.
-        commands: Arc<Mutex<Vec<Box<FinalCommand + Send + Sync>>>>,
+        commands: Arc<Vec<Box<FinalCommand + Send + Sync>>>,
         // Index of the command that holds the resource within `commands`.
         command_id: usize,
         // Type of the resource.
@@ -885,35 +1414,14 @@ unsafe impl<'a> Sync for CbKey<'a> {
 
 impl<'a> CbKey<'a> {
     #[inline]
-    fn conflicts_buffer(&self, commands_lock: Option<&Vec<Box<FinalCommand + Send + Sync>>>,
-                        buf: &BufferAccess)
-                        -> bool {
+    fn conflicts_buffer(&self, buf: &BufferAccess) -> bool {
         match *self {
             CbKey::Command {
                 ref commands,
                 command_id,
                 resource_ty,
                 resource_index,
-            } => {
-                let lock = if commands_lock.is_none() {
-                    Some(commands.lock().unwrap())
-                } else {
-                    None
-                };
-                let commands_lock = commands_lock.unwrap_or_else(|| lock.as_ref().unwrap());
-
-                // TODO: put the conflicts_* methods directly on the FinalCommand trait to avoid an indirect call?
-                match resource_ty {
-                    KeyTy::Buffer => {
-                        let c = &commands_lock[command_id];
-                        c.buffer(resource_index).conflicts_buffer(buf)
-                    },
-                    KeyTy::Image => {
-                        let c = &commands_lock[command_id];
-                        c.image(resource_index).conflicts_buffer(buf)
-                    },
-                }
-            },
+            } => commands[command_id].conflicts_buffer(resource_ty, resource_index, buf),
 
             CbKey::BufferRef(b) => b.conflicts_buffer(buf),
             CbKey::ImageRef(i) => i.conflicts_buffer(buf),
@@ -921,35 +1429,14 @@ impl<'a> CbKey<'a> {
     }
 
     #[inline]
-    fn conflicts_image(&self, commands_lock: Option<&Vec<Box<FinalCommand + Send + Sync>>>,
-                       img: &ImageAccess)
-                       -> bool {
+    fn conflicts_image(&self, img: &ImageAccess) -> bool {
         match *self {
             CbKey::Command {
                 ref commands,
                 command_id,
                 resource_ty,
                 resource_index,
-            } => {
-                let lock = if commands_lock.is_none() {
-                    Some(commands.lock().unwrap())
-                } else {
-                    None
-                };
-                let commands_lock = commands_lock.unwrap_or_else(|| lock.as_ref().unwrap());
-
-                // TODO: put the conflicts_* methods directly on the Command trait to avoid an indirect call?
-                match resource_ty {
-                    KeyTy::Buffer => {
-                        let c = &commands_lock[command_id];
-                        c.buffer(resource_index).conflicts_image(img)
-                    },
-                    KeyTy::Image => {
-                        let c = &commands_lock[command_id];
-                        c.image(resource_index).conflicts_image(img)
-                    },
-                }
-            },
+            } => commands[command_id].conflicts_image(resource_ty, resource_index, img),
 
             CbKey::BufferRef(b) => b.conflicts_image(img),
             CbKey::ImageRef(i) => i.conflicts_image(img),
@@ -962,10 +1449,10 @@ impl<'a> PartialEq for CbKey<'a> {
     fn eq(&self, other: &CbKey) -> bool {
         match *self {
             CbKey::BufferRef(a) => {
-                other.conflicts_buffer(None, a)
+                other.conflicts_buffer(a)
             },
             CbKey::ImageRef(a) => {
-                other.conflicts_image(None, a)
+                other.conflicts_image(a)
             },
             CbKey::Command {
                 ref commands,
@@ -973,16 +1460,14 @@ impl<'a> PartialEq for CbKey<'a> {
                 resource_ty,
                 resource_index,
             } => {
-                let commands_lock = commands.lock().unwrap();
-
                 match resource_ty {
                     KeyTy::Buffer => {
-                        let c = &commands_lock[command_id];
-                        other.conflicts_buffer(Some(&commands_lock), c.buffer(resource_index))
+                        let c = &commands[command_id];
+                        other.conflicts_buffer(c.buffer(resource_index))
                     },
                     KeyTy::Image => {
-                        let c = &commands_lock[command_id];
-                        other.conflicts_image(Some(&commands_lock), c.image(resource_index))
+                        let c = &commands[command_id];
+                        other.conflicts_image(c.image(resource_index))
                     },
                 }
             },
@@ -1003,15 +1488,13 @@ impl<'a> Hash for CbKey<'a> {
                 resource_ty,
                 resource_index,
             } => {
-                let commands_lock = commands.lock().unwrap();
-
                 match resource_ty {
                     KeyTy::Buffer => {
-                        let c = &commands_lock[command_id];
+                        let c = &commands[command_id];
                         c.buffer(resource_index).conflict_key().hash(state)
                     },
                     KeyTy::Image => {
-                        let c = &commands_lock[command_id];
+                        let c = &commands[command_id];
                         c.image(resource_index).conflict_key().hash(state)
                     },
                 }
@@ -1037,7 +1520,7 @@ impl<P> SyncCommandBuffer<P> {
     pub fn lock_submit(&self, future: &GpuFuture, queue: &Queue)
                        -> Result<(), CommandBufferExecError> {
 
-        let commands_lock = self.commands.lock().unwrap();
+        let commands_lock = &self.commands;
 
         // Number of resources in `self.resources` that have been successfully locked.
         let mut locked_resources = 0;
@@ -1177,7 +1660,7 @@ impl<P> SyncCommandBuffer<P> {
     /// The command buffer must have been successfully locked with `lock_submit()`.
     ///
     pub unsafe fn unlock(&self) {
-        let commands_lock = self.commands.lock().unwrap();
+        let commands_lock = &self.commands;
 
         for (key, val) in self.resources.iter() {
             let (command_id, resource_ty, resource_index) = match *key {
@@ -1219,9 +1702,9 @@ impl<P> SyncCommandBuffer<P> {
     pub fn check_buffer_access(
         &self, buffer: &BufferAccess, exclusive: bool, queue: &Queue)
         -> Result<Option<(PipelineStages, AccessFlagBits)>, AccessCheckError> {
-        // TODO: check the queue family
-
         if let Some(value) = self.resources.get(&CbKey::BufferRef(buffer)) {
+            check_released_to(value, queue)?;
+
             if !value.exclusive && exclusive {
                 return Err(AccessCheckError::Unknown);
             }
@@ -1239,9 +1722,9 @@ impl<P> SyncCommandBuffer<P> {
     pub fn check_image_access(
         &self, image: &ImageAccess, layout: ImageLayout, exclusive: bool, queue: &Queue)
         -> Result<Option<(PipelineStages, AccessFlagBits)>, AccessCheckError> {
-        // TODO: check the queue family
-
         if let Some(value) = self.resources.get(&CbKey::ImageRef(image)) {
+            check_released_to(value, queue)?;
+
             if layout != ImageLayout::Undefined && value.final_layout != layout {
                 return Err(AccessCheckError::Denied(AccessError::UnexpectedImageLayout {
                                                         allowed: value.final_layout,
@@ -1258,6 +1741,86 @@ impl<P> SyncCommandBuffer<P> {
 
         Err(AccessCheckError::Unknown)
     }
+
+    /// Returns the queue family ownership transfers released by the command buffer.
+    #[inline]
+    pub fn ownership_releases(&self) -> Vec<OwnershipTransfer> {
+        self.ownership_releases
+            .iter()
+            .map(|record| record.to_transfer(&*self.commands[record.command_id]))
+            .collect()
+    }
+
+    /// Returns the queue family ownership transfers acquired by the command buffer.
+    #[inline]
+    pub fn ownership_acquires(&self) -> Vec<OwnershipTransfer> {
+        self.ownership_acquires
+            .iter()
+            .map(|record| record.to_transfer(&*self.commands[record.command_id]))
+            .collect()
+    }
+
+    /// Destroys the command buffer and returns the command buffer allocated from the pool. The
+    /// resources used by the commands are released.
+    ///
+    /// See `UnsafeCommandBuffer::into_alloc`.
+    #[inline]
+    pub fn into_alloc(self) -> P {
+        self.inner.into_alloc()
+    }
+
+    /// Returns the list of the commands of the command buffer, with the pipeline barriers and
+    /// layout transitions that were automatically inserted between them.
+    ///
+    /// Returns `None` if `enable_barrier_report` wasn't called on the builder.
+    #[inline]
+    pub fn barrier_report(&self) -> Option<&BarrierReport> {
+        self.barrier_report.as_ref()
+    }
+
+    /// Returns the number of buffers used by the command buffer.
+    #[inline]
+    pub fn num_buffers(&self) -> usize {
+        self.buffers.len()
+    }
+
+    /// Returns the `index`th buffer used by the command buffer, and the way it is used by the
+    /// command buffer as a whole. Returns `None` if out of range.
+    #[inline]
+    pub fn buffer(&self, index: usize) -> Option<(&BufferAccess, SecondaryResourceUsage)> {
+        self.buffers.get(index).map(|&(command_id, resource_index, usage)| {
+            (self.commands[command_id].buffer(resource_index), usage)
+        })
+    }
+
+    /// Returns the number of images used by the command buffer.
+    #[inline]
+    pub fn num_images(&self) -> usize {
+        self.images.len()
+    }
+
+    /// Returns the `index`th image used by the command buffer, and the way it is used by the
+    /// command buffer as a whole. Returns `None` if out of range.
+    #[inline]
+    pub fn image(&self, index: usize) -> Option<(&ImageAccess, SecondaryResourceUsage)> {
+        self.images.get(index).map(|&(command_id, resource_index, usage)| {
+            (self.commands[command_id].image(resource_index), usage)
+        })
+    }
+}
+
+// Denies the access to a resource from `queue` if the command buffer released its ownership to
+// another queue family.
+#[inline]
+fn check_released_to(state: &ResourceFinalState, queue: &Queue) -> Result<(), AccessCheckError> {
+    match state.released_to {
+        Some(family) if family != queue.family().id() => {
+            Err(AccessCheckError::Denied(AccessError::OwnershipReleased {
+                                             destination_family: family,
+                                         }))
+        },
+        _ => Ok(()),
+    }
 }
 
 unsafe impl<P> DeviceOwned for SyncCommandBuffer<P> {
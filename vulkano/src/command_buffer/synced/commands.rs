@@ -8,15 +8,19 @@
 // according to those terms.
 
 use smallvec::SmallVec;
-use std::any::Any;
 use std::borrow::Cow;
 use std::mem;
 use std::ptr;
 use std::sync::Arc;
 use std::ffi::CString;
 
+use SafeDeref;
 use buffer::BufferAccess;
+use buffer::TypedBufferAccess;
+use command_buffer::ClearAttachment;
+use command_buffer::ClearRect;
 use command_buffer::CommandBuffer;
+use command_buffer::SecondaryCommandBuffer;
 use command_buffer::synced::base::Command;
 use command_buffer::synced::base::FinalCommand;
 use command_buffer::synced::base::KeyTy;
@@ -26,9 +30,12 @@ use command_buffer::sys::UnsafeCommandBufferBuilder;
 use command_buffer::sys::UnsafeCommandBufferBuilderBindVertexBuffer;
 use command_buffer::sys::UnsafeCommandBufferBuilderBufferImageCopy;
 use command_buffer::sys::UnsafeCommandBufferBuilderColorImageClear;
+use command_buffer::sys::UnsafeCommandBufferBuilderDepthStencilImageClear;
 use command_buffer::sys::UnsafeCommandBufferBuilderExecuteCommands;
 use command_buffer::sys::UnsafeCommandBufferBuilderImageCopy;
 use command_buffer::sys::UnsafeCommandBufferBuilderImageBlit;
+use command_buffer::sys::UnsafeCommandBufferBuilderPipelineBarrier;
+use command_buffer::sys::UnsafeCommandBufferBuilderImageResolve;
 use descriptor::descriptor::DescriptorDescTy;
 use descriptor::descriptor::ShaderStages;
 use descriptor::descriptor_set::DescriptorSet;
@@ -40,15 +47,110 @@ use image::ImageAccess;
 use image::ImageLayout;
 use pipeline::ComputePipelineAbstract;
 use pipeline::GraphicsPipelineAbstract;
+use pipeline::depth_stencil::StencilFaceFlags;
 use pipeline::input_assembly::IndexType;
 use pipeline::viewport::Scissor;
 use pipeline::viewport::Viewport;
+use query::QueryPoolAbstract;
+use query::QueryResultElement;
+use query::QueryResultFlags;
 use sampler::Filter;
 use sync::AccessFlagBits;
 use sync::Event;
 use sync::PipelineStages;
 
 impl<P> SyncCommandBufferBuilder<P> {
+    /// Calls `vkCmdPipelineBarrier` on the builder with a buffer memory barrier that acquires the
+    /// ownership of `buffer` from the queue family `source_family`.
+    ///
+    /// `destination_family` must be the queue family of the command buffer. A matching release
+    /// must have been submitted to a queue of `source_family` beforehand.
+    #[inline]
+    pub unsafe fn acquire_buffer_ownership<B>(&mut self, buffer: B, source_family: u32,
+                                              destination_family: u32)
+                                              -> Result<(), SyncCommandBufferBuilderError>
+        where B: BufferAccess + Send + Sync + 'static
+    {
+        self.append_command(OwnershipBufferCmd {
+                                buffer,
+                                source_family,
+                                destination_family,
+                                release: false,
+                            });
+        self.prev_cmd_ownership_resource(KeyTy::Buffer, ImageLayout::Undefined)?;
+        self.prev_cmd_acquired_ownership(KeyTy::Buffer,
+                                         source_family,
+                                         destination_family,
+                                         ImageLayout::Undefined);
+        Ok(())
+    }
+
+    /// Calls `vkCmdPipelineBarrier` on the builder with an image memory barrier that acquires the
+    /// ownership of `image` from the queue family `source_family`.
+    ///
+    /// `layout` must be the layout that was passed when releasing the image.
+    /// `destination_family` must be the queue family of the command buffer. A matching release
+    /// must have been submitted to a queue of `source_family` beforehand.
+    #[inline]
+    pub unsafe fn acquire_image_ownership<I>(&mut self, image: I, layout: ImageLayout,
+                                             source_family: u32, destination_family: u32)
+                                             -> Result<(), SyncCommandBufferBuilderError>
+        where I: ImageAccess + Send + Sync + 'static
+    {
+        self.append_command(OwnershipImageCmd {
+                                image,
+                                layout,
+                                source_family,
+                                destination_family,
+                                release: false,
+                            });
+        self.prev_cmd_ownership_resource(KeyTy::Image, layout)?;
+        self.prev_cmd_acquired_ownership(KeyTy::Image, source_family, destination_family, layout);
+        Ok(())
+    }
+
+    /// Calls `vkCmdBeginQuery` on the builder.
+    #[inline]
+    pub unsafe fn begin_query<Q>(&mut self, query_pool: Q, query: u32, precise: bool)
+        where Q: QueryPoolAbstract + Send + Sync + 'static
+    {
+        struct Cmd<Q> {
+            query_pool: Q,
+            query: u32,
+            precise: bool,
+        }
+
+        impl<P, Q> Command<P> for Cmd<Q>
+            where Q: QueryPoolAbstract + Send + Sync + 'static
+        {
+            fn name(&self) -> &'static str {
+                "vkCmdBeginQuery"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                out.begin_query(self.query_pool.inner().query(self.query).unwrap(), self.precise);
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                struct Fin<Q>(Q);
+                impl<Q> FinalCommand for Fin<Q>
+                    where Q: QueryPoolAbstract + Send + Sync + 'static
+                {
+                    fn name(&self) -> &'static str {
+                        "vkCmdBeginQuery"
+                    }
+                }
+                Box::new(Fin(self.query_pool))
+            }
+        }
+
+        self.append_command(Cmd {
+                                query_pool,
+                                query,
+                                precise,
+                            });
+    }
+
     /// Calls `vkBeginRenderPass` on the builder.
     // TODO: it shouldn't be possible to get an error if the framebuffer checked conflicts already
     // TODO: after begin_render_pass has been called, flushing should be forbidden and an error
@@ -629,6 +731,44 @@ impl<P> SyncCommandBufferBuilder<P> {
         Ok(())
     }
 
+    /// Calls `vkCmdClearAttachments` on the builder.
+    ///
+    /// Does nothing if the list of attachments or the list of rects is empty, as it would be a
+    /// no-op and isn't a valid usage of the command anyway.
+    #[inline]
+    pub unsafe fn clear_attachments<A, R>(&mut self, attachments: A, rects: R)
+        where A: Iterator<Item = ClearAttachment> + Send + Sync + 'static,
+              R: Iterator<Item = ClearRect> + Send + Sync + 'static
+    {
+        struct Cmd<A, R> {
+            attachments: Option<A>,
+            rects: Option<R>,
+        }
+
+        impl<P, A, R> Command<P> for Cmd<A, R>
+            where A: Iterator<Item = ClearAttachment>,
+                  R: Iterator<Item = ClearRect>
+        {
+            fn name(&self) -> &'static str {
+                "vkCmdClearAttachments"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                out.clear_attachments(self.attachments.take().unwrap(),
+                                      self.rects.take().unwrap());
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                Box::new("vkCmdClearAttachments")
+            }
+        }
+
+        self.append_command(Cmd {
+                                attachments: Some(attachments),
+                                rects: Some(rects),
+                            });
+    }
+
     /// Calls `vkCmdClearColorImage` on the builder.
     ///
     /// Does nothing if the list of regions is empty, as it would be a no-op and isn't a valid
@@ -719,6 +859,99 @@ impl<P> SyncCommandBufferBuilder<P> {
         Ok(())
     }
 
+    /// Calls `vkCmdClearDepthStencilImage` on the builder.
+    ///
+    /// Does nothing if the list of regions is empty, as it would be a no-op and isn't a valid
+    /// usage of the command anyway.
+    pub unsafe fn clear_depth_stencil_image<I, R>(&mut self, image: I, layout: ImageLayout,
+                                                  clear_value: ClearValue, regions: R)
+                                                  -> Result<(), SyncCommandBufferBuilderError>
+        where I: ImageAccess + Send + Sync + 'static,
+              R: Iterator<Item = UnsafeCommandBufferBuilderDepthStencilImageClear>
+                     + Send
+                     + Sync
+                     + 'static
+    {
+        struct Cmd<I, R> {
+            image: Option<I>,
+            layout: ImageLayout,
+            clear_value: ClearValue,
+            regions: Option<R>,
+        }
+
+        impl<P, I, R> Command<P> for Cmd<I, R>
+            where I: ImageAccess + Send + Sync + 'static,
+                  R: Iterator<Item = UnsafeCommandBufferBuilderDepthStencilImageClear>
+                         + Send
+                         + Sync
+                         + 'static
+        {
+            fn name(&self) -> &'static str {
+                "vkCmdClearDepthStencilImage"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                out.clear_depth_stencil_image(self.image.as_ref().unwrap(),
+                                              self.layout,
+                                              self.clear_value,
+                                              self.regions.take().unwrap());
+            }
+
+            fn into_final_command(mut self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                struct Fin<I>(I);
+                impl<I> FinalCommand for Fin<I>
+                    where I: ImageAccess + Send + Sync + 'static
+                {
+                    fn name(&self) -> &'static str {
+                        "vkCmdClearDepthStencilImage"
+                    }
+                    fn image(&self, num: usize) -> &ImageAccess {
+                        assert_eq!(num, 0);
+                        &self.0
+                    }
+                    fn image_name(&self, num: usize) -> Cow<'static, str> {
+                        assert_eq!(num, 0);
+                        "target".into()
+                    }
+                }
+
+                // Note: borrow checker somehow doesn't accept `self.image` without using an Option.
+                Box::new(Fin(self.image.take().unwrap()))
+            }
+
+            fn image(&self, num: usize) -> &ImageAccess {
+                assert_eq!(num, 0);
+                self.image.as_ref().unwrap()
+            }
+
+            fn image_name(&self, num: usize) -> Cow<'static, str> {
+                assert_eq!(num, 0);
+                "target".into()
+            }
+        }
+
+        self.append_command(Cmd {
+                                image: Some(image),
+                                layout,
+                                clear_value,
+                                regions: Some(regions),
+                            });
+        self.prev_cmd_resource(KeyTy::Image,
+                               0,
+                               true,
+                               PipelineStages {
+                                   transfer: true,
+                                   ..PipelineStages::none()
+                               },
+                               AccessFlagBits {
+                                   transfer_write: true,
+                                   ..AccessFlagBits::none()
+                               },
+                               layout,
+                               layout)?;
+        Ok(())
+    }
+
     /// Calls `vkCmdCopyBuffer` on the builder.
     ///
     /// Does nothing if the list of regions is empty, as it would be a no-op and isn't a valid
@@ -1080,6 +1313,97 @@ impl<P> SyncCommandBufferBuilder<P> {
         Ok(())
     }
 
+    /// Calls `vkCmdCopyQueryPoolResults` on the builder.
+    #[inline]
+    pub unsafe fn copy_query_pool_results<Q, D, T>(&mut self, query_pool: Q, first_query: u32,
+                                                   query_count: u32, destination: D,
+                                                   stride: usize, flags: QueryResultFlags)
+                                                   -> Result<(), SyncCommandBufferBuilderError>
+        where Q: QueryPoolAbstract + Send + Sync + 'static,
+              D: TypedBufferAccess<Content = [T]> + Send + Sync + 'static,
+              T: QueryResultElement
+    {
+        struct Cmd<Q, D> {
+            query_pool: Q,
+            first_query: u32,
+            query_count: u32,
+            destination: D,
+            stride: usize,
+            flags: QueryResultFlags,
+        }
+
+        impl<P, Q, D, T> Command<P> for Cmd<Q, D>
+            where Q: QueryPoolAbstract + Send + Sync + 'static,
+                  D: TypedBufferAccess<Content = [T]> + Send + Sync + 'static,
+                  T: QueryResultElement
+        {
+            fn name(&self) -> &'static str {
+                "vkCmdCopyQueryPoolResults"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                let queries = self.query_pool
+                    .inner()
+                    .queries_range(self.first_query, self.query_count)
+                    .unwrap();
+                out.copy_query_pool_results::<T>(queries, &self.destination, self.stride,
+                                                 self.flags);
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                struct Fin<Q, D>(Q, D);
+                impl<Q, D> FinalCommand for Fin<Q, D>
+                    where Q: QueryPoolAbstract + Send + Sync + 'static,
+                          D: BufferAccess + Send + Sync + 'static
+                {
+                    fn name(&self) -> &'static str {
+                        "vkCmdCopyQueryPoolResults"
+                    }
+                    fn buffer(&self, num: usize) -> &BufferAccess {
+                        assert_eq!(num, 0);
+                        &self.1
+                    }
+                    fn buffer_name(&self, _: usize) -> Cow<'static, str> {
+                        "destination".into()
+                    }
+                }
+                Box::new(Fin(self.query_pool, self.destination))
+            }
+
+            fn buffer(&self, num: usize) -> &BufferAccess {
+                assert_eq!(num, 0);
+                &self.destination
+            }
+
+            fn buffer_name(&self, _: usize) -> Cow<'static, str> {
+                "destination".into()
+            }
+        }
+
+        self.append_command(Cmd {
+                                query_pool,
+                                first_query,
+                                query_count,
+                                destination,
+                                stride,
+                                flags,
+                            });
+        self.prev_cmd_resource(KeyTy::Buffer,
+                               0,
+                               true,
+                               PipelineStages {
+                                   transfer: true,
+                                   ..PipelineStages::none()
+                               },
+                               AccessFlagBits {
+                                   transfer_write: true,
+                                   ..AccessFlagBits::none()
+                               },
+                               ImageLayout::Undefined,
+                               ImageLayout::Undefined)?;
+        Ok(())
+    }
+
     /// Calls `vkCmdDispatch` on the builder.
     #[inline]
     pub unsafe fn dispatch(&mut self, dimensions: [u32; 3]) {
@@ -1163,7 +1487,7 @@ impl<P> SyncCommandBufferBuilder<P> {
                                PipelineStages {
                                    draw_indirect: true,
                                    ..PipelineStages::none()
-                               }, // TODO: is draw_indirect correct?
+                               },
                                AccessFlagBits {
                                    indirect_command_read: true,
                                    ..AccessFlagBits::none()
@@ -1422,6 +1746,43 @@ impl<P> SyncCommandBufferBuilder<P> {
         self.prev_cmd_left_render_pass();
     }
 
+    /// Calls `vkCmdEndQuery` on the builder.
+    #[inline]
+    pub unsafe fn end_query<Q>(&mut self, query_pool: Q, query: u32)
+        where Q: QueryPoolAbstract + Send + Sync + 'static
+    {
+        struct Cmd<Q> {
+            query_pool: Q,
+            query: u32,
+        }
+
+        impl<P, Q> Command<P> for Cmd<Q>
+            where Q: QueryPoolAbstract + Send + Sync + 'static
+        {
+            fn name(&self) -> &'static str {
+                "vkCmdEndQuery"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                out.end_query(self.query_pool.inner().query(self.query).unwrap());
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                struct Fin<Q>(Q);
+                impl<Q> FinalCommand for Fin<Q>
+                    where Q: QueryPoolAbstract + Send + Sync + 'static
+                {
+                    fn name(&self) -> &'static str {
+                        "vkCmdEndQuery"
+                    }
+                }
+                Box::new(Fin(self.query_pool))
+            }
+        }
+
+        self.append_command(Cmd { query_pool, query });
+    }
+
     /// Starts the process of executing secondary command buffers. Returns an intermediate struct
     /// which can be used to add the command buffers.
     #[inline]
@@ -1584,6 +1945,78 @@ impl<P> SyncCommandBufferBuilder<P> {
                             });
     }
 
+    /// Calls `vkCmdPipelineBarrier` on the builder with a buffer memory barrier that releases the
+    /// ownership of `buffer` to the queue family `destination_family`.
+    ///
+    /// `source_family` must be the queue family of the command buffer. The buffer must then be
+    /// acquired by a queue of `destination_family` before it can be used there, and it can no
+    /// longer be used by `source_family`.
+    #[inline]
+    pub unsafe fn release_buffer_ownership<B>(&mut self, buffer: B, source_family: u32,
+                                              destination_family: u32)
+                                              -> Result<(), SyncCommandBufferBuilderError>
+        where B: BufferAccess + Send + Sync + 'static
+    {
+        self.append_command(OwnershipBufferCmd {
+                                buffer,
+                                source_family,
+                                destination_family,
+                                release: true,
+                            });
+        self.prev_cmd_ownership_resource(KeyTy::Buffer, ImageLayout::Undefined)?;
+        self.prev_cmd_released_ownership(KeyTy::Buffer,
+                                         source_family,
+                                         destination_family,
+                                         ImageLayout::Undefined);
+        Ok(())
+    }
+
+    /// Calls `vkCmdPipelineBarrier` on the builder with an image memory barrier that releases the
+    /// ownership of `image` to the queue family `destination_family`.
+    ///
+    /// The image must be in `layout`, and no layout transition happens during the transfer. Since
+    /// the image can't be transitioned after it has been released, `layout` should be the final
+    /// layout requirement of the image.
+    ///
+    /// `source_family` must be the queue family of the command buffer. The image must then be
+    /// acquired by a queue of `destination_family` before it can be used there, and it can no
+    /// longer be used by `source_family`.
+    #[inline]
+    pub unsafe fn release_image_ownership<I>(&mut self, image: I, layout: ImageLayout,
+                                             source_family: u32, destination_family: u32)
+                                             -> Result<(), SyncCommandBufferBuilderError>
+        where I: ImageAccess + Send + Sync + 'static
+    {
+        self.append_command(OwnershipImageCmd {
+                                image,
+                                layout,
+                                source_family,
+                                destination_family,
+                                release: true,
+                            });
+        self.prev_cmd_ownership_resource(KeyTy::Image, layout)?;
+        self.prev_cmd_released_ownership(KeyTy::Image, source_family, destination_family, layout);
+        Ok(())
+    }
+
+    // Declares the resource of a queue family ownership transfer command that has just been
+    // added. The transfer is considered as an exclusive access in all the stages, so that all
+    // the other usages of the resource are ordered with it.
+    #[inline]
+    fn prev_cmd_ownership_resource(&mut self, resource_ty: KeyTy, layout: ImageLayout)
+                                   -> Result<(), SyncCommandBufferBuilderError> {
+        self.prev_cmd_resource(resource_ty,
+                               0,
+                               true,
+                               PipelineStages {
+                                   all_commands: true,
+                                   ..PipelineStages::none()
+                               },
+                               AccessFlagBits::none(),
+                               layout,
+                               layout)
+    }
+
     /// Calls `vkCmdResetEvent` on the builder.
     #[inline]
     pub unsafe fn reset_event(&mut self, event: Arc<Event>, stages: PipelineStages) {
@@ -1615,32 +2048,209 @@ impl<P> SyncCommandBufferBuilder<P> {
         self.append_command(Cmd { event, stages });
     }
 
-    /// Calls `vkCmdSetBlendConstants` on the builder.
+    /// Calls `vkCmdResetQueryPool` on the builder.
     #[inline]
-    pub unsafe fn set_blend_constants(&mut self, constants: [f32; 4]) {
-        struct Cmd {
-            constants: [f32; 4],
+    pub unsafe fn reset_query_pool<Q>(&mut self, query_pool: Q, first_query: u32,
+                                      query_count: u32)
+        where Q: QueryPoolAbstract + Send + Sync + 'static
+    {
+        struct Cmd<Q> {
+            query_pool: Q,
+            first_query: u32,
+            query_count: u32,
         }
 
-        impl<P> Command<P> for Cmd {
+        impl<P, Q> Command<P> for Cmd<Q>
+            where Q: QueryPoolAbstract + Send + Sync + 'static
+        {
             fn name(&self) -> &'static str {
-                "vkCmdSetBlendConstants"
+                "vkCmdResetQueryPool"
             }
 
             unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
-                out.set_blend_constants(self.constants);
+                let queries = self.query_pool
+                    .inner()
+                    .queries_range(self.first_query, self.query_count)
+                    .unwrap();
+                out.reset_query_pool(queries);
             }
 
             fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
-                Box::new("vkCmdSetBlendConstants")
+                struct Fin<Q>(Q);
+                impl<Q> FinalCommand for Fin<Q>
+                    where Q: QueryPoolAbstract + Send + Sync + 'static
+                {
+                    fn name(&self) -> &'static str {
+                        "vkCmdResetQueryPool"
+                    }
+                }
+                Box::new(Fin(self.query_pool))
             }
         }
 
-        self.append_command(Cmd { constants });
+        self.append_command(Cmd {
+                                query_pool,
+                                first_query,
+                                query_count,
+                            });
     }
 
-    /// Calls `vkCmdSetDepthBias` on the builder.
-    #[inline]
+    /// Calls `vkCmdResolveImage` on the builder.
+    ///
+    /// Does nothing if the list of regions is empty, as it would be a no-op and isn't a valid
+    /// usage of the command anyway.
+    #[inline]
+    pub unsafe fn resolve_image<S, D, R>(&mut self, source: S, source_layout: ImageLayout,
+                                         destination: D, destination_layout: ImageLayout,
+                                         regions: R)
+                                         -> Result<(), SyncCommandBufferBuilderError>
+        where S: ImageAccess + Send + Sync + 'static,
+              D: ImageAccess + Send + Sync + 'static,
+              R: Iterator<Item = UnsafeCommandBufferBuilderImageResolve> + Send + Sync + 'static
+    {
+        struct Cmd<S, D, R> {
+            source: Option<S>,
+            source_layout: ImageLayout,
+            destination: Option<D>,
+            destination_layout: ImageLayout,
+            regions: Option<R>,
+        }
+
+        impl<P, S, D, R> Command<P> for Cmd<S, D, R>
+            where S: ImageAccess + Send + Sync + 'static,
+                  D: ImageAccess + Send + Sync + 'static,
+                  R: Iterator<Item = UnsafeCommandBufferBuilderImageResolve>
+        {
+            fn name(&self) -> &'static str {
+                "vkCmdResolveImage"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                out.resolve_image(self.source.as_ref().unwrap(),
+                               self.source_layout,
+                               self.destination.as_ref().unwrap(),
+                               self.destination_layout,
+                               self.regions.take().unwrap());
+            }
+
+            fn into_final_command(mut self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                struct Fin<S, D>(S, D);
+                impl<S, D> FinalCommand for Fin<S, D>
+                    where S: ImageAccess + Send + Sync + 'static,
+                          D: ImageAccess + Send + Sync + 'static
+                {
+                    fn name(&self) -> &'static str {
+                        "vkCmdResolveImage"
+                    }
+                    fn image(&self, num: usize) -> &ImageAccess {
+                        if num == 0 {
+                            &self.0
+                        } else if num == 1 {
+                            &self.1
+                        } else {
+                            panic!()
+                        }
+                    }
+                    fn image_name(&self, num: usize) -> Cow<'static, str> {
+                        if num == 0 {
+                            "source".into()
+                        } else if num == 1 {
+                            "destination".into()
+                        } else {
+                            panic!()
+                        }
+                    }
+                }
+
+                // Note: borrow checker somehow doesn't accept `self.source` and `self.destination`
+                // without using an Option.
+                Box::new(Fin(self.source.take().unwrap(),
+                             self.destination.take().unwrap()))
+            }
+
+            fn image(&self, num: usize) -> &ImageAccess {
+                if num == 0 {
+                    self.source.as_ref().unwrap()
+                } else if num == 1 {
+                    self.destination.as_ref().unwrap()
+                } else {
+                    panic!()
+                }
+            }
+
+            fn image_name(&self, num: usize) -> Cow<'static, str> {
+                if num == 0 {
+                    "source".into()
+                } else if num == 1 {
+                    "destination".into()
+                } else {
+                    panic!()
+                }
+            }
+        }
+
+        self.append_command(Cmd {
+                                source: Some(source),
+                                source_layout,
+                                destination: Some(destination),
+                                destination_layout,
+                                regions: Some(regions),
+                            });
+        self.prev_cmd_resource(KeyTy::Image,
+                               0,
+                               false,
+                               PipelineStages {
+                                   transfer: true,
+                                   ..PipelineStages::none()
+                               },
+                               AccessFlagBits {
+                                   transfer_read: true,
+                                   ..AccessFlagBits::none()
+                               },
+                               source_layout,
+                               source_layout)?;
+        self.prev_cmd_resource(KeyTy::Image,
+                               1,
+                               true,
+                               PipelineStages {
+                                   transfer: true,
+                                   ..PipelineStages::none()
+                               },
+                               AccessFlagBits {
+                                   transfer_write: true,
+                                   ..AccessFlagBits::none()
+                               },
+                               destination_layout,
+                               destination_layout)?;
+        Ok(())
+    }
+
+    /// Calls `vkCmdSetBlendConstants` on the builder.
+    #[inline]
+    pub unsafe fn set_blend_constants(&mut self, constants: [f32; 4]) {
+        struct Cmd {
+            constants: [f32; 4],
+        }
+
+        impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "vkCmdSetBlendConstants"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                out.set_blend_constants(self.constants);
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                Box::new("vkCmdSetBlendConstants")
+            }
+        }
+
+        self.append_command(Cmd { constants });
+    }
+
+    /// Calls `vkCmdSetDepthBias` on the builder.
+    #[inline]
     pub unsafe fn set_depth_bias(&mut self, constant_factor: f32, clamp: f32, slope_factor: f32) {
         struct Cmd {
             constant_factor: f32,
@@ -1725,6 +2335,22 @@ impl<P> SyncCommandBufferBuilder<P> {
         self.append_command(Cmd { event, stages });
     }
 
+    /// Calls `vkCmdSetEvent` on the builder, and uses the event as the first half of a split
+    /// barrier.
+    ///
+    /// Later, when the builder needs a barrier that only waits upon commands that were recorded
+    /// before this one, it waits on `event` with `vkCmdWaitEvents` instead of adding a full
+    /// pipeline barrier. This lets the commands recorded in-between overlap with the previous
+    /// ones.
+    ///
+    /// The event must not be used anywhere else until this command buffer has finished executing,
+    /// and it must be reset before this command buffer is submitted again.
+    #[inline]
+    pub unsafe fn signal_split_barrier(&mut self, event: Arc<Event>, stages: PipelineStages) {
+        self.set_event(event.clone(), stages);
+        self.prev_cmd_signaled_split_barrier(event, stages);
+    }
+
     /// Calls `vkCmdSetLineWidth` on the builder.
     #[inline]
     pub unsafe fn set_line_width(&mut self, line_width: f32) {
@@ -1749,7 +2375,89 @@ impl<P> SyncCommandBufferBuilder<P> {
         self.append_command(Cmd { line_width });
     }
 
-    // TODO: stencil states
+    /// Calls `vkCmdSetStencilCompareMask` on the builder.
+    #[inline]
+    pub unsafe fn set_stencil_compare_mask(&mut self, face_mask: StencilFaceFlags, compare_mask: u32) {
+        struct Cmd {
+            face_mask: StencilFaceFlags,
+            compare_mask: u32,
+        }
+
+        impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "vkCmdSetStencilCompareMask"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                out.set_stencil_compare_mask(self.face_mask, self.compare_mask);
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                Box::new("vkCmdSetStencilCompareMask")
+            }
+        }
+
+        self.append_command(Cmd {
+                                face_mask,
+                                compare_mask,
+                            });
+    }
+
+    /// Calls `vkCmdSetStencilReference` on the builder.
+    #[inline]
+    pub unsafe fn set_stencil_reference(&mut self, face_mask: StencilFaceFlags, reference: u32) {
+        struct Cmd {
+            face_mask: StencilFaceFlags,
+            reference: u32,
+        }
+
+        impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "vkCmdSetStencilReference"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                out.set_stencil_reference(self.face_mask, self.reference);
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                Box::new("vkCmdSetStencilReference")
+            }
+        }
+
+        self.append_command(Cmd {
+                                face_mask,
+                                reference,
+                            });
+    }
+
+    /// Calls `vkCmdSetStencilWriteMask` on the builder.
+    #[inline]
+    pub unsafe fn set_stencil_write_mask(&mut self, face_mask: StencilFaceFlags, write_mask: u32) {
+        struct Cmd {
+            face_mask: StencilFaceFlags,
+            write_mask: u32,
+        }
+
+        impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "vkCmdSetStencilWriteMask"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                out.set_stencil_write_mask(self.face_mask, self.write_mask);
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                Box::new("vkCmdSetStencilWriteMask")
+            }
+        }
+
+        self.append_command(Cmd {
+                                face_mask,
+                                write_mask,
+                            });
+    }
 
     /// Calls `vkCmdSetScissor` on the builder.
     ///
@@ -1820,26 +2528,31 @@ impl<P> SyncCommandBufferBuilder<P> {
     }
 
     /// Calls `vkCmdUpdateBuffer` on the builder.
+    ///
+    /// The data is kept alive until the command buffer is built. The number of bytes that are
+    /// written is the size of `buffer`, which must not be larger than the data.
     #[inline]
-    pub unsafe fn update_buffer<B, D>(&mut self, buffer: B, data: D)
+    pub unsafe fn update_buffer<B, D, Dd>(&mut self, buffer: B, data: Dd)
         where B: BufferAccess + Send + Sync + 'static,
-              D: Send + Sync + 'static
+              D: ?Sized,
+              Dd: SafeDeref<Target = D> + Send + Sync + 'static
     {
-        struct Cmd<B, D> {
+        struct Cmd<B, Dd> {
             buffer: B,
-            data: D,
+            data: Dd,
         }
 
-        impl<P, B, D> Command<P> for Cmd<B, D>
+        impl<P, B, D, Dd> Command<P> for Cmd<B, Dd>
             where B: BufferAccess + Send + Sync + 'static,
-                  D: Send + Sync + 'static
+                  D: ?Sized,
+                  Dd: SafeDeref<Target = D> + Send + Sync + 'static
         {
             fn name(&self) -> &'static str {
                 "vkCmdUpdateBuffer"
             }
 
             unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
-                out.update_buffer(&self.buffer, &self.data);
+                out.update_buffer(&self.buffer, &*self.data);
             }
 
             fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
@@ -1887,6 +2600,253 @@ impl<P> SyncCommandBufferBuilder<P> {
                                ImageLayout::Undefined)
             .unwrap();
     }
+
+    /// Calls `vkCmdWaitEvents` on the builder, with a global memory barrier.
+    ///
+    /// `source_stages` must be the union of the stages that were passed when signaling the events.
+    ///
+    /// Does nothing if the list of events is empty.
+    pub unsafe fn wait_events<I>(&mut self, events: I, source_stages: PipelineStages,
+                                 source_access: AccessFlagBits,
+                                 destination_stages: PipelineStages,
+                                 destination_access: AccessFlagBits)
+        where I: IntoIterator<Item = Arc<Event>>
+    {
+        struct Cmd {
+            events: SmallVec<[Arc<Event>; 4]>,
+            source_stages: PipelineStages,
+            source_access: AccessFlagBits,
+            destination_stages: PipelineStages,
+            destination_access: AccessFlagBits,
+        }
+
+        impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "vkCmdWaitEvents"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                let mut barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+                barrier.add_memory_barrier(self.source_stages,
+                                           self.source_access,
+                                           self.destination_stages,
+                                           self.destination_access,
+                                           false);
+                out.wait_events(self.events.iter().map(|e| &**e), &barrier);
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                struct Fin(SmallVec<[Arc<Event>; 4]>);
+                impl FinalCommand for Fin {
+                    fn name(&self) -> &'static str {
+                        "vkCmdWaitEvents"
+                    }
+                }
+                Box::new(Fin(self.events))
+            }
+        }
+
+        self.append_command(Cmd {
+                                events: events.into_iter().collect(),
+                                source_stages,
+                                source_access,
+                                destination_stages,
+                                destination_access,
+                            });
+    }
+
+    /// Calls `vkCmdWriteTimestamp` on the builder.
+    #[inline]
+    pub unsafe fn write_timestamp<Q>(&mut self, query_pool: Q, query: u32, stage: PipelineStages)
+        where Q: QueryPoolAbstract + Send + Sync + 'static
+    {
+        struct Cmd<Q> {
+            query_pool: Q,
+            query: u32,
+            stage: PipelineStages,
+        }
+
+        impl<P, Q> Command<P> for Cmd<Q>
+            where Q: QueryPoolAbstract + Send + Sync + 'static
+        {
+            fn name(&self) -> &'static str {
+                "vkCmdWriteTimestamp"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                out.write_timestamp(self.query_pool.inner().query(self.query).unwrap(),
+                                    self.stage);
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                struct Fin<Q>(Q);
+                impl<Q> FinalCommand for Fin<Q>
+                    where Q: QueryPoolAbstract + Send + Sync + 'static
+                {
+                    fn name(&self) -> &'static str {
+                        "vkCmdWriteTimestamp"
+                    }
+                }
+                Box::new(Fin(self.query_pool))
+            }
+        }
+
+        self.append_command(Cmd {
+                                query_pool,
+                                query,
+                                stage,
+                            });
+    }
+}
+
+// Command that releases or acquires the ownership of a buffer.
+struct OwnershipBufferCmd<B> {
+    buffer: B,
+    source_family: u32,
+    destination_family: u32,
+    // True for a release, false for an acquire.
+    release: bool,
+}
+
+impl<P, B> Command<P> for OwnershipBufferCmd<B>
+    where B: BufferAccess + Send + Sync + 'static
+{
+    fn name(&self) -> &'static str {
+        "vkCmdPipelineBarrier"
+    }
+
+    unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+        let (source_stage, destination_stage) = ownership_transfer_stages(self.release);
+        let mut barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+        barrier.add_buffer_memory_barrier(&self.buffer,
+                                          source_stage,
+                                          AccessFlagBits::none(),
+                                          destination_stage,
+                                          AccessFlagBits::none(),
+                                          false,
+                                          Some((self.source_family, self.destination_family)),
+                                          0,
+                                          self.buffer.size());
+        out.pipeline_barrier(&barrier);
+    }
+
+    fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+        struct Fin<B>(B);
+        impl<B> FinalCommand for Fin<B>
+            where B: BufferAccess + Send + Sync + 'static
+        {
+            fn name(&self) -> &'static str {
+                "vkCmdPipelineBarrier"
+            }
+            fn buffer(&self, num: usize) -> &BufferAccess {
+                assert_eq!(num, 0);
+                &self.0
+            }
+            fn buffer_name(&self, _: usize) -> Cow<'static, str> {
+                "buffer".into()
+            }
+        }
+        Box::new(Fin(self.buffer))
+    }
+
+    fn buffer(&self, num: usize) -> &BufferAccess {
+        assert_eq!(num, 0);
+        &self.buffer
+    }
+
+    fn buffer_name(&self, _: usize) -> Cow<'static, str> {
+        "buffer".into()
+    }
+}
+
+// Command that releases or acquires the ownership of an image.
+struct OwnershipImageCmd<I> {
+    image: I,
+    layout: ImageLayout,
+    source_family: u32,
+    destination_family: u32,
+    // True for a release, false for an acquire.
+    release: bool,
+}
+
+impl<P, I> Command<P> for OwnershipImageCmd<I>
+    where I: ImageAccess + Send + Sync + 'static
+{
+    fn name(&self) -> &'static str {
+        "vkCmdPipelineBarrier"
+    }
+
+    unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+        let (source_stage, destination_stage) = ownership_transfer_stages(self.release);
+        let mut barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+        barrier.add_image_memory_barrier(&self.image,
+                                         0 .. self.image.mipmap_levels(),
+                                         0 .. self.image.dimensions().array_layers(),
+                                         source_stage,
+                                         AccessFlagBits::none(),
+                                         destination_stage,
+                                         AccessFlagBits::none(),
+                                         false,
+                                         Some((self.source_family, self.destination_family)),
+                                         self.layout,
+                                         self.layout);
+        out.pipeline_barrier(&barrier);
+    }
+
+    fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+        struct Fin<I>(I);
+        impl<I> FinalCommand for Fin<I>
+            where I: ImageAccess + Send + Sync + 'static
+        {
+            fn name(&self) -> &'static str {
+                "vkCmdPipelineBarrier"
+            }
+            fn image(&self, num: usize) -> &ImageAccess {
+                assert_eq!(num, 0);
+                &self.0
+            }
+            fn image_name(&self, _: usize) -> Cow<'static, str> {
+                "image".into()
+            }
+        }
+        Box::new(Fin(self.image))
+    }
+
+    fn image(&self, num: usize) -> &ImageAccess {
+        assert_eq!(num, 0);
+        &self.image
+    }
+
+    fn image_name(&self, _: usize) -> Cow<'static, str> {
+        "image".into()
+    }
+}
+
+// Returns the source and destination stages of the barrier of a queue family ownership transfer.
+//
+// The stages that come before a release and after an acquire are already synchronized by the
+// barriers that the builder inserts around the transfer command, therefore the other side of the
+// barrier doesn't wait on anything.
+#[inline]
+fn ownership_transfer_stages(release: bool) -> (PipelineStages, PipelineStages) {
+    let all_commands = PipelineStages {
+        all_commands: true,
+        ..PipelineStages::none()
+    };
+
+    if release {
+        (all_commands,
+         PipelineStages {
+             bottom_of_pipe: true,
+             ..PipelineStages::none()
+         })
+    } else {
+        (PipelineStages {
+             top_of_pipe: true,
+             ..PipelineStages::none()
+         },
+         all_commands)
+    }
 }
 
 pub struct SyncCommandBufferBuilderBindDescriptorSets<'b, P: 'b> {
@@ -2206,18 +3166,22 @@ impl<'a, P> SyncCommandBufferBuilderBindVertexBuffer<'a, P> {
 }
 
 /// Prototype for a `vkCmdExecuteCommands`.
-// FIXME: synchronization not implemented yet
 pub struct SyncCommandBufferBuilderExecuteCommands<'a, P: 'a> {
     builder: &'a mut SyncCommandBufferBuilder<P>,
     inner: UnsafeCommandBufferBuilderExecuteCommands,
-    command_buffers: Vec<Box<Any + Send + Sync>>,
+    command_buffers: Vec<Box<SecondaryCommandBuffer + Send + Sync>>,
 }
 
 impl<'a, P> SyncCommandBufferBuilderExecuteCommands<'a, P> {
     /// Adds a command buffer to the list.
+    ///
+    /// # Safety
+    ///
+    /// `lock_record()` must have been called on the command buffer. It is automatically unlocked
+    /// once the command is destroyed.
     #[inline]
-    pub fn add<C>(&mut self, command_buffer: C)
-        where C: CommandBuffer + Send + Sync + 'static
+    pub unsafe fn add<C>(&mut self, command_buffer: C)
+        where C: CommandBuffer + SecondaryCommandBuffer + Send + Sync + 'static
     {
         self.inner.add(&command_buffer);
         self.command_buffers
@@ -2226,9 +3190,67 @@ impl<'a, P> SyncCommandBufferBuilderExecuteCommands<'a, P> {
 
     #[inline]
     pub unsafe fn submit(self) -> Result<(), SyncCommandBufferBuilderError> {
+        // List of the secondary command buffers, that unlocks them when destroyed.
+        struct LockedCbs(Vec<Box<SecondaryCommandBuffer + Send + Sync>>);
+
+        impl LockedCbs {
+            // Finds the command buffer that holds the `num`th buffer of the list, and the index
+            // of the buffer within that command buffer.
+            fn find_buffer(&self, mut num: usize) -> (usize, usize) {
+                for (cb_num, cb) in self.0.iter().enumerate() {
+                    if num < cb.num_buffers() {
+                        return (cb_num, num);
+                    }
+                    num -= cb.num_buffers();
+                }
+                panic!()
+            }
+
+            // Same as `find_buffer`, but for images.
+            fn find_image(&self, mut num: usize) -> (usize, usize) {
+                for (cb_num, cb) in self.0.iter().enumerate() {
+                    if num < cb.num_images() {
+                        return (cb_num, num);
+                    }
+                    num -= cb.num_images();
+                }
+                panic!()
+            }
+
+            fn buffer(&self, num: usize) -> &BufferAccess {
+                let (cb_num, num) = self.find_buffer(num);
+                self.0[cb_num].buffer(num).unwrap().0
+            }
+
+            fn image(&self, num: usize) -> &ImageAccess {
+                let (cb_num, num) = self.find_image(num);
+                self.0[cb_num].image(num).unwrap().0
+            }
+
+            fn buffer_name(&self, num: usize) -> Cow<'static, str> {
+                let (cb_num, num) = self.find_buffer(num);
+                format!("Buffer {} of secondary command buffer {}", num, cb_num).into()
+            }
+
+            fn image_name(&self, num: usize) -> Cow<'static, str> {
+                let (cb_num, num) = self.find_image(num);
+                format!("Image {} of secondary command buffer {}", num, cb_num).into()
+            }
+        }
+
+        impl Drop for LockedCbs {
+            fn drop(&mut self) {
+                for cb in self.0.iter() {
+                    unsafe {
+                        cb.unlock_record();
+                    }
+                }
+            }
+        }
+
         struct Cmd {
             inner: Option<UnsafeCommandBufferBuilderExecuteCommands>,
-            command_buffers: Vec<Box<Any + Send + Sync>>,
+            command_buffers: LockedCbs,
         }
 
         impl<P> Command<P> for Cmd {
@@ -2241,20 +3263,78 @@ impl<'a, P> SyncCommandBufferBuilderExecuteCommands<'a, P> {
             }
 
             fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
-                struct Fin(Vec<Box<Any + Send + Sync>>);
+                struct Fin(LockedCbs);
                 impl FinalCommand for Fin {
                     fn name(&self) -> &'static str {
                         "vkCmdExecuteCommands"
                     }
+                    fn buffer(&self, num: usize) -> &BufferAccess {
+                        self.0.buffer(num)
+                    }
+                    fn image(&self, num: usize) -> &ImageAccess {
+                        self.0.image(num)
+                    }
+                    fn buffer_name(&self, num: usize) -> Cow<'static, str> {
+                        self.0.buffer_name(num)
+                    }
+                    fn image_name(&self, num: usize) -> Cow<'static, str> {
+                        self.0.image_name(num)
+                    }
                 }
                 Box::new(Fin(self.command_buffers))
             }
+
+            fn buffer(&self, num: usize) -> &BufferAccess {
+                self.command_buffers.buffer(num)
+            }
+
+            fn image(&self, num: usize) -> &ImageAccess {
+                self.command_buffers.image(num)
+            }
+
+            fn buffer_name(&self, num: usize) -> Cow<'static, str> {
+                self.command_buffers.buffer_name(num)
+            }
+
+            fn image_name(&self, num: usize) -> Cow<'static, str> {
+                self.command_buffers.image_name(num)
+            }
         }
 
+        let buffers_usage: Vec<_> = self.command_buffers
+            .iter()
+            .flat_map(|cb| (0 .. cb.num_buffers()).map(move |n| cb.buffer(n).unwrap().1))
+            .collect();
+        let images_usage: Vec<_> = self.command_buffers
+            .iter()
+            .flat_map(|cb| (0 .. cb.num_images()).map(move |n| cb.image(n).unwrap().1))
+            .collect();
+
         self.builder.append_command(Cmd {
                                         inner: Some(self.inner),
-                                        command_buffers: self.command_buffers,
+                                        command_buffers: LockedCbs(self.command_buffers),
                                     });
+
+        for (n, usage) in buffers_usage.into_iter().enumerate() {
+            self.builder.prev_cmd_resource(KeyTy::Buffer,
+                                           n,
+                                           usage.exclusive,
+                                           usage.stages,
+                                           usage.access,
+                                           ImageLayout::Undefined,
+                                           ImageLayout::Undefined)?;
+        }
+
+        for (n, usage) in images_usage.into_iter().enumerate() {
+            self.builder.prev_cmd_resource(KeyTy::Image,
+                                           n,
+                                           usage.exclusive,
+                                           usage.stages,
+                                           usage.access,
+                                           usage.initial_layout,
+                                           usage.final_layout)?;
+        }
+
         Ok(())
     }
 }
@@ -9,15 +9,23 @@
 
 //! Contains `SyncCommandBufferBuilder` and `SyncCommandBuffer`.
 
+pub use self::base::KeyTy;
 pub use self::base::SyncCommandBuffer;
 pub use self::base::SyncCommandBufferBuilder;
 pub use self::base::SyncCommandBufferBuilderError;
 pub use self::commands::SyncCommandBufferBuilderBindDescriptorSets;
 pub use self::commands::SyncCommandBufferBuilderBindVertexBuffer;
 pub use self::commands::SyncCommandBufferBuilderExecuteCommands;
+pub use self::report::BarrierReport;
+pub use self::report::BarrierReportCommand;
+pub use self::report::BarrierReportEntry;
+pub use self::report::InsertedBarrier;
+pub use self::report::InsertedBarrierResource;
+pub use self::report::InsertedBarrierTy;
 
 mod base;
 mod commands;
+mod report;
 
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,249 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use command_buffer::synced::base::KeyTy;
+use image::ImageLayout;
+use sync::AccessFlagBits;
+use sync::PipelineStages;
+
+/// List of the commands recorded by a `SyncCommandBuffer`, with the pipeline barriers and layout
+/// transitions that the builder automatically inserted between them.
+///
+/// The report must be enabled with `enable_barrier_report()` on the builder. You can then obtain
+/// it with `SyncCommandBuffer::barrier_report()` once the command buffer has been built. The `Display` implementation prints it as readable text, which is useful to find out
+/// which commands cause barriers.
+#[derive(Debug, Clone, Default)]
+pub struct BarrierReport {
+    /// The commands and barriers, in the order in which they were recorded in the command buffer.
+    pub entries: Vec<BarrierReportEntry>,
+}
+
+/// Entry of a `BarrierReport`.
+#[derive(Debug, Clone)]
+pub enum BarrierReportEntry {
+    /// A command that was added to the builder.
+    Command(BarrierReportCommand),
+    /// A barrier that was inserted by the builder.
+    Barrier(InsertedBarrier),
+}
+
+/// Command of a `BarrierReport`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BarrierReportCommand {
+    /// Index of the command within the command buffer, starting from 0.
+    pub index: usize,
+    /// Name of the command, for example `vkCmdCopyBuffer`.
+    pub name: &'static str,
+}
+
+/// Barrier that was inserted by a `SyncCommandBufferBuilder`.
+#[derive(Debug, Clone)]
+pub struct InsertedBarrier {
+    /// How the barrier was recorded.
+    pub ty: InsertedBarrierTy,
+    /// The resources that the barrier synchronizes.
+    pub resources: Vec<InsertedBarrierResource>,
+}
+
+/// How an `InsertedBarrier` was recorded.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum InsertedBarrierTy {
+    /// Recorded with `vkCmdPipelineBarrier`.
+    PipelineBarrier,
+    /// Recorded with `vkCmdWaitEvents`, on an event that was signaled with
+    /// `signal_split_barrier`.
+    WaitEvents,
+    /// Recorded with `vkCmdPipelineBarrier` at the end of the command buffer, in order to
+    /// transition images to the layout they are expected to be in afterwards.
+    ///
+    /// The transitions of images that aren't used by the last commands are merged with the last
+    /// pipeline barrier instead, if there is one.
+    FinalLayoutTransition,
+}
+
+/// A buffer or image synchronized by an `InsertedBarrier`.
+#[derive(Debug, Clone)]
+pub struct InsertedBarrierResource {
+    /// Whether the resource is a buffer or an image.
+    pub resource_ty: KeyTy,
+    /// Vulkan handle of the resource. This is the `VkBuffer` of a buffer, which may be shared by
+    /// multiple buffer slices, or the `VkImage` of an image.
+    pub resource_handle: u64,
+    /// Name of the resource within the command that uses it, for example `source`.
+    pub resource_name: Cow<'static, str>,
+
+    /// The command that last used the resource before the barrier. `None` if the barrier
+    /// transitions the image from the layout it is in at the start of the command buffer.
+    pub source_command: Option<BarrierReportCommand>,
+    /// The stages of the source command that the barrier waits upon.
+    pub source_stages: PipelineStages,
+    /// The accesses of the source command that the barrier makes available.
+    pub source_access: AccessFlagBits,
+
+    /// The command that caused the barrier. `None` for the transitions of images to their final
+    /// layout.
+    pub destination_command: Option<BarrierReportCommand>,
+    /// The stages of the destination command that wait upon the barrier.
+    pub destination_stages: PipelineStages,
+    /// The accesses of the destination command that the barrier makes visible.
+    pub destination_access: AccessFlagBits,
+
+    /// Layout of the image before the barrier. `Undefined` for buffers.
+    pub old_layout: ImageLayout,
+    /// Layout of the image after the barrier. `Undefined` for buffers.
+    pub new_layout: ImageLayout,
+}
+
+impl fmt::Display for BarrierReport {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        for entry in &self.entries {
+            match *entry {
+                BarrierReportEntry::Command(ref command) => {
+                    writeln!(fmt, "{}", command)?;
+                },
+                BarrierReportEntry::Barrier(ref barrier) => {
+                    write!(fmt, "{}", barrier)?;
+                },
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for BarrierReportCommand {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "#{} {}", self.index, self.name)
+    }
+}
+
+impl fmt::Display for InsertedBarrier {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let ty = match self.ty {
+            InsertedBarrierTy::PipelineBarrier => "pipeline barrier",
+            InsertedBarrierTy::WaitEvents => "wait events",
+            InsertedBarrierTy::FinalLayoutTransition => "final layout transition",
+        };
+
+        writeln!(fmt, "    {}", ty)?;
+        for resource in &self.resources {
+            writeln!(fmt, "        {}", resource)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for InsertedBarrierResource {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let ty = match self.resource_ty {
+            KeyTy::Buffer => "buffer",
+            KeyTy::Image => "image",
+        };
+        write!(fmt, "{} {:#x} `{}`: ", ty, self.resource_handle, self.resource_name)?;
+
+        match self.source_command {
+            Some(command) => write!(fmt, "{}", command)?,
+            None => write!(fmt, "start")?,
+        }
+        write!(fmt,
+               " [{}; {}] -> ",
+               flag_names(self.source_stages.names()),
+               flag_names(self.source_access.names()))?;
+
+        match self.destination_command {
+            Some(command) => write!(fmt, "{}", command)?,
+            None => write!(fmt, "end")?,
+        }
+        write!(fmt,
+               " [{}; {}]",
+               flag_names(self.destination_stages.names()),
+               flag_names(self.destination_access.names()))?;
+
+        if self.resource_ty == KeyTy::Image {
+            write!(fmt, ", layout {:?} -> {:?}", self.old_layout, self.new_layout)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Joins the names of the flags that are set, or returns `none` if there is none.
+fn flag_names(names: Vec<&'static str>) -> String {
+    if names.is_empty() {
+        "none".to_owned()
+    } else {
+        names.join(" | ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let report = BarrierReport {
+            entries: vec![
+                BarrierReportEntry::Command(BarrierReportCommand {
+                                                index: 0,
+                                                name: "vkCmdFillBuffer",
+                                            }),
+                BarrierReportEntry::Barrier(InsertedBarrier {
+                    ty: InsertedBarrierTy::PipelineBarrier,
+                    resources: vec![
+                        InsertedBarrierResource {
+                            resource_ty: KeyTy::Buffer,
+                            resource_handle: 0x2a,
+                            resource_name: "source".into(),
+                            source_command: Some(BarrierReportCommand {
+                                                     index: 0,
+                                                     name: "vkCmdFillBuffer",
+                                                 }),
+                            source_stages: PipelineStages {
+                                transfer: true,
+                                ..PipelineStages::none()
+                            },
+                            source_access: AccessFlagBits {
+                                transfer_write: true,
+                                ..AccessFlagBits::none()
+                            },
+                            destination_command: Some(BarrierReportCommand {
+                                                          index: 1,
+                                                          name: "vkCmdCopyBuffer",
+                                                      }),
+                            destination_stages: PipelineStages {
+                                transfer: true,
+                                ..PipelineStages::none()
+                            },
+                            destination_access: AccessFlagBits::none(),
+                            old_layout: ImageLayout::Undefined,
+                            new_layout: ImageLayout::Undefined,
+                        },
+                    ],
+                }),
+                BarrierReportEntry::Command(BarrierReportCommand {
+                                                index: 1,
+                                                name: "vkCmdCopyBuffer",
+                                            }),
+            ],
+        };
+
+        assert_eq!(report.to_string(),
+                   "#0 vkCmdFillBuffer\n\
+                    \x20   pipeline barrier\n\
+                    \x20       buffer 0x2a `source`: #0 vkCmdFillBuffer [transfer; transfer_write] -> \
+                    #1 vkCmdCopyBuffer [transfer; none]\n\
+                    #1 vkCmdCopyBuffer\n");
+    }
+}
@@ -7,15 +7,36 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use std::borrow::Cow;
 use std::iter;
+use std::sync::Arc;
 
+use buffer::BufferAccess;
 use buffer::BufferUsage;
 use buffer::CpuAccessibleBuffer;
+use command_buffer::synced::base::Command;
+use command_buffer::synced::base::FinalCommand;
+use command_buffer::synced::base::KeyTy;
 use command_buffer::synced::base::SyncCommandBufferBuilder;
 use command_buffer::synced::base::SyncCommandBufferBuilderError;
+use command_buffer::synced::report::BarrierReport;
+use command_buffer::synced::report::BarrierReportEntry;
+use command_buffer::synced::report::InsertedBarrier;
+use command_buffer::synced::report::InsertedBarrierTy;
 use command_buffer::sys::Flags;
 use command_buffer::sys::Kind;
+use command_buffer::sys::UnsafeCommandBufferBuilder;
 use device::Device;
+use format::Format;
+use image::AttachmentImage;
+use image::ImageAccess;
+use image::ImageLayout;
+use sync::AccessCheckError;
+use sync::AccessError;
+use sync::AccessFlagBits;
+use sync::Event;
+use sync::PipelineStages;
+use VulkanObject;
 
 #[test]
 fn basic_creation() {
@@ -42,3 +63,507 @@ fn basic_conflict() {
         };
     }
 }
+
+#[test]
+fn conflict_reports_last_command() {
+    unsafe {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let pool = Device::standard_command_pool(&device, queue.family());
+        let mut sync = SyncCommandBufferBuilder::new(&pool, Kind::primary(), Flags::None).unwrap();
+
+        let buf = buffer(&device);
+        sync.copy_buffer(buf.clone(), buffer(&device), iter::once((0, 0, 4)))
+            .unwrap();
+
+        // The source of the second copy is read by both copies, but the destination only
+        // conflicts with the second one.
+        match sync.copy_buffer(buf.clone(), buf, iter::once((0, 0, 4))) {
+            Err(SyncCommandBufferBuilderError::Conflict {
+                    command1_param,
+                    command1_offset,
+                    command2_param,
+                    command2_offset,
+                    ..
+                }) => {
+                assert_eq!(command1_param, "source");
+                assert_eq!(command1_offset, 1);
+                assert_eq!(command2_param, "destination");
+                assert_eq!(command2_offset, 1);
+            },
+            _ => panic!(),
+        };
+    }
+}
+
+#[test]
+fn barrier_report() {
+    unsafe {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let pool = Device::standard_command_pool(&device, queue.family());
+        let mut sync = SyncCommandBufferBuilder::new(&pool, Kind::primary(), Flags::None).unwrap();
+        sync.enable_barrier_report();
+
+        let source = CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(), 0u32)
+            .unwrap();
+        let destination = CpuAccessibleBuffer::from_data(device, BufferUsage::all(), 0u32)
+            .unwrap();
+
+        let source_handle = source.inner().buffer.internal_object();
+
+        sync.fill_buffer(source.clone(), 0);
+        sync.copy_buffer(source, destination, iter::once((0, 0, 4)))
+            .unwrap();
+        let cb = sync.build().unwrap();
+
+        let entries = &cb.barrier_report().unwrap().entries;
+        assert_eq!(entries.len(), 3);
+
+        match entries[0] {
+            BarrierReportEntry::Command(ref command) => assert_eq!(command.index, 0),
+            _ => panic!(),
+        };
+
+        match entries[1] {
+            BarrierReportEntry::Barrier(ref barrier) => {
+                assert_eq!(barrier.ty, InsertedBarrierTy::PipelineBarrier);
+                assert_eq!(barrier.resources.len(), 1);
+                let resource = &barrier.resources[0];
+                assert_eq!(resource.resource_handle, source_handle);
+                assert_eq!(resource.source_command.unwrap().index, 0);
+                assert_eq!(resource.destination_command.unwrap().index, 1);
+                assert!(resource.source_access.transfer_write);
+                assert!(resource.destination_access.transfer_read);
+            },
+            _ => panic!(),
+        };
+
+        match entries[2] {
+            BarrierReportEntry::Command(ref command) => assert_eq!(command.index, 1),
+            _ => panic!(),
+        };
+    }
+}
+
+#[test]
+fn barrier_report_disabled_by_default() {
+    unsafe {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let pool = Device::standard_command_pool(&device, queue.family());
+        let mut sync = SyncCommandBufferBuilder::new(&pool, Kind::primary(), Flags::None).unwrap();
+
+        let buf = buffer(&device);
+        sync.fill_buffer(buf.clone(), 0);
+        sync.fill_buffer(buf, 0);
+        let cb = sync.build().unwrap();
+
+        assert!(cb.barrier_report().is_none());
+    }
+}
+
+// Returns the barriers of a report.
+fn barriers(report: &BarrierReport) -> Vec<&InsertedBarrier> {
+    report
+        .entries
+        .iter()
+        .filter_map(|entry| match *entry {
+                        BarrierReportEntry::Barrier(ref barrier) => Some(barrier),
+                        _ => None,
+                    })
+        .collect()
+}
+
+// Creates a buffer that can be used with the transfer commands.
+fn buffer(device: &Arc<Device>) -> Arc<CpuAccessibleBuffer<u32>> {
+    CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(), 0u32).unwrap()
+}
+
+// Command that reads an image without recording anything, so that the tests can choose the
+// stages in which the image is used.
+struct ReadImage<I>(I);
+
+impl<P, I> Command<P> for ReadImage<I>
+    where I: ImageAccess + Send + Sync + 'static
+{
+    fn name(&self) -> &'static str {
+        "read_image"
+    }
+
+    unsafe fn send(&mut self, _: &mut UnsafeCommandBufferBuilder<P>) {
+    }
+
+    fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+        struct Fin<I>(I);
+        impl<I> FinalCommand for Fin<I>
+            where I: ImageAccess + Send + Sync + 'static
+        {
+            fn name(&self) -> &'static str {
+                "read_image"
+            }
+            fn image(&self, _num: usize) -> &ImageAccess {
+                &self.0
+            }
+            fn image_name(&self, _num: usize) -> Cow<'static, str> {
+                "image".into()
+            }
+        }
+        Box::new(Fin(self.0))
+    }
+
+    fn image(&self, _num: usize) -> &ImageAccess {
+        &self.0
+    }
+
+    fn image_name(&self, _num: usize) -> Cow<'static, str> {
+        "image".into()
+    }
+}
+
+// Adds a `ReadImage` command that reads `image` in `stages`.
+unsafe fn read_image<P, I>(sync: &mut SyncCommandBufferBuilder<P>, image: I,
+                           stages: PipelineStages)
+    where I: ImageAccess + Send + Sync + 'static
+{
+    sync.append_command(ReadImage(image));
+    sync.prev_cmd_resource(KeyTy::Image,
+                           0,
+                           false,
+                           stages,
+                           AccessFlagBits {
+                               shader_read: true,
+                               ..AccessFlagBits::none()
+                           },
+                           ImageLayout::ShaderReadOnlyOptimal,
+                           ImageLayout::ShaderReadOnlyOptimal)
+        .unwrap();
+}
+
+#[test]
+fn read_after_read_no_barrier() {
+    unsafe {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let pool = Device::standard_command_pool(&device, queue.family());
+        let mut sync = SyncCommandBufferBuilder::new(&pool, Kind::primary(), Flags::None).unwrap();
+        sync.enable_barrier_report();
+
+        let source = buffer(&device);
+        sync.copy_buffer(source.clone(), buffer(&device), iter::once((0, 0, 4)))
+            .unwrap();
+        sync.copy_buffer(source, buffer(&device), iter::once((0, 0, 4)))
+            .unwrap();
+        let cb = sync.build().unwrap();
+
+        assert!(barriers(cb.barrier_report().unwrap()).is_empty());
+    }
+}
+
+#[test]
+fn write_after_read_execution_dependency() {
+    unsafe {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let pool = Device::standard_command_pool(&device, queue.family());
+        let mut sync = SyncCommandBufferBuilder::new(&pool, Kind::primary(), Flags::None).unwrap();
+        sync.enable_barrier_report();
+
+        let source = buffer(&device);
+        sync.copy_buffer(source.clone(), buffer(&device), iter::once((0, 0, 4)))
+            .unwrap();
+        sync.fill_buffer(source, 0);
+        let cb = sync.build().unwrap();
+
+        let barriers = barriers(cb.barrier_report().unwrap());
+        assert_eq!(barriers.len(), 1);
+        assert_eq!(barriers[0].resources.len(), 1);
+        let resource = &barriers[0].resources[0];
+        assert!(resource.source_stages.transfer);
+        assert_eq!(resource.source_access, AccessFlagBits::none());
+        assert_eq!(resource.destination_access, AccessFlagBits::none());
+    }
+}
+
+#[test]
+fn read_after_write_source_access() {
+    unsafe {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let pool = Device::standard_command_pool(&device, queue.family());
+        let mut sync = SyncCommandBufferBuilder::new(&pool, Kind::primary(), Flags::None).unwrap();
+        sync.enable_barrier_report();
+
+        // The source of the second copy is written by the first one, which also reads from
+        // another buffer.
+        let buf = buffer(&device);
+        sync.copy_buffer(buffer(&device), buf.clone(), iter::once((0, 0, 4)))
+            .unwrap();
+        sync.copy_buffer(buf, buffer(&device), iter::once((0, 0, 4)))
+            .unwrap();
+        let cb = sync.build().unwrap();
+
+        let barriers = barriers(cb.barrier_report().unwrap());
+        assert_eq!(barriers.len(), 1);
+        let resource = &barriers[0].resources[0];
+        assert_eq!(resource.source_access,
+                   AccessFlagBits {
+                       transfer_write: true,
+                       ..AccessFlagBits::none()
+                   });
+        assert!(resource.destination_access.transfer_read);
+    }
+}
+
+#[test]
+fn adjacent_barriers_coalesced() {
+    unsafe {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let pool = Device::standard_command_pool(&device, queue.family());
+        let mut sync = SyncCommandBufferBuilder::new(&pool, Kind::primary(), Flags::None).unwrap();
+        sync.enable_barrier_report();
+
+        let a = buffer(&device);
+        let b = buffer(&device);
+        sync.fill_buffer(a.clone(), 0);
+        sync.fill_buffer(b.clone(), 0);
+        sync.copy_buffer(a, buffer(&device), iter::once((0, 0, 4)))
+            .unwrap();
+        sync.copy_buffer(b, buffer(&device), iter::once((0, 0, 4)))
+            .unwrap();
+        let cb = sync.build().unwrap();
+
+        let barriers = barriers(cb.barrier_report().unwrap());
+        assert_eq!(barriers.len(), 1);
+        assert_eq!(barriers[0].resources.len(), 2);
+    }
+}
+
+#[test]
+fn barrier_after_last_use() {
+    unsafe {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let pool = Device::standard_command_pool(&device, queue.family());
+        let mut sync = SyncCommandBufferBuilder::new(&pool, Kind::primary(), Flags::None).unwrap();
+        sync.enable_barrier_report();
+
+        let a = buffer(&device);
+        let b = buffer(&device);
+        sync.fill_buffer(a.clone(), 0);
+        sync.fill_buffer(b.clone(), 0);
+        sync.copy_buffer(b, buffer(&device), iter::once((0, 0, 4)))
+            .unwrap();
+        sync.copy_buffer(a.clone(), buffer(&device), iter::once((0, 0, 4)))
+            .unwrap();
+        sync.fill_buffer(a, 0);
+        let cb = sync.build().unwrap();
+
+        // The barrier before the last fill must come after the last copy, which reads from the
+        // same buffer.
+        let entries = &cb.barrier_report().unwrap().entries;
+        let last_copy = entries
+            .iter()
+            .position(|entry| match *entry {
+                          BarrierReportEntry::Command(ref command) => command.index == 3,
+                          _ => false,
+                      })
+            .unwrap();
+        let last_barrier = entries
+            .iter()
+            .rposition(|entry| match *entry {
+                           BarrierReportEntry::Barrier(ref barrier) => {
+                               barrier.resources.iter().any(|resource| {
+                                   resource.destination_command.map(|c| c.index) == Some(4)
+                               })
+                           },
+                           _ => false,
+                       })
+            .unwrap();
+        assert!(last_copy < last_barrier);
+    }
+}
+
+#[test]
+fn split_barrier_waited_then_cleared() {
+    unsafe {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let pool = Device::standard_command_pool(&device, queue.family());
+        let mut sync = SyncCommandBufferBuilder::new(&pool, Kind::primary(), Flags::None).unwrap();
+        sync.enable_barrier_report();
+
+        let event = Arc::new(Event::alloc(device.clone()).unwrap());
+        let transfer = PipelineStages {
+            transfer: true,
+            ..PipelineStages::none()
+        };
+
+        let a = buffer(&device);
+        let b = buffer(&device);
+        sync.fill_buffer(a.clone(), 0);
+        sync.signal_split_barrier(event, transfer);
+        assert!(sync.has_pending_split_barrier());
+
+        // Only depends on the command before the event.
+        sync.copy_buffer(a, b.clone(), iter::once((0, 0, 4)))
+            .unwrap();
+        assert!(sync.has_pending_split_barrier());
+
+        // Depends on the copy, which flushes the barrier of the copy as a wait on the event.
+        sync.fill_buffer(b, 0);
+        assert!(!sync.has_pending_split_barrier());
+        let cb = sync.build().unwrap();
+
+        let barriers = barriers(cb.barrier_report().unwrap());
+        assert_eq!(barriers.len(), 2);
+        assert_eq!(barriers[0].ty, InsertedBarrierTy::WaitEvents);
+        assert_eq!(barriers[1].ty, InsertedBarrierTy::PipelineBarrier);
+    }
+}
+
+#[test]
+fn split_barrier_not_used_for_later_sources() {
+    unsafe {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let pool = Device::standard_command_pool(&device, queue.family());
+        let mut sync = SyncCommandBufferBuilder::new(&pool, Kind::primary(), Flags::None).unwrap();
+        sync.enable_barrier_report();
+
+        let event = Arc::new(Event::alloc(device.clone()).unwrap());
+        let transfer = PipelineStages {
+            transfer: true,
+            ..PipelineStages::none()
+        };
+
+        // The write happens after the event was signaled, so the event can't be waited upon.
+        let a = buffer(&device);
+        sync.signal_split_barrier(event, transfer);
+        sync.fill_buffer(a.clone(), 0);
+        sync.copy_buffer(a, buffer(&device), iter::once((0, 0, 4)))
+            .unwrap();
+        let cb = sync.build().unwrap();
+
+        let barriers = barriers(cb.barrier_report().unwrap());
+        assert_eq!(barriers.len(), 1);
+        assert_eq!(barriers[0].ty, InsertedBarrierTy::PipelineBarrier);
+    }
+}
+
+#[test]
+fn initial_transition_visible_to_later_reads() {
+    unsafe {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let pool = Device::standard_command_pool(&device, queue.family());
+        let mut sync = SyncCommandBufferBuilder::new(&pool, Kind::primary(), Flags::None).unwrap();
+        sync.enable_barrier_report();
+
+        let image = AttachmentImage::sampled(device.clone(), [1, 1], Format::R8G8B8A8Unorm)
+            .unwrap();
+        assert_ne!(image.initial_layout_requirement(), ImageLayout::ShaderReadOnlyOptimal);
+
+        // The first read transitions the image, and the second one reads it in another stage.
+        read_image(&mut sync,
+                   image.clone(),
+                   PipelineStages {
+                       fragment_shader: true,
+                       ..PipelineStages::none()
+                   });
+        read_image(&mut sync,
+                   image,
+                   PipelineStages {
+                       compute_shader: true,
+                       ..PipelineStages::none()
+                   });
+        let cb = sync.build().unwrap();
+
+        // There is no barrier between the two reads, so the transition must be visible to the
+        // compute shader as well.
+        let entries = &cb.barrier_report().unwrap().entries;
+        match entries[0] {
+            BarrierReportEntry::Barrier(ref barrier) => {
+                assert_eq!(barrier.resources.len(), 1);
+                let resource = &barrier.resources[0];
+                assert!(resource.source_command.is_none());
+                assert!(resource.destination_stages.all_commands);
+                assert!(resource.destination_access.memory_read);
+                assert_eq!(resource.new_layout, ImageLayout::ShaderReadOnlyOptimal);
+            },
+            _ => panic!(),
+        };
+        match (&entries[1], &entries[2]) {
+            (&BarrierReportEntry::Command(ref first), &BarrierReportEntry::Command(ref second)) => {
+                assert_eq!(first.index, 0);
+                assert_eq!(second.index, 1);
+            },
+            _ => panic!(),
+        };
+    }
+}
+
+#[test]
+fn ownership_release_then_acquire() {
+    unsafe {
+        let (device, gfx_queue, other_queue) = gfx_dev_and_two_queues!();
+        let gfx_family = gfx_queue.family().id();
+        let other_family = other_queue.family().id();
+        let buf = buffer(&device);
+
+        let pool = Device::standard_command_pool(&device, gfx_queue.family());
+        let mut sync = SyncCommandBufferBuilder::new(&pool, Kind::primary(), Flags::None).unwrap();
+        sync.fill_buffer(buf.clone(), 0);
+        sync.release_buffer_ownership(buf.clone(), gfx_family, other_family)
+            .unwrap();
+        let release_cb = sync.build().unwrap();
+
+        let pool = Device::standard_command_pool(&device, other_queue.family());
+        let mut sync = SyncCommandBufferBuilder::new(&pool, Kind::primary(), Flags::None).unwrap();
+        sync.acquire_buffer_ownership(buf.clone(), gfx_family, other_family)
+            .unwrap();
+        sync.fill_buffer(buf, 0);
+        let acquire_cb = sync.build().unwrap();
+
+        let releases = release_cb.ownership_releases();
+        let acquires = acquire_cb.ownership_acquires();
+        assert_eq!(releases.len(), 1);
+        assert_eq!(acquires.len(), 1);
+        assert!(release_cb.ownership_acquires().is_empty());
+        assert!(acquire_cb.ownership_releases().is_empty());
+
+        assert_eq!(releases[0].source_family, gfx_family);
+        assert_eq!(releases[0].destination_family, other_family);
+        assert_eq!(acquires[0].source_family, gfx_family);
+        assert_eq!(acquires[0].destination_family, other_family);
+        assert!(releases[0].resource.conflicts(&acquires[0].resource));
+    }
+}
+
+#[test]
+fn ownership_released_access_denied() {
+    unsafe {
+        let (device, gfx_queue, other_queue) = gfx_dev_and_two_queues!();
+        let other_family = other_queue.family().id();
+        let buf = buffer(&device);
+
+        let pool = Device::standard_command_pool(&device, gfx_queue.family());
+        let mut sync = SyncCommandBufferBuilder::new(&pool, Kind::primary(), Flags::None).unwrap();
+        sync.fill_buffer(buf.clone(), 0);
+        sync.release_buffer_ownership(buf.clone(), gfx_queue.family().id(), other_family)
+            .unwrap();
+        let cb = sync.build().unwrap();
+
+        match cb.check_buffer_access(&buf, false, &gfx_queue) {
+            Err(AccessCheckError::Denied(AccessError::OwnershipReleased {
+                                             destination_family,
+                                         })) => {
+                assert_eq!(destination_family, other_family);
+            },
+            _ => panic!(),
+        }
+
+        assert!(cb.check_buffer_access(&buf, false, &other_queue).is_ok());
+    }
+}
@@ -20,6 +20,8 @@ use VulkanObject;
 use buffer::BufferAccess;
 use buffer::BufferInner;
 use check_errors;
+use command_buffer::ClearAttachment;
+use command_buffer::ClearRect;
 use command_buffer::CommandBuffer;
 use command_buffer::pool::CommandPool;
 use command_buffer::pool::CommandPoolAlloc;
@@ -31,7 +33,6 @@ use device::Device;
 use device::DeviceOwned;
 use format::ClearValue;
 use format::FormatTy;
-use format::PossibleCompressedFormatDesc;
 use framebuffer::EmptySinglePassRenderPassDesc;
 use framebuffer::Framebuffer;
 use framebuffer::FramebufferAbstract;
@@ -44,10 +45,13 @@ use image::ImageLayout;
 use instance::QueueFamily;
 use pipeline::ComputePipelineAbstract;
 use pipeline::GraphicsPipelineAbstract;
+use pipeline::depth_stencil::StencilFaceFlags;
 use pipeline::input_assembly::IndexType;
 use pipeline::viewport::Scissor;
 use pipeline::viewport::Viewport;
 use query::QueryPipelineStatisticFlags;
+use query::QueryResultElement;
+use query::QueryResultFlags;
 use query::UnsafeQueriesRange;
 use query::UnsafeQuery;
 use sampler::Filter;
@@ -574,11 +578,9 @@ impl<P> UnsafeCommandBufferBuilder<P> {
               D: ?Sized + ImageAccess,
               R: Iterator<Item = UnsafeCommandBufferBuilderImageCopy>
     {
-        // TODO: The correct check here is that the uncompressed element size of the source is
-        // equal to the compressed element size of the destination.
-        debug_assert!(source.format().is_compressed() ||
-                      destination.format().is_compressed() ||
-                      source.format().size() == destination.format().size());
+        // The size of a block of the source must be equal to the size of a block of the
+        // destination. For uncompressed formats, a block is a single texel.
+        debug_assert_eq!(source.format().block_size(), destination.format().block_size());
 
         // Depth/Stencil formats are required to match exactly.
         debug_assert!(!source.format().ty().is_depth_and_or_stencil() ||
@@ -588,12 +590,14 @@ impl<P> UnsafeCommandBufferBuilder<P> {
         let source = source.inner();
         debug_assert!(source.image.usage_transfer_source());
         debug_assert!(source_layout == ImageLayout::General ||
-                      source_layout == ImageLayout::TransferSrcOptimal);
+                      source_layout == ImageLayout::TransferSrcOptimal ||
+                      source_layout == ImageLayout::SharedPresent);
 
         let destination = destination.inner();
         debug_assert!(destination.image.usage_transfer_destination());
         debug_assert!(destination_layout == ImageLayout::General ||
-                      destination_layout == ImageLayout::TransferDstOptimal);
+                      destination_layout == ImageLayout::TransferDstOptimal ||
+                      destination_layout == ImageLayout::SharedPresent);
 
         let regions: SmallVec<[_; 8]> = regions
             .filter_map(|copy| {
@@ -682,14 +686,16 @@ impl<P> UnsafeCommandBufferBuilder<P> {
         debug_assert!(source.image.supports_blit_source());
         debug_assert!(source.image.usage_transfer_source());
         debug_assert!(source_layout == ImageLayout::General ||
-                          source_layout == ImageLayout::TransferSrcOptimal);
+                          source_layout == ImageLayout::TransferSrcOptimal ||
+                          source_layout == ImageLayout::SharedPresent);
 
         debug_assert_eq!(destination.samples(), 1);
         let destination = destination.inner();
         debug_assert!(destination.image.supports_blit_destination());
         debug_assert!(destination.image.usage_transfer_destination());
         debug_assert!(destination_layout == ImageLayout::General ||
-                          destination_layout == ImageLayout::TransferDstOptimal);
+                          destination_layout == ImageLayout::TransferDstOptimal ||
+                          destination_layout == ImageLayout::SharedPresent);
 
         let regions: SmallVec<[_; 8]> = regions
             .filter_map(|blit| {
@@ -763,18 +769,84 @@ impl<P> UnsafeCommandBufferBuilder<P> {
                         filter as u32);
     }
 
-    // TODO: missing structs
-    /*/// Calls `vkCmdClearAttachments` on the builder.
+    /// Calls `vkCmdClearAttachments` on the builder.
     ///
     /// Does nothing if the list of attachments or the list of rects is empty, as it would be a
     /// no-op and isn't a valid usage of the command anyway.
     #[inline]
     pub unsafe fn clear_attachments<A, R>(&mut self, attachments: A, rects: R)
-        where A: Iterator<Item = >,
-              R: Iterator<Item = >
+        where A: Iterator<Item = ClearAttachment>,
+              R: Iterator<Item = ClearRect>
     {
-        let attachments: SmallVec<[_; 16]> = attachments.map().collect();
-        let rects: SmallVec<[_; 4]> = rects.map().collect();
+        let attachments: SmallVec<[_; 16]> = attachments
+            .map(|attachment| match attachment {
+                     ClearAttachment::Color(color, color_attachment) => {
+                         let color = match color {
+                             ClearValue::Float(val) => vk::ClearColorValue { float32: val },
+                             ClearValue::Int(val) => vk::ClearColorValue { int32: val },
+                             ClearValue::Uint(val) => vk::ClearColorValue { uint32: val },
+                             _ => vk::ClearColorValue { float32: [0.0; 4] },
+                         };
+
+                         vk::ClearAttachment {
+                             aspectMask: vk::IMAGE_ASPECT_COLOR_BIT,
+                             colorAttachment: color_attachment,
+                             clearValue: vk::ClearValue { color },
+                         }
+                     },
+                     ClearAttachment::Depth(depth) => {
+                         vk::ClearAttachment {
+                             aspectMask: vk::IMAGE_ASPECT_DEPTH_BIT,
+                             colorAttachment: 0,
+                             clearValue: vk::ClearValue {
+                                 depthStencil: vk::ClearDepthStencilValue { depth, stencil: 0 },
+                             },
+                         }
+                     },
+                     ClearAttachment::Stencil(stencil) => {
+                         vk::ClearAttachment {
+                             aspectMask: vk::IMAGE_ASPECT_STENCIL_BIT,
+                             colorAttachment: 0,
+                             clearValue: vk::ClearValue {
+                                 depthStencil: vk::ClearDepthStencilValue {
+                                     depth: 0.0,
+                                     stencil,
+                                 },
+                             },
+                         }
+                     },
+                     ClearAttachment::DepthStencil((depth, stencil)) => {
+                         vk::ClearAttachment {
+                             aspectMask: vk::IMAGE_ASPECT_DEPTH_BIT |
+                                 vk::IMAGE_ASPECT_STENCIL_BIT,
+                             colorAttachment: 0,
+                             clearValue: vk::ClearValue {
+                                 depthStencil: vk::ClearDepthStencilValue { depth, stencil },
+                             },
+                         }
+                     },
+                 })
+            .collect();
+
+        let rects: SmallVec<[_; 4]> = rects
+            .map(|rect| {
+                     debug_assert!(rect.layer_count != 0);
+                     vk::ClearRect {
+                         rect: vk::Rect2D {
+                             offset: vk::Offset2D {
+                                 x: rect.rect_offset[0] as i32,
+                                 y: rect.rect_offset[1] as i32,
+                             },
+                             extent: vk::Extent2D {
+                                 width: rect.rect_extent[0],
+                                 height: rect.rect_extent[1],
+                             },
+                         },
+                         baseArrayLayer: rect.base_array_layer,
+                         layerCount: rect.layer_count,
+                     }
+                 })
+            .collect();
 
         if attachments.is_empty() || rects.is_empty() {
             return;
@@ -784,7 +856,7 @@ impl<P> UnsafeCommandBufferBuilder<P> {
         let cmd = self.internal_object();
         vk.CmdClearAttachments(cmd, attachments.len() as u32, attachments.as_ptr(),
                                rects.len() as u32, rects.as_ptr());
-    }*/
+    }
 
     /// Calls `vkCmdClearColorImage` on the builder.
     ///
@@ -802,7 +874,8 @@ impl<P> UnsafeCommandBufferBuilder<P> {
 
         let image = image.inner();
         debug_assert!(image.image.usage_transfer_destination());
-        debug_assert!(layout == ImageLayout::General || layout == ImageLayout::TransferDstOptimal);
+        debug_assert!(layout == ImageLayout::General || layout == ImageLayout::TransferDstOptimal ||
+                      layout == ImageLayout::SharedPresent);
 
         let color = match color {
             ClearValue::Float(val) => {
@@ -854,6 +927,74 @@ impl<P> UnsafeCommandBufferBuilder<P> {
                               regions.as_ptr());
     }
 
+    /// Calls `vkCmdClearDepthStencilImage` on the builder.
+    ///
+    /// Does nothing if the list of regions is empty, as it would be a no-op and isn't a valid
+    /// usage of the command anyway.
+    pub unsafe fn clear_depth_stencil_image<I, R>(&mut self, image: &I, layout: ImageLayout,
+                                                  clear_value: ClearValue, regions: R)
+        where I: ?Sized + ImageAccess,
+              R: Iterator<Item = UnsafeCommandBufferBuilderDepthStencilImageClear>
+    {
+        debug_assert!(image.format().ty() == FormatTy::Depth ||
+                          image.format().ty() == FormatTy::Stencil ||
+                          image.format().ty() == FormatTy::DepthStencil);
+
+        let image = image.inner();
+        debug_assert!(image.image.usage_transfer_destination());
+        debug_assert!(layout == ImageLayout::General || layout == ImageLayout::TransferDstOptimal);
+
+        let clear_value = match clear_value {
+            ClearValue::Depth(depth) => {
+                vk::ClearDepthStencilValue { depth, stencil: 0 }
+            },
+            ClearValue::Stencil(stencil) => {
+                vk::ClearDepthStencilValue { depth: 0.0, stencil }
+            },
+            ClearValue::DepthStencil((depth, stencil)) => {
+                vk::ClearDepthStencilValue { depth, stencil }
+            },
+            _ => {
+                vk::ClearDepthStencilValue { depth: 0.0, stencil: 0 }
+            },
+        };
+
+        let regions: SmallVec<[_; 8]> = regions
+            .filter_map(|region| {
+                debug_assert!(region.layer_count + region.base_array_layer <=
+                                  image.num_layers as u32);
+                debug_assert!(region.level_count + region.base_mip_level <=
+                                  image.num_mipmap_levels as u32);
+                debug_assert!(!region.aspect.color);
+
+                if region.layer_count == 0 || region.level_count == 0 {
+                    return None;
+                }
+
+                Some(vk::ImageSubresourceRange {
+                         aspectMask: region.aspect.to_vk_bits(),
+                         baseMipLevel: region.base_mip_level + image.first_mipmap_level as u32,
+                         levelCount: region.level_count,
+                         baseArrayLayer: region.base_array_layer + image.first_layer as u32,
+                         layerCount: region.layer_count,
+                     })
+            })
+            .collect();
+
+        if regions.is_empty() {
+            return;
+        }
+
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+        vk.CmdClearDepthStencilImage(cmd,
+                                     image.image.internal_object(),
+                                     layout as u32,
+                                     &clear_value,
+                                     regions.len() as u32,
+                                     regions.as_ptr());
+    }
+
     /// Calls `vkCmdCopyBuffer` on the builder.
     ///
     /// Does nothing if the list of regions is empty, as it would be a no-op and isn't a valid
@@ -916,7 +1057,8 @@ impl<P> UnsafeCommandBufferBuilder<P> {
         let destination = destination.inner();
         debug_assert!(destination.image.usage_transfer_destination());
         debug_assert!(destination_layout == ImageLayout::General ||
-                          destination_layout == ImageLayout::TransferDstOptimal);
+                          destination_layout == ImageLayout::TransferDstOptimal ||
+                          destination_layout == ImageLayout::SharedPresent);
 
         let regions: SmallVec<[_; 8]> = regions
             .map(|copy| {
@@ -977,7 +1119,8 @@ impl<P> UnsafeCommandBufferBuilder<P> {
         let source = source.inner();
         debug_assert!(source.image.usage_transfer_source());
         debug_assert!(source_layout == ImageLayout::General ||
-                          source_layout == ImageLayout::TransferSrcOptimal);
+                          source_layout == ImageLayout::TransferSrcOptimal ||
+                          source_layout == ImageLayout::SharedPresent);
 
         let destination = destination.inner();
         debug_assert!(destination.offset < destination.buffer.size());
@@ -1027,14 +1170,19 @@ impl<P> UnsafeCommandBufferBuilder<P> {
     }
 
     /// Calls `vkCmdCopyQueryPoolResults` on the builder.
+    ///
+    /// The results are written as 64 bits values if `T` is `u64`, and 32 bits values otherwise.
     #[inline]
-    pub unsafe fn copy_query_pool_results(&mut self, queries: UnsafeQueriesRange,
-                                          destination: &BufferAccess, stride: usize) {
+    pub unsafe fn copy_query_pool_results<T>(&mut self, queries: UnsafeQueriesRange,
+                                             destination: &BufferAccess, stride: usize,
+                                             flags: QueryResultFlags)
+        where T: QueryResultElement
+    {
         let destination = destination.inner();
         debug_assert!(destination.offset < destination.buffer.size());
         debug_assert!(destination.buffer.usage_transfer_destination());
 
-        let flags = 0; // FIXME:
+        let flags = flags.into_vulkan_bits() | T::FLAG;
 
         let vk = self.device().pointers();
         let cmd = self.internal_object();
@@ -1294,6 +1442,94 @@ impl<P> UnsafeCommandBufferBuilder<P> {
                              queries.count());
     }
 
+    /// Calls `vkCmdResolveImage` on the builder.
+    ///
+    /// Does nothing if the list of regions is empty, as it would be a no-op and isn't a valid
+    /// usage of the command anyway.
+    #[inline]
+    pub unsafe fn resolve_image<S, D, R>(&mut self, source: &S, source_layout: ImageLayout,
+                                         destination: &D, destination_layout: ImageLayout,
+                                         regions: R)
+        where S: ?Sized + ImageAccess,
+              D: ?Sized + ImageAccess,
+              R: Iterator<Item = UnsafeCommandBufferBuilderImageResolve>
+    {
+        debug_assert_eq!(source.format(), destination.format());
+        debug_assert!(source.samples() > 1);
+        debug_assert_eq!(destination.samples(), 1);
+
+        let source = source.inner();
+        debug_assert!(source_layout == ImageLayout::General ||
+                      source_layout == ImageLayout::TransferSrcOptimal);
+
+        let destination = destination.inner();
+        debug_assert!(destination.image.supports_color_attachment());
+        debug_assert!(destination_layout == ImageLayout::General ||
+                      destination_layout == ImageLayout::TransferDstOptimal);
+
+        let regions: SmallVec<[_; 8]> = regions
+            .filter_map(|resolve| {
+                debug_assert!(resolve.source_base_array_layer + resolve.layer_count <=
+                                  source.num_layers as u32);
+                debug_assert!(resolve.destination_base_array_layer + resolve.layer_count <=
+                                  destination.num_layers as u32);
+                debug_assert!(resolve.source_mip_level < source.num_mipmap_levels as u32);
+                debug_assert!(resolve.destination_mip_level <
+                                  destination.num_mipmap_levels as u32);
+
+                if resolve.layer_count == 0 {
+                    return None;
+                }
+
+                Some(vk::ImageResolve {
+                    srcSubresource: vk::ImageSubresourceLayers {
+                        aspectMask: vk::IMAGE_ASPECT_COLOR_BIT,
+                        mipLevel: resolve.source_mip_level,
+                        baseArrayLayer: resolve.source_base_array_layer +
+                            source.first_layer as u32,
+                        layerCount: resolve.layer_count,
+                    },
+                    srcOffset: vk::Offset3D {
+                        x: resolve.source_offset[0],
+                        y: resolve.source_offset[1],
+                        z: resolve.source_offset[2],
+                    },
+                    dstSubresource: vk::ImageSubresourceLayers {
+                        aspectMask: vk::IMAGE_ASPECT_COLOR_BIT,
+                        mipLevel: resolve.destination_mip_level,
+                        baseArrayLayer: resolve.destination_base_array_layer +
+                            destination.first_layer as u32,
+                        layerCount: resolve.layer_count,
+                    },
+                    dstOffset: vk::Offset3D {
+                        x: resolve.destination_offset[0],
+                        y: resolve.destination_offset[1],
+                        z: resolve.destination_offset[2],
+                    },
+                    extent: vk::Extent3D {
+                        width: resolve.extent[0],
+                        height: resolve.extent[1],
+                        depth: resolve.extent[2],
+                    },
+                })
+            })
+            .collect();
+
+        if regions.is_empty() {
+            return;
+        }
+
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+        vk.CmdResolveImage(cmd,
+                           source.image.internal_object(),
+                           source_layout as u32,
+                           destination.image.internal_object(),
+                           destination_layout as u32,
+                           regions.len() as u32,
+                           regions.as_ptr());
+    }
+
     /// Calls `vkCmdSetBlendConstants` on the builder.
     #[inline]
     pub unsafe fn set_blend_constants(&mut self, constants: [f32; 4]) {
@@ -1342,32 +1578,32 @@ impl<P> UnsafeCommandBufferBuilder<P> {
         vk.CmdSetLineWidth(cmd, line_width);
     }
 
-    // TODO: missing enum for face_mask
-    /*/// Calls `vkCmdSetStencilCompareMask` on the builder.
+    /// Calls `vkCmdSetStencilCompareMask` on the builder.
     #[inline]
-    pub unsafe fn set_stencil_compare_mask(&mut self, face_mask: , compare_mask: u32) {
+    pub unsafe fn set_stencil_compare_mask(&mut self, face_mask: StencilFaceFlags,
+                                           compare_mask: u32) {
         let vk = self.device().pointers();
         let cmd = self.internal_object();
-        vk.CmdSetStencilCompareMask(cmd, face_mask, compare_mask);
-    }*/
+        vk.CmdSetStencilCompareMask(cmd, face_mask.into(), compare_mask);
+    }
 
-    // TODO: missing enum for face_mask
-    /*/// Calls `vkCmdSetStencilReference` on the builder.
+    /// Calls `vkCmdSetStencilReference` on the builder.
     #[inline]
-    pub unsafe fn set_stencil_reference(&mut self, face_mask: , reference: u32) {
+    pub unsafe fn set_stencil_reference(&mut self, face_mask: StencilFaceFlags,
+                                        reference: u32) {
         let vk = self.device().pointers();
         let cmd = self.internal_object();
-        vk.CmdSetStencilReference(cmd, face_mask, reference);
-    }*/
+        vk.CmdSetStencilReference(cmd, face_mask.into(), reference);
+    }
 
-    // TODO: missing enum for face_mask
-    /*/// Calls `vkCmdSetStencilWriteMask` on the builder.
+    /// Calls `vkCmdSetStencilWriteMask` on the builder.
     #[inline]
-    pub unsafe fn set_stencil_write_mask(&mut self, face_mask: , write_mask: u32) {
+    pub unsafe fn set_stencil_write_mask(&mut self, face_mask: StencilFaceFlags,
+                                         write_mask: u32) {
         let vk = self.device().pointers();
         let cmd = self.internal_object();
-        vk.CmdSetStencilWriteMask(cmd, face_mask, write_mask);
-    }*/
+        vk.CmdSetStencilWriteMask(cmd, face_mask.into(), write_mask);
+    }
 
     /// Calls `vkCmdSetScissor` on the builder.
     ///
@@ -1461,6 +1697,38 @@ impl<P> UnsafeCommandBufferBuilder<P> {
                            data as *const D as *const _);
     }
 
+    /// Calls `vkCmdWaitEvents` on the builder.
+    ///
+    /// The source stages of `command` must be the union of the stages that were passed when
+    /// signaling the events. The dependency flags of `command` are ignored.
+    ///
+    /// Does nothing if the list of events or the barrier is empty.
+    pub unsafe fn wait_events<'a, I>(&mut self, events: I,
+                                     command: &UnsafeCommandBufferBuilderPipelineBarrier)
+        where I: Iterator<Item = &'a Event>
+    {
+        let events: SmallVec<[_; 4]> = events.map(|event| event.internal_object()).collect();
+
+        if events.is_empty() || command.is_empty() {
+            return;
+        }
+
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+
+        vk.CmdWaitEvents(cmd,
+                         events.len() as u32,
+                         events.as_ptr(),
+                         command.src_stage_mask,
+                         command.dst_stage_mask,
+                         command.memory_barriers.len() as u32,
+                         command.memory_barriers.as_ptr(),
+                         command.buffer_barriers.len() as u32,
+                         command.buffer_barriers.as_ptr(),
+                         command.image_barriers.len() as u32,
+                         command.image_barriers.as_ptr());
+    }
+
     /// Calls `vkCmdWriteTimestamp` on the builder.
     #[inline]
     pub unsafe fn write_timestamp(&mut self, query: UnsafeQuery, stages: PipelineStages) {
@@ -1621,6 +1889,16 @@ pub struct UnsafeCommandBufferBuilderColorImageClear {
     pub layer_count: u32,
 }
 
+// TODO: move somewhere else?
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct UnsafeCommandBufferBuilderDepthStencilImageClear {
+    pub aspect: UnsafeCommandBufferBuilderImageAspect,
+    pub base_mip_level: u32,
+    pub level_count: u32,
+    pub base_array_layer: u32,
+    pub layer_count: u32,
+}
+
 // TODO: move somewhere else?
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct UnsafeCommandBufferBuilderBufferImageCopy {
@@ -1649,6 +1927,19 @@ pub struct UnsafeCommandBufferBuilderImageCopy {
     pub extent: [u32; 3],
 }
 
+// TODO: move somewhere else?
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct UnsafeCommandBufferBuilderImageResolve {
+    pub source_mip_level: u32,
+    pub destination_mip_level: u32,
+    pub source_base_array_layer: u32,
+    pub destination_base_array_layer: u32,
+    pub layer_count: u32,
+    pub source_offset: [i32; 3],
+    pub destination_offset: [i32; 3],
+    pub extent: [u32; 3],
+}
+
 // TODO: move somewhere else?
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct UnsafeCommandBufferBuilderImageBlit {
@@ -1920,6 +2211,17 @@ pub struct UnsafeCommandBuffer<P> {
     device: Arc<Device>,
 }
 
+impl<P> UnsafeCommandBuffer<P> {
+    /// Destroys the command buffer object and returns the command buffer allocated from the pool.
+    ///
+    /// The allocation can be turned back into a builder with `CommandPoolAlloc::into_builder`
+    /// in order to record the command buffer again.
+    #[inline]
+    pub fn into_alloc(self) -> P {
+        self.cmd
+    }
+}
+
 unsafe impl<P> DeviceOwned for UnsafeCommandBuffer<P> {
     #[inline]
     fn device(&self) -> &Arc<Device> {
@@ -15,23 +15,34 @@ use std::sync::Mutex;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
 
+use OomError;
 use SafeDeref;
 use VulkanObject;
 use buffer::BufferAccess;
+use command_buffer::pool::standard::StandardCommandPoolAlloc;
 use command_buffer::submit::SubmitAnyBuilder;
 use command_buffer::submit::SubmitCommandBufferBuilder;
+use command_buffer::sys::Flags;
+use command_buffer::sys::Kind;
+use command_buffer::sys::KindOcclusionQuery;
 use command_buffer::sys::UnsafeCommandBuffer;
+use command_buffer::sys::UnsafeCommandBufferBuilder;
+use command_buffer::sys::UnsafeCommandBufferBuilderPipelineBarrier;
 use device::Device;
 use device::DeviceOwned;
 use device::Queue;
+use framebuffer::RenderPassAbstract;
 use image::ImageAccess;
 use image::ImageLayout;
+use query::QueryPipelineStatisticFlags;
 use sync::AccessCheckError;
 use sync::AccessError;
 use sync::AccessFlagBits;
 use sync::FlushError;
 use sync::GpuFuture;
 use sync::NowFuture;
+use sync::OwnershipTransfer;
+use sync::OwnershipTransferResource;
 use sync::PipelineStages;
 use sync::now;
 
@@ -95,6 +106,10 @@ pub unsafe trait CommandBuffer: DeviceOwned {
     /// This function returns an object that implements the `GpuFuture` trait. See the
     /// documentation of the `sync` module for more information.
     ///
+    /// If the future released the ownership of resources to the queue family of `queue`, and the
+    /// command buffer doesn't acquire them itself, then a command buffer that acquires them is
+    /// automatically submitted before this one.
+    ///
     /// The command buffer is not actually executed until you call `flush()` on the object.
     /// You are encouraged to chain together as many futures as possible before calling `flush()`,
     /// and call `.then_signal_future()` before doing so. Note however that once you called
@@ -125,11 +140,36 @@ pub unsafe trait CommandBuffer: DeviceOwned {
             assert!(future.queue().unwrap().is_same(&queue));
         }
 
+        let acquire = {
+            let family = queue.family().id();
+            let acquired = self.ownership_acquires();
+            let mut acquire = Ok(None);
+            future.with_ownership_releases(&mut |releases| {
+                let releases = releases
+                    .iter()
+                    .filter(|release| release.destination_family == family)
+                    .filter(|release| {
+                        !acquired.iter().any(|acquire| {
+                            acquire.source_family == release.source_family &&
+                                acquire.resource.conflicts(&release.resource)
+                        })
+                    })
+                    .cloned()
+                    .collect::<Vec<_>>();
+
+                if !releases.is_empty() {
+                    acquire = ownership_acquire_command_buffer(&queue, &releases).map(Some);
+                }
+            });
+            acquire?
+        };
+
         self.lock_submit(&future, &queue)?;
 
         Ok(CommandBufferExecFuture {
                previous: future,
                command_buffer: self,
+               acquire,
                queue: queue,
                submitted: Mutex::new(false),
                finished: AtomicBool::new(false),
@@ -143,9 +183,84 @@ pub unsafe trait CommandBuffer: DeviceOwned {
                           queue: &Queue)
                           -> Result<Option<(PipelineStages, AccessFlagBits)>, AccessCheckError>;
 
+    /// Returns the queue family ownership transfers released by this command buffer.
+    #[inline]
+    fn ownership_releases(&self) -> Vec<OwnershipTransfer> {
+        Vec::new()
+    }
+
+    /// Returns the queue family ownership transfers acquired by this command buffer.
+    #[inline]
+    fn ownership_acquires(&self) -> Vec<OwnershipTransfer> {
+        Vec::new()
+    }
+
     // FIXME: lots of other methods
 }
 
+// Records a command buffer that acquires, on the queue family of `queue`, the ownership of the
+// resources of `releases`.
+fn ownership_acquire_command_buffer(queue: &Queue, releases: &[OwnershipTransfer])
+                                    -> Result<UnsafeCommandBuffer<StandardCommandPoolAlloc>,
+                                              OomError> {
+    unsafe {
+        let pool = Device::standard_command_pool(queue.device(), queue.family());
+        let mut builder = UnsafeCommandBufferBuilder::new(&pool,
+                                                          Kind::primary(),
+                                                          Flags::OneTimeSubmit)?;
+
+        // The command buffer that follows doesn't know about the transfer, therefore we make the
+        // resources available to all the stages and accesses.
+        let source_stages = PipelineStages {
+            top_of_pipe: true,
+            ..PipelineStages::none()
+        };
+        let destination_stages = PipelineStages {
+            all_commands: true,
+            ..PipelineStages::none()
+        };
+        let destination_access = AccessFlagBits {
+            memory_read: true,
+            memory_write: true,
+            ..AccessFlagBits::none()
+        };
+
+        let mut barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+        for release in releases {
+            let families = Some((release.source_family, release.destination_family));
+            match release.resource {
+                OwnershipTransferResource::Buffer(buffer) => {
+                    barrier.add_buffer_memory_barrier(buffer,
+                                                      source_stages,
+                                                      AccessFlagBits::none(),
+                                                      destination_stages,
+                                                      destination_access,
+                                                      false,
+                                                      families,
+                                                      0,
+                                                      buffer.size());
+                },
+                OwnershipTransferResource::Image(image, layout) => {
+                    barrier.add_image_memory_barrier(image,
+                                                     0 .. image.mipmap_levels(),
+                                                     0 .. image.dimensions().array_layers(),
+                                                     source_stages,
+                                                     AccessFlagBits::none(),
+                                                     destination_stages,
+                                                     destination_access,
+                                                     false,
+                                                     families,
+                                                     layout,
+                                                     layout);
+                },
+            }
+        }
+
+        builder.pipeline_barrier(&barrier);
+        builder.build()
+    }
+}
+
 unsafe impl<T> CommandBuffer for T
     where T: SafeDeref,
           T::Target: CommandBuffer
@@ -180,6 +295,133 @@ unsafe impl<T> CommandBuffer for T
                           -> Result<Option<(PipelineStages, AccessFlagBits)>, AccessCheckError> {
         (**self).check_image_access(image, layout, exclusive, queue)
     }
+
+    #[inline]
+    fn ownership_releases(&self) -> Vec<OwnershipTransfer> {
+        (**self).ownership_releases()
+    }
+
+    #[inline]
+    fn ownership_acquires(&self) -> Vec<OwnershipTransfer> {
+        (**self).ownership_acquires()
+    }
+}
+
+/// Secondary command buffer that can be executed from within a primary command buffer with
+/// `execute_commands`.
+///
+/// # Safety
+///
+/// The resources returned by `buffer()` and `image()` must include all the resources used by the
+/// command buffer, and their usage must be accurate.
+pub unsafe trait SecondaryCommandBuffer: DeviceOwned {
+    /// Checks whether this command buffer is allowed to be recorded to a primary command buffer,
+    /// and if so locks it.
+    ///
+    /// If you call this function, then you should call `unlock_record` afterwards, once the
+    /// primary command buffer has been destroyed.
+    fn lock_record(&self) -> Result<(), CommandBufferExecError>;
+
+    /// Unlocks the command buffer. Should be called once for each call to `lock_record`.
+    ///
+    /// # Safety
+    ///
+    /// Must not be called if you haven't called `lock_record` before.
+    unsafe fn unlock_record(&self);
+
+    /// Returns true if the command buffer can be executed multiple times simultaneously.
+    ///
+    /// If this returns false, then the primary command buffers that execute this command buffer
+    /// can't be executed multiple times simultaneously either.
+    fn simultaneous_use(&self) -> bool;
+
+    /// Returns the render pass and the subpass index that the command buffer continues, or `None`
+    /// if it must be executed outside of a render pass.
+    fn render_pass(&self) -> Option<(&RenderPassAbstract, u32)>;
+
+    /// Returns whether an occlusion query and which pipeline statistics queries can be active in
+    /// the primary command buffer when this command buffer is executed.
+    fn inherited_queries(&self) -> (KindOcclusionQuery, QueryPipelineStatisticFlags);
+
+    /// Returns the number of buffers accessed by this command buffer.
+    fn num_buffers(&self) -> usize;
+
+    /// Returns the `index`th buffer of this command buffer, or `None` if out of range, along with
+    /// the way it is used by the command buffer.
+    fn buffer(&self, index: usize) -> Option<(&BufferAccess, SecondaryResourceUsage)>;
+
+    /// Returns the number of images accessed by this command buffer.
+    fn num_images(&self) -> usize;
+
+    /// Returns the `index`th image of this command buffer, or `None` if out of range, along with
+    /// the way it is used by the command buffer.
+    fn image(&self, index: usize) -> Option<(&ImageAccess, SecondaryResourceUsage)>;
+}
+
+unsafe impl<T> SecondaryCommandBuffer for T
+    where T: SafeDeref,
+          T::Target: SecondaryCommandBuffer
+{
+    #[inline]
+    fn lock_record(&self) -> Result<(), CommandBufferExecError> {
+        (**self).lock_record()
+    }
+
+    #[inline]
+    unsafe fn unlock_record(&self) {
+        (**self).unlock_record();
+    }
+
+    #[inline]
+    fn simultaneous_use(&self) -> bool {
+        (**self).simultaneous_use()
+    }
+
+    #[inline]
+    fn render_pass(&self) -> Option<(&RenderPassAbstract, u32)> {
+        (**self).render_pass()
+    }
+
+    #[inline]
+    fn inherited_queries(&self) -> (KindOcclusionQuery, QueryPipelineStatisticFlags) {
+        (**self).inherited_queries()
+    }
+
+    #[inline]
+    fn num_buffers(&self) -> usize {
+        (**self).num_buffers()
+    }
+
+    #[inline]
+    fn buffer(&self, index: usize) -> Option<(&BufferAccess, SecondaryResourceUsage)> {
+        (**self).buffer(index)
+    }
+
+    #[inline]
+    fn num_images(&self) -> usize {
+        (**self).num_images()
+    }
+
+    #[inline]
+    fn image(&self, index: usize) -> Option<(&ImageAccess, SecondaryResourceUsage)> {
+        (**self).image(index)
+    }
+}
+
+/// Describes how a secondary command buffer uses one of its resources.
+#[derive(Debug, Copy, Clone)]
+pub struct SecondaryResourceUsage {
+    /// True if the resource is written by the command buffer, or if an image layout transition is
+    /// performed.
+    pub exclusive: bool,
+    /// Union of the pipeline stages in which the resource is accessed.
+    pub stages: PipelineStages,
+    /// Union of the types of access to the resource.
+    pub access: AccessFlagBits,
+    /// Layout the image must be in when the command buffer starts. `Undefined` for buffers.
+    pub initial_layout: ImageLayout,
+    /// Layout the image is in when the command buffer ends. `Undefined` for buffers.
+    pub final_layout: ImageLayout,
 }
 
 /// Represents a command buffer being executed by the GPU and the moment when the execution
@@ -191,6 +433,9 @@ pub struct CommandBufferExecFuture<F, Cb>
 {
     previous: F,
     command_buffer: Cb,
+    // Command buffer that acquires the resources released to the queue family of `queue` by
+    // `previous`. Submitted before `command_buffer`.
+    acquire: Option<UnsafeCommandBuffer<StandardCommandPoolAlloc>>,
     queue: Arc<Queue>,
     // True if the command buffer has already been submitted.
     // If flush is called multiple times, we want to block so that only one flushing is executed.
@@ -199,6 +444,20 @@ pub struct CommandBufferExecFuture<F, Cb>
     finished: AtomicBool,
 }
 
+impl<F, Cb> CommandBufferExecFuture<F, Cb>
+    where F: GpuFuture,
+          Cb: CommandBuffer
+{
+    // Adds the command buffer to a submission, after the command buffer that acquires the
+    // resources released by the previous future if any.
+    unsafe fn add_command_buffers<'a>(&'a self, builder: &mut SubmitCommandBufferBuilder<'a>) {
+        if let Some(ref acquire) = self.acquire {
+            builder.add_command_buffer(acquire);
+        }
+        builder.add_command_buffer(self.command_buffer.inner());
+    }
+}
+
 unsafe impl<F, Cb> GpuFuture for CommandBufferExecFuture<F, Cb>
     where F: GpuFuture,
           Cb: CommandBuffer
@@ -212,17 +471,17 @@ unsafe impl<F, Cb> GpuFuture for CommandBufferExecFuture<F, Cb>
         Ok(match self.previous.build_submission()? {
                SubmitAnyBuilder::Empty => {
                    let mut builder = SubmitCommandBufferBuilder::new();
-                   builder.add_command_buffer(self.command_buffer.inner());
+                   self.add_command_buffers(&mut builder);
                    SubmitAnyBuilder::CommandBuffer(builder)
                },
                SubmitAnyBuilder::SemaphoresWait(sem) => {
                    let mut builder: SubmitCommandBufferBuilder = sem.into();
-                   builder.add_command_buffer(self.command_buffer.inner());
+                   self.add_command_buffers(&mut builder);
                    SubmitAnyBuilder::CommandBuffer(builder)
                },
                SubmitAnyBuilder::CommandBuffer(mut builder) => {
                    // FIXME: add pipeline barrier
-                   builder.add_command_buffer(self.command_buffer.inner());
+                   self.add_command_buffers(&mut builder);
                    SubmitAnyBuilder::CommandBuffer(builder)
                },
                SubmitAnyBuilder::QueuePresent(_) |
@@ -307,6 +566,23 @@ unsafe impl<F, Cb> GpuFuture for CommandBufferExecFuture<F, Cb>
             },
         }
     }
+
+    #[inline]
+    fn with_ownership_releases(&self, f: &mut FnMut(&[OwnershipTransfer])) {
+        // The releases of the previous future to our queue family have been acquired, either by
+        // the command buffer itself or by `acquire`.
+        let family = self.queue.family().id();
+        let command_buffer = &self.command_buffer;
+        self.previous.with_ownership_releases(&mut |previous| {
+            let mut releases = previous
+                .iter()
+                .filter(|release| release.destination_family != family)
+                .cloned()
+                .collect::<Vec<_>>();
+            releases.extend(command_buffer.ownership_releases());
+            f(&releases)
+        })
+    }
 }
 
 unsafe impl<F, Cb> DeviceOwned for CommandBufferExecFuture<F, Cb>
@@ -356,7 +632,14 @@ pub enum CommandBufferExecError {
     /// the GPU and was not created with the "concurrent" flag.
     ExclusiveAlreadyInUse,
 
-    // TODO: missing entries (eg. wrong queue family, secondary command buffer)
+    /// Tried to execute a primary command buffer from within another command buffer.
+    NotSecondary,
+
+    /// Not enough memory to record the command buffer that acquires the resources released by
+    /// the previous future.
+    OomError(OomError),
+
+    // TODO: missing entries (eg. wrong queue family)
 }
 
 impl error::Error for CommandBufferExecError {
@@ -375,6 +658,13 @@ impl error::Error for CommandBufferExecError {
                 "the command buffer or one of the secondary command buffers it executes is \
                  already in use by the GPU and was not created with the \"concurrent\" flag"
             },
+            CommandBufferExecError::NotSecondary => {
+                "tried to execute a primary command buffer from within another command buffer"
+            },
+            CommandBufferExecError::OomError(_) => {
+                "not enough memory to record the command buffer that acquires the resources \
+                 released by the previous future"
+            },
         }
     }
 
@@ -382,6 +672,7 @@ impl error::Error for CommandBufferExecError {
     fn cause(&self) -> Option<&error::Error> {
         match *self {
             CommandBufferExecError::AccessError { ref error, .. } => Some(error),
+            CommandBufferExecError::OomError(ref err) => Some(err),
             _ => None,
         }
     }
@@ -393,3 +684,57 @@ impl fmt::Display for CommandBufferExecError {
         write!(fmt, "{}", error::Error::description(self))
     }
 }
+
+impl From<OomError> for CommandBufferExecError {
+    #[inline]
+    fn from(err: OomError) -> CommandBufferExecError {
+        CommandBufferExecError::OomError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::CommandBuffer;
+    use sync::GpuFuture;
+
+    #[test]
+    fn execute_after_acquires_released_ownership() {
+        let (device, gfx_queue, other_queue) = gfx_dev_and_two_queues!();
+        let buffer = CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(), 0u32)
+            .unwrap();
+
+        let release = AutoCommandBufferBuilder::primary_one_time_submit(device.clone(),
+                                                                        gfx_queue.family())
+            .unwrap()
+            .fill_buffer(buffer.clone(), 0)
+            .unwrap()
+            .release_buffer_ownership(buffer.clone(), other_queue.family())
+            .unwrap()
+            .build()
+            .unwrap();
+        let future = release.execute(gfx_queue).unwrap();
+
+        let mut num_releases = 0;
+        future.with_ownership_releases(&mut |releases| num_releases = releases.len());
+        assert_eq!(num_releases, 1);
+
+        // This command buffer doesn't acquire the buffer itself, so `execute_after` must submit
+        // an acquire operation before it.
+        let usage = AutoCommandBufferBuilder::primary_one_time_submit(device,
+                                                                      other_queue.family())
+            .unwrap()
+            .fill_buffer(buffer, 1)
+            .unwrap()
+            .build()
+            .unwrap();
+        let future = usage.execute_after(future, other_queue).unwrap();
+        assert!(future.acquire.is_some());
+
+        let mut num_releases = 0;
+        future.with_ownership_releases(&mut |releases| num_releases = releases.len());
+        assert_eq!(num_releases, 0);
+    }
+}
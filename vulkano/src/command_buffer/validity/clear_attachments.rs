@@ -0,0 +1,277 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+
+use command_buffer::ClearAttachment;
+use command_buffer::ClearRect;
+use format::ClearValue;
+use format::FormatTy;
+use framebuffer::RenderPassDesc;
+
+/// Checks whether a clear attachments command is valid.
+///
+/// `framebuffer_dimensions` should be `None` if the framebuffer isn't known, in which case the
+/// rects are not checked against it.
+///
+/// # Panic
+///
+/// - Panics if `subpass` is out of range of the subpasses of `render_pass`.
+///
+pub fn check_clear_attachments<Rp>(render_pass: &Rp, subpass: u32,
+                                   framebuffer_dimensions: Option<[u32; 3]>,
+                                   attachments: &[ClearAttachment], rects: &[ClearRect])
+                                   -> Result<(), CheckClearAttachmentsError>
+    where Rp: ?Sized + RenderPassDesc
+{
+    let subpass_desc = render_pass
+        .subpass_desc(subpass as usize)
+        .expect("subpass index out of range");
+
+    let depth_stencil_ty = subpass_desc.depth_stencil.map(|(atch_num, _)| {
+        render_pass.attachment_desc(atch_num).unwrap().format.ty()
+    });
+
+    for attachment in attachments {
+        match *attachment {
+            ClearAttachment::Color(value, color_attachment) => {
+                let num_color_attachments = subpass_desc.color_attachments.len() as u32;
+                if color_attachment >= num_color_attachments {
+                    return Err(CheckClearAttachmentsError::ColorAttachmentIndexOutOfRange {
+                                   color_attachment,
+                                   num_color_attachments,
+                               });
+                }
+
+                let atch_num = subpass_desc.color_attachments[color_attachment as usize].0;
+
+                let format_ty = render_pass.attachment_desc(atch_num).unwrap().format.ty();
+                match (format_ty, value) {
+                    (FormatTy::Float, ClearValue::Float(_)) => (),
+                    (FormatTy::Uint, ClearValue::Uint(_)) => (),
+                    (FormatTy::Sint, ClearValue::Int(_)) => (),
+                    _ => {
+                        return Err(CheckClearAttachmentsError::ColorValueMismatch {
+                                       color_attachment,
+                                   });
+                    },
+                }
+            },
+            ClearAttachment::Depth(depth) => {
+                check_depth_stencil(depth_stencil_ty, Some(depth), false)?;
+            },
+            ClearAttachment::Stencil(_) => {
+                check_depth_stencil(depth_stencil_ty, None, true)?;
+            },
+            ClearAttachment::DepthStencil((depth, _)) => {
+                check_depth_stencil(depth_stencil_ty, Some(depth), true)?;
+            },
+        }
+    }
+
+    for (rect_index, rect) in rects.iter().enumerate() {
+        if rect.rect_extent[0] == 0 || rect.rect_extent[1] == 0 || rect.layer_count == 0 {
+            return Err(CheckClearAttachmentsError::EmptyRect { rect_index });
+        }
+
+        if let Some(dimensions) = framebuffer_dimensions {
+            let ranges = [
+                (rect.rect_offset[0], rect.rect_extent[0], dimensions[0]),
+                (rect.rect_offset[1], rect.rect_extent[1], dimensions[1]),
+                (rect.base_array_layer, rect.layer_count, dimensions[2]),
+            ];
+
+            for &(start, len, max) in ranges.iter() {
+                match start.checked_add(len) {
+                    Some(end) if end <= max => (),
+                    _ => return Err(CheckClearAttachmentsError::RectOutOfBounds { rect_index }),
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Checks the depth and/or stencil parts of a clear against the depth-stencil attachment of the
+// subpass.
+fn check_depth_stencil(depth_stencil_ty: Option<FormatTy>, depth: Option<f32>, stencil: bool)
+                       -> Result<(), CheckClearAttachmentsError> {
+    if let Some(depth) = depth {
+        match depth_stencil_ty {
+            Some(FormatTy::Depth) |
+            Some(FormatTy::DepthStencil) => (),
+            _ => return Err(CheckClearAttachmentsError::MissingDepthAttachment),
+        }
+
+        if depth < 0.0 || depth > 1.0 {
+            return Err(CheckClearAttachmentsError::DepthValueOutOfRange);
+        }
+    }
+
+    if stencil {
+        match depth_stencil_ty {
+            Some(FormatTy::Stencil) |
+            Some(FormatTy::DepthStencil) => (),
+            _ => return Err(CheckClearAttachmentsError::MissingStencilAttachment),
+        }
+    }
+
+    Ok(())
+}
+
+/// Error that can happen from `check_clear_attachments`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CheckClearAttachmentsError {
+    /// The index of a color attachment is out of range of the color attachments of the subpass.
+    ColorAttachmentIndexOutOfRange {
+        /// The index that was passed.
+        color_attachment: u32,
+        /// Number of color attachments in the subpass.
+        num_color_attachments: u32,
+    },
+    /// The clear value of a color attachment doesn't match the format of the attachment.
+    ColorValueMismatch {
+        /// The index of the color attachment.
+        color_attachment: u32,
+    },
+    /// Tried to clear a depth value, but the subpass doesn't have a depth attachment.
+    MissingDepthAttachment,
+    /// Tried to clear a stencil value, but the subpass doesn't have a stencil attachment.
+    MissingStencilAttachment,
+    /// The depth value must be between 0.0 and 1.0.
+    DepthValueOutOfRange,
+    /// A rect has a width, height or number of layers of zero.
+    EmptyRect {
+        /// The index of the rect.
+        rect_index: usize,
+    },
+    /// A rect is outside of the dimensions of the framebuffer.
+    RectOutOfBounds {
+        /// The index of the rect.
+        rect_index: usize,
+    },
+}
+
+impl error::Error for CheckClearAttachmentsError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CheckClearAttachmentsError::ColorAttachmentIndexOutOfRange { .. } => {
+                "the index of a color attachment is out of range of the color attachments of the \
+                 subpass"
+            },
+            CheckClearAttachmentsError::ColorValueMismatch { .. } => {
+                "the clear value of a color attachment doesn't match the format of the attachment"
+            },
+            CheckClearAttachmentsError::MissingDepthAttachment => {
+                "tried to clear a depth value, but the subpass doesn't have a depth attachment"
+            },
+            CheckClearAttachmentsError::MissingStencilAttachment => {
+                "tried to clear a stencil value, but the subpass doesn't have a stencil attachment"
+            },
+            CheckClearAttachmentsError::DepthValueOutOfRange => {
+                "the depth value must be between 0.0 and 1.0"
+            },
+            CheckClearAttachmentsError::EmptyRect { .. } => {
+                "a rect has a width, height or number of layers of zero"
+            },
+            CheckClearAttachmentsError::RectOutOfBounds { .. } => {
+                "a rect is outside of the dimensions of the framebuffer"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CheckClearAttachmentsError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use framebuffer::EmptySinglePassRenderPassDesc;
+
+    fn rect(offset: [u32; 2], extent: [u32; 2]) -> ClearRect {
+        ClearRect {
+            rect_offset: offset,
+            rect_extent: extent,
+            base_array_layer: 0,
+            layer_count: 1,
+        }
+    }
+
+    #[test]
+    fn color_attachment_out_of_range() {
+        let attachments = [ClearAttachment::Color(ClearValue::Float([0.0; 4]), 0)];
+
+        match check_clear_attachments(&EmptySinglePassRenderPassDesc, 0, None, &attachments,
+                                      &[rect([0, 0], [1, 1])]) {
+            Err(CheckClearAttachmentsError::ColorAttachmentIndexOutOfRange {
+                    color_attachment: 0,
+                    num_color_attachments: 0,
+                }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn missing_depth_stencil() {
+        match check_clear_attachments(&EmptySinglePassRenderPassDesc, 0, None,
+                                      &[ClearAttachment::Depth(1.0)], &[]) {
+            Err(CheckClearAttachmentsError::MissingDepthAttachment) => (),
+            _ => panic!(),
+        }
+
+        match check_clear_attachments(&EmptySinglePassRenderPassDesc, 0, None,
+                                      &[ClearAttachment::Stencil(0)], &[]) {
+            Err(CheckClearAttachmentsError::MissingStencilAttachment) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn empty_rect() {
+        match check_clear_attachments(&EmptySinglePassRenderPassDesc, 0, None, &[],
+                                      &[rect([0, 0], [1, 1]), rect([0, 0], [0, 1])]) {
+            Err(CheckClearAttachmentsError::EmptyRect { rect_index: 1 }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn rect_out_of_bounds() {
+        let dimensions = Some([64, 32, 1]);
+
+        assert!(check_clear_attachments(&EmptySinglePassRenderPassDesc, 0, dimensions, &[],
+                                        &[rect([32, 0], [32, 32])])
+                    .is_ok());
+
+        match check_clear_attachments(&EmptySinglePassRenderPassDesc, 0, dimensions, &[],
+                                      &[rect([32, 16], [32, 32])]) {
+            Err(CheckClearAttachmentsError::RectOutOfBounds { rect_index: 0 }) => (),
+            _ => panic!(),
+        }
+
+        match check_clear_attachments(&EmptySinglePassRenderPassDesc, 0, dimensions, &[],
+                                      &[rect([1, 0], [u32::max_value(), 32])]) {
+            Err(CheckClearAttachmentsError::RectOutOfBounds { rect_index: 0 }) => (),
+            _ => panic!(),
+        }
+
+        // The rect can't be checked if the framebuffer isn't known.
+        assert!(check_clear_attachments(&EmptySinglePassRenderPassDesc, 0, None, &[],
+                                        &[rect([32, 16], [32, 32])])
+                    .is_ok());
+    }
+}
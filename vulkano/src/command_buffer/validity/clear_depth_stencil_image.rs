@@ -0,0 +1,238 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+
+use VulkanObject;
+use device::Device;
+use device::DeviceOwned;
+use format::ClearValue;
+use format::FormatTy;
+use image::ImageAccess;
+
+/// Checks whether a clear depth stencil image command is valid.
+///
+/// The aspects that are cleared are determined by `clear_value`. A `Depth` value only clears the
+/// depth aspect, a `Stencil` value only clears the stencil aspect, and a `DepthStencil` value
+/// clears both.
+///
+/// # Panic
+///
+/// - Panics if the destination was not created with `device`.
+///
+pub fn check_clear_depth_stencil_image<I>(device: &Device, image: &I, first_layer: u32,
+                                          num_layers: u32, first_mipmap: u32, num_mipmaps: u32,
+                                          clear_value: ClearValue)
+                                          -> Result<(), CheckClearDepthStencilImageError>
+    where I: ?Sized + ImageAccess
+{
+    assert_eq!(image.inner().image.device().internal_object(),
+               device.internal_object());
+
+    if !image.inner().image.usage_transfer_destination() {
+        return Err(CheckClearDepthStencilImageError::MissingTransferUsage);
+    }
+
+    let (depth, stencil) = match clear_value {
+        ClearValue::Depth(depth) => (Some(depth), false),
+        ClearValue::Stencil(_) => (None, true),
+        ClearValue::DepthStencil((depth, _)) => (Some(depth), true),
+        _ => return Err(CheckClearDepthStencilImageError::NotDepthStencilClearValue),
+    };
+
+    let format_ty = image.format().ty();
+
+    if let Some(depth) = depth {
+        if format_ty != FormatTy::Depth && format_ty != FormatTy::DepthStencil {
+            return Err(CheckClearDepthStencilImageError::MissingDepthAspect);
+        }
+
+        if depth < 0.0 || depth > 1.0 {
+            return Err(CheckClearDepthStencilImageError::DepthValueOutOfRange);
+        }
+    }
+
+    if stencil {
+        if format_ty != FormatTy::Stencil && format_ty != FormatTy::DepthStencil {
+            return Err(CheckClearDepthStencilImageError::MissingStencilAspect);
+        }
+    }
+
+    if num_layers == 0 || num_mipmaps == 0 {
+        return Err(CheckClearDepthStencilImageError::OutOfRange);
+    }
+
+    match first_layer.checked_add(num_layers) {
+        Some(end) if end <= image.dimensions().array_layers() => (),
+        _ => return Err(CheckClearDepthStencilImageError::OutOfRange),
+    }
+
+    match first_mipmap.checked_add(num_mipmaps) {
+        Some(end) if end <= image.mipmap_levels() => (),
+        _ => return Err(CheckClearDepthStencilImageError::OutOfRange),
+    }
+
+    Ok(())
+}
+
+/// Error that can happen from `check_clear_depth_stencil_image`.
+#[derive(Debug, Copy, Clone)]
+pub enum CheckClearDepthStencilImageError {
+    /// The image is missing the transfer destination usage.
+    MissingTransferUsage,
+    /// The clear value is not a depth, stencil or depth-stencil value.
+    NotDepthStencilClearValue,
+    /// The clear value contains a depth value, but the format of the image doesn't have a depth
+    /// aspect.
+    MissingDepthAspect,
+    /// The clear value contains a stencil value, but the format of the image doesn't have a
+    /// stencil aspect.
+    MissingStencilAspect,
+    /// The depth value must be between 0.0 and 1.0.
+    DepthValueOutOfRange,
+    /// The array layers and mipmap levels are out of range, or the number of layers or mipmaps
+    /// is zero.
+    OutOfRange,
+}
+
+impl error::Error for CheckClearDepthStencilImageError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CheckClearDepthStencilImageError::MissingTransferUsage => {
+                "the image is missing the transfer destination usage"
+            },
+            CheckClearDepthStencilImageError::NotDepthStencilClearValue => {
+                "the clear value is not a depth, stencil or depth-stencil value"
+            },
+            CheckClearDepthStencilImageError::MissingDepthAspect => {
+                "the clear value contains a depth value, but the format of the image doesn't \
+                 have a depth aspect"
+            },
+            CheckClearDepthStencilImageError::MissingStencilAspect => {
+                "the clear value contains a stencil value, but the format of the image doesn't \
+                 have a stencil aspect"
+            },
+            CheckClearDepthStencilImageError::DepthValueOutOfRange => {
+                "the depth value must be between 0.0 and 1.0"
+            },
+            CheckClearDepthStencilImageError::OutOfRange => {
+                "the array layers and mipmap levels are out of range, or the number of layers \
+                 or mipmaps is zero"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CheckClearDepthStencilImageError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use format::Format;
+    use image::AttachmentImage;
+    use image::ImageUsage;
+
+    fn transfer_dst() -> ImageUsage {
+        ImageUsage {
+            transfer_destination: true,
+            ..ImageUsage::none()
+        }
+    }
+
+    #[test]
+    fn missing_transfer_usage() {
+        let (device, _) = gfx_dev_and_queue!();
+        let image = AttachmentImage::new(device.clone(), [16, 16], Format::D16Unorm).unwrap();
+
+        match check_clear_depth_stencil_image(&device, &image, 0, 1, 0, 1,
+                                              ClearValue::Depth(1.0)) {
+            Err(CheckClearDepthStencilImageError::MissingTransferUsage) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn color_clear_value() {
+        let (device, _) = gfx_dev_and_queue!();
+        let image = AttachmentImage::with_usage(device.clone(), [16, 16], Format::D16Unorm,
+                                                transfer_dst())
+            .unwrap();
+
+        match check_clear_depth_stencil_image(&device, &image, 0, 1, 0, 1,
+                                              ClearValue::Float([0.0; 4])) {
+            Err(CheckClearDepthStencilImageError::NotDepthStencilClearValue) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn missing_stencil_aspect() {
+        let (device, _) = gfx_dev_and_queue!();
+        let image = AttachmentImage::with_usage(device.clone(), [16, 16], Format::D16Unorm,
+                                                transfer_dst())
+            .unwrap();
+
+        assert!(check_clear_depth_stencil_image(&device, &image, 0, 1, 0, 1,
+                                                ClearValue::Depth(1.0))
+                    .is_ok());
+
+        match check_clear_depth_stencil_image(&device, &image, 0, 1, 0, 1,
+                                              ClearValue::Stencil(0)) {
+            Err(CheckClearDepthStencilImageError::MissingStencilAspect) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn out_of_range() {
+        let (device, _) = gfx_dev_and_queue!();
+        let image = AttachmentImage::with_usage(device.clone(), [16, 16], Format::D16Unorm,
+                                                transfer_dst())
+            .unwrap();
+
+        match check_clear_depth_stencil_image(&device, &image, 0, 2, 0, 1,
+                                              ClearValue::Depth(1.0)) {
+            Err(CheckClearDepthStencilImageError::OutOfRange) => (),
+            _ => panic!(),
+        }
+
+        match check_clear_depth_stencil_image(&device, &image, 1, u32::max_value(), 0, 1,
+                                              ClearValue::Depth(1.0)) {
+            Err(CheckClearDepthStencilImageError::OutOfRange) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn zero_layers_or_mipmaps() {
+        let (device, _) = gfx_dev_and_queue!();
+        let image = AttachmentImage::with_usage(device.clone(), [16, 16], Format::D16Unorm,
+                                                transfer_dst())
+            .unwrap();
+
+        match check_clear_depth_stencil_image(&device, &image, 0, 0, 0, 1,
+                                              ClearValue::Depth(1.0)) {
+            Err(CheckClearDepthStencilImageError::OutOfRange) => (),
+            _ => panic!(),
+        }
+
+        match check_clear_depth_stencil_image(&device, &image, 0, 1, 0, 0,
+                                              ClearValue::Depth(1.0)) {
+            Err(CheckClearDepthStencilImageError::OutOfRange) => (),
+            _ => panic!(),
+        }
+    }
+}
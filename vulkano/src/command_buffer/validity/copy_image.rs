@@ -11,8 +11,10 @@ use std::error;
 use std::fmt;
 
 use VulkanObject;
+use command_buffer::ImageCopy;
 use device::Device;
 use device::DeviceOwned;
+use format::Format;
 use format::FormatTy;
 use format::PossibleCompressedFormatDesc;
 use image::ImageAccess;
@@ -64,11 +66,7 @@ pub fn check_copy_image<S, D>(device: &Device, source: &S, source_offset: [i32;
         }
     }
 
-    // TODO: The correct check here is that the uncompressed element size of the source is
-    // equal to the compressed element size of the destination.  However, format doesn't
-    // currently expose this information, so to be safe, we simply disallow compressed formats.
-    if source.format().is_compressed() || destination.format().is_compressed() ||
-            (source.format().size() != destination.format().size()) {
+    if !formats_compatible(source.format(), destination.format()) {
         return Err(CheckCopyImageError::SizeIncompatibleFormatsTypes {
                        source_format_ty: source.format().ty(),
                        destination_format_ty: destination.format().ty(),
@@ -97,34 +95,62 @@ pub fn check_copy_image<S, D>(device: &Device, source: &S, source_offset: [i32;
         return Err(CheckCopyImageError::DestinationCoordinatesOutOfRange);
     }
 
-    if source_offset[0] < 0 ||
-            source_offset[0] as u32 + extent[0] > source_dimensions.width() {
-        return Err(CheckCopyImageError::SourceCoordinatesOutOfRange);
-    }
-
-    if source_offset[1] < 0 ||
-            source_offset[1] as u32 + extent[1] > source_dimensions.height() {
-        return Err(CheckCopyImageError::SourceCoordinatesOutOfRange);
-    }
+    // The extent is expressed in texels of the source. When copying between a compressed and an
+    // uncompressed format, each block of the compressed image corresponds to one texel of the
+    // uncompressed image.
+    let source_block = source.format().block_dimensions();
+    let destination_block = destination.format().block_dimensions();
+    let destination_extent = if source_block != [1, 1] && destination_block == [1, 1] {
+        // Rounded up, without overflowing if the extent is too large.
+        [extent[0] / source_block[0] + (extent[0] % source_block[0] != 0) as u32,
+         extent[1] / source_block[1] + (extent[1] % source_block[1] != 0) as u32,
+         extent[2]]
+    } else if source_block == [1, 1] && destination_block != [1, 1] {
+        match (extent[0].checked_mul(destination_block[0]),
+               extent[1].checked_mul(destination_block[1])) {
+            (Some(width), Some(height)) => [width, height, extent[2]],
+            _ => return Err(CheckCopyImageError::DestinationCoordinatesOutOfRange),
+        }
+    } else {
+        extent
+    };
 
-    if source_offset[2] < 0 ||
-            source_offset[2] as u32 + extent[2] > source_dimensions.depth() {
+    if source_offset.iter().any(|&o| o < 0) {
         return Err(CheckCopyImageError::SourceCoordinatesOutOfRange);
     }
 
-    if destination_offset[0] < 0 ||
-            destination_offset[0] as u32 + extent[0] > destination_dimensions.width() {
+    if destination_offset.iter().any(|&o| o < 0) {
         return Err(CheckCopyImageError::DestinationCoordinatesOutOfRange);
     }
 
-    if destination_offset[1] < 0 ||
-            destination_offset[1] as u32 + extent[1] > destination_dimensions.height() {
-        return Err(CheckCopyImageError::DestinationCoordinatesOutOfRange);
+    match check_block_region([source_offset[0] as u32,
+                              source_offset[1] as u32,
+                              source_offset[2] as u32],
+                             extent,
+                             source_dimensions.width_height_depth(),
+                             source_block) {
+        Ok(()) => (),
+        Err(BlockRegionError::OutOfRange) => {
+            return Err(CheckCopyImageError::SourceCoordinatesOutOfRange);
+        },
+        Err(BlockRegionError::NotBlockAligned) => {
+            return Err(CheckCopyImageError::RegionNotBlockAligned);
+        },
     }
 
-    if destination_offset[2] < 0 ||
-            destination_offset[2] as u32 + extent[2] > destination_dimensions.depth() {
-        return Err(CheckCopyImageError::DestinationCoordinatesOutOfRange);
+    match check_block_region([destination_offset[0] as u32,
+                              destination_offset[1] as u32,
+                              destination_offset[2] as u32],
+                             destination_extent,
+                             destination_dimensions.width_height_depth(),
+                             destination_block) {
+        Ok(()) => (),
+        Err(BlockRegionError::OutOfRange) => {
+            return Err(CheckCopyImageError::DestinationCoordinatesOutOfRange);
+        },
+        Err(BlockRegionError::NotBlockAligned) => {
+            return Err(CheckCopyImageError::RegionNotBlockAligned);
+        },
     }
 
     match source_dimensions {
@@ -146,15 +172,15 @@ pub fn check_copy_image<S, D>(device: &Device, source: &S, source_offset: [i32;
 
     match destination_dimensions {
         ImageDimensions::Dim1d { .. } => {
-            if destination_offset[1] != 0 || extent[1] != 1 {
+            if destination_offset[1] != 0 || destination_extent[1] != 1 {
                 return Err(CheckCopyImageError::IncompatibleRangeForImageType);
             }
-            if destination_offset[2] != 0 || extent[2] != 1 {
+            if destination_offset[2] != 0 || destination_extent[2] != 1 {
                 return Err(CheckCopyImageError::IncompatibleRangeForImageType);
             }
         },
         ImageDimensions::Dim2d { .. } => {
-            if destination_offset[2] != 0 || extent[2] != 1 {
+            if destination_offset[2] != 0 || destination_extent[2] != 1 {
                 return Err(CheckCopyImageError::IncompatibleRangeForImageType);
             }
         },
@@ -164,6 +190,93 @@ pub fn check_copy_image<S, D>(device: &Device, source: &S, source_offset: [i32;
     Ok(())
 }
 
+// Returns true if images can be copied between the two formats.
+fn formats_compatible(source: Format, destination: Format) -> bool {
+    if source.compatibility_class() == destination.compatibility_class() {
+        return true;
+    }
+
+    // A compressed format and an uncompressed format are size-compatible if the size of a block
+    // of the compressed format is equal to the size of a texel of the uncompressed format.
+    source.is_compressed() != destination.is_compressed() &&
+        !source.ty().is_depth_and_or_stencil() &&
+        !destination.ty().is_depth_and_or_stencil() &&
+        source.block_size() == destination.block_size()
+}
+
+/// Error that can happen from `check_block_region`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(super) enum BlockRegionError {
+    OutOfRange,
+    NotBlockAligned,
+}
+
+// Checks that a region of a mipmap level of an image whose texels are stored by blocks of
+// `block` texels is within the mipmap level and is aligned to the blocks.
+//
+// The offset must be a multiple of the block dimensions. The extent must also be a multiple of
+// the block dimensions, unless the region ends at the edge of the mipmap level.
+pub(super) fn check_block_region(offset: [u32; 3], extent: [u32; 3], dimensions: [u32; 3],
+                                 block: [u32; 2])
+                                 -> Result<(), BlockRegionError> {
+    let block = [block[0], block[1], 1];
+
+    for i in 0 .. 3 {
+        let end = match offset[i].checked_add(extent[i]) {
+            Some(end) if end <= dimensions[i] => end,
+            _ => return Err(BlockRegionError::OutOfRange),
+        };
+
+        if offset[i] % block[i] != 0 {
+            return Err(BlockRegionError::NotBlockAligned);
+        }
+
+        if extent[i] % block[i] != 0 && end != dimensions[i] {
+            return Err(BlockRegionError::NotBlockAligned);
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks whether a copy image command with a list of regions is valid.
+///
+/// Each region is checked like with `check_copy_image`. In addition, the source and the
+/// destination must have the aspect that each region copies.
+///
+/// # Panic
+///
+/// - Panics if the source or the destination was not created with `device`.
+///
+pub fn check_copy_image_regions<S, D>(device: &Device, source: &S, destination: &D,
+                                      regions: &[ImageCopy])
+                                      -> Result<(), CheckCopyImageError>
+    where S: ?Sized + ImageAccess,
+          D: ?Sized + ImageAccess
+{
+    for region in regions {
+        if !region.aspect.is_in_format(source.format()) ||
+            !region.aspect.is_in_format(destination.format())
+        {
+            return Err(CheckCopyImageError::MissingAspect);
+        }
+
+        check_copy_image(device,
+                         source,
+                         region.source_offset,
+                         region.source_base_array_layer,
+                         region.source_mip_level,
+                         destination,
+                         region.destination_offset,
+                         region.destination_base_array_layer,
+                         region.destination_mip_level,
+                         region.extent,
+                         region.layer_count)?;
+    }
+
+    Ok(())
+}
+
 /// Error that can happen from `check_copy_image`.
 #[derive(Debug, Copy, Clone)]
 pub enum CheckCopyImageError {
@@ -186,6 +299,11 @@ pub enum CheckCopyImageError {
     DestinationCoordinatesOutOfRange,
     /// The offsets or extent are incompatible with the image type.
     IncompatibleRangeForImageType,
+    /// The source or the destination doesn't have the aspect to copy.
+    MissingAspect,
+    /// The offsets or extent of a compressed image aren't multiples of the dimensions of its
+    /// blocks.
+    RegionNotBlockAligned,
 }
 
 impl error::Error for CheckCopyImageError {
@@ -219,6 +337,13 @@ impl error::Error for CheckCopyImageError {
             CheckCopyImageError::IncompatibleRangeForImageType => {
                 "the offsets or extent are incompatible with the image type"
             },
+            CheckCopyImageError::MissingAspect => {
+                "the source or the destination doesn't have the aspect to copy"
+            },
+            CheckCopyImageError::RegionNotBlockAligned => {
+                "the offsets or extent of a compressed image aren't multiples of the dimensions \
+                 of its blocks"
+            },
         }
     }
 }
@@ -229,3 +354,102 @@ impl fmt::Display for CheckCopyImageError {
         write!(fmt, "{}", error::Error::description(self))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Dimensions;
+    use image::ImageUsage;
+    use image::StorageImage;
+    use std::iter;
+
+    #[test]
+    fn compatible_formats() {
+        assert!(formats_compatible(Format::R8G8B8A8Unorm, Format::R32Sfloat));
+        assert!(formats_compatible(Format::BC7UnormBlock, Format::BC7SrgbBlock));
+        assert!(formats_compatible(Format::BC7UnormBlock, Format::R32G32B32A32Uint));
+        assert!(formats_compatible(Format::R16G16B16A16Uint, Format::BC1_RGBAUnormBlock));
+        assert!(!formats_compatible(Format::BC7UnormBlock, Format::BC3UnormBlock));
+        assert!(!formats_compatible(Format::BC7UnormBlock, Format::R8G8B8A8Unorm));
+        assert!(!formats_compatible(Format::D32Sfloat, Format::R32Sfloat));
+    }
+
+    #[test]
+    fn block_region() {
+        assert_eq!(check_block_region([0, 0, 0], [16, 16, 1], [16, 16, 1], [1, 1]), Ok(()));
+        assert_eq!(check_block_region([4, 8, 0], [8, 4, 1], [16, 16, 1], [4, 4]), Ok(()));
+        // Partial block at the edge of the image.
+        assert_eq!(check_block_region([8, 0, 0], [10, 6, 1], [18, 6, 1], [4, 4]), Ok(()));
+        // Partial block that covers a small mipmap level.
+        assert_eq!(check_block_region([0, 0, 0], [2, 2, 1], [2, 2, 1], [4, 4]), Ok(()));
+
+        assert_eq!(check_block_region([2, 0, 0], [4, 4, 1], [16, 16, 1], [4, 4]),
+                   Err(BlockRegionError::NotBlockAligned));
+        assert_eq!(check_block_region([0, 0, 0], [6, 4, 1], [16, 16, 1], [4, 4]),
+                   Err(BlockRegionError::NotBlockAligned));
+        assert_eq!(check_block_region([12, 0, 0], [8, 4, 1], [16, 16, 1], [4, 4]),
+                   Err(BlockRegionError::OutOfRange));
+        assert_eq!(check_block_region([0, 0, 0], [17, 1, 1], [16, 16, 1], [1, 1]),
+                   Err(BlockRegionError::OutOfRange));
+        // Whole block that extends past the edge of a small mipmap level.
+        assert_eq!(check_block_region([0, 0, 0], [4, 4, 1], [2, 2, 1], [4, 4]),
+                   Err(BlockRegionError::OutOfRange));
+        assert_eq!(check_block_region([4, 0, 0], [u32::max_value() - 3, 4, 1], [16, 16, 1],
+                                      [4, 4]),
+                   Err(BlockRegionError::OutOfRange));
+    }
+
+    #[test]
+    fn compressed_to_uncompressed() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let usage = ImageUsage {
+            transfer_source: true,
+            transfer_destination: true,
+            ..ImageUsage::none()
+        };
+        let source = match StorageImage::with_usage(device.clone(),
+                                                    Dimensions::Dim2d {
+                                                        width: 16,
+                                                        height: 16,
+                                                    },
+                                                    Format::BC7UnormBlock,
+                                                    usage,
+                                                    iter::once(queue.family())) {
+            Ok(image) => image,
+            Err(_) => return, // BC formats are not supported.
+        };
+        let destination = StorageImage::with_usage(device.clone(),
+                                                   Dimensions::Dim2d {
+                                                       width: 4,
+                                                       height: 4,
+                                                   },
+                                                   Format::R32G32B32A32Uint,
+                                                   usage,
+                                                   iter::once(queue.family()))
+            .unwrap();
+
+        // The extent is in texels of the source, which is 4x4 blocks.
+        check_copy_image(&device, &source, [0, 0, 0], 0, 0, &destination, [0, 0, 0], 0, 0,
+                         [16, 16, 1], 1)
+            .unwrap();
+
+        match check_copy_image(&device, &source, [2, 0, 0], 0, 0, &destination, [0, 0, 0], 0, 0,
+                               [4, 4, 1], 1) {
+            Err(CheckCopyImageError::RegionNotBlockAligned) => (),
+            _ => panic!(),
+        }
+
+        match check_copy_image(&device, &destination, [0, 0, 0], 0, 0, &source, [0, 0, 0], 0, 0,
+                               [4, 4, 1], 1) {
+            Ok(()) => (),
+            _ => panic!(),
+        }
+
+        match check_copy_image(&device, &destination, [0, 0, 0], 0, 0, &source, [0, 0, 0], 0, 0,
+                               [4, 5, 1], 1) {
+            Err(CheckCopyImageError::SourceCoordinatesOutOfRange) => (),
+            _ => panic!(),
+        }
+    }
+}
@@ -9,9 +9,15 @@
 
 use std::error;
 use std::fmt;
+use std::mem;
 
 use VulkanObject;
+use buffer::BufferAccess;
 use buffer::TypedBufferAccess;
+use command_buffer::BufferImageCopy;
+use command_buffer::ImageAspect;
+use command_buffer::validity::copy_image::BlockRegionError;
+use command_buffer::validity::copy_image::check_block_region;
 use device::Device;
 use device::DeviceOwned;
 use format::AcceptsPixels;
@@ -29,11 +35,13 @@ pub enum CheckCopyBufferImageTy {
 /// Checks whether a copy buffer-image command is valid. Can check both buffer-to-image copies and
 /// image-to-buffer copies.
 ///
+/// For compressed formats, the offset and size must be multiples of the dimensions of a block,
+/// unless the region ends at the edge of the image, and the buffer contains whole blocks.
+///
 /// # Panic
 ///
 /// - Panics if the buffer and image were not created with `device`.
 ///
-// TODO: handle compressed image formats
 pub fn check_copy_buffer_image<B, I, P>(device: &Device, buffer: &B, image: &I,
                                         ty: CheckCopyBufferImageTy, image_offset: [u32; 3],
                                         image_size: [u32; 3], image_first_layer: u32,
@@ -42,6 +50,124 @@ pub fn check_copy_buffer_image<B, I, P>(device: &Device, buffer: &B, image: &I,
     where I: ?Sized + ImageAccess,
           B: ?Sized + TypedBufferAccess<Content = [P]>,
           Format: AcceptsPixels<P> // TODO: use a trait on the image itself instead
+{
+    check_usage(device, buffer, image, ty)?;
+
+    check_image_range(image,
+                      image_offset,
+                      image_size,
+                      image_first_layer,
+                      image_num_layers,
+                      image_mipmap)?;
+
+    image.format().ensure_accepts()?;
+
+    {
+        let block = image.format().block_dimensions();
+        let num_blocks = ((image_size[0] + block[0] - 1) / block[0]) as usize *
+            ((image_size[1] + block[1] - 1) / block[1]) as usize *
+            image_size[2] as usize * image_num_layers as usize;
+        let required_len = num_blocks * image.format().rate() as usize;
+        if required_len > buffer.len() {
+            return Err(CheckCopyBufferImageError::BufferTooSmall {
+                           required_len: required_len,
+                           actual_len: buffer.len(),
+                       });
+        }
+    }
+
+    // TODO: check memory overlap?
+
+    Ok(())
+}
+
+/// Checks whether a copy buffer-image command with a list of regions is valid. Can check both
+/// buffer-to-image copies and image-to-buffer copies.
+///
+/// Contrary to `check_copy_buffer_image`, the type of the elements of the buffer is checked
+/// against the aspect of the image that each region copies rather than against the format of the
+/// image.
+///
+/// For compressed formats, the row length and image height in the buffer must also be multiples
+/// of the dimensions of a block.
+///
+/// # Panic
+///
+/// - Panics if the buffer and image were not created with `device`.
+///
+pub fn check_copy_buffer_image_regions<B, I, P>(device: &Device, buffer: &B, image: &I,
+                                                ty: CheckCopyBufferImageTy,
+                                                regions: &[BufferImageCopy])
+                                                -> Result<(), CheckCopyBufferImageError>
+    where I: ?Sized + ImageAccess,
+          B: ?Sized + TypedBufferAccess<Content = [P]>
+{
+    check_usage(device, buffer, image, ty)?;
+
+    let element_size = mem::size_of::<P>();
+
+    for region in regions {
+        if region.image_aspect == ImageAspect::DepthStencil {
+            return Err(CheckCopyBufferImageError::MultipleAspects);
+        }
+
+        if !region.image_aspect.is_in_format(image.format()) {
+            return Err(CheckCopyBufferImageError::MissingAspect);
+        }
+
+        check_image_range(image,
+                          region.image_offset,
+                          region.image_extent,
+                          region.image_base_array_layer,
+                          region.image_layer_count,
+                          region.image_mip_level)?;
+
+        let block_size = match aspect_block_size(image.format(), region.image_aspect) {
+            Some(size) if element_size != 0 && size % element_size == 0 => size,
+            _ => return Err(IncompatiblePixelsType.into()),
+        };
+
+        // The offset in bytes must be a multiple of 4 and of the size of a block.
+        let byte_offset = buffer.inner().offset + region.buffer_offset * element_size;
+        if byte_offset % 4 != 0 || byte_offset % block_size != 0 {
+            return Err(CheckCopyBufferImageError::UnalignedBufferOffset);
+        }
+
+        let block = image.format().block_dimensions();
+        if region.buffer_row_length % block[0] != 0 ||
+            region.buffer_image_height % block[1] != 0
+        {
+            return Err(CheckCopyBufferImageError::RegionNotBlockAligned);
+        }
+
+        if region.buffer_row_length != 0 && region.buffer_row_length < region.image_extent[0] {
+            return Err(CheckCopyBufferImageError::BufferRowLengthTooSmall);
+        }
+
+        if region.buffer_image_height != 0 &&
+            region.buffer_image_height < region.image_extent[1]
+        {
+            return Err(CheckCopyBufferImageError::BufferImageHeightTooSmall);
+        }
+
+        let required_len = region.buffer_offset +
+            buffer_blocks(region, block) * (block_size / element_size);
+        if required_len > buffer.len() {
+            return Err(CheckCopyBufferImageError::BufferTooSmall {
+                           required_len: required_len,
+                           actual_len: buffer.len(),
+                       });
+        }
+    }
+
+    Ok(())
+}
+
+// Checks the usages of the buffer and the image and the number of samples of the image.
+fn check_usage<B, I>(device: &Device, buffer: &B, image: &I, ty: CheckCopyBufferImageTy)
+                     -> Result<(), CheckCopyBufferImageError>
+    where I: ?Sized + ImageAccess,
+          B: ?Sized + BufferAccess
 {
     let buffer_inner = buffer.inner();
     let image_inner = image.inner();
@@ -74,6 +200,16 @@ pub fn check_copy_buffer_image<B, I, P>(device: &Device, buffer: &B, image: &I,
         return Err(CheckCopyBufferImageError::UnexpectedMultisampled);
     }
 
+    Ok(())
+}
+
+// Checks that the region of the image is within the image and is aligned to the blocks of its
+// format.
+fn check_image_range<I>(image: &I, image_offset: [u32; 3], image_size: [u32; 3],
+                        image_first_layer: u32, image_num_layers: u32, image_mipmap: u32)
+                        -> Result<(), CheckCopyBufferImageError>
+    where I: ?Sized + ImageAccess
+{
     let image_dimensions = match image.dimensions().mipmap_dimensions(image_mipmap) {
         Some(d) => d,
         None => return Err(CheckCopyBufferImageError::ImageCoordinatesOutOfRange),
@@ -83,34 +219,62 @@ pub fn check_copy_buffer_image<B, I, P>(device: &Device, buffer: &B, image: &I,
         return Err(CheckCopyBufferImageError::ImageCoordinatesOutOfRange);
     }
 
-    if image_offset[0] + image_size[0] > image_dimensions.width() {
-        return Err(CheckCopyBufferImageError::ImageCoordinatesOutOfRange);
+    match check_block_region(image_offset,
+                             image_size,
+                             image_dimensions.width_height_depth(),
+                             image.format().block_dimensions()) {
+        Ok(()) => Ok(()),
+        Err(BlockRegionError::OutOfRange) => {
+            Err(CheckCopyBufferImageError::ImageCoordinatesOutOfRange)
+        },
+        Err(BlockRegionError::NotBlockAligned) => {
+            Err(CheckCopyBufferImageError::RegionNotBlockAligned)
+        },
     }
+}
 
-    if image_offset[1] + image_size[1] > image_dimensions.height() {
-        return Err(CheckCopyBufferImageError::ImageCoordinatesOutOfRange);
+// Returns the size in bytes of a block of the given aspect of the format, when stored in a
+// buffer. Returns `None` if the format doesn't have a fixed block size.
+fn aspect_block_size(format: Format, aspect: ImageAspect) -> Option<usize> {
+    match aspect {
+        ImageAspect::Color => format.block_size(),
+        ImageAspect::Depth => match format {
+            Format::D16Unorm | Format::D16Unorm_S8Uint => Some(2),
+            Format::X8_D24UnormPack32 | Format::D24Unorm_S8Uint => Some(4),
+            Format::D32Sfloat | Format::D32Sfloat_S8Uint => Some(4),
+            _ => None,
+        },
+        ImageAspect::Stencil => Some(1),
+        ImageAspect::DepthStencil => None,
     }
+}
 
-    if image_offset[2] + image_size[2] > image_dimensions.depth() {
-        return Err(CheckCopyBufferImageError::ImageCoordinatesOutOfRange);
+// Returns the number of blocks of `block` texels that a region spans in the buffer, including
+// the padding between rows and slices.
+fn buffer_blocks(region: &BufferImageCopy, block: [u32; 2]) -> usize {
+    let extent = region.image_extent;
+    if extent[0] == 0 || extent[1] == 0 || extent[2] == 0 || region.image_layer_count == 0 {
+        return 0;
     }
 
-    image.format().ensure_accepts()?;
-
-    {
-        let num_texels = image_size[0] * image_size[1] * image_size[2] * image_num_layers;
-        let required_len = num_texels as usize * image.format().rate() as usize;
-        if required_len > buffer.len() {
-            return Err(CheckCopyBufferImageError::BufferTooSmall {
-                           required_len: required_len,
-                           actual_len: buffer.len(),
-                       });
-        }
-    }
+    let row_length = if region.buffer_row_length == 0 {
+        extent[0]
+    } else {
+        region.buffer_row_length
+    };
+    let image_height = if region.buffer_image_height == 0 {
+        extent[1]
+    } else {
+        region.buffer_image_height
+    };
 
-    // TODO: check memory overlap?
+    let blocks = |texels: u32, block: u32| ((texels + block - 1) / block) as usize;
+    let row_blocks = blocks(row_length, block[0]);
+    let height_blocks = blocks(image_height, block[1]);
+    let num_slices = extent[2] as usize * region.image_layer_count as usize;
 
-    Ok(())
+    ((num_slices - 1) * height_blocks + blocks(extent[1], block[1]) - 1) * row_blocks +
+        blocks(extent[0], block[0])
 }
 
 /// Error that can happen from `check_copy_buffer_image`.
@@ -135,6 +299,19 @@ pub enum CheckCopyBufferImageError {
         /// Actual number of elements in the buffer.
         actual_len: usize,
     },
+    /// The image doesn't have the aspect to copy.
+    MissingAspect,
+    /// A region copies both the depth and the stencil aspect, which is not allowed.
+    MultipleAspects,
+    /// The offset in the buffer must be a multiple of 4 bytes and of the size of a texel.
+    UnalignedBufferOffset,
+    /// The row length in the buffer is smaller than the width of the region.
+    BufferRowLengthTooSmall,
+    /// The image height in the buffer is smaller than the height of the region.
+    BufferImageHeightTooSmall,
+    /// The offset, extent, row length or image height of a compressed image isn't a multiple of
+    /// the dimensions of its blocks.
+    RegionNotBlockAligned,
 }
 
 impl error::Error for CheckCopyBufferImageError {
@@ -162,6 +339,25 @@ impl error::Error for CheckCopyBufferImageError {
             CheckCopyBufferImageError::BufferTooSmall { .. } => {
                 "the buffer is too small for the copy operation"
             },
+            CheckCopyBufferImageError::MissingAspect => {
+                "the image doesn't have the aspect to copy"
+            },
+            CheckCopyBufferImageError::MultipleAspects => {
+                "a region copies both the depth and the stencil aspect, which is not allowed"
+            },
+            CheckCopyBufferImageError::UnalignedBufferOffset => {
+                "the offset in the buffer must be a multiple of 4 bytes and of the size of a texel"
+            },
+            CheckCopyBufferImageError::BufferRowLengthTooSmall => {
+                "the row length in the buffer is smaller than the width of the region"
+            },
+            CheckCopyBufferImageError::BufferImageHeightTooSmall => {
+                "the image height in the buffer is smaller than the height of the region"
+            },
+            CheckCopyBufferImageError::RegionNotBlockAligned => {
+                "the offset, extent, row length or image height of a compressed image isn't a \
+                 multiple of the dimensions of its blocks"
+            },
         }
     }
 
@@ -188,3 +384,160 @@ impl From<IncompatiblePixelsType> for CheckCopyBufferImageError {
         CheckCopyBufferImageError::WrongPixelType(err)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use image::AttachmentImage;
+    use image::ImageUsage;
+    use std::iter;
+
+    fn region(aspect: ImageAspect, extent: [u32; 3]) -> BufferImageCopy {
+        BufferImageCopy {
+            buffer_offset: 0,
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_aspect: aspect,
+            image_mip_level: 0,
+            image_base_array_layer: 0,
+            image_layer_count: 1,
+            image_offset: [0, 0, 0],
+            image_extent: extent,
+        }
+    }
+
+    fn transfer_src() -> ImageUsage {
+        ImageUsage {
+            transfer_source: true,
+            ..ImageUsage::none()
+        }
+    }
+
+    #[test]
+    fn aspects_of_formats() {
+        assert!(ImageAspect::Color.is_in_format(Format::R8G8B8A8Unorm));
+        assert!(!ImageAspect::Depth.is_in_format(Format::R8G8B8A8Unorm));
+        assert!(ImageAspect::Depth.is_in_format(Format::D24Unorm_S8Uint));
+        assert!(ImageAspect::Stencil.is_in_format(Format::D24Unorm_S8Uint));
+        assert!(ImageAspect::DepthStencil.is_in_format(Format::D24Unorm_S8Uint));
+        assert!(!ImageAspect::Stencil.is_in_format(Format::D16Unorm));
+        assert!(!ImageAspect::Color.is_in_format(Format::S8Uint));
+    }
+
+    #[test]
+    fn texels_with_padding() {
+        let mut r = region(ImageAspect::Color, [4, 4, 1]);
+        assert_eq!(buffer_blocks(&r, [1, 1]), 16);
+
+        r.buffer_row_length = 8;
+        assert_eq!(buffer_blocks(&r, [1, 1]), 3 * 8 + 4);
+
+        r.buffer_image_height = 6;
+        r.image_layer_count = 2;
+        assert_eq!(buffer_blocks(&r, [1, 1]), (6 + 3) * 8 + 4);
+
+        r.image_extent = [0, 4, 1];
+        assert_eq!(buffer_blocks(&r, [1, 1]), 0);
+    }
+
+    #[test]
+    fn blocks_with_padding() {
+        let mut r = region(ImageAspect::Color, [16, 16, 1]);
+        assert_eq!(buffer_blocks(&r, [4, 4]), 16);
+
+        // Partial blocks at the edge of the image.
+        r.image_extent = [18, 6, 1];
+        assert_eq!(buffer_blocks(&r, [4, 4]), 5 * 2);
+
+        r.buffer_row_length = 32;
+        r.buffer_image_height = 8;
+        r.image_layer_count = 2;
+        assert_eq!(buffer_blocks(&r, [4, 4]), (2 + 1) * 8 + 5);
+    }
+
+    #[test]
+    fn stencil_readback() {
+        let (device, _) = gfx_dev_and_queue!();
+        let image = AttachmentImage::with_usage(device.clone(), [16, 16],
+                                                Format::D24Unorm_S8Uint, transfer_src())
+            .unwrap();
+        let buffer = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(),
+                                                    iter::repeat(0u8).take(16 * 16))
+            .unwrap();
+
+        let regions = [region(ImageAspect::Stencil, [16, 16, 1])];
+        check_copy_buffer_image_regions(&device, &buffer, &image,
+                                        CheckCopyBufferImageTy::ImageToBuffer, &regions)
+            .unwrap();
+
+        // The depth aspect is 4 bytes per texel.
+        let regions = [region(ImageAspect::Depth, [16, 16, 1])];
+        match check_copy_buffer_image_regions(&device, &buffer, &image,
+                                              CheckCopyBufferImageTy::ImageToBuffer, &regions) {
+            Err(CheckCopyBufferImageError::BufferTooSmall { required_len: 1024, .. }) => (),
+            _ => panic!(),
+        }
+
+        let regions = [region(ImageAspect::DepthStencil, [16, 16, 1])];
+        match check_copy_buffer_image_regions(&device, &buffer, &image,
+                                              CheckCopyBufferImageTy::ImageToBuffer, &regions) {
+            Err(CheckCopyBufferImageError::MultipleAspects) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn missing_aspect() {
+        let (device, _) = gfx_dev_and_queue!();
+        let image = AttachmentImage::with_usage(device.clone(), [16, 16], Format::R8G8B8A8Unorm,
+                                                transfer_src())
+            .unwrap();
+        let buffer = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(),
+                                                    iter::repeat(0u32).take(16 * 16))
+            .unwrap();
+
+        let regions = [region(ImageAspect::Depth, [16, 16, 1])];
+        match check_copy_buffer_image_regions(&device, &buffer, &image,
+                                              CheckCopyBufferImageTy::ImageToBuffer, &regions) {
+            Err(CheckCopyBufferImageError::MissingAspect) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn buffer_layout() {
+        let (device, _) = gfx_dev_and_queue!();
+        let image = AttachmentImage::with_usage(device.clone(), [16, 16], Format::R8G8B8A8Unorm,
+                                                transfer_src())
+            .unwrap();
+        let buffer = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(),
+                                                    iter::repeat(0u8).take(4096))
+            .unwrap();
+
+        let mut r = region(ImageAspect::Color, [16, 16, 1]);
+        r.buffer_offset = 2;
+        match check_copy_buffer_image_regions(&device, &buffer, &image,
+                                              CheckCopyBufferImageTy::ImageToBuffer, &[r]) {
+            Err(CheckCopyBufferImageError::UnalignedBufferOffset) => (),
+            _ => panic!(),
+        }
+
+        let mut r = region(ImageAspect::Color, [16, 16, 1]);
+        r.buffer_row_length = 8;
+        match check_copy_buffer_image_regions(&device, &buffer, &image,
+                                              CheckCopyBufferImageTy::ImageToBuffer, &[r]) {
+            Err(CheckCopyBufferImageError::BufferRowLengthTooSmall) => (),
+            _ => panic!(),
+        }
+
+        let mut r = region(ImageAspect::Color, [16, 16, 1]);
+        r.buffer_row_length = 32;
+        match check_copy_buffer_image_regions(&device, &buffer, &image,
+                                              CheckCopyBufferImageTy::ImageToBuffer, &[r]) {
+            Err(CheckCopyBufferImageError::BufferTooSmall { .. }) => (),
+            _ => panic!(),
+        }
+    }
+}
@@ -0,0 +1,275 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+
+use buffer::BufferAccess;
+use descriptor::descriptor::DescriptorBufferDesc;
+use descriptor::descriptor::DescriptorDescTy;
+use descriptor::descriptor_set::DescriptorSetWithOffsets;
+use device::Device;
+
+/// Checks whether the dynamic offsets of a list of descriptor sets are valid.
+///
+/// Each descriptor set must have exactly one offset per dynamic buffer descriptor. Each offset
+/// must be a multiple of the `min_uniform_buffer_offset_alignment` or
+/// `min_storage_buffer_offset_alignment` limit, and must not make the range bound to the
+/// descriptor go past the end of the buffer.
+pub fn check_dynamic_offsets(device: &Device, sets: &[DescriptorSetWithOffsets])
+                             -> Result<(), CheckDynamicOffsetsError> {
+    let limits = device.physical_device().limits();
+    let uniform_alignment = limits.min_uniform_buffer_offset_alignment();
+    let storage_alignment = limits.min_storage_buffer_offset_alignment();
+
+    for (set_num, set) in sets.iter().enumerate() {
+        let descriptor_set = set.descriptor_set();
+        let dynamic_offsets = set.dynamic_offsets();
+
+        // List of the dynamic bindings of the set, with their array count and whether they are
+        // storage buffers.
+        let dynamic_bindings = (0 .. descriptor_set.num_bindings())
+            .filter_map(|binding_num| {
+                let desc = match descriptor_set.descriptor(binding_num) {
+                    Some(desc) => desc,
+                    None => return None,
+                };
+
+                match desc.ty {
+                    DescriptorDescTy::Buffer(DescriptorBufferDesc {
+                                                 dynamic: Some(true),
+                                                 storage,
+                                             }) => Some((binding_num, desc.array_count, storage)),
+                    _ => None,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let expected = dynamic_bindings
+            .iter()
+            .map(|&(_, array_count, _)| array_count as usize)
+            .sum();
+        if dynamic_offsets.len() != expected {
+            return Err(CheckDynamicOffsetsError::WrongNumberOfOffsets {
+                           set_num,
+                           expected,
+                           obtained: dynamic_offsets.len(),
+                       });
+        }
+
+        let mut dynamic_offsets = dynamic_offsets.iter();
+
+        for (binding_num, array_count, storage) in dynamic_bindings {
+            let required_alignment = if storage {
+                storage_alignment
+            } else {
+                uniform_alignment
+            };
+
+            // The buffers bound to this binding, in the order of the array elements.
+            let mut buffers = (0 .. descriptor_set.num_buffers())
+                .filter_map(|n| descriptor_set.buffer(n))
+                .filter(|&(_, desc_num)| desc_num as usize == binding_num)
+                .map(|(buffer, _)| buffer);
+
+            for _ in 0 .. array_count {
+                let offset = *dynamic_offsets.next().unwrap();
+
+                if offset as u64 % required_alignment != 0 {
+                    return Err(CheckDynamicOffsetsError::UnalignedOffset {
+                                   set_num,
+                                   binding_num,
+                                   offset,
+                                   required_alignment,
+                               });
+                }
+
+                if let Some(buffer) = buffers.next() {
+                    let inner = buffer.inner();
+                    if inner.offset + buffer.size() + offset as usize > inner.buffer.size() {
+                        return Err(CheckDynamicOffsetsError::OffsetOutOfRange {
+                                       set_num,
+                                       binding_num,
+                                       offset,
+                                   });
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when checking the dynamic offsets of descriptor sets.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CheckDynamicOffsetsError {
+    /// The number of dynamic offsets doesn't match the number of dynamic buffer descriptors in
+    /// the set.
+    WrongNumberOfOffsets {
+        /// The index of the set.
+        set_num: usize,
+        /// Number of dynamic buffer descriptors in the set.
+        expected: usize,
+        /// Number of dynamic offsets that were provided.
+        obtained: usize,
+    },
+
+    /// A dynamic offset is not a multiple of the required alignment.
+    UnalignedOffset {
+        /// The index of the set.
+        set_num: usize,
+        /// The binding number of the descriptor.
+        binding_num: usize,
+        /// The offset that was provided.
+        offset: u32,
+        /// The alignment that the offset must respect.
+        required_alignment: u64,
+    },
+
+    /// A dynamic offset makes the range bound to the descriptor go past the end of the buffer.
+    OffsetOutOfRange {
+        /// The index of the set.
+        set_num: usize,
+        /// The binding number of the descriptor.
+        binding_num: usize,
+        /// The offset that was provided.
+        offset: u32,
+    },
+}
+
+impl error::Error for CheckDynamicOffsetsError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CheckDynamicOffsetsError::WrongNumberOfOffsets { .. } => {
+                "the number of dynamic offsets doesn't match the number of dynamic buffer \
+                 descriptors in the set"
+            },
+            CheckDynamicOffsetsError::UnalignedOffset { .. } => {
+                "a dynamic offset is not a multiple of the required alignment"
+            },
+            CheckDynamicOffsetsError::OffsetOutOfRange { .. } => {
+                "a dynamic offset makes the range bound to the descriptor go past the end of the \
+                 buffer"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CheckDynamicOffsetsError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use descriptor::descriptor::DescriptorDesc;
+    use descriptor::descriptor::ShaderStages;
+    use descriptor::descriptor_set::PersistentDescriptorSet;
+    use descriptor::pipeline_layout::PipelineLayoutDesc;
+    use descriptor::pipeline_layout::RuntimePipelineDesc;
+    use std::iter;
+    use std::sync::Arc;
+
+    // Builds a set with one dynamic uniform buffer descriptor, bound to the first `range_len`
+    // bytes of a buffer of `buffer_len` bytes.
+    fn dynamic_uniform_set<O>(device: Arc<Device>, buffer_len: usize, range_len: usize,
+                              dynamic_offsets: O)
+                              -> DescriptorSetWithOffsets
+        where O: IntoIterator<Item = u32>
+    {
+        let desc = DescriptorDesc {
+            ty: DescriptorDescTy::Buffer(DescriptorBufferDesc {
+                                             dynamic: Some(true),
+                                             storage: false,
+                                         }),
+            array_count: 1,
+            stages: ShaderStages::all(),
+            readonly: true,
+        };
+
+        let layout = RuntimePipelineDesc::new(iter::once(iter::once(Some(desc))), iter::empty())
+            .unwrap()
+            .build(device.clone())
+            .unwrap();
+
+        let buffer = CpuAccessibleBuffer::from_iter(device,
+                                                    BufferUsage::uniform_buffer(),
+                                                    (0 .. buffer_len).map(|_| 0u8))
+            .unwrap();
+
+        let set = PersistentDescriptorSet::start(layout, 0)
+            .add_buffer(buffer.into_buffer_slice().slice(0 .. range_len).unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+
+        DescriptorSetWithOffsets::new(set, dynamic_offsets)
+    }
+
+    #[test]
+    fn wrong_number_of_offsets() {
+        let (device, _) = gfx_dev_and_queue!();
+        let set = dynamic_uniform_set(device.clone(), 16, 16, iter::empty());
+
+        match check_dynamic_offsets(&device, &[set]) {
+            Err(CheckDynamicOffsetsError::WrongNumberOfOffsets {
+                    set_num: 0,
+                    expected: 1,
+                    obtained: 0,
+                }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn unaligned_offset() {
+        let (device, _) = gfx_dev_and_queue!();
+        let alignment = device
+            .physical_device()
+            .limits()
+            .min_uniform_buffer_offset_alignment() as usize;
+        if alignment <= 1 {
+            return;
+        }
+
+        let set = dynamic_uniform_set(device.clone(), alignment * 2, 16, iter::once(1));
+
+        match check_dynamic_offsets(&device, &[set]) {
+            Err(CheckDynamicOffsetsError::UnalignedOffset { offset: 1, .. }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn offset_out_of_range() {
+        let (device, _) = gfx_dev_and_queue!();
+        let alignment = device
+            .physical_device()
+            .limits()
+            .min_uniform_buffer_offset_alignment() as usize;
+
+        let valid = dynamic_uniform_set(device.clone(), alignment + 16, 16,
+                                        iter::once(alignment as u32));
+        assert!(check_dynamic_offsets(&device, &[valid]).is_ok());
+
+        let invalid = dynamic_uniform_set(device.clone(), alignment + 16, 16,
+                                          iter::once(alignment as u32 * 2));
+        match check_dynamic_offsets(&device, &[invalid]) {
+            Err(CheckDynamicOffsetsError::OffsetOutOfRange { .. }) => (),
+            _ => panic!(),
+        }
+    }
+}
@@ -71,7 +71,77 @@ pub fn check_dynamic_state_validity<Pl>(pipeline: &Pl, state: &DynamicState)
         }
     }
 
-    // TODO: don't forget to implement the rest
+    if pipeline.has_dynamic_depth_bias() {
+        if let Some(depth_bias) = state.depth_bias {
+            if depth_bias.clamp != 0.0 && !device.enabled_features().depth_bias_clamp {
+                return Err(CheckDynamicStateValidityError::DepthBiasClampMissingExtension);
+            }
+        } else {
+            return Err(CheckDynamicStateValidityError::DepthBiasMissing);
+        }
+
+    } else {
+        if state.depth_bias.is_some() {
+            return Err(CheckDynamicStateValidityError::DepthBiasNotDynamic);
+        }
+    }
+
+    if pipeline.has_dynamic_depth_bounds() {
+        if let Some(ref depth_bounds) = state.depth_bounds {
+            if depth_bounds.start < 0.0 || depth_bounds.start > 1.0 || depth_bounds.end < 0.0 ||
+                depth_bounds.end > 1.0
+            {
+                return Err(CheckDynamicStateValidityError::DepthBoundsOutOfRange);
+            }
+        } else {
+            return Err(CheckDynamicStateValidityError::DepthBoundsMissing);
+        }
+
+    } else {
+        if state.depth_bounds.is_some() {
+            return Err(CheckDynamicStateValidityError::DepthBoundsNotDynamic);
+        }
+    }
+
+    if pipeline.has_dynamic_stencil_compare_mask() {
+        if state.compare_mask.is_none() {
+            return Err(CheckDynamicStateValidityError::CompareMaskMissing);
+        }
+    } else {
+        if state.compare_mask.is_some() {
+            return Err(CheckDynamicStateValidityError::CompareMaskNotDynamic);
+        }
+    }
+
+    if pipeline.has_dynamic_stencil_write_mask() {
+        if state.write_mask.is_none() {
+            return Err(CheckDynamicStateValidityError::WriteMaskMissing);
+        }
+    } else {
+        if state.write_mask.is_some() {
+            return Err(CheckDynamicStateValidityError::WriteMaskNotDynamic);
+        }
+    }
+
+    if pipeline.has_dynamic_stencil_reference() {
+        if state.reference.is_none() {
+            return Err(CheckDynamicStateValidityError::ReferenceMissing);
+        }
+    } else {
+        if state.reference.is_some() {
+            return Err(CheckDynamicStateValidityError::ReferenceNotDynamic);
+        }
+    }
+
+    if pipeline.has_dynamic_blend_constants() {
+        if state.blend_constants.is_none() {
+            return Err(CheckDynamicStateValidityError::BlendConstantsMissing);
+        }
+    } else {
+        if state.blend_constants.is_some() {
+            return Err(CheckDynamicStateValidityError::BlendConstantsNotDynamic);
+        }
+    }
 
     Ok(())
 }
@@ -108,6 +178,39 @@ pub enum CheckDynamicStateValidityError {
         /// Number of scissors that were passed.
         obtained: usize,
     },
+    /// Passed a dynamic depth bias, while the pipeline doesn't have depth bias set as dynamic.
+    DepthBiasNotDynamic,
+    /// The pipeline has a dynamic depth bias, but no depth bias value was passed.
+    DepthBiasMissing,
+    /// The `depth_bias_clamp` feature must be enabled in order to use a depth bias clamp
+    /// different from 0.0.
+    DepthBiasClampMissingExtension,
+    /// Passed dynamic depth bounds, while the pipeline doesn't have depth bounds set as dynamic.
+    DepthBoundsNotDynamic,
+    /// The pipeline has dynamic depth bounds, but no depth bounds were passed.
+    DepthBoundsMissing,
+    /// The depth bounds must be between 0.0 and 1.0.
+    DepthBoundsOutOfRange,
+    /// Passed a dynamic stencil compare mask, while the pipeline doesn't have the stencil
+    /// compare mask set as dynamic.
+    CompareMaskNotDynamic,
+    /// The pipeline has a dynamic stencil compare mask, but no compare mask was passed.
+    CompareMaskMissing,
+    /// Passed a dynamic stencil write mask, while the pipeline doesn't have the stencil write
+    /// mask set as dynamic.
+    WriteMaskNotDynamic,
+    /// The pipeline has a dynamic stencil write mask, but no write mask was passed.
+    WriteMaskMissing,
+    /// Passed a dynamic stencil reference, while the pipeline doesn't have the stencil
+    /// reference set as dynamic.
+    ReferenceNotDynamic,
+    /// The pipeline has a dynamic stencil reference, but no reference was passed.
+    ReferenceMissing,
+    /// Passed dynamic blend constants, while the pipeline doesn't have blend constants set as
+    /// dynamic.
+    BlendConstantsNotDynamic,
+    /// The pipeline has dynamic blend constants, but no blend constants were passed.
+    BlendConstantsMissing,
 }
 
 impl error::Error for CheckDynamicStateValidityError {
@@ -144,6 +247,55 @@ impl error::Error for CheckDynamicStateValidityError {
             CheckDynamicStateValidityError::ScissorsCountMismatch { .. } => {
                 "the number of dynamic scissors doesn't match the expected number of scissors"
             },
+            CheckDynamicStateValidityError::DepthBiasNotDynamic => {
+                "passed a dynamic depth bias, while the pipeline doesn't have depth bias set as \
+                 dynamic"
+            },
+            CheckDynamicStateValidityError::DepthBiasMissing => {
+                "the pipeline has a dynamic depth bias, but no depth bias value was passed"
+            },
+            CheckDynamicStateValidityError::DepthBiasClampMissingExtension => {
+                "the `depth_bias_clamp` feature must be enabled in order to use a depth bias \
+                 clamp different from 0.0"
+            },
+            CheckDynamicStateValidityError::DepthBoundsNotDynamic => {
+                "passed dynamic depth bounds, while the pipeline doesn't have depth bounds set as \
+                 dynamic"
+            },
+            CheckDynamicStateValidityError::DepthBoundsMissing => {
+                "the pipeline has dynamic depth bounds, but no depth bounds were passed"
+            },
+            CheckDynamicStateValidityError::DepthBoundsOutOfRange => {
+                "the depth bounds must be between 0.0 and 1.0"
+            },
+            CheckDynamicStateValidityError::CompareMaskNotDynamic => {
+                "passed a dynamic stencil compare mask, while the pipeline doesn't have the \
+                 stencil compare mask set as dynamic"
+            },
+            CheckDynamicStateValidityError::CompareMaskMissing => {
+                "the pipeline has a dynamic stencil compare mask, but no compare mask was passed"
+            },
+            CheckDynamicStateValidityError::WriteMaskNotDynamic => {
+                "passed a dynamic stencil write mask, while the pipeline doesn't have the stencil \
+                 write mask set as dynamic"
+            },
+            CheckDynamicStateValidityError::WriteMaskMissing => {
+                "the pipeline has a dynamic stencil write mask, but no write mask was passed"
+            },
+            CheckDynamicStateValidityError::ReferenceNotDynamic => {
+                "passed a dynamic stencil reference, while the pipeline doesn't have the stencil \
+                 reference set as dynamic"
+            },
+            CheckDynamicStateValidityError::ReferenceMissing => {
+                "the pipeline has a dynamic stencil reference, but no reference was passed"
+            },
+            CheckDynamicStateValidityError::BlendConstantsNotDynamic => {
+                "passed dynamic blend constants, while the pipeline doesn't have blend constants \
+                 set as dynamic"
+            },
+            CheckDynamicStateValidityError::BlendConstantsMissing => {
+                "the pipeline has dynamic blend constants, but no blend constants were passed"
+            },
         }
     }
 }
@@ -155,4 +307,166 @@ impl fmt::Display for CheckDynamicStateValidityError {
     }
 }
 
-// TODO: tests
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use command_buffer::DynamicStencilValue;
+    use framebuffer::EmptySinglePassRenderPassDesc;
+    use framebuffer::RenderPassDesc;
+    use pipeline::raster::DepthBias;
+
+    // Builds a pipeline with fixed state and a pipeline customized with `$dynamic`, then checks
+    // that `$state` is only accepted by the latter and that the latter requires it to be set.
+    macro_rules! check_dynamic_state {
+        ($device:expr, |$builder:ident| $dynamic:expr, $state:expr, $not_dynamic:ident,
+         $missing:ident) => ({
+            let render_pass = Arc::new(EmptySinglePassRenderPassDesc
+                                           .build_render_pass($device.clone())
+                                           .unwrap());
+            let fixed = empty_gfx_pipeline!($device, render_pass.clone()).unwrap();
+            let dynamic = empty_gfx_pipeline!($device, render_pass, |$builder| $dynamic)
+                .unwrap();
+
+            let state = $state;
+            check_dynamic_state_validity(&dynamic, &state).unwrap();
+            check_dynamic_state_validity(&fixed, &DynamicState::none()).unwrap();
+
+            match check_dynamic_state_validity(&fixed, &state) {
+                Err(CheckDynamicStateValidityError::$not_dynamic) => (),
+                _ => panic!(),
+            }
+
+            match check_dynamic_state_validity(&dynamic, &DynamicState::none()) {
+                Err(CheckDynamicStateValidityError::$missing) => (),
+                _ => panic!(),
+            }
+        });
+    }
+
+    fn bias(clamp: f32) -> DepthBias {
+        DepthBias {
+            constant_factor: 1.0,
+            clamp: clamp,
+            slope_factor: 1.0,
+        }
+    }
+
+    #[test]
+    fn depth_bias() {
+        let (device, _) = gfx_dev_and_queue!();
+        check_dynamic_state!(device, |b| b.depth_bias_dynamic(),
+                             DynamicState { depth_bias: Some(bias(0.0)), ..DynamicState::none() },
+                             DepthBiasNotDynamic, DepthBiasMissing);
+    }
+
+    #[test]
+    fn depth_bias_clamp_missing_feature() {
+        let (device, _) = gfx_dev_and_queue!();
+        let render_pass = Arc::new(EmptySinglePassRenderPassDesc
+                                       .build_render_pass(device.clone())
+                                       .unwrap());
+        let pipeline = empty_gfx_pipeline!(device, render_pass, |b| b.depth_bias_dynamic())
+            .unwrap();
+
+        let state = DynamicState { depth_bias: Some(bias(0.5)), ..DynamicState::none() };
+        match check_dynamic_state_validity(&pipeline, &state) {
+            Err(CheckDynamicStateValidityError::DepthBiasClampMissingExtension) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn depth_bias_clamp() {
+        let (device, _) = gfx_dev_and_queue!(depth_bias_clamp);
+        let render_pass = Arc::new(EmptySinglePassRenderPassDesc
+                                       .build_render_pass(device.clone())
+                                       .unwrap());
+        let pipeline = empty_gfx_pipeline!(device, render_pass, |b| b.depth_bias_dynamic())
+            .unwrap();
+
+        let state = DynamicState { depth_bias: Some(bias(0.5)), ..DynamicState::none() };
+        check_dynamic_state_validity(&pipeline, &state).unwrap();
+    }
+
+    #[test]
+    fn depth_bounds() {
+        let (device, _) = gfx_dev_and_queue!(depth_bounds);
+        check_dynamic_state!(device, |b| b.depth_bounds_dynamic(),
+                             DynamicState {
+                                 depth_bounds: Some(0.25 .. 0.75),
+                                 ..DynamicState::none()
+                             },
+                             DepthBoundsNotDynamic, DepthBoundsMissing);
+    }
+
+    #[test]
+    fn depth_bounds_out_of_range() {
+        let (device, _) = gfx_dev_and_queue!(depth_bounds);
+        let render_pass = Arc::new(EmptySinglePassRenderPassDesc
+                                       .build_render_pass(device.clone())
+                                       .unwrap());
+        let pipeline = empty_gfx_pipeline!(device, render_pass, |b| b.depth_bounds_dynamic())
+            .unwrap();
+
+        let state = DynamicState { depth_bounds: Some(0.0 .. 1.0), ..DynamicState::none() };
+        check_dynamic_state_validity(&pipeline, &state).unwrap();
+
+        let state = DynamicState { depth_bounds: Some(-0.5 .. 1.0), ..DynamicState::none() };
+        match check_dynamic_state_validity(&pipeline, &state) {
+            Err(CheckDynamicStateValidityError::DepthBoundsOutOfRange) => (),
+            _ => panic!(),
+        }
+
+        let state = DynamicState { depth_bounds: Some(0.0 .. 1.5), ..DynamicState::none() };
+        match check_dynamic_state_validity(&pipeline, &state) {
+            Err(CheckDynamicStateValidityError::DepthBoundsOutOfRange) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn stencil_compare_mask() {
+        let (device, _) = gfx_dev_and_queue!();
+        check_dynamic_state!(device, |b| b.stencil_compare_mask_dynamic(),
+                             DynamicState {
+                                 compare_mask: Some(DynamicStencilValue::both(0xff)),
+                                 ..DynamicState::none()
+                             },
+                             CompareMaskNotDynamic, CompareMaskMissing);
+    }
+
+    #[test]
+    fn stencil_write_mask() {
+        let (device, _) = gfx_dev_and_queue!();
+        check_dynamic_state!(device, |b| b.stencil_write_mask_dynamic(),
+                             DynamicState {
+                                 write_mask: Some(DynamicStencilValue::both(0xff)),
+                                 ..DynamicState::none()
+                             },
+                             WriteMaskNotDynamic, WriteMaskMissing);
+    }
+
+    #[test]
+    fn stencil_reference() {
+        let (device, _) = gfx_dev_and_queue!();
+        check_dynamic_state!(device, |b| b.stencil_reference_dynamic(),
+                             DynamicState {
+                                 reference: Some(DynamicStencilValue { front: 1, back: 2 }),
+                                 ..DynamicState::none()
+                             },
+                             ReferenceNotDynamic, ReferenceMissing);
+    }
+
+    #[test]
+    fn blend_constants() {
+        let (device, _) = gfx_dev_and_queue!();
+        check_dynamic_state!(device, |b| b.blend_constants_dynamic(),
+                             DynamicState {
+                                 blend_constants: Some([0.0, 0.5, 1.0, 1.0]),
+                                 ..DynamicState::none()
+                             },
+                             BlendConstantsNotDynamic, BlendConstantsMissing);
+    }
+}
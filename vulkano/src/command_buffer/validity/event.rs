@@ -0,0 +1,290 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+
+use VulkanObject;
+use device::Device;
+use device::DeviceOwned;
+use sync::AccessFlagBits;
+use sync::Event;
+use sync::PipelineStages;
+
+/// Checks whether a set event or reset event command is valid.
+///
+/// # Panic
+///
+/// - Panics if the event was not created with `device`.
+///
+pub fn check_event_command(device: &Device, event: &Event, stages: PipelineStages)
+                           -> Result<(), CheckEventCommandError> {
+    assert_eq!(event.device().internal_object(), device.internal_object());
+
+    if stages == PipelineStages::none() {
+        return Err(CheckEventCommandError::NoStages);
+    }
+
+    if stages.host {
+        return Err(CheckEventCommandError::HostStage);
+    }
+
+    match check_stage_features(device, stages) {
+        Ok(()) => (),
+        Err(MissingStageFeature::GeometryShader) => {
+            return Err(CheckEventCommandError::GeometryShaderFeatureNotEnabled);
+        },
+        Err(MissingStageFeature::TessellationShader) => {
+            return Err(CheckEventCommandError::TessellationShaderFeatureNotEnabled);
+        },
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when attempting to add a `set_event` or `reset_event` command.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CheckEventCommandError {
+    /// No pipeline stage was specified.
+    NoStages,
+    /// The host stage can't be used when setting or resetting an event from a command buffer.
+    HostStage,
+    /// A geometry shader stage was specified, but the `geometry_shader` feature is not enabled.
+    GeometryShaderFeatureNotEnabled,
+    /// A tessellation stage was specified, but the `tessellation_shader` feature is not enabled.
+    TessellationShaderFeatureNotEnabled,
+}
+
+impl error::Error for CheckEventCommandError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CheckEventCommandError::NoStages => {
+                "no pipeline stage was specified"
+            },
+            CheckEventCommandError::HostStage => {
+                "the host stage can't be used when setting or resetting an event from a command \
+                 buffer"
+            },
+            CheckEventCommandError::GeometryShaderFeatureNotEnabled => {
+                "a geometry shader stage was specified, but the `geometry_shader` feature is not \
+                 enabled"
+            },
+            CheckEventCommandError::TessellationShaderFeatureNotEnabled => {
+                "a tessellation stage was specified, but the `tessellation_shader` feature is not \
+                 enabled"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CheckEventCommandError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+/// Checks whether a wait events command is valid.
+///
+/// # Panic
+///
+/// - Panics if one of the events was not created with `device`.
+///
+pub fn check_wait_events<'a, I>(device: &Device, events: I, source_stages: PipelineStages,
+                                source_access: AccessFlagBits,
+                                destination_stages: PipelineStages,
+                                destination_access: AccessFlagBits)
+                                -> Result<(), CheckWaitEventsError>
+    where I: IntoIterator<Item = &'a Event>
+{
+    let mut num_events = 0;
+    for event in events {
+        assert_eq!(event.device().internal_object(), device.internal_object());
+        num_events += 1;
+    }
+
+    if num_events == 0 {
+        return Err(CheckWaitEventsError::NoEvents);
+    }
+
+    if source_stages == PipelineStages::none() || destination_stages == PipelineStages::none() {
+        return Err(CheckWaitEventsError::NoStages);
+    }
+
+    if destination_stages.host {
+        return Err(CheckWaitEventsError::DestinationHostStage);
+    }
+
+    if !source_access.is_compatible_with(&source_stages) ||
+        !destination_access.is_compatible_with(&destination_stages)
+    {
+        return Err(CheckWaitEventsError::AccessNotSupportedByStages);
+    }
+
+    for &stages in &[source_stages, destination_stages] {
+        match check_stage_features(device, stages) {
+            Ok(()) => (),
+            Err(MissingStageFeature::GeometryShader) => {
+                return Err(CheckWaitEventsError::GeometryShaderFeatureNotEnabled);
+            },
+            Err(MissingStageFeature::TessellationShader) => {
+                return Err(CheckWaitEventsError::TessellationShaderFeatureNotEnabled);
+            },
+        }
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when attempting to add a `wait_events` command.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CheckWaitEventsError {
+    /// The list of events is empty.
+    NoEvents,
+    /// The source or destination stages are empty.
+    NoStages,
+    /// The host stage can't be used as a destination stage.
+    DestinationHostStage,
+    /// The source or destination accesses aren't supported by the corresponding stages.
+    AccessNotSupportedByStages,
+    /// A geometry shader stage was specified, but the `geometry_shader` feature is not enabled.
+    GeometryShaderFeatureNotEnabled,
+    /// A tessellation stage was specified, but the `tessellation_shader` feature is not enabled.
+    TessellationShaderFeatureNotEnabled,
+}
+
+impl error::Error for CheckWaitEventsError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CheckWaitEventsError::NoEvents => {
+                "the list of events is empty"
+            },
+            CheckWaitEventsError::NoStages => {
+                "the source or destination stages are empty"
+            },
+            CheckWaitEventsError::DestinationHostStage => {
+                "the host stage can't be used as a destination stage"
+            },
+            CheckWaitEventsError::AccessNotSupportedByStages => {
+                "the source or destination accesses aren't supported by the corresponding stages"
+            },
+            CheckWaitEventsError::GeometryShaderFeatureNotEnabled => {
+                "a geometry shader stage was specified, but the `geometry_shader` feature is not \
+                 enabled"
+            },
+            CheckWaitEventsError::TessellationShaderFeatureNotEnabled => {
+                "a tessellation stage was specified, but the `tessellation_shader` feature is not \
+                 enabled"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CheckWaitEventsError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+// Feature that is required by a pipeline stage but is not enabled on the device.
+enum MissingStageFeature {
+    GeometryShader,
+    TessellationShader,
+}
+
+// Checks that the features required by the given stages are enabled.
+fn check_stage_features(device: &Device, stages: PipelineStages)
+                        -> Result<(), MissingStageFeature> {
+    if stages.geometry_shader && !device.enabled_features().geometry_shader {
+        return Err(MissingStageFeature::GeometryShader);
+    }
+
+    if (stages.tessellation_control_shader || stages.tessellation_evaluation_shader) &&
+        !device.enabled_features().tessellation_shader
+    {
+        return Err(MissingStageFeature::TessellationShader);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_event_host_stage() {
+        let (device, _) = gfx_dev_and_queue!();
+        let event = Event::alloc(device.clone()).unwrap();
+
+        let stages = PipelineStages {
+            host: true,
+            ..PipelineStages::none()
+        };
+
+        match check_event_command(&device, &event, stages) {
+            Err(CheckEventCommandError::HostStage) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn set_event_no_stages() {
+        let (device, _) = gfx_dev_and_queue!();
+        let event = Event::alloc(device.clone()).unwrap();
+
+        match check_event_command(&device, &event, PipelineStages::none()) {
+            Err(CheckEventCommandError::NoStages) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn wait_events_empty() {
+        let (device, _) = gfx_dev_and_queue!();
+        let stages = PipelineStages {
+            transfer: true,
+            ..PipelineStages::none()
+        };
+
+        match check_wait_events(&device, None, stages, AccessFlagBits::none(), stages,
+                                AccessFlagBits::none()) {
+            Err(CheckWaitEventsError::NoEvents) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn wait_events_host_source() {
+        let (device, _) = gfx_dev_and_queue!();
+        let event = Event::alloc(device.clone()).unwrap();
+
+        let source = PipelineStages {
+            host: true,
+            ..PipelineStages::none()
+        };
+        let destination = PipelineStages {
+            transfer: true,
+            ..PipelineStages::none()
+        };
+
+        assert!(check_wait_events(&device, Some(&event), source, AccessFlagBits::none(),
+                                  destination, AccessFlagBits::none())
+                    .is_ok());
+
+        match check_wait_events(&device, Some(&event), destination, AccessFlagBits::none(),
+                                source, AccessFlagBits::none()) {
+            Err(CheckWaitEventsError::DestinationHostStage) => (),
+            _ => panic!(),
+        }
+    }
+}
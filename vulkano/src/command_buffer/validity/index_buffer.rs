@@ -7,8 +7,10 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use std::cmp;
 use std::error;
 use std::fmt;
+use std::mem;
 
 use VulkanObject;
 use buffer::BufferAccess;
@@ -16,6 +18,7 @@ use buffer::TypedBufferAccess;
 use device::Device;
 use device::DeviceOwned;
 use pipeline::input_assembly::Index;
+use pipeline::input_assembly::IndexBounds;
 
 /// Checks whether an index buffer can be bound.
 ///
@@ -35,10 +38,11 @@ pub fn check_index_buffer<B, I>(device: &Device, buffer: &B)
         return Err(CheckIndexBufferError::BufferMissingUsage);
     }
 
-    // TODO: The sum of offset and the address of the range of VkDeviceMemory object that is
-    //       backing buffer, must be a multiple of the type indicated by indexType
-
-    // TODO: fullDrawIndexUint32 feature
+    // The memory that backs the buffer is always aligned to at least the size of an index, so
+    // only the offset within the buffer needs to be checked.
+    if buffer.inner().offset % mem::size_of::<I>() != 0 {
+        return Err(CheckIndexBufferError::WrongAlignment);
+    }
 
     Ok(CheckIndexBuffer { num_indices: buffer.len() })
 }
@@ -85,6 +89,96 @@ impl fmt::Display for CheckIndexBufferError {
     }
 }
 
+/// Checks whether the indices of an index buffer whose content is known can be used to draw
+/// with vertex buffers that contain `vertex_count` vertices.
+///
+/// Each index (except for the special value that restarts the primitive if `primitive_restart`
+/// is true) must not exceed the `max_draw_indexed_index_value` limit of the device. If the
+/// `full_draw_index_uint32` feature isn't enabled, the indices must also not exceed `0xffffff`.
+///
+/// `vertex_count` is the number of vertices of the vertex buffers whose input rate is per-vertex,
+/// or `None` if there is no such buffer. If it is `Some`, each index must also be less than it.
+pub fn check_index_range(device: &Device, bounds: &IndexBounds, primitive_restart: bool,
+                         vertex_count: Option<u32>)
+                         -> Result<(), CheckIndexRangeError> {
+    let max_index = match bounds.max_vertex_index(primitive_restart) {
+        Some(max_index) => max_index,
+        None => return Ok(()),
+    };
+
+    let mut max_allowed = device
+        .physical_device()
+        .limits()
+        .max_draw_indexed_index_value();
+    if !device.enabled_features().full_draw_index_uint32 {
+        max_allowed = cmp::min(max_allowed, 0xffffff);
+    }
+
+    if max_index > max_allowed {
+        return Err(CheckIndexRangeError::IndexTooLarge {
+                       index: max_index,
+                       max: max_allowed,
+                   });
+    }
+
+    if let Some(vertex_count) = vertex_count {
+        if max_index >= vertex_count {
+            return Err(CheckIndexRangeError::IndexOutOfRange {
+                           index: max_index,
+                           vertex_count: vertex_count,
+                       });
+        }
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when checking the indices of an index buffer.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CheckIndexRangeError {
+    /// The bounds of the indices of the index buffer aren't known, for example because a
+    /// `CpuAccessibleBuffer` is locked for writing.
+    UnknownIndices,
+    /// An index exceeds the largest value supported by the device.
+    IndexTooLarge {
+        /// The index.
+        index: u32,
+        /// The largest supported value.
+        max: u32,
+    },
+    /// An index is out of range of the vertex buffers.
+    IndexOutOfRange {
+        /// The index.
+        index: u32,
+        /// Number of vertices available in the vertex buffers.
+        vertex_count: u32,
+    },
+}
+
+impl error::Error for CheckIndexRangeError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CheckIndexRangeError::UnknownIndices => {
+                "the bounds of the indices of the index buffer aren't known"
+            },
+            CheckIndexRangeError::IndexTooLarge { .. } => {
+                "an index exceeds the largest value supported by the device"
+            },
+            CheckIndexRangeError::IndexOutOfRange { .. } => {
+                "an index is out of range of the vertex buffers"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CheckIndexRangeError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -132,4 +226,50 @@ mod tests {
                                  let _ = check_index_buffer(&dev2, &buffer);
                              });
     }
+
+    #[test]
+    fn index_bounds() {
+        let bounds = IndexBounds::from_indices(&[3u16, 0xffff, 7, 1]);
+        assert_eq!(bounds.max_vertex_index(false), Some(0xffff));
+        assert_eq!(bounds.max_vertex_index(true), Some(7));
+
+        let bounds = IndexBounds::from_indices(&[0xffffffffu32]);
+        assert_eq!(bounds.max_vertex_index(true), None);
+        assert_eq!(IndexBounds::from_indices(&[] as &[u32]).max_vertex_index(false), None);
+    }
+
+    #[test]
+    fn index_range() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let bounds = IndexBounds::from_indices(&[0u16, 5, 9, 0xffff]);
+        assert_eq!(check_index_range(&device, &bounds, true, Some(10)), Ok(()));
+
+        match check_index_range(&device, &bounds, true, Some(9)) {
+            Err(CheckIndexRangeError::IndexOutOfRange { index: 9, vertex_count: 9 }) => (),
+            _ => panic!(),
+        }
+
+        match check_index_range(&device, &bounds, false, Some(10)) {
+            Err(CheckIndexRangeError::IndexOutOfRange { index: 0xffff, .. }) => (),
+            _ => panic!(),
+        }
+
+        let empty = IndexBounds::default();
+        assert_eq!(check_index_range(&device, &empty, false, Some(0)), Ok(()));
+
+        // Without per-vertex buffers, only the limits of the device apply.
+        assert_eq!(check_index_range(&device, &bounds, false, None), Ok(()));
+    }
+
+    #[test]
+    fn index_too_large() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let bounds = IndexBounds::from_indices(&[0x1000000u32]);
+        match check_index_range(&device, &bounds, false, Some(0x2000000)) {
+            Err(CheckIndexRangeError::IndexTooLarge { index: 0x1000000, .. }) => (),
+            _ => panic!(),
+        }
+    }
 }
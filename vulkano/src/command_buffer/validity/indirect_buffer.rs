@@ -0,0 +1,130 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+
+use VulkanObject;
+use buffer::BufferAccess;
+use device::Device;
+use device::DeviceOwned;
+
+/// Checks whether an indirect buffer can be bound.
+///
+/// # Panic
+///
+/// - Panics if the buffer was not created with `device`.
+///
+pub fn check_indirect_buffer<Inb>(device: &Device, buffer: &Inb)
+                                  -> Result<(), CheckIndirectBufferError>
+    where Inb: ?Sized + BufferAccess
+{
+    assert_eq!(buffer.inner().buffer.device().internal_object(),
+               device.internal_object());
+
+    if !buffer.inner().buffer.usage_indirect_buffer() {
+        return Err(CheckIndirectBufferError::BufferMissingUsage);
+    }
+
+    if buffer.inner().offset % 4 != 0 {
+        return Err(CheckIndirectBufferError::WrongAlignment);
+    }
+
+    if buffer.size() == 0 {
+        return Err(CheckIndirectBufferError::BufferEmpty);
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when checking whether binding an indirect buffer is valid.
+#[derive(Debug, Copy, Clone)]
+pub enum CheckIndirectBufferError {
+    /// The "indirect buffer" usage must be enabled on the indirect buffer.
+    BufferMissingUsage,
+    /// The offset of the indirect buffer must be a multiple of 4.
+    WrongAlignment,
+    /// The indirect buffer doesn't contain any command to read.
+    BufferEmpty,
+}
+
+impl error::Error for CheckIndirectBufferError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CheckIndirectBufferError::BufferMissingUsage => {
+                "the indirect buffer usage must be enabled on the indirect buffer"
+            },
+            CheckIndirectBufferError::WrongAlignment => {
+                "the offset of the indirect buffer must be a multiple of 4"
+            },
+            CheckIndirectBufferError::BufferEmpty => {
+                "the indirect buffer doesn't contain any command to read"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CheckIndirectBufferError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use command_buffer::DispatchIndirectCommand;
+
+    #[test]
+    fn missing_usage() {
+        let (device, _) = gfx_dev_and_queue!();
+        let command = DispatchIndirectCommand { x: 1, y: 1, z: 1 };
+        let buffer = CpuAccessibleBuffer::from_iter(device.clone(),
+                                                    BufferUsage::vertex_buffer(),
+                                                    Some(command).into_iter())
+            .unwrap();
+
+        match check_indirect_buffer(&device, &buffer) {
+            Err(CheckIndirectBufferError::BufferMissingUsage) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn empty() {
+        let (device, _) = gfx_dev_and_queue!();
+        let buffer = CpuAccessibleBuffer::from_iter(device.clone(),
+                                                    BufferUsage::indirect_buffer(),
+                                                    None::<DispatchIndirectCommand>.into_iter())
+            .unwrap();
+
+        match check_indirect_buffer(&device, &buffer) {
+            Err(CheckIndirectBufferError::BufferEmpty) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn wrong_device() {
+        let (dev1, _) = gfx_dev_and_queue!();
+        let (dev2, _) = gfx_dev_and_queue!();
+        let command = DispatchIndirectCommand { x: 1, y: 1, z: 1 };
+        let buffer = CpuAccessibleBuffer::from_iter(dev1, BufferUsage::all(),
+                                                    Some(command).into_iter())
+            .unwrap();
+
+        assert_should_panic!({
+                                 let _ = check_indirect_buffer(&dev2, &buffer);
+                             });
+    }
+}
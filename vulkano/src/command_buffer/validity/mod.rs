@@ -10,30 +10,57 @@
 //! Functions that check the validity of commands.
 
 pub use self::blit_image::{CheckBlitImageError, check_blit_image};
+pub use self::clear_attachments::{CheckClearAttachmentsError, check_clear_attachments};
 pub use self::clear_color_image::{CheckClearColorImageError, check_clear_color_image};
+pub use self::clear_depth_stencil_image::{CheckClearDepthStencilImageError,
+                                          check_clear_depth_stencil_image};
 pub use self::copy_buffer::{CheckCopyBuffer, CheckCopyBufferError, check_copy_buffer};
-pub use self::copy_image::{CheckCopyImageError, check_copy_image};
+pub use self::copy_image::{CheckCopyImageError, check_copy_image, check_copy_image_regions};
 pub use self::copy_image_buffer::{CheckCopyBufferImageError, CheckCopyBufferImageTy,
-                                  check_copy_buffer_image};
+                                  check_copy_buffer_image, check_copy_buffer_image_regions};
 pub use self::descriptor_sets::{CheckDescriptorSetsValidityError, check_descriptor_sets_validity};
 pub use self::dispatch::{CheckDispatchError, check_dispatch};
+pub use self::dynamic_offsets::{CheckDynamicOffsetsError, check_dynamic_offsets};
 pub use self::dynamic_state::{CheckDynamicStateValidityError, check_dynamic_state_validity};
+pub use self::event::{CheckEventCommandError, CheckWaitEventsError, check_event_command,
+                      check_wait_events};
 pub use self::fill_buffer::{CheckFillBufferError, check_fill_buffer};
-pub use self::index_buffer::{CheckIndexBuffer, CheckIndexBufferError, check_index_buffer};
+pub use self::indirect_buffer::{CheckIndirectBufferError, check_indirect_buffer};
+pub use self::index_buffer::{CheckIndexBuffer, CheckIndexBufferError, CheckIndexRangeError,
+                             check_index_buffer, check_index_range};
 pub use self::push_constants::{CheckPushConstantsValidityError, check_push_constants_validity};
-pub use self::update_buffer::{CheckUpdateBufferError, check_update_buffer};
+pub use self::query::{CheckBeginQueryError, CheckCopyQueryPoolResults,
+                      CheckCopyQueryPoolResultsError, CheckResetQueryPoolError,
+                      CheckWriteTimestampError, check_begin_query, check_copy_query_pool_results,
+                      check_reset_query_pool, check_write_timestamp};
+pub use self::queue_family_ownership::{CheckQueueFamilyOwnershipTransferError,
+                                       check_queue_family_ownership_transfer};
+pub use self::resolve_image::{CheckResolveImageError, check_resolve_image};
+pub use self::transfer_image_layout::{CheckTransferImageLayoutError, CheckTransferImageLayoutTy,
+                                      check_transfer_image_layout};
+pub use self::update_buffer::{CheckUpdateBufferError, check_update_buffer,
+                              check_update_buffer_slice};
 pub use self::vertex_buffers::{CheckVertexBuffer, CheckVertexBufferError, check_vertex_buffers};
 
 mod blit_image;
+mod clear_attachments;
 mod clear_color_image;
+mod clear_depth_stencil_image;
 mod copy_buffer;
 mod copy_image;
 mod copy_image_buffer;
 mod descriptor_sets;
 mod dispatch;
+mod dynamic_offsets;
 mod dynamic_state;
+mod event;
 mod fill_buffer;
 mod index_buffer;
+mod indirect_buffer;
 mod push_constants;
+mod query;
+mod queue_family_ownership;
+mod resolve_image;
+mod transfer_image_layout;
 mod update_buffer;
 mod vertex_buffers;
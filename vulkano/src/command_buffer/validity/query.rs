@@ -0,0 +1,413 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+use std::mem;
+
+use VulkanObject;
+use buffer::TypedBufferAccess;
+use device::Device;
+use device::DeviceOwned;
+use instance::QueueFamily;
+use query::QueryPoolAbstract;
+use query::QueryResultElement;
+use query::QueryResultFlags;
+use query::QueryType;
+use sync::PipelineStages;
+
+/// Checks whether a begin query command is valid.
+///
+/// # Panic
+///
+/// - Panics if the query pool was not created with `device`.
+///
+pub fn check_begin_query<Q>(device: &Device, query_pool: &Q, query: u32, precise: bool)
+                            -> Result<(), CheckBeginQueryError>
+    where Q: ?Sized + QueryPoolAbstract
+{
+    let pool = query_pool.inner();
+    assert_eq!(pool.device().internal_object(), device.internal_object());
+
+    if pool.query(query).is_none() {
+        return Err(CheckBeginQueryError::QueryOutOfRange);
+    }
+
+    match pool.ty() {
+        QueryType::Occlusion => {
+            if precise && !device.enabled_features().occlusion_query_precise {
+                return Err(CheckBeginQueryError::OcclusionQueryPreciseFeatureNotEnabled);
+            }
+        },
+        QueryType::PipelineStatistics(_) => {
+            if precise {
+                return Err(CheckBeginQueryError::NotPermittedForQueryType);
+            }
+        },
+        QueryType::Timestamp => {
+            return Err(CheckBeginQueryError::NotPermittedForQueryType);
+        },
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when attempting to add a `begin_query` command.
+#[derive(Debug, Copy, Clone)]
+pub enum CheckBeginQueryError {
+    /// The query index is out of range of the query pool.
+    QueryOutOfRange,
+    /// Timestamp queries can't be begun, and only occlusion queries can be precise.
+    NotPermittedForQueryType,
+    /// A precise occlusion query was requested but the corresponding feature wasn't enabled.
+    OcclusionQueryPreciseFeatureNotEnabled,
+}
+
+impl error::Error for CheckBeginQueryError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CheckBeginQueryError::QueryOutOfRange => {
+                "the query index is out of range of the query pool"
+            },
+            CheckBeginQueryError::NotPermittedForQueryType => {
+                "the operation is not permitted for the type of the query pool"
+            },
+            CheckBeginQueryError::OcclusionQueryPreciseFeatureNotEnabled => {
+                "a precise occlusion query was requested but the corresponding feature wasn't \
+                 enabled"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CheckBeginQueryError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+/// Checks whether a write timestamp command is valid.
+///
+/// `queue_family` is the queue family of the command buffer that the command is added to.
+///
+/// # Panic
+///
+/// - Panics if the query pool was not created with `device`.
+///
+pub fn check_write_timestamp<Q>(device: &Device, queue_family: QueueFamily, query_pool: &Q,
+                                query: u32, stage: PipelineStages)
+                                -> Result<(), CheckWriteTimestampError>
+    where Q: ?Sized + QueryPoolAbstract
+{
+    let pool = query_pool.inner();
+    assert_eq!(pool.device().internal_object(), device.internal_object());
+
+    if queue_family.timestamp_valid_bits().is_none() {
+        return Err(CheckWriteTimestampError::TimestampsNotSupported);
+    }
+
+    if pool.query(query).is_none() {
+        return Err(CheckWriteTimestampError::QueryOutOfRange);
+    }
+
+    match pool.ty() {
+        QueryType::Timestamp => (),
+        _ => return Err(CheckWriteTimestampError::NotPermittedForQueryType),
+    }
+
+    if stage.into_vulkan_bits().count_ones() != 1 {
+        return Err(CheckWriteTimestampError::NotSingleStage);
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when attempting to add a `write_timestamp` command.
+#[derive(Debug, Copy, Clone)]
+pub enum CheckWriteTimestampError {
+    /// The query index is out of range of the query pool.
+    QueryOutOfRange,
+    /// The query pool is not a timestamp query pool.
+    NotPermittedForQueryType,
+    /// Exactly one pipeline stage must be specified.
+    NotSingleStage,
+    /// The queue family of the command buffer doesn't support timestamps.
+    TimestampsNotSupported,
+}
+
+impl error::Error for CheckWriteTimestampError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CheckWriteTimestampError::QueryOutOfRange => {
+                "the query index is out of range of the query pool"
+            },
+            CheckWriteTimestampError::NotPermittedForQueryType => {
+                "the query pool is not a timestamp query pool"
+            },
+            CheckWriteTimestampError::NotSingleStage => {
+                "exactly one pipeline stage must be specified"
+            },
+            CheckWriteTimestampError::TimestampsNotSupported => {
+                "the queue family of the command buffer doesn't support timestamps"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CheckWriteTimestampError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+/// Checks whether a reset query pool command is valid.
+///
+/// # Panic
+///
+/// - Panics if the query pool was not created with `device`.
+///
+pub fn check_reset_query_pool<Q>(device: &Device, query_pool: &Q, first_query: u32,
+                                 query_count: u32)
+                                 -> Result<(), CheckResetQueryPoolError>
+    where Q: ?Sized + QueryPoolAbstract
+{
+    let pool = query_pool.inner();
+    assert_eq!(pool.device().internal_object(), device.internal_object());
+
+    if query_count == 0 || pool.queries_range(first_query, query_count).is_none() {
+        return Err(CheckResetQueryPoolError::QueriesOutOfRange);
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when attempting to add a `reset_query_pool` command.
+#[derive(Debug, Copy, Clone)]
+pub enum CheckResetQueryPoolError {
+    /// The range of queries is empty or out of range of the query pool.
+    QueriesOutOfRange,
+}
+
+impl error::Error for CheckResetQueryPoolError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CheckResetQueryPoolError::QueriesOutOfRange => {
+                "the range of queries is empty or out of range of the query pool"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CheckResetQueryPoolError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+/// Checks whether a copy query pool results command is valid.
+///
+/// # Panic
+///
+/// - Panics if the query pool or the buffer were not created with `device`.
+///
+pub fn check_copy_query_pool_results<Q, D, T>(device: &Device, query_pool: &Q, first_query: u32,
+                                              query_count: u32, destination: &D,
+                                              flags: QueryResultFlags)
+                                              -> Result<CheckCopyQueryPoolResults,
+                                                        CheckCopyQueryPoolResultsError>
+    where Q: ?Sized + QueryPoolAbstract,
+          D: ?Sized + TypedBufferAccess<Content = [T]>,
+          T: QueryResultElement
+{
+    let pool = query_pool.inner();
+    assert_eq!(pool.device().internal_object(), device.internal_object());
+    assert_eq!(destination.inner().buffer.device().internal_object(),
+               device.internal_object());
+
+    if query_count == 0 || pool.queries_range(first_query, query_count).is_none() {
+        return Err(CheckCopyQueryPoolResultsError::QueriesOutOfRange);
+    }
+
+    if let QueryType::Timestamp = pool.ty() {
+        if flags.partial {
+            return Err(CheckCopyQueryPoolResultsError::NotPermittedForQueryType);
+        }
+    }
+
+    if !destination.inner().buffer.usage_transfer_destination() {
+        return Err(CheckCopyQueryPoolResultsError::BufferMissingUsage);
+    }
+
+    if destination.inner().offset % mem::size_of::<T>() != 0 {
+        return Err(CheckCopyQueryPoolResultsError::WrongAlignment);
+    }
+
+    let values_per_query = pool.ty().result_len() + if flags.with_availability { 1 } else { 0 };
+    let required_len = values_per_query * query_count as usize;
+
+    if destination.len() < required_len {
+        return Err(CheckCopyQueryPoolResultsError::BufferTooSmall {
+                       required_len,
+                       actual_len: destination.len(),
+                   });
+    }
+
+    Ok(CheckCopyQueryPoolResults { stride: values_per_query * mem::size_of::<T>() })
+}
+
+/// Information returned if `check_copy_query_pool_results` succeeds.
+pub struct CheckCopyQueryPoolResults {
+    /// Stride in bytes between the results of two consecutive queries in the destination.
+    pub stride: usize,
+}
+
+/// Error that can happen when attempting to add a `copy_query_pool_results` command.
+#[derive(Debug, Copy, Clone)]
+pub enum CheckCopyQueryPoolResultsError {
+    /// The range of queries is empty or out of range of the query pool.
+    QueriesOutOfRange,
+    /// Partial results can't be requested for timestamp queries.
+    NotPermittedForQueryType,
+    /// The "transfer destination" usage must be enabled on the buffer.
+    BufferMissingUsage,
+    /// The offset of the buffer must be a multiple of the size of the results.
+    WrongAlignment,
+    /// The buffer is too small to hold the results.
+    BufferTooSmall {
+        /// Required number of elements in the buffer.
+        required_len: usize,
+        /// Actual number of elements in the buffer.
+        actual_len: usize,
+    },
+}
+
+impl error::Error for CheckCopyQueryPoolResultsError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CheckCopyQueryPoolResultsError::QueriesOutOfRange => {
+                "the range of queries is empty or out of range of the query pool"
+            },
+            CheckCopyQueryPoolResultsError::NotPermittedForQueryType => {
+                "partial results can't be requested for timestamp queries"
+            },
+            CheckCopyQueryPoolResultsError::BufferMissingUsage => {
+                "the transfer destination usage must be enabled on the buffer"
+            },
+            CheckCopyQueryPoolResultsError::WrongAlignment => {
+                "the offset of the buffer must be a multiple of the size of the results"
+            },
+            CheckCopyQueryPoolResultsError::BufferTooSmall { .. } => {
+                "the buffer is too small to hold the results"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CheckCopyQueryPoolResultsError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use query::UnsafeQueryPool;
+
+    #[test]
+    fn begin_timestamp() {
+        let (device, _) = gfx_dev_and_queue!();
+        let pool = UnsafeQueryPool::new(device.clone(), QueryType::Timestamp, 4).unwrap();
+
+        match check_begin_query(&device, &pool, 0, false) {
+            Err(CheckBeginQueryError::NotPermittedForQueryType) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn write_timestamp_occlusion() {
+        let (device, queue) = gfx_dev_and_queue!();
+        if queue.family().timestamp_valid_bits().is_none() {
+            return;
+        }
+
+        let pool = UnsafeQueryPool::new(device.clone(), QueryType::Occlusion, 4).unwrap();
+        let stage = PipelineStages {
+            bottom_of_pipe: true,
+            ..PipelineStages::none()
+        };
+
+        match check_write_timestamp(&device, queue.family(), &pool, 0, stage) {
+            Err(CheckWriteTimestampError::NotPermittedForQueryType) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn write_timestamp_queue_family() {
+        let (device, _) = gfx_dev_and_queue!();
+        let pool = UnsafeQueryPool::new(device.clone(), QueryType::Timestamp, 4).unwrap();
+        let stage = PipelineStages {
+            bottom_of_pipe: true,
+            ..PipelineStages::none()
+        };
+
+        for family in device.physical_device().queue_families() {
+            match check_write_timestamp(&device, family, &pool, 0, stage) {
+                Ok(()) => assert!(family.timestamp_valid_bits().is_some()),
+                Err(CheckWriteTimestampError::TimestampsNotSupported) => {
+                    assert!(family.timestamp_valid_bits().is_none())
+                },
+                _ => panic!(),
+            }
+        }
+    }
+
+    #[test]
+    fn reset_out_of_range() {
+        let (device, _) = gfx_dev_and_queue!();
+        let pool = UnsafeQueryPool::new(device.clone(), QueryType::Occlusion, 4).unwrap();
+
+        assert!(check_reset_query_pool(&device, &pool, 0, 4).is_ok());
+        match check_reset_query_pool(&device, &pool, 2, 3) {
+            Err(CheckResetQueryPoolError::QueriesOutOfRange) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn copy_buffer_too_small() {
+        let (device, _) = gfx_dev_and_queue!();
+        let pool = UnsafeQueryPool::new(device.clone(), QueryType::Occlusion, 4).unwrap();
+        let buffer = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(),
+                                                    [0u64; 4].iter().cloned())
+            .unwrap();
+
+        let flags = QueryResultFlags {
+            with_availability: true,
+            ..QueryResultFlags::default()
+        };
+
+        match check_copy_query_pool_results(&device, &pool, 0, 4, &buffer, flags) {
+            Err(CheckCopyQueryPoolResultsError::BufferTooSmall { required_len: 8, .. }) => (),
+            _ => panic!(),
+        }
+    }
+}
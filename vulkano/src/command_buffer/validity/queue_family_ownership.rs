@@ -0,0 +1,112 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+
+use device::Device;
+use instance::QueueFamily;
+
+/// Checks whether the ownership of a resource can be transferred from the `source` queue family
+/// to the `destination` queue family.
+///
+/// # Panic
+///
+/// - Panics if one of the queue families doesn't belong to the physical device of `device`.
+///
+pub fn check_queue_family_ownership_transfer(device: &Device, source: QueueFamily,
+                                             destination: QueueFamily)
+                                             -> Result<(), CheckQueueFamilyOwnershipTransferError> {
+    let physical_device = device.physical_device().index();
+    assert_eq!(source.physical_device().index(), physical_device);
+    assert_eq!(destination.physical_device().index(), physical_device);
+
+    if source.id() == destination.id() {
+        return Err(CheckQueueFamilyOwnershipTransferError::SameQueueFamily);
+    }
+
+    for family in &[source, destination] {
+        if !device.active_queue_families().any(|f| f.id() == family.id()) {
+            return Err(CheckQueueFamilyOwnershipTransferError::QueueFamilyNotActive {
+                           family: family.id(),
+                       });
+        }
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when attempting to add a queue family ownership transfer command.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CheckQueueFamilyOwnershipTransferError {
+    /// The source and destination queue families are the same.
+    SameQueueFamily,
+    /// No queue of one of the queue families was requested when creating the device.
+    QueueFamilyNotActive {
+        /// Id of the queue family.
+        family: u32,
+    },
+}
+
+impl error::Error for CheckQueueFamilyOwnershipTransferError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CheckQueueFamilyOwnershipTransferError::SameQueueFamily => {
+                "the source and destination queue families are the same"
+            },
+            CheckQueueFamilyOwnershipTransferError::QueueFamilyNotActive { .. } => {
+                "no queue of one of the queue families was requested when creating the device"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CheckQueueFamilyOwnershipTransferError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_queue_family() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        match check_queue_family_ownership_transfer(&device, queue.family(), queue.family()) {
+            Err(CheckQueueFamilyOwnershipTransferError::SameQueueFamily) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn inactive_queue_family() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let physical_device = device.physical_device();
+        let inactive = match physical_device
+            .queue_families()
+            .find(|f| !device.active_queue_families().any(|a| a.id() == f.id()))
+        {
+            Some(f) => f,
+            None => return,
+        };
+
+        match check_queue_family_ownership_transfer(&device, queue.family(), inactive) {
+            Err(CheckQueueFamilyOwnershipTransferError::QueueFamilyNotActive { family }) => {
+                assert_eq!(family, inactive.id());
+            },
+            _ => panic!(),
+        }
+    }
+}
@@ -0,0 +1,267 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+
+use VulkanObject;
+use device::Device;
+use device::DeviceOwned;
+use image::ImageAccess;
+use image::ImageDimensions;
+
+/// Checks whether a resolve image command is valid.
+///
+/// # Panic
+///
+/// - Panics if the source or the destination was not created with `device`.
+///
+pub fn check_resolve_image<S, D>(device: &Device, source: &S, source_offset: [i32; 3],
+                                 source_base_array_layer: u32, source_mip_level: u32,
+                                 destination: &D, destination_offset: [i32; 3],
+                                 destination_base_array_layer: u32, destination_mip_level: u32,
+                                 extent: [u32; 3], layer_count: u32)
+                                 -> Result<(), CheckResolveImageError>
+    where S: ?Sized + ImageAccess,
+          D: ?Sized + ImageAccess
+{
+    let source_inner = source.inner();
+    let destination_inner = destination.inner();
+
+    assert_eq!(source_inner.image.device().internal_object(),
+               device.internal_object());
+    assert_eq!(destination_inner.image.device().internal_object(),
+               device.internal_object());
+
+    if !source_inner.image.usage_transfer_source() {
+        return Err(CheckResolveImageError::MissingTransferSourceUsage);
+    }
+
+    if !destination_inner.image.usage_transfer_destination() {
+        return Err(CheckResolveImageError::MissingTransferDestinationUsage);
+    }
+
+    if source.samples() == 1 {
+        return Err(CheckResolveImageError::SourceNotMultisampled);
+    }
+
+    if destination.samples() != 1 {
+        return Err(CheckResolveImageError::DestinationMultisampled);
+    }
+
+    if source.format() != destination.format() {
+        return Err(CheckResolveImageError::FormatMismatch);
+    }
+
+    if !source.has_color() {
+        return Err(CheckResolveImageError::NotColorFormat);
+    }
+
+    if !destination_inner.image.supports_color_attachment() {
+        return Err(CheckResolveImageError::DestinationFormatNotSupported);
+    }
+
+    if layer_count == 0 {
+        return Err(CheckResolveImageError::SourceCoordinatesOutOfRange);
+    }
+
+    let source_dimensions = match source
+        .dimensions()
+        .mipmap_dimensions(source_mip_level) {
+        Some(d) => d,
+        None => return Err(CheckResolveImageError::SourceCoordinatesOutOfRange),
+    };
+
+    let destination_dimensions = match destination
+        .dimensions()
+        .mipmap_dimensions(destination_mip_level) {
+        Some(d) => d,
+        None => return Err(CheckResolveImageError::DestinationCoordinatesOutOfRange),
+    };
+
+    if !region_in_range(source_dimensions, source_offset, source_base_array_layer, extent,
+                        layer_count)
+    {
+        return Err(CheckResolveImageError::SourceCoordinatesOutOfRange);
+    }
+
+    if !region_in_range(destination_dimensions, destination_offset,
+                        destination_base_array_layer, extent, layer_count)
+    {
+        return Err(CheckResolveImageError::DestinationCoordinatesOutOfRange);
+    }
+
+    Ok(())
+}
+
+// Returns true if the region fits in an image of the given dimensions and is compatible with the
+// image type.
+fn region_in_range(dimensions: ImageDimensions, offset: [i32; 3], base_array_layer: u32,
+                   extent: [u32; 3], layer_count: u32)
+                   -> bool {
+    match base_array_layer.checked_add(layer_count) {
+        Some(end) if end <= dimensions.array_layers() => (),
+        _ => return false,
+    }
+
+    let size = [dimensions.width(), dimensions.height(), dimensions.depth()];
+    for i in 0 .. 3 {
+        if offset[i] < 0 {
+            return false;
+        }
+
+        match (offset[i] as u32).checked_add(extent[i]) {
+            Some(end) if end <= size[i] => (),
+            _ => return false,
+        }
+    }
+
+    match dimensions {
+        ImageDimensions::Dim1d { .. } => {
+            offset[1] == 0 && extent[1] == 1 && offset[2] == 0 && extent[2] == 1
+        },
+        ImageDimensions::Dim2d { .. } => offset[2] == 0 && extent[2] == 1,
+        ImageDimensions::Dim3d { .. } => true,
+    }
+}
+
+/// Error that can happen from `check_resolve_image`.
+#[derive(Debug, Copy, Clone)]
+pub enum CheckResolveImageError {
+    /// The source is missing the transfer source usage.
+    MissingTransferSourceUsage,
+    /// The destination is missing the transfer destination usage.
+    MissingTransferDestinationUsage,
+    /// The source must have more than one sample.
+    SourceNotMultisampled,
+    /// The destination must have exactly one sample.
+    DestinationMultisampled,
+    /// The source and destination must have the same format.
+    FormatMismatch,
+    /// Only images with a color format can be resolved.
+    NotColorFormat,
+    /// The format of the destination doesn't support being used as a color attachment.
+    DestinationFormatNotSupported,
+    /// The offsets, array layers and/or mipmap levels are out of range in the source image, or
+    /// incompatible with its type.
+    SourceCoordinatesOutOfRange,
+    /// The offsets, array layers and/or mipmap levels are out of range in the destination image,
+    /// or incompatible with its type.
+    DestinationCoordinatesOutOfRange,
+}
+
+impl error::Error for CheckResolveImageError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CheckResolveImageError::MissingTransferSourceUsage => {
+                "the source is missing the transfer source usage"
+            },
+            CheckResolveImageError::MissingTransferDestinationUsage => {
+                "the destination is missing the transfer destination usage"
+            },
+            CheckResolveImageError::SourceNotMultisampled => {
+                "the source must have more than one sample"
+            },
+            CheckResolveImageError::DestinationMultisampled => {
+                "the destination must have exactly one sample"
+            },
+            CheckResolveImageError::FormatMismatch => {
+                "the source and destination must have the same format"
+            },
+            CheckResolveImageError::NotColorFormat => {
+                "only images with a color format can be resolved"
+            },
+            CheckResolveImageError::DestinationFormatNotSupported => {
+                "the format of the destination doesn't support being used as a color attachment"
+            },
+            CheckResolveImageError::SourceCoordinatesOutOfRange => {
+                "the offsets, array layers and/or mipmap levels are out of range in the source \
+                 image"
+            },
+            CheckResolveImageError::DestinationCoordinatesOutOfRange => {
+                "the offsets, array layers and/or mipmap levels are out of range in the \
+                 destination image"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CheckResolveImageError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use format::Format;
+    use image::AttachmentImage;
+    use image::ImageUsage;
+
+    #[test]
+    fn source_not_multisampled() {
+        let (device, _) = gfx_dev_and_queue!();
+        let usage = ImageUsage {
+            transfer_source: true,
+            transfer_destination: true,
+            ..ImageUsage::none()
+        };
+        let source = AttachmentImage::with_usage(device.clone(), [16, 16],
+                                                 Format::R8G8B8A8Unorm, usage)
+            .unwrap();
+        let destination = AttachmentImage::with_usage(device.clone(), [16, 16],
+                                                      Format::R8G8B8A8Unorm, usage)
+            .unwrap();
+
+        match check_resolve_image(&device, &source, [0, 0, 0], 0, 0, &destination, [0, 0, 0], 0,
+                                  0, [16, 16, 1], 1) {
+            Err(CheckResolveImageError::SourceNotMultisampled) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn format_mismatch() {
+        let (device, _) = gfx_dev_and_queue!();
+        let usage = ImageUsage {
+            transfer_source: true,
+            transfer_destination: true,
+            ..ImageUsage::none()
+        };
+        let source = AttachmentImage::multisampled_with_usage(device.clone(), [16, 16], 4,
+                                                              Format::R8G8B8A8Unorm, usage)
+            .unwrap();
+        let destination = AttachmentImage::with_usage(device.clone(), [16, 16],
+                                                      Format::B8G8R8A8Unorm, usage)
+            .unwrap();
+
+        match check_resolve_image(&device, &source, [0, 0, 0], 0, 0, &destination, [0, 0, 0], 0,
+                                  0, [16, 16, 1], 1) {
+            Err(CheckResolveImageError::FormatMismatch) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn region_overflow() {
+        let dimensions = ImageDimensions::Dim2d {
+            width: 16,
+            height: 16,
+            array_layers: 4,
+            cubemap_compatible: false,
+        };
+
+        assert!(region_in_range(dimensions, [8, 8, 0], 0, [8, 8, 1], 4));
+        assert!(!region_in_range(dimensions, [8, 0, 0], 0, [u32::max_value(), 16, 1], 1));
+        assert!(!region_in_range(dimensions, [0, 0, 0], 2, [16, 16, 1], u32::max_value()));
+    }
+}
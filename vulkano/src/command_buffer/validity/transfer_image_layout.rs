@@ -0,0 +1,139 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+
+use device::Device;
+use image::ImageLayout;
+
+/// Whether the image is read from or written to by the transfer command.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CheckTransferImageLayoutTy {
+    Source,
+    Destination,
+}
+
+/// Checks whether an image can be in `layout` when it is used by a transfer command, such as a
+/// copy, a blit or a clear.
+///
+/// The source of a transfer can be in the `TransferSrcOptimal` layout and the destination in the
+/// `TransferDstOptimal` layout. Both can also be in the `General` layout, or in the
+/// `SharedPresent` layout if the `khr_shared_presentable_image` extension is enabled.
+pub fn check_transfer_image_layout(device: &Device, layout: ImageLayout,
+                                   ty: CheckTransferImageLayoutTy)
+                                   -> Result<(), CheckTransferImageLayoutError> {
+    match (layout, ty) {
+        (ImageLayout::TransferSrcOptimal, CheckTransferImageLayoutTy::Source) => Ok(()),
+        (ImageLayout::TransferDstOptimal, CheckTransferImageLayoutTy::Destination) => Ok(()),
+        (ImageLayout::General, _) => Ok(()),
+        (ImageLayout::SharedPresent, _) => {
+            if device.loaded_extensions().khr_shared_presentable_image {
+                Ok(())
+            } else {
+                Err(CheckTransferImageLayoutError::SharedPresentExtensionNotEnabled)
+            }
+        },
+        (layout, ty) => Err(CheckTransferImageLayoutError::UnsupportedLayout { layout, ty }),
+    }
+}
+
+/// Error that can happen from `check_transfer_image_layout`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CheckTransferImageLayoutError {
+    /// The layout can't be used by a transfer command for this kind of access.
+    UnsupportedLayout {
+        /// The layout that was requested.
+        layout: ImageLayout,
+        /// Whether the image is the source or the destination of the command.
+        ty: CheckTransferImageLayoutTy,
+    },
+    /// The `SharedPresent` layout was requested, but the `khr_shared_presentable_image` extension
+    /// is not enabled.
+    SharedPresentExtensionNotEnabled,
+}
+
+impl error::Error for CheckTransferImageLayoutError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CheckTransferImageLayoutError::UnsupportedLayout { .. } => {
+                "the layout can't be used by a transfer command for this kind of access"
+            },
+            CheckTransferImageLayoutError::SharedPresentExtensionNotEnabled => {
+                "the `SharedPresent` layout was requested, but the `khr_shared_presentable_image` \
+                 extension is not enabled"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CheckTransferImageLayoutError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transfer_layouts() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        assert!(check_transfer_image_layout(&device, ImageLayout::TransferSrcOptimal,
+                                            CheckTransferImageLayoutTy::Source)
+                    .is_ok());
+        assert!(check_transfer_image_layout(&device, ImageLayout::TransferDstOptimal,
+                                            CheckTransferImageLayoutTy::Destination)
+                    .is_ok());
+
+        match check_transfer_image_layout(&device, ImageLayout::TransferDstOptimal,
+                                          CheckTransferImageLayoutTy::Source) {
+            Err(CheckTransferImageLayoutError::UnsupportedLayout { .. }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn general_layout() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        assert!(check_transfer_image_layout(&device, ImageLayout::General,
+                                            CheckTransferImageLayoutTy::Source)
+                    .is_ok());
+        assert!(check_transfer_image_layout(&device, ImageLayout::General,
+                                            CheckTransferImageLayoutTy::Destination)
+                    .is_ok());
+    }
+
+    #[test]
+    fn shared_present_missing_extension() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        match check_transfer_image_layout(&device, ImageLayout::SharedPresent,
+                                          CheckTransferImageLayoutTy::Destination) {
+            Err(CheckTransferImageLayoutError::SharedPresentExtensionNotEnabled) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn other_layouts() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        match check_transfer_image_layout(&device, ImageLayout::ShaderReadOnlyOptimal,
+                                          CheckTransferImageLayoutTy::Destination) {
+            Err(CheckTransferImageLayoutError::UnsupportedLayout { .. }) => (),
+            _ => panic!(),
+        }
+    }
+}
@@ -13,12 +13,16 @@ use std::fmt;
 use std::mem;
 
 use VulkanObject;
+use buffer::BufferAccess;
 use buffer::TypedBufferAccess;
 use device::Device;
 use device::DeviceOwned;
 
 /// Checks whether an update buffer command is valid.
 ///
+/// If the buffer and the data don't have the same size, only the smallest of the two sizes is
+/// written.
+///
 /// # Panic
 ///
 /// - Panics if the buffer not created with `device`.
@@ -27,6 +31,45 @@ pub fn check_update_buffer<B, D>(device: &Device, buffer: &B, data: &D)
                                  -> Result<(), CheckUpdateBufferError>
     where B: ?Sized + TypedBufferAccess<Content = D>,
           D: ?Sized
+{
+    let size = cmp::min(buffer.size(), mem::size_of_val(data));
+    check_range(device, buffer, 0, size)
+}
+
+/// Checks whether an update buffer command that writes `data` to the elements of `buffer`
+/// starting at `first_element` is valid.
+///
+/// # Panic
+///
+/// - Panics if the buffer not created with `device`.
+///
+pub fn check_update_buffer_slice<B, T>(device: &Device, buffer: &B, first_element: usize,
+                                       data: &[T])
+                                       -> Result<(), CheckUpdateBufferError>
+    where B: ?Sized + TypedBufferAccess<Content = [T]>
+{
+    // A slice of zero-sized elements doesn't write anything.
+    if mem::size_of::<T>() == 0 {
+        return Err(CheckUpdateBufferError::DataEmpty);
+    }
+
+    let buffer_len = buffer.size() / mem::size_of::<T>();
+    match first_element.checked_add(data.len()) {
+        Some(end) if end <= buffer_len => (),
+        _ => return Err(CheckUpdateBufferError::OutOfRange),
+    }
+
+    check_range(device,
+                buffer,
+                first_element * mem::size_of::<T>(),
+                mem::size_of_val(data))
+}
+
+// Checks the part of the command that is common to all the ways of updating a buffer. `offset`
+// is relative to the start of `buffer`, and `size` is the number of bytes that are written.
+fn check_range<B>(device: &Device, buffer: &B, offset: usize, size: usize)
+                  -> Result<(), CheckUpdateBufferError>
+    where B: ?Sized + BufferAccess
 {
     assert_eq!(buffer.inner().buffer.device().internal_object(),
                device.internal_object());
@@ -35,11 +78,13 @@ pub fn check_update_buffer<B, D>(device: &Device, buffer: &B, data: &D)
         return Err(CheckUpdateBufferError::BufferMissingUsage);
     }
 
-    if buffer.inner().offset % 4 != 0 {
+    if (buffer.inner().offset + offset) % 4 != 0 {
         return Err(CheckUpdateBufferError::WrongAlignment);
     }
 
-    let size = cmp::min(buffer.size(), mem::size_of_val(data));
+    if size == 0 {
+        return Err(CheckUpdateBufferError::DataEmpty);
+    }
 
     if size % 4 != 0 {
         return Err(CheckUpdateBufferError::WrongAlignment);
@@ -57,8 +102,12 @@ pub fn check_update_buffer<B, D>(device: &Device, buffer: &B, data: &D)
 pub enum CheckUpdateBufferError {
     /// The "transfer destination" usage must be enabled on the buffer.
     BufferMissingUsage,
+    /// The range that is written is out of range of the buffer.
+    OutOfRange,
     /// The data or size must be 4-bytes aligned.
     WrongAlignment,
+    /// The data must not be empty.
+    DataEmpty,
     /// The data must not be larger than 64k bytes.
     DataTooLarge,
 }
@@ -70,9 +119,13 @@ impl error::Error for CheckUpdateBufferError {
             CheckUpdateBufferError::BufferMissingUsage => {
                 "the transfer destination usage must be enabled on the buffer"
             },
+            CheckUpdateBufferError::OutOfRange => {
+                "the range that is written is out of range of the buffer"
+            },
             CheckUpdateBufferError::WrongAlignment => {
                 "the offset or size are not aligned to 4 bytes"
             },
+            CheckUpdateBufferError::DataEmpty => "data is empty",
             CheckUpdateBufferError::DataTooLarge => "data is too large",
         }
     }
@@ -88,7 +141,6 @@ impl fmt::Display for CheckUpdateBufferError {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use buffer::BufferAccess;
     use buffer::BufferUsage;
     use buffer::CpuAccessibleBuffer;
 
@@ -150,6 +202,76 @@ mod tests {
         }
     }
 
+    #[test]
+    fn slice_out_of_range() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let buffer = CpuAccessibleBuffer::from_iter(device.clone(),
+                                                    BufferUsage::transfer_destination(),
+                                                    0u32 .. 16)
+            .unwrap();
+
+        assert!(check_update_buffer_slice(&device, &buffer, 12, &[0u32; 4]).is_ok());
+
+        match check_update_buffer_slice(&device, &buffer, 13, &[0u32; 4]) {
+            Err(CheckUpdateBufferError::OutOfRange) => (),
+            _ => panic!(),
+        }
+
+        match check_update_buffer_slice(&device, &buffer, usize::max_value(), &[0u32; 4]) {
+            Err(CheckUpdateBufferError::OutOfRange) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn slice_wrong_alignment() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let buffer = CpuAccessibleBuffer::from_iter(device.clone(),
+                                                    BufferUsage::transfer_destination(),
+                                                    (0 .. 64).map(|_| 0u8))
+            .unwrap();
+
+        assert!(check_update_buffer_slice(&device, &buffer, 8, &[0u8; 8]).is_ok());
+
+        match check_update_buffer_slice(&device, &buffer, 2, &[0u8; 8]) {
+            Err(CheckUpdateBufferError::WrongAlignment) => (),
+            _ => panic!(),
+        }
+
+        match check_update_buffer_slice(&device, &buffer, 8, &[0u8; 6]) {
+            Err(CheckUpdateBufferError::WrongAlignment) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn slice_empty() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let buffer = CpuAccessibleBuffer::from_iter(device.clone(),
+                                                    BufferUsage::transfer_destination(),
+                                                    0u32 .. 16)
+            .unwrap();
+
+        match check_update_buffer_slice(&device, &buffer, 0, &[] as &[u32]) {
+            Err(CheckUpdateBufferError::DataEmpty) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn slice_zero_sized_elements() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let buffer = CpuAccessibleBuffer::from_iter(device.clone(),
+                                                    BufferUsage::transfer_destination(),
+                                                    (0 .. 4).map(|_| ()))
+            .unwrap();
+
+        match check_update_buffer_slice(&device, &buffer, 0, &[(); 4]) {
+            Err(CheckUpdateBufferError::DataEmpty) => (),
+            _ => panic!(),
+        }
+    }
+
     #[test]
     fn wrong_device() {
         let (dev1, queue) = gfx_dev_and_queue!();
@@ -7,13 +7,18 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use std::iter;
+
 use descriptor::descriptor::DescriptorDesc;
 use descriptor::descriptor_set::DescriptorSet;
 use descriptor::descriptor_set::DescriptorSetDesc;
 
 /// A collection of descriptor set objects.
+///
+/// In order to pass dynamic offsets, wrap each descriptor set in a `DescriptorSetWithOffsets`
+/// and pass either a single `DescriptorSetWithOffsets` or a `Vec` of them.
 pub unsafe trait DescriptorSetsCollection {
-    fn into_vec(self) -> Vec<Box<DescriptorSet + Send + Sync>>;
+    fn into_vec(self) -> Vec<DescriptorSetWithOffsets>;
 
     /// Returns the number of descriptors in the set. Includes possibly empty descriptors.
     ///
@@ -30,7 +35,7 @@ pub unsafe trait DescriptorSetsCollection {
 
 unsafe impl DescriptorSetsCollection for () {
     #[inline]
-    fn into_vec(self) -> Vec<Box<DescriptorSet + Send + Sync>> {
+    fn into_vec(self) -> Vec<DescriptorSetWithOffsets> {
         vec![]
     }
 
@@ -49,8 +54,8 @@ unsafe impl<T> DescriptorSetsCollection for T
     where T: DescriptorSet + Send + Sync + 'static
 {
     #[inline]
-    fn into_vec(self) -> Vec<Box<DescriptorSet + Send + Sync>> {
-        vec![Box::new(self) as Box<_>]
+    fn into_vec(self) -> Vec<DescriptorSetWithOffsets> {
+        vec![DescriptorSetWithOffsets::new(self, iter::empty())]
     }
 
     #[inline]
@@ -74,10 +79,10 @@ unsafe impl<T> DescriptorSetsCollection for Vec<T>
     where T: DescriptorSet + Send + Sync + 'static
 {
     #[inline]
-    fn into_vec(self) -> Vec<Box<DescriptorSet + Send + Sync>> {
+    fn into_vec(self) -> Vec<DescriptorSetWithOffsets> {
         let mut v = Vec::new();
         for o in self {
-            v.push(Box::new(o) as Box<_>);
+            v.push(DescriptorSetWithOffsets::new(o, iter::empty()));
         }
         return v;
     }
@@ -92,6 +97,47 @@ unsafe impl<T> DescriptorSetsCollection for Vec<T>
     }
 }
 
+unsafe impl DescriptorSetsCollection for DescriptorSetWithOffsets {
+    #[inline]
+    fn into_vec(self) -> Vec<DescriptorSetWithOffsets> {
+        vec![self]
+    }
+
+    #[inline]
+    fn num_bindings_in_set(&self, set: usize) -> Option<usize> {
+        match set {
+            0 => Some(self.descriptor_set.num_bindings()),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    fn descriptor(&self, set: usize, binding: usize) -> Option<DescriptorDesc> {
+        match set {
+            0 => DescriptorSetDesc::descriptor(&self.descriptor_set, binding),
+            _ => None,
+        }
+    }
+}
+
+unsafe impl DescriptorSetsCollection for Vec<DescriptorSetWithOffsets> {
+    #[inline]
+    fn into_vec(self) -> Vec<DescriptorSetWithOffsets> {
+        self
+    }
+
+    #[inline]
+    fn num_bindings_in_set(&self, set: usize) -> Option<usize> {
+        self.get(set).map(|x| x.descriptor_set.num_bindings())
+    }
+
+    #[inline]
+    fn descriptor(&self, set: usize, binding: usize) -> Option<DescriptorDesc> {
+        self.get(set)
+            .and_then(|x| DescriptorSetDesc::descriptor(&x.descriptor_set, binding))
+    }
+}
+
 macro_rules! impl_collection {
     ($first:ident $(, $others:ident)+) => (
         unsafe impl<$first$(, $others)+> DescriptorSetsCollection for ($first, $($others),+)
@@ -99,15 +145,15 @@ macro_rules! impl_collection {
                   $(, $others: DescriptorSet + DescriptorSetDesc + Send + Sync + 'static)*
         {
             #[inline]
-            fn into_vec(self) -> Vec<Box<DescriptorSet + Send + Sync>> {
+            fn into_vec(self) -> Vec<DescriptorSetWithOffsets> {
                 #![allow(non_snake_case)]
 
                 let ($first, $($others,)*) = self;
 
                 let mut list = Vec::new();
-                list.push(Box::new($first) as Box<_>);
+                list.push(DescriptorSetWithOffsets::new($first, iter::empty()));
                 $(
-                    list.push(Box::new($others) as Box<_>);
+                    list.push(DescriptorSetWithOffsets::new($others, iter::empty()));
                 )+
                 list
             }
@@ -187,3 +233,47 @@ impl_collection!(Z,
                  C,
                  B,
                  A);
+
+/// A descriptor set, along with the dynamic offsets to apply to its dynamic uniform and storage
+/// buffers when it is bound.
+///
+/// The offsets must be ordered by binding number, then by array element within each binding.
+/// There must be exactly one offset for each dynamic buffer descriptor of the set.
+pub struct DescriptorSetWithOffsets {
+    descriptor_set: Box<DescriptorSet + Send + Sync>,
+    dynamic_offsets: Vec<u32>,
+}
+
+impl DescriptorSetWithOffsets {
+    /// Builds a new `DescriptorSetWithOffsets`.
+    ///
+    /// The offsets are checked when the descriptor set is passed to a draw or dispatch command.
+    #[inline]
+    pub fn new<S, O>(descriptor_set: S, dynamic_offsets: O) -> DescriptorSetWithOffsets
+        where S: DescriptorSet + Send + Sync + 'static,
+              O: IntoIterator<Item = u32>
+    {
+        DescriptorSetWithOffsets {
+            descriptor_set: Box::new(descriptor_set) as Box<_>,
+            dynamic_offsets: dynamic_offsets.into_iter().collect(),
+        }
+    }
+
+    /// Returns the descriptor set.
+    #[inline]
+    pub fn descriptor_set(&self) -> &DescriptorSet {
+        &*self.descriptor_set
+    }
+
+    /// Returns the dynamic offsets.
+    #[inline]
+    pub fn dynamic_offsets(&self) -> &[u32] {
+        &self.dynamic_offsets
+    }
+
+    /// Destroys the object and returns the descriptor set and the dynamic offsets.
+    #[inline]
+    pub fn into_tuple(self) -> (Box<DescriptorSet + Send + Sync>, Vec<u32>) {
+        (self.descriptor_set, self.dynamic_offsets)
+    }
+}
@@ -34,12 +34,15 @@
 //! - The `SimpleDescriptorSet` type is a default implementation of the `DescriptorSet` trait.
 //! - The `DescriptorSetsCollection` trait is implemented on collections of types that implement
 //!   `DescriptorSet`. It is what you pass to the draw functions.
+//! - The `DescriptorSetWithOffsets` type wraps around a descriptor set and the dynamic offsets to
+//!   use when binding it.
 
 use SafeDeref;
 use buffer::BufferAccess;
 use descriptor::descriptor::DescriptorDesc;
 use image::ImageViewAccess;
 
+pub use self::collection::DescriptorSetWithOffsets;
 pub use self::collection::DescriptorSetsCollection;
 pub use self::fixed_size_pool::FixedSizeDescriptorSet;
 pub use self::fixed_size_pool::FixedSizeDescriptorSetBuilder;
@@ -423,9 +423,15 @@ impl<L, R> PersistentDescriptorSetBuilderArray<L, R>
                     }
 
                     unsafe {
-                        DescriptorWrite::storage_buffer(self.builder.binding_id as u32,
-                                                        self.array_element as u32,
-                                                        &buffer)
+                        if buffer_desc.dynamic == Some(true) {
+                            DescriptorWrite::dynamic_storage_buffer(self.builder.binding_id as u32,
+                                                                    self.array_element as u32,
+                                                                    &buffer)
+                        } else {
+                            DescriptorWrite::storage_buffer(self.builder.binding_id as u32,
+                                                            self.array_element as u32,
+                                                            &buffer)
+                        }
                     }
                 } else {
                     if !buffer.inner().buffer.usage_uniform_buffer() {
@@ -434,9 +440,15 @@ impl<L, R> PersistentDescriptorSetBuilderArray<L, R>
                     }
 
                     unsafe {
-                        DescriptorWrite::uniform_buffer(self.builder.binding_id as u32,
-                                                        self.array_element as u32,
-                                                        &buffer)
+                        if buffer_desc.dynamic == Some(true) {
+                            DescriptorWrite::dynamic_uniform_buffer(self.builder.binding_id as u32,
+                                                                    self.array_element as u32,
+                                                                    &buffer)
+                        } else {
+                            DescriptorWrite::uniform_buffer(self.builder.binding_id as u32,
+                                                            self.array_element as u32,
+                                                            &buffer)
+                        }
                     }
                 }
             },
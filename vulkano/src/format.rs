@@ -157,7 +157,8 @@ pub unsafe trait AcceptsPixels<T> {
     /// Returns an error if `T` cannot be used as a source of pixels for `Self`.
     fn ensure_accepts(&self) -> Result<(), IncompatiblePixelsType>;
 
-    /// The number of `T`s which make up a single pixel.
+    /// The number of `T`s which make up a single pixel, or a single block of pixels for
+    /// compressed formats.
     ///
     /// ```
     /// use vulkano::format::{AcceptsPixels, R8G8B8A8Srgb};
@@ -408,6 +409,15 @@ macro_rules! formats {
         formats!(__inner_strongstorage_common__ $name $ty);
     };
     (__inner_strongstorage__ $name:ident ) => {};
+    (__inner_strongstorage__ $name:ident block=[$ty:ty; $dim:expr]) => {
+        unsafe impl AcceptsPixels<$ty> for $name {
+            fn ensure_accepts(&self) -> Result<(), IncompatiblePixelsType> { Ok(()) }
+            fn rate(&self) -> u32 { $dim }
+        }
+        unsafe impl AcceptsPixels<[$ty; $dim]> for $name {
+            fn ensure_accepts(&self) -> Result<(), IncompatiblePixelsType> { Ok(()) }
+        }
+    };
 
     (__inner_strongstorage_common__ $name:ident $ty:ty) => {
         unsafe impl StrongStorage for $name {
@@ -550,60 +560,146 @@ formats! {
     D16Unorm_S8Uint => FORMAT_D16_UNORM_S8_UINT [None] [depthstencil] {},
     D24Unorm_S8Uint => FORMAT_D24_UNORM_S8_UINT [None] [depthstencil] {},
     D32Sfloat_S8Uint => FORMAT_D32_SFLOAT_S8_UINT [None] [depthstencil] {},
-    BC1_RGBUnormBlock => FORMAT_BC1_RGB_UNORM_BLOCK [None] [compressed=texture_compression_bc] {},
-    BC1_RGBSrgbBlock => FORMAT_BC1_RGB_SRGB_BLOCK [None] [compressed=texture_compression_bc] {},
-    BC1_RGBAUnormBlock => FORMAT_BC1_RGBA_UNORM_BLOCK [None] [compressed=texture_compression_bc] {},
-    BC1_RGBASrgbBlock => FORMAT_BC1_RGBA_SRGB_BLOCK [None] [compressed=texture_compression_bc] {},
-    BC2UnormBlock => FORMAT_BC2_UNORM_BLOCK [None] [compressed=texture_compression_bc] {},
-    BC2SrgbBlock => FORMAT_BC2_SRGB_BLOCK [None] [compressed=texture_compression_bc] {},
-    BC3UnormBlock => FORMAT_BC3_UNORM_BLOCK [None] [compressed=texture_compression_bc] {},
-    BC3SrgbBlock => FORMAT_BC3_SRGB_BLOCK [None] [compressed=texture_compression_bc] {},
-    BC4UnormBlock => FORMAT_BC4_UNORM_BLOCK [None] [compressed=texture_compression_bc] {},
-    BC4SnormBlock => FORMAT_BC4_SNORM_BLOCK [None] [compressed=texture_compression_bc] {},
-    BC5UnormBlock => FORMAT_BC5_UNORM_BLOCK [None] [compressed=texture_compression_bc] {},
-    BC5SnormBlock => FORMAT_BC5_SNORM_BLOCK [None] [compressed=texture_compression_bc] {},
-    BC6HUfloatBlock => FORMAT_BC6H_UFLOAT_BLOCK [None] [compressed=texture_compression_bc] {},
-    BC6HSfloatBlock => FORMAT_BC6H_SFLOAT_BLOCK [None] [compressed=texture_compression_bc] {},
-    BC7UnormBlock => FORMAT_BC7_UNORM_BLOCK [None] [compressed=texture_compression_bc] {},
-    BC7SrgbBlock => FORMAT_BC7_SRGB_BLOCK [None] [compressed=texture_compression_bc] {},
-    ETC2_R8G8B8UnormBlock => FORMAT_ETC2_R8G8B8_UNORM_BLOCK [None] [compressed=texture_compression_etc2] {},
-    ETC2_R8G8B8SrgbBlock => FORMAT_ETC2_R8G8B8_SRGB_BLOCK [None] [compressed=texture_compression_etc2] {},
-    ETC2_R8G8B8A1UnormBlock => FORMAT_ETC2_R8G8B8A1_UNORM_BLOCK [None] [compressed=texture_compression_etc2] {},
-    ETC2_R8G8B8A1SrgbBlock => FORMAT_ETC2_R8G8B8A1_SRGB_BLOCK [None] [compressed=texture_compression_etc2] {},
-    ETC2_R8G8B8A8UnormBlock => FORMAT_ETC2_R8G8B8A8_UNORM_BLOCK [None] [compressed=texture_compression_etc2] {},
-    ETC2_R8G8B8A8SrgbBlock => FORMAT_ETC2_R8G8B8A8_SRGB_BLOCK [None] [compressed=texture_compression_etc2] {},
-    EAC_R11UnormBlock => FORMAT_EAC_R11_UNORM_BLOCK [None] [compressed=texture_compression_etc2] {},
-    EAC_R11SnormBlock => FORMAT_EAC_R11_SNORM_BLOCK [None] [compressed=texture_compression_etc2] {},
-    EAC_R11G11UnormBlock => FORMAT_EAC_R11G11_UNORM_BLOCK [None] [compressed=texture_compression_etc2] {},
-    EAC_R11G11SnormBlock => FORMAT_EAC_R11G11_SNORM_BLOCK [None] [compressed=texture_compression_etc2] {},
-    ASTC_4x4UnormBlock => FORMAT_ASTC_4x4_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_4x4SrgbBlock => FORMAT_ASTC_4x4_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_5x4UnormBlock => FORMAT_ASTC_5x4_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_5x4SrgbBlock => FORMAT_ASTC_5x4_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_5x5UnormBlock => FORMAT_ASTC_5x5_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_5x5SrgbBlock => FORMAT_ASTC_5x5_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_6x5UnormBlock => FORMAT_ASTC_6x5_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_6x5SrgbBlock => FORMAT_ASTC_6x5_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_6x6UnormBlock => FORMAT_ASTC_6x6_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_6x6SrgbBlock => FORMAT_ASTC_6x6_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_8x5UnormBlock => FORMAT_ASTC_8x5_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_8x5SrgbBlock => FORMAT_ASTC_8x5_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_8x6UnormBlock => FORMAT_ASTC_8x6_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_8x6SrgbBlock => FORMAT_ASTC_8x6_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_8x8UnormBlock => FORMAT_ASTC_8x8_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_8x8SrgbBlock => FORMAT_ASTC_8x8_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_10x5UnormBlock => FORMAT_ASTC_10x5_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_10x5SrgbBlock => FORMAT_ASTC_10x5_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_10x6UnormBlock => FORMAT_ASTC_10x6_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_10x6SrgbBlock => FORMAT_ASTC_10x6_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_10x8UnormBlock => FORMAT_ASTC_10x8_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_10x8SrgbBlock => FORMAT_ASTC_10x8_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_10x10UnormBlock => FORMAT_ASTC_10x10_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_10x10SrgbBlock => FORMAT_ASTC_10x10_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_12x10UnormBlock => FORMAT_ASTC_12x10_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_12x10SrgbBlock => FORMAT_ASTC_12x10_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_12x12UnormBlock => FORMAT_ASTC_12x12_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
-    ASTC_12x12SrgbBlock => FORMAT_ASTC_12x12_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
+    BC1_RGBUnormBlock => FORMAT_BC1_RGB_UNORM_BLOCK [None] [compressed=texture_compression_bc] {block=[u8; 8]},
+    BC1_RGBSrgbBlock => FORMAT_BC1_RGB_SRGB_BLOCK [None] [compressed=texture_compression_bc] {block=[u8; 8]},
+    BC1_RGBAUnormBlock => FORMAT_BC1_RGBA_UNORM_BLOCK [None] [compressed=texture_compression_bc] {block=[u8; 8]},
+    BC1_RGBASrgbBlock => FORMAT_BC1_RGBA_SRGB_BLOCK [None] [compressed=texture_compression_bc] {block=[u8; 8]},
+    BC2UnormBlock => FORMAT_BC2_UNORM_BLOCK [None] [compressed=texture_compression_bc] {block=[u8; 16]},
+    BC2SrgbBlock => FORMAT_BC2_SRGB_BLOCK [None] [compressed=texture_compression_bc] {block=[u8; 16]},
+    BC3UnormBlock => FORMAT_BC3_UNORM_BLOCK [None] [compressed=texture_compression_bc] {block=[u8; 16]},
+    BC3SrgbBlock => FORMAT_BC3_SRGB_BLOCK [None] [compressed=texture_compression_bc] {block=[u8; 16]},
+    BC4UnormBlock => FORMAT_BC4_UNORM_BLOCK [None] [compressed=texture_compression_bc] {block=[u8; 8]},
+    BC4SnormBlock => FORMAT_BC4_SNORM_BLOCK [None] [compressed=texture_compression_bc] {block=[u8; 8]},
+    BC5UnormBlock => FORMAT_BC5_UNORM_BLOCK [None] [compressed=texture_compression_bc] {block=[u8; 16]},
+    BC5SnormBlock => FORMAT_BC5_SNORM_BLOCK [None] [compressed=texture_compression_bc] {block=[u8; 16]},
+    BC6HUfloatBlock => FORMAT_BC6H_UFLOAT_BLOCK [None] [compressed=texture_compression_bc] {block=[u8; 16]},
+    BC6HSfloatBlock => FORMAT_BC6H_SFLOAT_BLOCK [None] [compressed=texture_compression_bc] {block=[u8; 16]},
+    BC7UnormBlock => FORMAT_BC7_UNORM_BLOCK [None] [compressed=texture_compression_bc] {block=[u8; 16]},
+    BC7SrgbBlock => FORMAT_BC7_SRGB_BLOCK [None] [compressed=texture_compression_bc] {block=[u8; 16]},
+    ETC2_R8G8B8UnormBlock => FORMAT_ETC2_R8G8B8_UNORM_BLOCK [None] [compressed=texture_compression_etc2] {block=[u8; 8]},
+    ETC2_R8G8B8SrgbBlock => FORMAT_ETC2_R8G8B8_SRGB_BLOCK [None] [compressed=texture_compression_etc2] {block=[u8; 8]},
+    ETC2_R8G8B8A1UnormBlock => FORMAT_ETC2_R8G8B8A1_UNORM_BLOCK [None] [compressed=texture_compression_etc2] {block=[u8; 8]},
+    ETC2_R8G8B8A1SrgbBlock => FORMAT_ETC2_R8G8B8A1_SRGB_BLOCK [None] [compressed=texture_compression_etc2] {block=[u8; 8]},
+    ETC2_R8G8B8A8UnormBlock => FORMAT_ETC2_R8G8B8A8_UNORM_BLOCK [None] [compressed=texture_compression_etc2] {block=[u8; 16]},
+    ETC2_R8G8B8A8SrgbBlock => FORMAT_ETC2_R8G8B8A8_SRGB_BLOCK [None] [compressed=texture_compression_etc2] {block=[u8; 16]},
+    EAC_R11UnormBlock => FORMAT_EAC_R11_UNORM_BLOCK [None] [compressed=texture_compression_etc2] {block=[u8; 8]},
+    EAC_R11SnormBlock => FORMAT_EAC_R11_SNORM_BLOCK [None] [compressed=texture_compression_etc2] {block=[u8; 8]},
+    EAC_R11G11UnormBlock => FORMAT_EAC_R11G11_UNORM_BLOCK [None] [compressed=texture_compression_etc2] {block=[u8; 16]},
+    EAC_R11G11SnormBlock => FORMAT_EAC_R11G11_SNORM_BLOCK [None] [compressed=texture_compression_etc2] {block=[u8; 16]},
+    ASTC_4x4UnormBlock => FORMAT_ASTC_4x4_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {block=[u8; 16]},
+    ASTC_4x4SrgbBlock => FORMAT_ASTC_4x4_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {block=[u8; 16]},
+    ASTC_5x4UnormBlock => FORMAT_ASTC_5x4_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {block=[u8; 16]},
+    ASTC_5x4SrgbBlock => FORMAT_ASTC_5x4_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {block=[u8; 16]},
+    ASTC_5x5UnormBlock => FORMAT_ASTC_5x5_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {block=[u8; 16]},
+    ASTC_5x5SrgbBlock => FORMAT_ASTC_5x5_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {block=[u8; 16]},
+    ASTC_6x5UnormBlock => FORMAT_ASTC_6x5_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {block=[u8; 16]},
+    ASTC_6x5SrgbBlock => FORMAT_ASTC_6x5_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {block=[u8; 16]},
+    ASTC_6x6UnormBlock => FORMAT_ASTC_6x6_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {block=[u8; 16]},
+    ASTC_6x6SrgbBlock => FORMAT_ASTC_6x6_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {block=[u8; 16]},
+    ASTC_8x5UnormBlock => FORMAT_ASTC_8x5_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {block=[u8; 16]},
+    ASTC_8x5SrgbBlock => FORMAT_ASTC_8x5_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {block=[u8; 16]},
+    ASTC_8x6UnormBlock => FORMAT_ASTC_8x6_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {block=[u8; 16]},
+    ASTC_8x6SrgbBlock => FORMAT_ASTC_8x6_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {block=[u8; 16]},
+    ASTC_8x8UnormBlock => FORMAT_ASTC_8x8_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {block=[u8; 16]},
+    ASTC_8x8SrgbBlock => FORMAT_ASTC_8x8_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {block=[u8; 16]},
+    ASTC_10x5UnormBlock => FORMAT_ASTC_10x5_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {block=[u8; 16]},
+    ASTC_10x5SrgbBlock => FORMAT_ASTC_10x5_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {block=[u8; 16]},
+    ASTC_10x6UnormBlock => FORMAT_ASTC_10x6_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {block=[u8; 16]},
+    ASTC_10x6SrgbBlock => FORMAT_ASTC_10x6_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {block=[u8; 16]},
+    ASTC_10x8UnormBlock => FORMAT_ASTC_10x8_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {block=[u8; 16]},
+    ASTC_10x8SrgbBlock => FORMAT_ASTC_10x8_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {block=[u8; 16]},
+    ASTC_10x10UnormBlock => FORMAT_ASTC_10x10_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {block=[u8; 16]},
+    ASTC_10x10SrgbBlock => FORMAT_ASTC_10x10_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {block=[u8; 16]},
+    ASTC_12x10UnormBlock => FORMAT_ASTC_12x10_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {block=[u8; 16]},
+    ASTC_12x10SrgbBlock => FORMAT_ASTC_12x10_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {block=[u8; 16]},
+    ASTC_12x12UnormBlock => FORMAT_ASTC_12x12_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {block=[u8; 16]},
+    ASTC_12x12SrgbBlock => FORMAT_ASTC_12x12_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {block=[u8; 16]},
+}
+
+impl Format {
+    /// Returns the width and height in texels of a block of this format.
+    ///
+    /// Compressed formats store their texels by blocks of multiple texels. The other formats have
+    /// blocks of one texel.
+    pub fn block_dimensions(&self) -> [u32; 2] {
+        match *self {
+            Format::ASTC_4x4UnormBlock | Format::ASTC_4x4SrgbBlock => [4, 4],
+            Format::ASTC_5x4UnormBlock | Format::ASTC_5x4SrgbBlock => [5, 4],
+            Format::ASTC_5x5UnormBlock | Format::ASTC_5x5SrgbBlock => [5, 5],
+            Format::ASTC_6x5UnormBlock | Format::ASTC_6x5SrgbBlock => [6, 5],
+            Format::ASTC_6x6UnormBlock | Format::ASTC_6x6SrgbBlock => [6, 6],
+            Format::ASTC_8x5UnormBlock | Format::ASTC_8x5SrgbBlock => [8, 5],
+            Format::ASTC_8x6UnormBlock | Format::ASTC_8x6SrgbBlock => [8, 6],
+            Format::ASTC_8x8UnormBlock | Format::ASTC_8x8SrgbBlock => [8, 8],
+            Format::ASTC_10x5UnormBlock | Format::ASTC_10x5SrgbBlock => [10, 5],
+            Format::ASTC_10x6UnormBlock | Format::ASTC_10x6SrgbBlock => [10, 6],
+            Format::ASTC_10x8UnormBlock | Format::ASTC_10x8SrgbBlock => [10, 8],
+            Format::ASTC_10x10UnormBlock | Format::ASTC_10x10SrgbBlock => [10, 10],
+            Format::ASTC_12x10UnormBlock | Format::ASTC_12x10SrgbBlock => [12, 10],
+            Format::ASTC_12x12UnormBlock | Format::ASTC_12x12SrgbBlock => [12, 12],
+            // All the BC, ETC2 and EAC formats.
+            _ if self.ty() == FormatTy::Compressed => [4, 4],
+            _ => [1, 1],
+        }
+    }
+
+    /// Returns the size in bytes of a block of this format. This is the same as `size()` for
+    /// uncompressed formats.
+    ///
+    /// Returns `None` for depth-stencil formats, whose memory layout is implementation-defined.
+    pub fn block_size(&self) -> Option<usize> {
+        match *self {
+            Format::BC1_RGBUnormBlock | Format::BC1_RGBSrgbBlock | Format::BC1_RGBAUnormBlock |
+            Format::BC1_RGBASrgbBlock | Format::BC4UnormBlock | Format::BC4SnormBlock |
+            Format::ETC2_R8G8B8UnormBlock | Format::ETC2_R8G8B8SrgbBlock |
+            Format::ETC2_R8G8B8A1UnormBlock | Format::ETC2_R8G8B8A1SrgbBlock |
+            Format::EAC_R11UnormBlock | Format::EAC_R11SnormBlock => Some(8),
+            // All the other compressed formats, including ASTC.
+            _ if self.ty() == FormatTy::Compressed => Some(16),
+            _ => self.size(),
+        }
+    }
+
+    /// Returns the compatibility class of this format.
+    ///
+    /// Formats that belong to the same class have the same memory layout, and images can be
+    /// copied between them.
+    pub fn compatibility_class(&self) -> FormatCompatibilityClass {
+        match *self {
+            Format::BC1_RGBUnormBlock | Format::BC1_RGBSrgbBlock => FormatCompatibilityClass::BC1Rgb,
+            Format::BC1_RGBAUnormBlock | Format::BC1_RGBASrgbBlock => {
+                FormatCompatibilityClass::BC1Rgba
+            },
+            Format::BC2UnormBlock | Format::BC2SrgbBlock => FormatCompatibilityClass::BC2,
+            Format::BC3UnormBlock | Format::BC3SrgbBlock => FormatCompatibilityClass::BC3,
+            Format::BC4UnormBlock | Format::BC4SnormBlock => FormatCompatibilityClass::BC4,
+            Format::BC5UnormBlock | Format::BC5SnormBlock => FormatCompatibilityClass::BC5,
+            Format::BC6HUfloatBlock | Format::BC6HSfloatBlock => FormatCompatibilityClass::BC6H,
+            Format::BC7UnormBlock | Format::BC7SrgbBlock => FormatCompatibilityClass::BC7,
+            Format::ETC2_R8G8B8UnormBlock | Format::ETC2_R8G8B8SrgbBlock => {
+                FormatCompatibilityClass::Etc2Rgb
+            },
+            Format::ETC2_R8G8B8A1UnormBlock | Format::ETC2_R8G8B8A1SrgbBlock => {
+                FormatCompatibilityClass::Etc2Rgba
+            },
+            Format::ETC2_R8G8B8A8UnormBlock | Format::ETC2_R8G8B8A8SrgbBlock => {
+                FormatCompatibilityClass::Etc2EacRgba
+            },
+            Format::EAC_R11UnormBlock | Format::EAC_R11SnormBlock => FormatCompatibilityClass::EacR,
+            Format::EAC_R11G11UnormBlock | Format::EAC_R11G11SnormBlock => {
+                FormatCompatibilityClass::EacRg
+            },
+            _ => match self.ty() {
+                FormatTy::Compressed => FormatCompatibilityClass::Astc(self.block_dimensions()),
+                FormatTy::Depth | FormatTy::Stencil | FormatTy::DepthStencil => {
+                    FormatCompatibilityClass::DepthStencil(*self)
+                },
+                FormatTy::Float | FormatTy::Uint | FormatTy::Sint => {
+                    FormatCompatibilityClass::Bits(self.size().unwrap() as u32 * 8)
+                },
+            },
+        }
+    }
 }
 
 pub unsafe trait FormatDesc {
@@ -744,14 +840,15 @@ macro_rules! impl_pixel {
         unsafe impl AcceptsPixels<$ty> for Format {
             fn ensure_accepts(&self) -> Result<(), IncompatiblePixelsType> {
                 // TODO: Be more strict: accept only if the format has a matching AcceptsPixels impl.
-                if self.size().map_or(false, |x| x % mem::size_of::<$ty>() == 0) {
+                if self.block_size().map_or(false, |x| x % mem::size_of::<$ty>() == 0) {
                     Ok(())
                 } else {
                     Err(IncompatiblePixelsType)
                 }
             }
             fn rate(&self) -> u32 {
-                (self.size().expect("this format cannot accept pixels") / mem::size_of::<$ty>()) as u32
+                (self.block_size().expect("this format cannot accept pixels") /
+                     mem::size_of::<$ty>()) as u32
             }
         }
     }
@@ -789,6 +886,31 @@ impl FormatTy {
     }
 }
 
+/// Compatibility class of a format, as defined in the "Compatible formats" section of the Vulkan
+/// specifications.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FormatCompatibilityClass {
+    /// Uncompressed color formats whose texels have the given size in bits.
+    Bits(u32),
+    BC1Rgb,
+    BC1Rgba,
+    BC2,
+    BC3,
+    BC4,
+    BC5,
+    BC6H,
+    BC7,
+    Etc2Rgb,
+    Etc2Rgba,
+    Etc2EacRgba,
+    EacR,
+    EacRg,
+    /// ASTC formats with the given block dimensions.
+    Astc([u32; 2]),
+    /// Each depth, stencil or depth-stencil format is alone in its class.
+    DepthStencil(Format),
+}
+
 /// Describes a uniform value that will be used to fill an image.
 // TODO: should have the same layout as `vk::ClearValue` for performance
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -956,3 +1078,44 @@ macro_rules! impl_clear_values_tuple {
 }
 
 impl_clear_values_tuple!(A B C D E F G H I J K L M N O P Q R S T U V W X Y Z);
+
+#[cfg(test)]
+mod tests {
+    use format::Format;
+    use format::FormatCompatibilityClass;
+
+    #[test]
+    fn block_dimensions() {
+        assert_eq!(Format::R8G8B8A8Unorm.block_dimensions(), [1, 1]);
+        assert_eq!(Format::BC7UnormBlock.block_dimensions(), [4, 4]);
+        assert_eq!(Format::EAC_R11UnormBlock.block_dimensions(), [4, 4]);
+        assert_eq!(Format::ASTC_10x6SrgbBlock.block_dimensions(), [10, 6]);
+    }
+
+    #[test]
+    fn block_size() {
+        assert_eq!(Format::R8G8B8A8Unorm.block_size(), Some(4));
+        assert_eq!(Format::BC1_RGBAUnormBlock.block_size(), Some(8));
+        assert_eq!(Format::BC7SrgbBlock.block_size(), Some(16));
+        assert_eq!(Format::ETC2_R8G8B8A1UnormBlock.block_size(), Some(8));
+        assert_eq!(Format::ETC2_R8G8B8A8UnormBlock.block_size(), Some(16));
+        assert_eq!(Format::ASTC_12x12UnormBlock.block_size(), Some(16));
+        assert_eq!(Format::D24Unorm_S8Uint.block_size(), None);
+    }
+
+    #[test]
+    fn compatibility_class() {
+        assert_eq!(Format::R8G8B8A8Unorm.compatibility_class(),
+                   Format::R32Uint.compatibility_class());
+        assert_eq!(Format::R32G32B32A32Sfloat.compatibility_class(),
+                   FormatCompatibilityClass::Bits(128));
+        assert_eq!(Format::BC7UnormBlock.compatibility_class(),
+                   Format::BC7SrgbBlock.compatibility_class());
+        assert!(Format::BC7UnormBlock.compatibility_class() !=
+                    Format::BC6HUfloatBlock.compatibility_class());
+        assert_eq!(Format::ASTC_8x8SrgbBlock.compatibility_class(),
+                   FormatCompatibilityClass::Astc([8, 8]));
+        assert!(Format::D16Unorm.compatibility_class() !=
+                    Format::R16Unorm.compatibility_class());
+    }
+}
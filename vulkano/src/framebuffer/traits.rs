@@ -12,6 +12,7 @@ use format::ClearValue;
 use framebuffer::FramebufferSys;
 use framebuffer::RenderPassDesc;
 use framebuffer::RenderPassSys;
+use image::ImageLayout;
 use image::ImageViewAccess;
 use pipeline::shader::ShaderInterfaceDef;
 
@@ -217,6 +218,14 @@ pub unsafe trait RenderPassCompatible<Other: ?Sized>: RenderPassDesc
     /// `Render Pass Compatibility` section of the Vulkan specs.
     // TODO: return proper error
     fn is_compatible_with(&self, other: &Other) -> bool;
+
+    /// Returns `true` if the subpass `subpass` of this layout is compatible with the subpass
+    /// `other_subpass` of the other layout, which means that their color, input, resolve and
+    /// depth-stencil attachment references are compatible.
+    ///
+    /// Returns `false` if one of the subpasses doesn't exist.
+    fn is_subpass_compatible_with(&self, subpass: u32, other: &Other, other_subpass: u32)
+                                  -> bool;
 }
 
 unsafe impl<A: ?Sized, B: ?Sized> RenderPassCompatible<B> for A
@@ -237,12 +246,91 @@ unsafe impl<A: ?Sized, B: ?Sized> RenderPassCompatible<B> for A
             }
         }
 
-        return true;
+        if self.num_subpasses() != other.num_subpasses() {
+            return false;
+        }
+
+        for subpass in 0 .. self.num_subpasses() as u32 {
+            if !self.is_subpass_compatible_with(subpass, other, subpass) {
+                return false;
+            }
+        }
+
+        if self.num_dependencies() != other.num_dependencies() {
+            return false;
+        }
+
+        for dep_num in 0 .. self.num_dependencies() {
+            let my_dep = self.dependency_desc(dep_num).unwrap();
+            let other_dep = other.dependency_desc(dep_num).unwrap();
+
+            if my_dep.source_subpass != other_dep.source_subpass ||
+                my_dep.destination_subpass != other_dep.destination_subpass ||
+                my_dep.source_stages != other_dep.source_stages ||
+                my_dep.destination_stages != other_dep.destination_stages ||
+                my_dep.source_access != other_dep.source_access ||
+                my_dep.destination_access != other_dep.destination_access ||
+                my_dep.by_region != other_dep.by_region
+            {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn is_subpass_compatible_with(&self, subpass: u32, other: &B, other_subpass: u32) -> bool {
+        let my_desc = match self.subpass_desc(subpass as usize) {
+            Some(d) => d,
+            None => return false,
+        };
+
+        let other_desc = match other.subpass_desc(other_subpass as usize) {
+            Some(d) => d,
+            None => return false,
+        };
 
-        // FIXME: finish
+        // The depth-stencil attachment is compared like a list of zero or one reference.
+        let my_depth_stencil = my_desc.depth_stencil.into_iter().collect::<Vec<_>>();
+        let other_depth_stencil = other_desc.depth_stencil.into_iter().collect::<Vec<_>>();
+
+        attachment_refs_compatible(self,
+                                   &my_desc.color_attachments,
+                                   other,
+                                   &other_desc.color_attachments) &&
+            attachment_refs_compatible(self,
+                                       &my_desc.input_attachments,
+                                       other,
+                                       &other_desc.input_attachments) &&
+            attachment_refs_compatible(self,
+                                       &my_desc.resolve_attachments,
+                                       other,
+                                       &other_desc.resolve_attachments) &&
+            attachment_refs_compatible(self, &my_depth_stencil, other, &other_depth_stencil)
     }
 }
 
+// Returns true if two lists of attachment references are compatible. Two references are
+// compatible if the attachments they point to have the same format and number of samples. Since
+// unused attachments aren't supported, a reference is never compatible with a missing one.
+fn attachment_refs_compatible<A: ?Sized, B: ?Sized>(a: &A, a_refs: &[(usize, ImageLayout)],
+                                                    b: &B, b_refs: &[(usize, ImageLayout)])
+                                                    -> bool
+    where A: RenderPassDesc,
+          B: RenderPassDesc
+{
+    if a_refs.len() != b_refs.len() {
+        return false;
+    }
+
+    a_refs.iter().zip(b_refs.iter()).all(|(&(a_num, _), &(b_num, _))| {
+        match (a.attachment_desc(a_num), b.attachment_desc(b_num)) {
+            (Some(a_atch), Some(b_atch)) => a_atch.is_compatible_with(&b_atch),
+            _ => false,
+        }
+    })
+}
+
 /// Represents a subpass within a `RenderPassAbstract` object.
 ///
 /// This struct doesn't correspond to anything in Vulkan. It is simply an equivalent to a
@@ -347,3 +435,145 @@ impl<L> Into<(L, u32)> for Subpass<L> {
         (self.render_pass, self.subpass_id)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::iter;
+
+    use format::ClearValue;
+    use format::Format;
+    use framebuffer::AttachmentDescription;
+    use framebuffer::LoadOp;
+    use framebuffer::PassDependencyDescription;
+    use framebuffer::PassDescription;
+    use framebuffer::RenderPassCompatible;
+    use framebuffer::RenderPassDesc;
+    use framebuffer::RenderPassDescClearValues;
+    use framebuffer::StoreOp;
+    use image::ImageLayout;
+
+    // Render pass description whose attachments and subpasses are stored in lists.
+    struct TestDesc {
+        attachments: Vec<(Format, u32)>,
+        subpasses: Vec<PassDescription>,
+    }
+
+    unsafe impl RenderPassDesc for TestDesc {
+        fn num_attachments(&self) -> usize {
+            self.attachments.len()
+        }
+
+        fn attachment_desc(&self, num: usize) -> Option<AttachmentDescription> {
+            self.attachments.get(num).map(|&(format, samples)| {
+                AttachmentDescription {
+                    format,
+                    samples,
+                    load: LoadOp::Clear,
+                    store: StoreOp::Store,
+                    stencil_load: LoadOp::Clear,
+                    stencil_store: StoreOp::Store,
+                    initial_layout: ImageLayout::Undefined,
+                    final_layout: ImageLayout::ColorAttachmentOptimal,
+                }
+            })
+        }
+
+        fn num_subpasses(&self) -> usize {
+            self.subpasses.len()
+        }
+
+        fn subpass_desc(&self, num: usize) -> Option<PassDescription> {
+            self.subpasses.get(num).cloned()
+        }
+
+        fn num_dependencies(&self) -> usize {
+            0
+        }
+
+        fn dependency_desc(&self, _: usize) -> Option<PassDependencyDescription> {
+            None
+        }
+    }
+
+    unsafe impl RenderPassDescClearValues<Vec<ClearValue>> for TestDesc {
+        fn convert_clear_values(&self, _: Vec<ClearValue>) -> Box<Iterator<Item = ClearValue>> {
+            Box::new(iter::empty())
+        }
+    }
+
+    fn pass(color: Vec<usize>, depth_stencil: Option<usize>) -> PassDescription {
+        PassDescription {
+            color_attachments: color
+                .into_iter()
+                .map(|n| (n, ImageLayout::ColorAttachmentOptimal))
+                .collect(),
+            depth_stencil: depth_stencil.map(|n| (n, ImageLayout::DepthStencilAttachmentOptimal)),
+            input_attachments: vec![],
+            resolve_attachments: vec![],
+            preserve_attachments: vec![],
+        }
+    }
+
+    #[test]
+    fn identical_render_passes() {
+        let a = TestDesc {
+            attachments: vec![(Format::R8G8B8A8Unorm, 1), (Format::D16Unorm, 1)],
+            subpasses: vec![pass(vec![0], Some(1))],
+        };
+        let b = TestDesc {
+            attachments: vec![(Format::R8G8B8A8Unorm, 1), (Format::D16Unorm, 1)],
+            subpasses: vec![pass(vec![0], Some(1))],
+        };
+
+        assert!(RenderPassCompatible::is_compatible_with(&a, &b));
+        assert!(a.is_subpass_compatible_with(0, &b, 0));
+    }
+
+    #[test]
+    fn different_subpass_attachments() {
+        // Same attachments, but the second render pass doesn't use the depth attachment.
+        let a = TestDesc {
+            attachments: vec![(Format::R8G8B8A8Unorm, 1), (Format::D16Unorm, 1)],
+            subpasses: vec![pass(vec![0], Some(1))],
+        };
+        let b = TestDesc {
+            attachments: vec![(Format::R8G8B8A8Unorm, 1), (Format::D16Unorm, 1)],
+            subpasses: vec![pass(vec![0], None)],
+        };
+
+        assert!(!a.is_subpass_compatible_with(0, &b, 0));
+        assert!(!RenderPassCompatible::is_compatible_with(&a, &b));
+    }
+
+    #[test]
+    fn compatible_subpasses_of_different_render_passes() {
+        // The second subpass of `a` uses the same kind of attachment as the only subpass of `b`.
+        let a = TestDesc {
+            attachments: vec![(Format::R8G8B8A8Unorm, 1), (Format::R8G8B8A8Unorm, 1)],
+            subpasses: vec![pass(vec![0], None), pass(vec![1], None)],
+        };
+        let b = TestDesc {
+            attachments: vec![(Format::R8G8B8A8Unorm, 1), (Format::R8G8B8A8Unorm, 1)],
+            subpasses: vec![pass(vec![1], None)],
+        };
+
+        assert!(a.is_subpass_compatible_with(1, &b, 0));
+        assert!(!a.is_subpass_compatible_with(2, &b, 0));
+        assert!(!RenderPassCompatible::is_compatible_with(&a, &b));
+    }
+
+    #[test]
+    fn different_sample_count() {
+        let a = TestDesc {
+            attachments: vec![(Format::R8G8B8A8Unorm, 1)],
+            subpasses: vec![pass(vec![0], None)],
+        };
+        let b = TestDesc {
+            attachments: vec![(Format::R8G8B8A8Unorm, 4)],
+            subpasses: vec![pass(vec![0], None)],
+        };
+
+        assert!(!a.is_subpass_compatible_with(0, &b, 0));
+        assert!(!RenderPassCompatible::is_compatible_with(&a, &b));
+    }
+}
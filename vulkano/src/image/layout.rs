@@ -39,4 +39,7 @@ pub enum ImageLayout {
     TransferDstOptimal = vk::IMAGE_LAYOUT_TRANSFER_DST_OPTIMAL,
     Preinitialized = vk::IMAGE_LAYOUT_PREINITIALIZED,
     PresentSrc = vk::IMAGE_LAYOUT_PRESENT_SRC_KHR,
+    /// Layout of a shared presentable image, which can be presented and used by any operation
+    /// at the same time. Requires the `khr_shared_presentable_image` extension.
+    SharedPresent = vk::IMAGE_LAYOUT_SHARED_PRESENT_KHR,
 }
@@ -731,6 +731,13 @@ impl UnsafeImage {
         (self.format_features & vk::FORMAT_FEATURE_BLIT_DST_BIT) != 0
     }
 
+    /// Returns true if the format of the image can be used for color attachments and as the
+    /// destination of resolve operations.
+    #[inline]
+    pub fn supports_color_attachment(&self) -> bool {
+        (self.format_features & vk::FORMAT_FEATURE_COLOR_ATTACHMENT_BIT) != 0
+    }
+
     /// Returns true if the image can be sampled with a linear filtering.
     #[inline]
     pub fn supports_linear_filtering(&self) -> bool {
@@ -13,8 +13,8 @@
 //! pipelines on the disk.
 //!
 //! You can create either an empty cache or a cache from some initial data. Whenever you create a
-//! graphics or compute pipeline, you have the possibility to pass a reference to that cache.
-//! TODO: ^ that's not the case yet
+//! graphics or compute pipeline, you have the possibility to pass a reference to that cache, with
+//! `GraphicsPipelineBuilder::cache` or the `cache` parameter of `ComputePipeline::new`.
 //! The Vulkan implementation will then look in the cache for an existing entry, or add one if it
 //! doesn't exist.
 //!
@@ -22,18 +22,41 @@
 //! of [`get_data`](struct.PipelineCache.html#method.get_data) for example of how to store the data
 //! on the disk, and [`with_data`](struct.PipelineCache.html#method.with_data) for how to reload it.
 //!
+//! The simplest way to persist a cache is to use
+//! [`save_to_path`](struct.PipelineCache.html#method.save_to_path) and
+//! [`load_from_path`](struct.PipelineCache.html#method.load_from_path). The latter checks that the
+//! data was produced by the same kind of physical device and driver version, and falls back to an
+//! empty cache if it wasn't.
+//!
 
+use std::error;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::io::Write;
 use std::mem;
+use std::path::Path;
+use std::process;
 use std::ptr;
 use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
 
 use device::Device;
+use device::DeviceOwned;
+use instance::PhysicalDevice;
 
 use OomError;
 use VulkanObject;
 use check_errors;
 use vk;
 
+// Distinguishes the temporary files of concurrent calls to `save_to_path` within a process.
+static NEXT_TEMPORARY_ID: AtomicUsize = AtomicUsize::new(0);
+
 /// Opaque cache that contains pipeline objects.
 ///
 /// See [the documentation of the module](index.html) for more info.
@@ -54,7 +77,10 @@ impl PipelineCache {
     ///
     /// This example loads a cache from a file, if it exists.
     /// See [`get_data`](#method.get_data) for how to store the data in a file.
-    /// TODO: there's a header in the cached data that must be checked ; talk about this
+    ///
+    /// The header of the data isn't checked. Use [`with_checked_data`](#method.with_checked_data)
+    /// or [`load_from_path`](#method.load_from_path) to reject data that was produced by another
+    /// physical device or driver version.
     ///
     /// ```
     /// # use std::sync::Arc;
@@ -87,6 +113,93 @@ impl PipelineCache {
         PipelineCache::new_impl(device, Some(initial_data))
     }
 
+    /// Builds a new pipeline cache from existing data, after checking that the header of the data
+    /// matches the physical device of `device`.
+    ///
+    /// If the header doesn't match, for example because the data was produced by another GPU or
+    /// by another version of the driver, an empty cache is returned along with the reason why the
+    /// data was rejected.
+    ///
+    /// Only the header is checked. The rest of the data is assumed to have been obtained with
+    /// [`get_data`](#method.get_data) on a compatible device.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use vulkano::device::Device;
+    /// use vulkano::pipeline::cache::PipelineCache;
+    /// # let device: Arc<Device> = return;
+    /// # let data: Vec<u8> = return;
+    ///
+    /// let (cache, rejected) = PipelineCache::with_checked_data(device.clone(), &data).unwrap();
+    /// if let Some(reason) = rejected {
+    ///     println!("Pipeline cache data rejected: {}", reason);
+    /// }
+    /// ```
+    pub fn with_checked_data(device: Arc<Device>, data: &[u8])
+                             -> Result<(Arc<PipelineCache>, Option<PipelineCacheDataError>),
+                                       OomError> {
+        match PipelineCache::check_data(device.physical_device(), data) {
+            Ok(()) => {
+                let cache = unsafe { PipelineCache::new_impl(device, Some(data))? };
+                Ok((cache, None))
+            },
+            Err(err) => {
+                let cache = PipelineCache::empty(device)?;
+                Ok((cache, Some(err)))
+            },
+        }
+    }
+
+    /// Checks whether the header of some data obtained with [`get_data`](#method.get_data)
+    /// matches `physical_device`.
+    pub fn check_data(physical_device: PhysicalDevice, data: &[u8])
+                      -> Result<(), PipelineCacheDataError> {
+        check_header(data,
+                     physical_device.pci_vendor_id(),
+                     physical_device.pci_device_id(),
+                     physical_device.uuid())
+    }
+
+    /// Loads a pipeline cache from a file that was written by
+    /// [`save_to_path`](#method.save_to_path).
+    ///
+    /// The data is checked in the same way as [`with_checked_data`](#method.with_checked_data).
+    /// If the file can't be read or if its content doesn't match the physical device, an empty
+    /// cache is returned along with the reason. A file that doesn't exist, for example the first
+    /// time the program is run, results in an `Io` error of kind `NotFound`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use vulkano::device::Device;
+    /// use vulkano::pipeline::cache::PipelineCache;
+    /// # let device: Arc<Device> = return;
+    ///
+    /// let (cache, _) = PipelineCache::load_from_path(device.clone(), "pipeline_cache.bin")
+    ///     .unwrap();
+    /// // Create pipelines with the cache here.
+    /// let _ = cache.save_to_path("pipeline_cache.bin");
+    /// ```
+    pub fn load_from_path<P>(device: Arc<Device>, path: P)
+                             -> Result<(Arc<PipelineCache>, Option<PipelineCacheLoadError>),
+                                       OomError>
+        where P: AsRef<Path>
+    {
+        let data = match read_file(path.as_ref()) {
+            Ok(data) => data,
+            Err(err) => {
+                let cache = PipelineCache::empty(device)?;
+                return Ok((cache, Some(PipelineCacheLoadError::Io(err))));
+            },
+        };
+
+        let (cache, rejected) = PipelineCache::with_checked_data(device, &data)?;
+        Ok((cache, rejected.map(PipelineCacheLoadError::InvalidData)))
+    }
+
     /// Builds a new empty pipeline cache.
     ///
     /// # Example
@@ -214,6 +327,40 @@ impl PipelineCache {
             Ok(data)
         }
     }
+
+    /// Writes the data of the cache to a file, so that it can be reloaded with
+    /// [`load_from_path`](#method.load_from_path).
+    ///
+    /// The data is first written to a temporary file next to `path`, which is then renamed. An
+    /// existing file is therefore either entirely replaced or left untouched, even if the program
+    /// stops in the middle of the write.
+    pub fn save_to_path<P>(&self, path: P) -> Result<(), PipelineCacheSaveError>
+        where P: AsRef<Path>
+    {
+        let path = path.as_ref();
+        let data = self.get_data()?;
+
+        // Concurrent saves to the same path, from this process or others, must not share the
+        // temporary file.
+        let mut temporary_path = OsString::from(path.as_os_str());
+        temporary_path.push(format!(".{}-{}.tmp",
+                                    process::id(),
+                                    NEXT_TEMPORARY_ID.fetch_add(1, Ordering::Relaxed)));
+        let temporary_path = Path::new(&temporary_path);
+
+        let result = File::create(temporary_path).and_then(|mut file| {
+            file.write_all(&data)?;
+            file.sync_all()
+        });
+
+        match result.and_then(|()| fs::rename(temporary_path, path)) {
+            Ok(()) => Ok(()),
+            Err(err) => {
+                let _ = fs::remove_file(temporary_path);
+                Err(PipelineCacheSaveError::Io(err))
+            },
+        }
+    }
 }
 
 unsafe impl VulkanObject for PipelineCache {
@@ -227,6 +374,13 @@ unsafe impl VulkanObject for PipelineCache {
     }
 }
 
+unsafe impl DeviceOwned for PipelineCache {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
 impl Drop for PipelineCache {
     #[inline]
     fn drop(&mut self) {
@@ -237,9 +391,260 @@ impl Drop for PipelineCache {
     }
 }
 
+// Size of the header written by `vkGetPipelineCacheData` with
+// `VK_PIPELINE_CACHE_HEADER_VERSION_ONE`.
+const HEADER_SIZE: usize = 32;
+
+// Checks the header of the data of a pipeline cache. All the values of the header are stored with
+// the least significant byte first.
+fn check_header(data: &[u8], vendor_id: u32, device_id: u32, uuid: &[u8; 16])
+                -> Result<(), PipelineCacheDataError> {
+    if data.len() < HEADER_SIZE {
+        return Err(PipelineCacheDataError::HeaderTooShort { len: data.len() });
+    }
+
+    let read_u32 = |offset: usize| {
+        data[offset .. offset + 4]
+            .iter()
+            .rev()
+            .fold(0u32, |value, &byte| (value << 8) | byte as u32)
+    };
+
+    let header_len = read_u32(0);
+    if (header_len as usize) < HEADER_SIZE || header_len as usize > data.len() {
+        return Err(PipelineCacheDataError::InvalidHeaderLength { header_len: header_len });
+    }
+
+    let version = read_u32(4);
+    if version != vk::PIPELINE_CACHE_HEADER_VERSION_ONE {
+        return Err(PipelineCacheDataError::UnsupportedVersion { version: version });
+    }
+
+    let obtained = read_u32(8);
+    if obtained != vendor_id {
+        return Err(PipelineCacheDataError::VendorIdMismatch {
+                       expected: vendor_id,
+                       obtained: obtained,
+                   });
+    }
+
+    let obtained = read_u32(12);
+    if obtained != device_id {
+        return Err(PipelineCacheDataError::DeviceIdMismatch {
+                       expected: device_id,
+                       obtained: obtained,
+                   });
+    }
+
+    if data[16 .. 32] != uuid[..] {
+        let mut obtained = [0; 16];
+        obtained.copy_from_slice(&data[16 .. 32]);
+        return Err(PipelineCacheDataError::UuidMismatch {
+                       expected: *uuid,
+                       obtained: obtained,
+                   });
+    }
+
+    Ok(())
+}
+
+// Reads the whole content of a file.
+fn read_file(path: &Path) -> Result<Vec<u8>, io::Error> {
+    let mut data = Vec::new();
+    File::open(path)?.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// Reason why the data of a pipeline cache was rejected by `PipelineCache::check_data`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PipelineCacheDataError {
+    /// The data is too short to contain a header.
+    HeaderTooShort {
+        /// Length of the data.
+        len: usize,
+    },
+    /// The length written in the header is too small or larger than the data.
+    InvalidHeaderLength {
+        /// The length written in the header.
+        header_len: u32,
+    },
+    /// The version of the header isn't supported.
+    UnsupportedVersion {
+        /// The version written in the header.
+        version: u32,
+    },
+    /// The data was produced by a device from another vendor.
+    VendorIdMismatch {
+        /// Vendor ID of the physical device.
+        expected: u32,
+        /// Vendor ID written in the header.
+        obtained: u32,
+    },
+    /// The data was produced by another kind of device.
+    DeviceIdMismatch {
+        /// Device ID of the physical device.
+        expected: u32,
+        /// Device ID written in the header.
+        obtained: u32,
+    },
+    /// The data was produced by another version of the driver, or by an incompatible device.
+    UuidMismatch {
+        /// Pipeline cache UUID of the physical device.
+        expected: [u8; 16],
+        /// UUID written in the header.
+        obtained: [u8; 16],
+    },
+}
+
+impl error::Error for PipelineCacheDataError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            PipelineCacheDataError::HeaderTooShort { .. } => {
+                "the data is too short to contain a pipeline cache header"
+            },
+            PipelineCacheDataError::InvalidHeaderLength { .. } => {
+                "the length written in the pipeline cache header is invalid"
+            },
+            PipelineCacheDataError::UnsupportedVersion { .. } => {
+                "the version of the pipeline cache header isn't supported"
+            },
+            PipelineCacheDataError::VendorIdMismatch { .. } => {
+                "the pipeline cache data was produced by a device from another vendor"
+            },
+            PipelineCacheDataError::DeviceIdMismatch { .. } => {
+                "the pipeline cache data was produced by another kind of device"
+            },
+            PipelineCacheDataError::UuidMismatch { .. } => {
+                "the pipeline cache data was produced by another driver version or an \
+                 incompatible device"
+            },
+        }
+    }
+}
+
+impl fmt::Display for PipelineCacheDataError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+/// Reason why `PipelineCache::load_from_path` returned an empty cache.
+#[derive(Debug)]
+pub enum PipelineCacheLoadError {
+    /// The file couldn't be read.
+    Io(io::Error),
+    /// The content of the file doesn't match the physical device.
+    InvalidData(PipelineCacheDataError),
+}
+
+impl error::Error for PipelineCacheLoadError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            PipelineCacheLoadError::Io(_) => "the pipeline cache file couldn't be read",
+            PipelineCacheLoadError::InvalidData(_) => {
+                "the content of the pipeline cache file doesn't match the physical device"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            PipelineCacheLoadError::Io(ref err) => Some(err),
+            PipelineCacheLoadError::InvalidData(ref err) => Some(err),
+        }
+    }
+}
+
+impl fmt::Display for PipelineCacheLoadError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<io::Error> for PipelineCacheLoadError {
+    #[inline]
+    fn from(err: io::Error) -> PipelineCacheLoadError {
+        PipelineCacheLoadError::Io(err)
+    }
+}
+
+impl From<PipelineCacheDataError> for PipelineCacheLoadError {
+    #[inline]
+    fn from(err: PipelineCacheDataError) -> PipelineCacheLoadError {
+        PipelineCacheLoadError::InvalidData(err)
+    }
+}
+
+/// Error that can happen when calling `PipelineCache::save_to_path`.
+#[derive(Debug)]
+pub enum PipelineCacheSaveError {
+    /// Not enough memory to retrieve the data of the cache.
+    OomError(OomError),
+    /// The file couldn't be written.
+    Io(io::Error),
+}
+
+impl error::Error for PipelineCacheSaveError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            PipelineCacheSaveError::OomError(_) => "not enough memory available",
+            PipelineCacheSaveError::Io(_) => "the pipeline cache file couldn't be written",
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            PipelineCacheSaveError::OomError(ref err) => Some(err),
+            PipelineCacheSaveError::Io(ref err) => Some(err),
+        }
+    }
+}
+
+impl fmt::Display for PipelineCacheSaveError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<OomError> for PipelineCacheSaveError {
+    #[inline]
+    fn from(err: OomError) -> PipelineCacheSaveError {
+        PipelineCacheSaveError::OomError(err)
+    }
+}
+
+impl From<io::Error> for PipelineCacheSaveError {
+    #[inline]
+    fn from(err: io::Error) -> PipelineCacheSaveError {
+        PipelineCacheSaveError::Io(err)
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use super::check_header;
+    use descriptor::pipeline_layout::EmptyPipelineDesc;
+    use framebuffer::EmptySinglePassRenderPassDesc;
+    use framebuffer::RenderPassDesc;
+    use pipeline::ComputePipeline;
     use pipeline::cache::PipelineCache;
+    use pipeline::cache::PipelineCacheDataError;
+    use pipeline::cache::PipelineCacheLoadError;
+    use pipeline::shader::ShaderModule;
+    use std::env;
+    use std::ffi::CStr;
+    use std::fs;
+    use std::io;
+    use std::process;
+    use std::sync::Arc;
 
     #[test]
     fn merge_self_forbidden() {
@@ -249,4 +654,140 @@ mod tests {
                                  pipeline.merge(&[&pipeline]).unwrap();
                              });
     }
+
+    #[test]
+    fn graphics_pipeline_fills_cache() {
+        let (device, _) = gfx_dev_and_queue!();
+        let cache = PipelineCache::empty(device.clone()).unwrap();
+        let empty_len = cache.get_data().unwrap().len();
+
+        let render_pass = Arc::new(EmptySinglePassRenderPassDesc
+                                       .build_render_pass(device.clone())
+                                       .unwrap());
+        let _pipeline = empty_gfx_pipeline!(device, render_pass,
+                                            |builder| builder.cache(cache.clone())).unwrap();
+
+        assert!(cache.get_data().unwrap().len() > empty_len);
+    }
+
+    #[test]
+    fn compute_pipeline_fills_cache() {
+        let (device, _) = gfx_dev_and_queue!();
+        let cache = PipelineCache::empty(device.clone()).unwrap();
+        let empty_len = cache.get_data().unwrap().len();
+
+        let module = unsafe { ShaderModule::new(device.clone(), &::tests::EMPTY_CS).unwrap() };
+        let shader = unsafe {
+            let name = CStr::from_bytes_with_nul(b"main\0").unwrap();
+            module.compute_entry_point::<(), _>(name, EmptyPipelineDesc)
+        };
+        let _pipeline = ComputePipeline::new(device.clone(), &shader, &(), Some(cache.clone()))
+            .unwrap();
+
+        assert!(cache.get_data().unwrap().len() > empty_len);
+    }
+
+    // Builds a header with the given values, followed by some payload.
+    fn header(len: u32, version: u32, vendor_id: u32, device_id: u32, uuid: [u8; 16]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for &value in &[len, version, vendor_id, device_id] {
+            for shift in 0 .. 4 {
+                data.push((value >> (shift * 8)) as u8);
+            }
+        }
+        data.extend_from_slice(&uuid);
+        data.extend_from_slice(&[0xab; 64]);
+        data
+    }
+
+    #[test]
+    fn header_valid() {
+        let data = header(32, 1, 0x10de, 0x1b80, [7; 16]);
+        assert_eq!(check_header(&data, 0x10de, 0x1b80, &[7; 16]), Ok(()));
+    }
+
+    #[test]
+    fn header_too_short() {
+        let data = header(32, 1, 0x10de, 0x1b80, [7; 16]);
+        assert_eq!(check_header(&data[.. 20], 0x10de, 0x1b80, &[7; 16]),
+                   Err(PipelineCacheDataError::HeaderTooShort { len: 20 }));
+        assert_eq!(check_header(&[], 0x10de, 0x1b80, &[7; 16]),
+                   Err(PipelineCacheDataError::HeaderTooShort { len: 0 }));
+    }
+
+    #[test]
+    fn header_invalid_length() {
+        let data = header(16, 1, 0x10de, 0x1b80, [7; 16]);
+        assert_eq!(check_header(&data, 0x10de, 0x1b80, &[7; 16]),
+                   Err(PipelineCacheDataError::InvalidHeaderLength { header_len: 16 }));
+
+        let data = header(0x10000, 1, 0x10de, 0x1b80, [7; 16]);
+        assert_eq!(check_header(&data, 0x10de, 0x1b80, &[7; 16]),
+                   Err(PipelineCacheDataError::InvalidHeaderLength { header_len: 0x10000 }));
+    }
+
+    #[test]
+    fn header_mismatch() {
+        let data = header(32, 2, 0x10de, 0x1b80, [7; 16]);
+        assert_eq!(check_header(&data, 0x10de, 0x1b80, &[7; 16]),
+                   Err(PipelineCacheDataError::UnsupportedVersion { version: 2 }));
+
+        let data = header(32, 1, 0x1002, 0x1b80, [7; 16]);
+        assert_eq!(check_header(&data, 0x10de, 0x1b80, &[7; 16]),
+                   Err(PipelineCacheDataError::VendorIdMismatch {
+                           expected: 0x10de,
+                           obtained: 0x1002,
+                       }));
+
+        let data = header(32, 1, 0x10de, 0x1c03, [7; 16]);
+        assert_eq!(check_header(&data, 0x10de, 0x1b80, &[7; 16]),
+                   Err(PipelineCacheDataError::DeviceIdMismatch {
+                           expected: 0x1b80,
+                           obtained: 0x1c03,
+                       }));
+
+        let data = header(32, 1, 0x10de, 0x1b80, [8; 16]);
+        assert_eq!(check_header(&data, 0x10de, 0x1b80, &[7; 16]),
+                   Err(PipelineCacheDataError::UuidMismatch {
+                           expected: [7; 16],
+                           obtained: [8; 16],
+                       }));
+    }
+
+    #[test]
+    fn checked_data_fallback() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let (_, rejected) = PipelineCache::with_checked_data(device.clone(), &[1, 2, 3]).unwrap();
+        assert_eq!(rejected, Some(PipelineCacheDataError::HeaderTooShort { len: 3 }));
+
+        let data = PipelineCache::empty(device.clone()).unwrap().get_data().unwrap();
+        let (_, rejected) = PipelineCache::with_checked_data(device.clone(), &data).unwrap();
+        assert_eq!(rejected, None);
+    }
+
+    #[test]
+    fn save_and_load() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let path = env::temp_dir().join(format!("vulkano-pipeline-cache-save-and-load-{}-{}.bin",
+                                                process::id(),
+                                                device.physical_device().index()));
+        let _ = fs::remove_file(&path);
+
+        match PipelineCache::load_from_path(device.clone(), &path) {
+            Ok((_, Some(PipelineCacheLoadError::Io(ref err))))
+                if err.kind() == io::ErrorKind::NotFound => (),
+            _ => panic!(),
+        }
+
+        let cache = PipelineCache::empty(device.clone()).unwrap();
+        cache.save_to_path(&path).unwrap();
+        match PipelineCache::load_from_path(device.clone(), &path) {
+            Ok((_, None)) => (),
+            _ => panic!(),
+        }
+
+        fs::remove_file(&path).unwrap();
+    }
 }
@@ -24,6 +24,7 @@ use descriptor::pipeline_layout::PipelineLayoutDescPcRange;
 use descriptor::pipeline_layout::PipelineLayoutNotSupersetError;
 use descriptor::pipeline_layout::PipelineLayoutSuperset;
 use descriptor::pipeline_layout::PipelineLayoutSys;
+use pipeline::cache::PipelineCache;
 use pipeline::shader::EntryPointAbstract;
 use pipeline::shader::SpecializationConstants;
 
@@ -55,8 +56,17 @@ struct Inner {
 
 impl ComputePipeline<()> {
     /// Builds a new `ComputePipeline`.
+    ///
+    /// If `cache` is `Some`, the Vulkan implementation looks in the cache for an existing entry,
+    /// or adds one if it doesn't exist.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the pipeline cache was not created with `device`.
+    ///
     pub fn new<Cs>(
-        device: Arc<Device>, shader: &Cs, specialization: &Cs::SpecializationConstants)
+        device: Arc<Device>, shader: &Cs, specialization: &Cs::SpecializationConstants,
+        cache: Option<Arc<PipelineCache>>)
         -> Result<ComputePipeline<PipelineLayout<Cs::PipelineLayout>>, ComputePipelineCreationError>
         where Cs::PipelineLayout: Clone,
               Cs: EntryPointAbstract
@@ -66,7 +76,8 @@ impl ComputePipeline<()> {
             ComputePipeline::with_unchecked_pipeline_layout(device,
                                                             shader,
                                                             specialization,
-                                                            pipeline_layout)
+                                                            pipeline_layout,
+                                                            cache)
         }
     }
 }
@@ -76,9 +87,14 @@ impl<Pl> ComputePipeline<Pl> {
     ///
     /// An error will be returned if the pipeline layout isn't a superset of what the shader
     /// uses.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the pipeline cache was not created with `device`.
+    ///
     pub fn with_pipeline_layout<Cs>(device: Arc<Device>, shader: &Cs,
                                     specialization: &Cs::SpecializationConstants,
-                                    pipeline_layout: Pl)
+                                    pipeline_layout: Pl, cache: Option<Arc<PipelineCache>>)
                                     -> Result<ComputePipeline<Pl>, ComputePipelineCreationError>
         where Cs::PipelineLayout: Clone,
               Cs: EntryPointAbstract,
@@ -89,7 +105,8 @@ impl<Pl> ComputePipeline<Pl> {
             ComputePipeline::with_unchecked_pipeline_layout(device,
                                                             shader,
                                                             specialization,
-                                                            pipeline_layout)
+                                                            pipeline_layout,
+                                                            cache)
         }
     }
 
@@ -97,7 +114,7 @@ impl<Pl> ComputePipeline<Pl> {
     /// superset of what the shader expects.
     pub unsafe fn with_unchecked_pipeline_layout<Cs>(
         device: Arc<Device>, shader: &Cs, specialization: &Cs::SpecializationConstants,
-        pipeline_layout: Pl)
+        pipeline_layout: Pl, cache: Option<Arc<PipelineCache>>)
         -> Result<ComputePipeline<Pl>, ComputePipelineCreationError>
         where Cs::PipelineLayout: Clone,
               Cs: EntryPointAbstract,
@@ -105,6 +122,14 @@ impl<Pl> ComputePipeline<Pl> {
     {
         let vk = device.pointers();
 
+        let cache_handle = match cache {
+            Some(ref cache) => {
+                assert_eq!(cache.device().internal_object(), device.internal_object());
+                cache.internal_object()
+            },
+            None => 0,
+        };
+
         let pipeline = {
             let spec_descriptors = Cs::SpecializationConstants::descriptors();
             let specialization = vk::SpecializationInfo {
@@ -140,7 +165,7 @@ impl<Pl> ComputePipeline<Pl> {
 
             let mut output = mem::uninitialized();
             check_errors(vk.CreateComputePipelines(device.internal_object(),
-                                                   0,
+                                                   cache_handle,
                                                    1,
                                                    &infos,
                                                    ptr::null(),
@@ -964,7 +989,8 @@ mod tests {
 
         let pipeline = Arc::new(ComputePipeline::new(device.clone(),
                                                      &shader,
-                                                     &SpecConsts { VALUE: 0x12345678 })
+                                                     &SpecConsts { VALUE: 0x12345678 },
+                                                     None)
                                     .unwrap());
 
         let data_buffer = CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(), 0)
@@ -158,6 +158,22 @@ impl Default for Stencil {
     }
 }
 
+/// Faces that a dynamic stencil state applies to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u32)]
+pub enum StencilFaceFlags {
+    StencilFaceFrontBit = vk::STENCIL_FACE_FRONT_BIT,
+    StencilFaceBackBit = vk::STENCIL_FACE_BACK_BIT,
+    StencilFrontAndBack = vk::STENCIL_FRONT_AND_BACK,
+}
+
+impl Into<vk::StencilFaceFlags> for StencilFaceFlags {
+    #[inline]
+    fn into(self) -> vk::StencilFaceFlags {
+        self as vk::StencilFaceFlags
+    }
+}
+
 /// Operation to perform after the depth and stencil tests.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(u32)]
@@ -13,18 +13,21 @@
 
 use smallvec::SmallVec;
 use std::mem;
+use std::ops::Range;
 use std::ptr;
 use std::sync::Arc;
 use std::u32;
 
 use descriptor::pipeline_layout::PipelineLayoutAbstract;
 use device::Device;
+use device::DeviceOwned;
 use framebuffer::RenderPassAbstract;
 use framebuffer::Subpass;
 use pipeline::blend::AttachmentBlend;
 use pipeline::blend::AttachmentsBlend;
 use pipeline::blend::Blend;
 use pipeline::blend::LogicOp;
+use pipeline::cache::PipelineCache;
 use pipeline::depth_stencil::Compare;
 use pipeline::depth_stencil::DepthBounds;
 use pipeline::depth_stencil::DepthStencil;
@@ -33,6 +36,7 @@ use pipeline::graphics_pipeline::GraphicsPipelineCreationError;
 use pipeline::graphics_pipeline::Inner as GraphicsPipelineInner;
 use pipeline::input_assembly::PrimitiveTopology;
 use pipeline::raster::CullMode;
+use pipeline::raster::DepthBias;
 use pipeline::raster::DepthBiasControl;
 use pipeline::raster::FrontFace;
 use pipeline::raster::PolygonMode;
@@ -75,6 +79,7 @@ pub struct GraphicsPipelineBuilder<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss,
     depth_stencil: DepthStencil,
     blend: Blend,
     render_pass: Option<Subpass<Rp>>,
+    cache: Option<Arc<PipelineCache>>,
 }
 
 // Additional parameters if tessellation is used.
@@ -121,6 +126,7 @@ impl
                 depth_stencil: DepthStencil::disabled(),
                 blend: Blend::pass_through(),
                 render_pass: None,
+                cache: None,
             }
         }
     }
@@ -305,6 +311,12 @@ impl<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp>
     /// Does the same as `build`, except that `build` automatically builds the pipeline layout
     /// object corresponding to the union of your shaders while this function allows you to specify
     /// the pipeline layout.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if a pipeline cache was passed to `cache` and was not created with
+    ///   `device`.
+    ///
     pub fn with_pipeline_layout<Pl>(mut self, device: Arc<Device>, pipeline_layout: Pl)
                                     -> Result<GraphicsPipeline<Vdef, Pl, Rp>,
                                               GraphicsPipelineCreationError>
@@ -314,6 +326,10 @@ impl<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp>
 
         let vk = device.pointers();
 
+        if let Some(ref cache) = self.cache {
+            assert_eq!(cache.device().internal_object(), device.internal_object());
+        }
+
         // Checking that the pipeline layout matches the shader stages.
         // TODO: more details in the errors
         PipelineLayoutSuperset::ensure_superset_of(&pipeline_layout,
@@ -1072,9 +1088,14 @@ impl<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp>
                 basePipelineIndex: -1, // TODO:
             };
 
+            let cache_handle = match self.cache {
+                Some(ref cache) => cache.internal_object(),
+                None => 0,
+            };
+
             let mut output = mem::uninitialized();
             check_errors(vk.CreateGraphicsPipelines(device.internal_object(),
-                                                    0,
+                                                    cache_handle,
                                                     1,
                                                     &infos,
                                                     ptr::null(),
@@ -1107,10 +1128,12 @@ impl<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp>
                dynamic_blend_constants: self.blend.blend_constants.is_none(),
 
                num_viewports: self.viewport.as_ref().unwrap().num_viewports(),
+               primitive_restart: self.input_assembly.primitiveRestartEnable != vk::FALSE,
+               per_vertex_input: binding_descriptions
+                   .iter()
+                   .any(|b| b.inputRate == vk::VERTEX_INPUT_RATE_VERTEX),
            })
     }
-
-    // TODO: add build_with_cache method
 }
 
 impl<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp>
@@ -1136,6 +1159,7 @@ impl<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp>
             depth_stencil: self.depth_stencil,
             blend: self.blend,
             render_pass: self.render_pass,
+            cache: self.cache,
         }
     }
 
@@ -1183,6 +1207,7 @@ impl<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp>
             depth_stencil: self.depth_stencil,
             blend: self.blend,
             render_pass: self.render_pass,
+            cache: self.cache,
         }
     }
 
@@ -1340,6 +1365,7 @@ impl<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp>
             depth_stencil: self.depth_stencil,
             blend: self.blend,
             render_pass: self.render_pass,
+            cache: self.cache,
         }
     }
 
@@ -1373,6 +1399,7 @@ impl<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp>
             depth_stencil: self.depth_stencil,
             blend: self.blend,
             render_pass: self.render_pass,
+            cache: self.cache,
         }
     }
 
@@ -1547,7 +1574,33 @@ impl<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp>
         self
     }
 
-    // TODO: missing DepthBiasControl
+    /// Disables the depth bias. This is the default.
+    #[inline]
+    pub fn depth_bias_disabled(mut self) -> Self {
+        self.raster.depth_bias = DepthBiasControl::Disabled;
+        self
+    }
+
+    /// Enables the depth bias with fixed values.
+    ///
+    /// A `clamp` value different from `0.0` requires the `depth_bias_clamp` feature to be enabled.
+    #[inline]
+    pub fn depth_bias(mut self, constant_factor: f32, clamp: f32, slope_factor: f32) -> Self {
+        self.raster.depth_bias = DepthBiasControl::Static(DepthBias {
+                                                              constant_factor,
+                                                              clamp,
+                                                              slope_factor,
+                                                          });
+        self
+    }
+
+    /// Enables the depth bias and sets its values as dynamic, which means that you will need to
+    /// set them when drawing.
+    #[inline]
+    pub fn depth_bias_dynamic(mut self) -> Self {
+        self.raster.depth_bias = DepthBiasControl::Dynamic;
+        self
+    }
 
     /// Disables sample shading. The fragment shader will only be run once per fragment (ie. per
     /// pixel) and not once by sample. The output will then be copied in all of the covered
@@ -1644,6 +1697,7 @@ impl<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp>
             depth_stencil: self.depth_stencil,
             blend: self.blend,
             render_pass: self.render_pass,
+            cache: self.cache,
         }
     }
 
@@ -1681,8 +1735,61 @@ impl<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp>
         self
     }
 
-    // TODO: missing tons of depth-stencil stuff
+    /// Disables the depth bounds test. This is the default.
+    #[inline]
+    pub fn depth_bounds_disabled(mut self) -> Self {
+        self.depth_stencil.depth_bounds_test = DepthBounds::Disabled;
+        self
+    }
 
+    /// Enables the depth bounds test with a fixed range.
+    ///
+    /// Requires the `depth_bounds` feature to be enabled.
+    #[inline]
+    pub fn depth_bounds(mut self, range: Range<f32>) -> Self {
+        self.depth_stencil.depth_bounds_test = DepthBounds::Fixed(range);
+        self
+    }
+
+    /// Enables the depth bounds test and sets its range as dynamic. The range will need to be
+    /// set before drawing.
+    ///
+    /// Requires the `depth_bounds` feature to be enabled.
+    #[inline]
+    pub fn depth_bounds_dynamic(mut self) -> Self {
+        self.depth_stencil.depth_bounds_test = DepthBounds::Dynamic;
+        self
+    }
+
+    /// Sets the stencil compare mask of both the front and back faces as dynamic. Its value will
+    /// need to be set before drawing.
+    #[inline]
+    pub fn stencil_compare_mask_dynamic(mut self) -> Self {
+        self.depth_stencil.stencil_front.compare_mask = None;
+        self.depth_stencil.stencil_back.compare_mask = None;
+        self
+    }
+
+    /// Sets the stencil write mask of both the front and back faces as dynamic. Its value will
+    /// need to be set before drawing.
+    #[inline]
+    pub fn stencil_write_mask_dynamic(mut self) -> Self {
+        self.depth_stencil.stencil_front.write_mask = None;
+        self.depth_stencil.stencil_back.write_mask = None;
+        self
+    }
+
+    /// Sets the stencil reference of both the front and back faces as dynamic. Its value will
+    /// need to be set before drawing.
+    ///
+    /// This lets you change the reference value between draw calls without building another
+    /// pipeline.
+    #[inline]
+    pub fn stencil_reference_dynamic(mut self) -> Self {
+        self.depth_stencil.stencil_front.reference = None;
+        self.depth_stencil.stencil_back.reference = None;
+        self
+    }
 
     #[inline]
     pub fn blend_collective(mut self, blend: AttachmentBlend) -> Self {
@@ -1760,8 +1867,22 @@ impl<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp>
             depth_stencil: self.depth_stencil,
             blend: self.blend,
             render_pass: Some(subpass),
+            cache: self.cache,
         }
     }
+
+    /// Enable caching of this pipeline via a `PipelineCache` object.
+    ///
+    /// If this pipeline already exists in the cache it will be used. If this pipeline doesn't
+    /// exist in the cache, it will be created and then added to the cache.
+    ///
+    /// The cache must have been created with the same device as the one passed when building
+    /// the pipeline.
+    #[inline]
+    pub fn cache(mut self, pipeline_cache: Arc<PipelineCache>) -> Self {
+        self.cache = Some(pipeline_cache);
+        self
+    }
 }
 
 impl<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp> Clone
@@ -1804,6 +1925,7 @@ impl<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp> Clone
             depth_stencil: self.depth_stencil.clone(),
             blend: self.blend.clone(),
             render_pass: self.render_pass.clone(),
+            cache: self.cache.clone(),
         }
     }
 }
@@ -72,6 +72,8 @@ pub struct GraphicsPipeline<VertexDefinition, Layout, RenderP> {
     dynamic_blend_constants: bool,
 
     num_viewports: u32,
+    primitive_restart: bool,
+    per_vertex_input: bool,
 }
 
 struct Inner {
@@ -165,6 +167,12 @@ impl<Mv, L, Rp> GraphicsPipeline<Mv, L, Rp> {
         self.dynamic_scissor
     }
 
+    /// Returns true if the depth bias used by this pipeline is dynamic.
+    #[inline]
+    pub fn has_dynamic_depth_bias(&self) -> bool {
+        self.dynamic_depth_bias
+    }
+
     /// Returns true if the depth bounds used by this pipeline are dynamic.
     #[inline]
     pub fn has_dynamic_depth_bounds(&self) -> bool {
@@ -188,6 +196,24 @@ impl<Mv, L, Rp> GraphicsPipeline<Mv, L, Rp> {
     pub fn has_dynamic_stencil_reference(&self) -> bool {
         self.dynamic_stencil_reference
     }
+
+    /// Returns true if the blend constants used by this pipeline are dynamic.
+    #[inline]
+    pub fn has_dynamic_blend_constants(&self) -> bool {
+        self.dynamic_blend_constants
+    }
+
+    /// Returns true if primitive restart is enabled for this pipeline.
+    #[inline]
+    pub fn has_primitive_restart(&self) -> bool {
+        self.primitive_restart
+    }
+
+    /// Returns true if at least one vertex buffer of this pipeline has a per-vertex input rate.
+    #[inline]
+    pub fn has_per_vertex_input(&self) -> bool {
+        self.per_vertex_input
+    }
 }
 
 unsafe impl<Mv, L, Rp> PipelineLayoutAbstract for GraphicsPipeline<Mv, L, Rp>
@@ -350,6 +376,9 @@ pub unsafe trait GraphicsPipelineAbstract: PipelineLayoutAbstract + RenderPassAb
     /// Returns true if the scissors used by this pipeline are dynamic.
     fn has_dynamic_scissors(&self) -> bool;
 
+    /// Returns true if the depth bias used by this pipeline is dynamic.
+    fn has_dynamic_depth_bias(&self) -> bool;
+
     /// Returns true if the depth bounds used by this pipeline are dynamic.
     fn has_dynamic_depth_bounds(&self) -> bool;
 
@@ -361,6 +390,15 @@ pub unsafe trait GraphicsPipelineAbstract: PipelineLayoutAbstract + RenderPassAb
 
     /// Returns true if the stencil references used by this pipeline are dynamic.
     fn has_dynamic_stencil_reference(&self) -> bool;
+
+    /// Returns true if the blend constants used by this pipeline are dynamic.
+    fn has_dynamic_blend_constants(&self) -> bool;
+
+    /// Returns true if primitive restart is enabled for this pipeline.
+    fn has_primitive_restart(&self) -> bool;
+
+    /// Returns true if at least one vertex buffer of this pipeline has a per-vertex input rate.
+    fn has_per_vertex_input(&self) -> bool;
 }
 
 unsafe impl<Mv, L, Rp> GraphicsPipelineAbstract for GraphicsPipeline<Mv, L, Rp>
@@ -398,6 +436,11 @@ unsafe impl<Mv, L, Rp> GraphicsPipelineAbstract for GraphicsPipeline<Mv, L, Rp>
         self.dynamic_scissor
     }
 
+    #[inline]
+    fn has_dynamic_depth_bias(&self) -> bool {
+        self.dynamic_depth_bias
+    }
+
     #[inline]
     fn has_dynamic_depth_bounds(&self) -> bool {
         self.dynamic_depth_bounds
@@ -417,6 +460,21 @@ unsafe impl<Mv, L, Rp> GraphicsPipelineAbstract for GraphicsPipeline<Mv, L, Rp>
     fn has_dynamic_stencil_reference(&self) -> bool {
         self.dynamic_stencil_reference
     }
+
+    #[inline]
+    fn has_dynamic_blend_constants(&self) -> bool {
+        self.dynamic_blend_constants
+    }
+
+    #[inline]
+    fn has_primitive_restart(&self) -> bool {
+        self.primitive_restart
+    }
+
+    #[inline]
+    fn has_per_vertex_input(&self) -> bool {
+        self.per_vertex_input
+    }
 }
 
 unsafe impl<T> GraphicsPipelineAbstract for T
@@ -453,6 +511,11 @@ unsafe impl<T> GraphicsPipelineAbstract for T
         (**self).has_dynamic_scissors()
     }
 
+    #[inline]
+    fn has_dynamic_depth_bias(&self) -> bool {
+        (**self).has_dynamic_depth_bias()
+    }
+
     #[inline]
     fn has_dynamic_depth_bounds(&self) -> bool {
         (**self).has_dynamic_depth_bounds()
@@ -472,6 +535,21 @@ unsafe impl<T> GraphicsPipelineAbstract for T
     fn has_dynamic_stencil_reference(&self) -> bool {
         (**self).has_dynamic_stencil_reference()
     }
+
+    #[inline]
+    fn has_dynamic_blend_constants(&self) -> bool {
+        (**self).has_dynamic_blend_constants()
+    }
+
+    #[inline]
+    fn has_primitive_restart(&self) -> bool {
+        (**self).has_primitive_restart()
+    }
+
+    #[inline]
+    fn has_per_vertex_input(&self) -> bool {
+        (**self).has_per_vertex_input()
+    }
 }
 
 /// Opaque object that represents the inside of the graphics pipeline.
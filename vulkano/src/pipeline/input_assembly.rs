@@ -12,6 +12,9 @@
 //! The input assembly is the stage where lists of vertices are turned into primitives.
 //!
 
+use std::cmp;
+
+use SafeDeref;
 use vk;
 
 /// How the input assembly stage should behave.
@@ -101,6 +104,9 @@ impl PrimitiveTopology {
 pub unsafe trait Index {
     /// Returns the type of data.
     fn ty() -> IndexType;
+
+    /// Returns the value of the index.
+    fn value(&self) -> u32;
 }
 
 unsafe impl Index for u16 {
@@ -108,6 +114,11 @@ unsafe impl Index for u16 {
     fn ty() -> IndexType {
         IndexType::U16
     }
+
+    #[inline(always)]
+    fn value(&self) -> u32 {
+        *self as u32
+    }
 }
 
 unsafe impl Index for u32 {
@@ -115,6 +126,11 @@ unsafe impl Index for u32 {
     fn ty() -> IndexType {
         IndexType::U32
     }
+
+    #[inline(always)]
+    fn value(&self) -> u32 {
+        *self
+    }
 }
 
 /// An enumeration of all valid index types.
@@ -125,3 +141,88 @@ pub enum IndexType {
     U16 = vk::INDEX_TYPE_UINT16,
     U32 = vk::INDEX_TYPE_UINT32,
 }
+
+impl IndexType {
+    /// Returns the special index value that restarts the primitive when primitive restart is
+    /// enabled.
+    #[inline]
+    pub fn restart_value(&self) -> u32 {
+        match *self {
+            IndexType::U16 => 0xffff,
+            IndexType::U32 => 0xffffffff,
+        }
+    }
+}
+
+/// Largest values of the indices of an index buffer whose content is known by the CPU.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct IndexBounds {
+    /// The largest index, or `None` if there is no index.
+    pub max: Option<u32>,
+    /// The largest index that isn't the special value that restarts the primitive, or `None` if
+    /// there is no such index.
+    pub max_without_restart: Option<u32>,
+}
+
+impl IndexBounds {
+    /// Computes the bounds of a list of indices.
+    #[inline]
+    pub fn from_indices<'a, I, It>(indices: It) -> IndexBounds
+        where I: Index + 'a,
+              It: IntoIterator<Item = &'a I>
+    {
+        let mut bounds = IndexBounds::default();
+        for index in indices {
+            bounds.add(index);
+        }
+        bounds
+    }
+
+    /// Updates the bounds with an additional index.
+    #[inline]
+    pub fn add<I>(&mut self, index: &I)
+        where I: Index
+    {
+        let value = index.value();
+        self.max = Some(self.max.map_or(value, |max| cmp::max(max, value)));
+        if value != I::ty().restart_value() {
+            self.max_without_restart = Some(self.max_without_restart
+                                                .map_or(value, |max| cmp::max(max, value)));
+        }
+    }
+
+    /// Returns the largest index that the GPU will use to fetch a vertex, depending on whether
+    /// primitive restart is enabled.
+    #[inline]
+    pub fn max_vertex_index(&self, primitive_restart: bool) -> Option<u32> {
+        if primitive_restart {
+            self.max_without_restart
+        } else {
+            self.max
+        }
+    }
+}
+
+/// Trait for index buffers whose content is known by the CPU.
+///
+/// This allows `AutoCommandBufferBuilder::draw_indexed_checked` to verify that the indices don't
+/// go past the end of the vertex buffers.
+pub unsafe trait KnownIndices {
+    /// Returns the bounds of the indices of the buffer, or `None` if the content can't be
+    /// determined right now.
+    ///
+    /// The returned value must match the content of the whole buffer at the time of the call.
+    /// Buffers that the CPU can write to, like `CpuAccessibleBuffer`, may be modified afterwards,
+    /// and those modifications aren't covered by the returned value.
+    fn index_bounds(&self) -> Option<IndexBounds>;
+}
+
+unsafe impl<T> KnownIndices for T
+    where T: SafeDeref,
+          T::Target: KnownIndices
+{
+    #[inline]
+    fn index_bounds(&self) -> Option<IndexBounds> {
+        (**self).index_bounds()
+    }
+}
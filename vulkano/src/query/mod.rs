@@ -21,9 +21,12 @@ use std::sync::Arc;
 
 use device::Device;
 use device::DeviceOwned;
+use instance::QueueFamily;
 
 use Error;
 use OomError;
+use SafeDeref;
+use Success;
 use VulkanObject;
 use check_errors;
 use vk;
@@ -31,6 +34,7 @@ use vk;
 pub struct UnsafeQueryPool {
     pool: vk::QueryPool,
     device: Arc<Device>,
+    ty: QueryType,
     num_slots: u32,
 }
 
@@ -72,10 +76,17 @@ impl UnsafeQueryPool {
         Ok(UnsafeQueryPool {
                pool: pool,
                device: device,
+               ty: ty,
                num_slots: num_slots,
            })
     }
 
+    /// Returns the type of the queries of that query pool.
+    #[inline]
+    pub fn ty(&self) -> QueryType {
+        self.ty
+    }
+
     /// Returns the number of slots of that query pool.
     #[inline]
     pub fn num_slots(&self) -> u32 {
@@ -99,7 +110,7 @@ impl UnsafeQueryPool {
     pub fn queries_range(&self, first_index: u32, count: u32) -> Option<UnsafeQueriesRange> {
         assert!(count >= 1);
 
-        if first_index + count < self.num_slots() {
+        if first_index.checked_add(count).map(|end| end <= self.num_slots()).unwrap_or(false) {
             Some(UnsafeQueriesRange {
                      pool: self,
                      first: first_index,
@@ -109,6 +120,60 @@ impl UnsafeQueryPool {
             None
         }
     }
+
+    /// Copies the results of a range of queries to `destination`.
+    ///
+    /// The results are tightly packed in `destination`, and are 64 bits values if `T` is `u64` or
+    /// 32 bits values if `T` is `u32`. Each query writes `ty().result_len()` values, plus one more
+    /// if `flags.with_availability` is true.
+    ///
+    /// Returns `true` if the results of all the queries were available, and `false` otherwise. If
+    /// `flags.wait` is true, then the results are always available.
+    pub fn get_results<T>(&self, first_query: u32, query_count: u32, destination: &mut [T],
+                          flags: QueryResultFlags)
+                          -> Result<bool, GetResultsError>
+        where T: QueryResultElement
+    {
+        if query_count == 0 || self.queries_range(first_query, query_count).is_none() {
+            return Err(GetResultsError::QueriesOutOfRange);
+        }
+
+        if let QueryType::Timestamp = self.ty {
+            if flags.partial {
+                return Err(GetResultsError::NotPermittedForQueryType);
+            }
+        }
+
+        let values_per_query = self.ty.result_len() + if flags.with_availability { 1 } else { 0 };
+        let required_len = values_per_query * query_count as usize;
+
+        if destination.len() < required_len {
+            return Err(GetResultsError::BufferTooSmall {
+                           required_len,
+                           actual_len: destination.len(),
+                       });
+        }
+
+        let stride = values_per_query * mem::size_of::<T>();
+
+        let result = unsafe {
+            let vk = self.device.pointers();
+            check_errors(vk.GetQueryPoolResults(self.device.internal_object(),
+                                                self.pool,
+                                                first_query,
+                                                query_count,
+                                                required_len * mem::size_of::<T>(),
+                                                destination.as_mut_ptr() as *mut _,
+                                                stride as vk::DeviceSize,
+                                                flags.into_vulkan_bits() | T::FLAG))?
+        };
+
+        match result {
+            Success::Success => Ok(true),
+            Success::NotReady => Ok(false),
+            s => panic!("unexpected success value: {:?}", s),
+        }
+    }
 }
 
 unsafe impl VulkanObject for UnsafeQueryPool {
@@ -129,6 +194,29 @@ unsafe impl DeviceOwned for UnsafeQueryPool {
     }
 }
 
+/// Trait for objects that contain a Vulkan query pool.
+pub unsafe trait QueryPoolAbstract: DeviceOwned {
+    /// Returns the inner unsafe query pool.
+    fn inner(&self) -> &UnsafeQueryPool;
+}
+
+unsafe impl QueryPoolAbstract for UnsafeQueryPool {
+    #[inline]
+    fn inner(&self) -> &UnsafeQueryPool {
+        self
+    }
+}
+
+unsafe impl<T> QueryPoolAbstract for T
+    where T: SafeDeref,
+          T::Target: QueryPoolAbstract
+{
+    #[inline]
+    fn inner(&self) -> &UnsafeQueryPool {
+        (**self).inner()
+    }
+}
+
 pub struct UnsafeQuery<'a> {
     pool: &'a UnsafeQueryPool,
     index: u32,
@@ -169,14 +257,37 @@ impl<'a> UnsafeQueriesRange<'a> {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum QueryType {
     Occlusion,
     PipelineStatistics(QueryPipelineStatisticFlags),
     Timestamp,
 }
 
-#[derive(Debug, Copy, Clone)]
+impl QueryType {
+    /// Returns the number of values that a single query of this type writes as its result,
+    /// not counting the availability value.
+    #[inline]
+    pub fn result_len(&self) -> usize {
+        match *self {
+            QueryType::Occlusion | QueryType::Timestamp => 1,
+            QueryType::PipelineStatistics(flags) => flags.count(),
+        }
+    }
+
+    /// Returns true if `other` is of the same kind of query, regardless of the statistics flags.
+    #[inline]
+    pub fn same_kind(&self, other: &QueryType) -> bool {
+        match (*self, *other) {
+            (QueryType::Occlusion, QueryType::Occlusion) => true,
+            (QueryType::PipelineStatistics(_), QueryType::PipelineStatistics(_)) => true,
+            (QueryType::Timestamp, QueryType::Timestamp) => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct QueryPipelineStatisticFlags {
     pub input_assembly_vertices: bool,
     pub input_assembly_primitives: bool,
@@ -208,6 +319,13 @@ impl QueryPipelineStatisticFlags {
             compute_shader_invocations: false,
         }
     }
+
+    /// Returns the number of statistics that are enabled.
+    #[inline]
+    pub fn count(&self) -> usize {
+        let vk: vk::QueryPipelineStatisticFlags = (*self).into();
+        vk.count_ones() as usize
+    }
 }
 
 impl Into<vk::QueryPipelineStatisticFlags> for QueryPipelineStatisticFlags {
@@ -250,6 +368,50 @@ impl Into<vk::QueryPipelineStatisticFlags> for QueryPipelineStatisticFlags {
     }
 }
 
+/// Flags that control how the results of queries are retrieved.
+///
+/// Whether the results are 32 or 64 bits is determined by the type of the destination.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct QueryResultFlags {
+    /// Wait for the results to become available before writing them.
+    pub wait: bool,
+    /// Write an additional value after each result indicating whether it is available.
+    pub with_availability: bool,
+    /// Allow writing partial results when the query isn't available yet.
+    pub partial: bool,
+}
+
+impl QueryResultFlags {
+    #[inline]
+    pub(crate) fn into_vulkan_bits(self) -> vk::QueryResultFlags {
+        let mut result = 0;
+        if self.wait {
+            result |= vk::QUERY_RESULT_WAIT_BIT;
+        }
+        if self.with_availability {
+            result |= vk::QUERY_RESULT_WITH_AVAILABILITY_BIT;
+        }
+        if self.partial {
+            result |= vk::QUERY_RESULT_PARTIAL_BIT;
+        }
+        result
+    }
+}
+
+/// Type that query results can be written as. Implemented on `u32` and `u64`.
+pub unsafe trait QueryResultElement: Copy + Send + Sync + 'static {
+    /// Additional flag to pass to Vulkan for this element type.
+    const FLAG: vk::QueryResultFlags;
+}
+
+unsafe impl QueryResultElement for u32 {
+    const FLAG: vk::QueryResultFlags = 0;
+}
+
+unsafe impl QueryResultElement for u64 {
+    const FLAG: vk::QueryResultFlags = vk::QUERY_RESULT_64_BIT;
+}
+
 impl Drop for UnsafeQueryPool {
     #[inline]
     fn drop(&mut self) {
@@ -315,14 +477,91 @@ impl From<Error> for QueryPoolCreationError {
     }
 }
 
-pub struct OcclusionQueriesPool {
+/// Error that can happen when retrieving the results of queries.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GetResultsError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The device has been lost.
+    DeviceLost,
+    /// The range of queries is empty or out of range of the query pool.
+    QueriesOutOfRange,
+    /// Partial results can't be requested for timestamp queries.
+    NotPermittedForQueryType,
+    /// The destination is too small to hold the results.
+    BufferTooSmall {
+        /// Required number of elements in the destination.
+        required_len: usize,
+        /// Actual number of elements in the destination.
+        actual_len: usize,
+    },
+}
+
+impl error::Error for GetResultsError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            GetResultsError::OomError(_) => "not enough memory available",
+            GetResultsError::DeviceLost => "the device was lost",
+            GetResultsError::QueriesOutOfRange => {
+                "the range of queries is empty or out of range of the query pool"
+            },
+            GetResultsError::NotPermittedForQueryType => {
+                "partial results can't be requested for timestamp queries"
+            },
+            GetResultsError::BufferTooSmall { .. } => {
+                "the destination is too small to hold the results"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            GetResultsError::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for GetResultsError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<OomError> for GetResultsError {
+    #[inline]
+    fn from(err: OomError) -> GetResultsError {
+        GetResultsError::OomError(err)
+    }
+}
+
+impl From<Error> for GetResultsError {
+    #[inline]
+    fn from(err: Error) -> GetResultsError {
+        match err {
+            err @ Error::OutOfHostMemory => GetResultsError::OomError(OomError::from(err)),
+            err @ Error::OutOfDeviceMemory => GetResultsError::OomError(OomError::from(err)),
+            Error::DeviceLost => GetResultsError::DeviceLost,
+            _ => panic!("unexpected error: {:?}", err),
+        }
+    }
+}
+
+/// A pool of occlusion queries.
+///
+/// Each query counts the number of samples that passed the depth and stencil tests between
+/// `begin_query` and `end_query`.
+pub struct OcclusionQueryPool {
     inner: UnsafeQueryPool,
 }
 
-impl OcclusionQueriesPool {
+impl OcclusionQueryPool {
     /// See the docs of new().
-    pub fn raw(device: Arc<Device>, num_slots: u32) -> Result<OcclusionQueriesPool, OomError> {
-        Ok(OcclusionQueriesPool {
+    pub fn raw(device: Arc<Device>, num_slots: u32) -> Result<OcclusionQueryPool, OomError> {
+        Ok(OcclusionQueryPool {
                inner: match UnsafeQueryPool::new(device, QueryType::Occlusion, num_slots) {
                    Ok(q) => q,
                    Err(QueryPoolCreationError::OomError(err)) => return Err(err),
@@ -340,8 +579,186 @@ impl OcclusionQueriesPool {
     /// - Panics if the device or host ran out of memory.
     ///
     #[inline]
-    pub fn new(device: Arc<Device>, num_slots: u32) -> Arc<OcclusionQueriesPool> {
-        Arc::new(OcclusionQueriesPool::raw(device, num_slots).unwrap())
+    pub fn new(device: Arc<Device>, num_slots: u32) -> Arc<OcclusionQueryPool> {
+        Arc::new(OcclusionQueryPool::raw(device, num_slots).unwrap())
+    }
+
+    /// Returns the number of slots of that query pool.
+    #[inline]
+    pub fn num_slots(&self) -> u32 {
+        self.inner.num_slots()
+    }
+
+    /// Copies the number of samples of a range of queries to `destination`.
+    ///
+    /// See the documentation of `UnsafeQueryPool::get_results`.
+    #[inline]
+    pub fn get_results<T>(&self, first_query: u32, query_count: u32, destination: &mut [T],
+                          flags: QueryResultFlags)
+                          -> Result<bool, GetResultsError>
+        where T: QueryResultElement
+    {
+        self.inner.get_results(first_query, query_count, destination, flags)
+    }
+}
+
+unsafe impl DeviceOwned for OcclusionQueryPool {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.inner.device()
+    }
+}
+
+unsafe impl QueryPoolAbstract for OcclusionQueryPool {
+    #[inline]
+    fn inner(&self) -> &UnsafeQueryPool {
+        &self.inner
+    }
+}
+
+/// Old name of `OcclusionQueryPool`.
+#[deprecated(note = "use OcclusionQueryPool instead")]
+pub type OcclusionQueriesPool = OcclusionQueryPool;
+
+/// A pool of timestamp queries.
+///
+/// Each query receives the value of a device counter that is incremented every
+/// `timestamp_period` nanoseconds, when the command written with `write_timestamp` reaches the
+/// given pipeline stage.
+pub struct TimestampQueryPool {
+    inner: UnsafeQueryPool,
+}
+
+impl TimestampQueryPool {
+    /// See the docs of new().
+    pub fn raw(device: Arc<Device>, num_slots: u32) -> Result<TimestampQueryPool, OomError> {
+        Ok(TimestampQueryPool {
+               inner: match UnsafeQueryPool::new(device, QueryType::Timestamp, num_slots) {
+                   Ok(q) => q,
+                   Err(QueryPoolCreationError::OomError(err)) => return Err(err),
+                   Err(QueryPoolCreationError::PipelineStatisticsQueryFeatureNotEnabled) => {
+                       unreachable!()
+                   },
+               },
+           })
+    }
+
+    /// Builds a new query pool.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the device or host ran out of memory.
+    ///
+    #[inline]
+    pub fn new(device: Arc<Device>, num_slots: u32) -> Arc<TimestampQueryPool> {
+        Arc::new(TimestampQueryPool::raw(device, num_slots).unwrap())
+    }
+
+    /// Returns the number of slots of that query pool.
+    #[inline]
+    pub fn num_slots(&self) -> u32 {
+        self.inner.num_slots()
+    }
+
+    /// Copies the raw timestamps of a range of queries to `destination`.
+    ///
+    /// See the documentation of `UnsafeQueryPool::get_results`. Use `to_nanoseconds` or
+    /// `elapsed_nanoseconds` to convert the values.
+    #[inline]
+    pub fn get_results<T>(&self, first_query: u32, query_count: u32, destination: &mut [T],
+                          flags: QueryResultFlags)
+                          -> Result<bool, GetResultsError>
+        where T: QueryResultElement
+    {
+        self.inner.get_results(first_query, query_count, destination, flags)
+    }
+
+    /// Converts a raw timestamp written by a queue of `queue_family` to nanoseconds.
+    ///
+    /// Only the bits that are valid for the queue family are taken into account. Returns `None`
+    /// if the queue family doesn't support timestamps.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the queue family doesn't belong to the same physical device as the pool.
+    ///
+    #[inline]
+    pub fn to_nanoseconds(&self, queue_family: QueueFamily, timestamp: u64) -> Option<f64> {
+        let mask = self.timestamp_mask(queue_family)?;
+        Some((timestamp & mask) as f64 * self.timestamp_period())
+    }
+
+    /// Returns the number of nanoseconds between two raw timestamps written by a queue of
+    /// `queue_family`, taking into account the fact that the counter may have wrapped around.
+    ///
+    /// Returns `None` if the queue family doesn't support timestamps.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the queue family doesn't belong to the same physical device as the pool.
+    ///
+    #[inline]
+    pub fn elapsed_nanoseconds(&self, queue_family: QueueFamily, start: u64, end: u64)
+                               -> Option<f64> {
+        let mask = self.timestamp_mask(queue_family)?;
+        let ticks = (end & mask).wrapping_sub(start & mask) & mask;
+        Some(ticks as f64 * self.timestamp_period())
+    }
+
+    // Returns the mask of the valid bits of the timestamps of a queue family.
+    fn timestamp_mask(&self, queue_family: QueueFamily) -> Option<u64> {
+        assert_eq!(queue_family.physical_device().internal_object(),
+                   self.device().physical_device().internal_object());
+
+        let bits = queue_family.timestamp_valid_bits()?;
+        if bits >= 64 {
+            Some(!0)
+        } else {
+            Some((1u64 << bits) - 1)
+        }
+    }
+
+    // Returns the number of nanoseconds per timestamp increment.
+    #[inline]
+    fn timestamp_period(&self) -> f64 {
+        self.device().physical_device().limits().timestamp_period() as f64
+    }
+}
+
+unsafe impl DeviceOwned for TimestampQueryPool {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.inner.device()
+    }
+}
+
+unsafe impl QueryPoolAbstract for TimestampQueryPool {
+    #[inline]
+    fn inner(&self) -> &UnsafeQueryPool {
+        &self.inner
+    }
+}
+
+/// A pool of pipeline statistics queries.
+///
+/// Each query counts the statistics enabled in its flags between `begin_query` and `end_query`.
+/// The values are written in the same order as the fields of `QueryPipelineStatisticFlags`,
+/// skipping the disabled ones.
+pub struct PipelineStatisticsQueryPool {
+    inner: UnsafeQueryPool,
+}
+
+impl PipelineStatisticsQueryPool {
+    /// Builds a new query pool.
+    ///
+    /// The `pipeline_statistics_query` feature must be enabled on the device.
+    #[inline]
+    pub fn new(device: Arc<Device>, flags: QueryPipelineStatisticFlags, num_slots: u32)
+               -> Result<Arc<PipelineStatisticsQueryPool>, QueryPoolCreationError> {
+        let ty = QueryType::PipelineStatistics(flags);
+        Ok(Arc::new(PipelineStatisticsQueryPool {
+                        inner: UnsafeQueryPool::new(device, ty, num_slots)?,
+                    }))
     }
 
     /// Returns the number of slots of that query pool.
@@ -349,27 +766,119 @@ impl OcclusionQueriesPool {
     pub fn num_slots(&self) -> u32 {
         self.inner.num_slots()
     }
+
+    /// Returns the statistics that are counted by the queries of that pool.
+    #[inline]
+    pub fn flags(&self) -> QueryPipelineStatisticFlags {
+        match self.inner.ty() {
+            QueryType::PipelineStatistics(flags) => flags,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Copies the statistics of a range of queries to `destination`.
+    ///
+    /// See the documentation of `UnsafeQueryPool::get_results`.
+    #[inline]
+    pub fn get_results<T>(&self, first_query: u32, query_count: u32, destination: &mut [T],
+                          flags: QueryResultFlags)
+                          -> Result<bool, GetResultsError>
+        where T: QueryResultElement
+    {
+        self.inner.get_results(first_query, query_count, destination, flags)
+    }
 }
 
-unsafe impl DeviceOwned for OcclusionQueriesPool {
+unsafe impl DeviceOwned for PipelineStatisticsQueryPool {
     #[inline]
     fn device(&self) -> &Arc<Device> {
         self.inner.device()
     }
 }
 
+unsafe impl QueryPoolAbstract for PipelineStatisticsQueryPool {
+    #[inline]
+    fn inner(&self) -> &UnsafeQueryPool {
+        &self.inner
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use query::OcclusionQueriesPool;
+    use query::GetResultsError;
+    use query::OcclusionQueryPool;
+    use query::PipelineStatisticsQueryPool;
     use query::QueryPipelineStatisticFlags;
     use query::QueryPoolCreationError;
+    use query::QueryResultFlags;
     use query::QueryType;
+    use query::TimestampQueryPool;
     use query::UnsafeQueryPool;
 
     #[test]
     fn occlusion_create() {
         let (device, _) = gfx_dev_and_queue!();
-        let _ = OcclusionQueriesPool::new(device, 256);
+        let _ = OcclusionQueryPool::new(device, 256);
+    }
+
+    #[test]
+    fn pipeline_statistics_pool_feature() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        match PipelineStatisticsQueryPool::new(device, QueryPipelineStatisticFlags::none(), 256) {
+            Err(QueryPoolCreationError::PipelineStatisticsQueryFeatureNotEnabled) => (),
+            _ => panic!(),
+        };
+    }
+
+    #[test]
+    fn get_results_too_small() {
+        let (device, _) = gfx_dev_and_queue!();
+        let pool = OcclusionQueryPool::new(device, 4);
+
+        let flags = QueryResultFlags {
+            with_availability: true,
+            ..QueryResultFlags::default()
+        };
+        let mut results = [0u64; 7];
+
+        match pool.get_results(0, 4, &mut results, flags) {
+            Err(GetResultsError::BufferTooSmall { required_len: 8, actual_len: 7 }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn timestamp_partial_forbidden() {
+        let (device, _) = gfx_dev_and_queue!();
+        let pool = TimestampQueryPool::new(device, 4);
+
+        let flags = QueryResultFlags {
+            partial: true,
+            ..QueryResultFlags::default()
+        };
+        let mut results = [0u32; 4];
+
+        match pool.get_results(0, 4, &mut results, flags) {
+            Err(GetResultsError::NotPermittedForQueryType) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn timestamp_elapsed_wraps() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let pool = TimestampQueryPool::new(device, 1);
+
+        let bits = match queue.family().timestamp_valid_bits() {
+            Some(bits) if bits < 64 => bits,
+            _ => return,
+        };
+
+        let max = (1u64 << bits) - 1;
+        let wrapped = pool.elapsed_nanoseconds(queue.family(), max, 1).unwrap();
+        let direct = pool.elapsed_nanoseconds(queue.family(), 0, 2).unwrap();
+        assert_eq!(wrapped, direct);
     }
 
     #[test]
@@ -382,4 +891,14 @@ mod tests {
             _ => panic!(),
         };
     }
+
+    #[test]
+    fn queries_range() {
+        let (device, _) = gfx_dev_and_queue!();
+        let pool = UnsafeQueryPool::new(device, QueryType::Occlusion, 8).unwrap();
+
+        assert!(pool.queries_range(0, 8).is_some());
+        assert!(pool.queries_range(7, 1).is_some());
+        assert!(pool.queries_range(7, 2).is_none());
+    }
 }
@@ -47,6 +47,7 @@ use sync::AccessFlagBits;
 use sync::Fence;
 use sync::FlushError;
 use sync::GpuFuture;
+use sync::OwnershipTransfer;
 use sync::PipelineStages;
 use sync::Semaphore;
 use sync::SharingMode;
@@ -1086,6 +1087,11 @@ unsafe impl<P, W> GpuFuture for PresentFuture<P, W>
                 .check_image_access(image, layout, exclusive, queue)
         }
     }
+
+    #[inline]
+    fn with_ownership_releases(&self, f: &mut FnMut(&[OwnershipTransfer])) {
+        self.previous.with_ownership_releases(f)
+    }
 }
 
 unsafe impl<P, W> DeviceOwned for PresentFuture<P, W>
@@ -26,6 +26,7 @@ use sync::AccessFlagBits;
 use sync::Fence;
 use sync::FlushError;
 use sync::GpuFuture;
+use sync::OwnershipTransfer;
 use sync::PipelineStages;
 
 /// Builds a new fence signal future.
@@ -410,6 +411,17 @@ unsafe impl<F> GpuFuture for FenceSignalFuture<F>
             Err(AccessCheckError::Unknown)
         }
     }
+
+    #[inline]
+    fn with_ownership_releases(&self, f: &mut FnMut(&[OwnershipTransfer])) {
+        // Once the previous future has been cleaned up, we no longer know which transfers it
+        // released. The user then has to acquire them manually.
+        let state = self.state.lock().unwrap();
+        match state.get_prev() {
+            Some(previous) => previous.with_ownership_releases(f),
+            None => f(&[]),
+        }
+    }
 }
 
 unsafe impl<F> DeviceOwned for FenceSignalFuture<F>
@@ -502,4 +514,9 @@ unsafe impl<F> GpuFuture for Arc<FenceSignalFuture<F>>
                           -> Result<Option<(PipelineStages, AccessFlagBits)>, AccessCheckError> {
         (**self).check_image_access(image, layout, exclusive, queue)
     }
+
+    #[inline]
+    fn with_ownership_releases(&self, f: &mut FnMut(&[OwnershipTransfer])) {
+        (**self).with_ownership_releases(f)
+    }
 }
@@ -20,6 +20,7 @@ use sync::AccessCheckError;
 use sync::AccessFlagBits;
 use sync::FlushError;
 use sync::GpuFuture;
+use sync::OwnershipTransfer;
 use sync::PipelineStages;
 
 use VulkanObject;
@@ -242,4 +243,15 @@ unsafe impl<A, B> GpuFuture for JoinFuture<A, B>
             },
         }
     }
+
+    #[inline]
+    fn with_ownership_releases(&self, f: &mut FnMut(&[OwnershipTransfer])) {
+        let second = &self.second;
+        self.first.with_ownership_releases(&mut |first| {
+            second.with_ownership_releases(&mut |second| {
+                let releases = first.iter().chain(second.iter()).cloned().collect::<Vec<_>>();
+                f(&releases)
+            })
+        })
+    }
 }
@@ -136,6 +136,20 @@ pub unsafe trait GpuFuture: DeviceOwned {
                           queue: &Queue)
                           -> Result<Option<(PipelineStages, AccessFlagBits)>, AccessCheckError>;
 
+    /// Calls `f` with the queue family ownership transfers that have been released by the
+    /// submissions of this future and that haven't been acquired yet.
+    ///
+    /// When a command buffer is executed after this future on a queue of the destination family
+    /// of one of these transfers, a matching acquire operation is automatically submitted before
+    /// it.
+    ///
+    /// The transfers are passed to a closure instead of being returned because they borrow the
+    /// resources of the submissions, which some futures can only access while they are locked.
+    #[inline]
+    fn with_ownership_releases(&self, f: &mut FnMut(&[OwnershipTransfer])) {
+        f(&[])
+    }
+
     /// Joins this future with another one, representing the moment when both events have happened.
     // TODO: handle errors
     fn join<F>(self, other: F) -> JoinFuture<Self, F>
@@ -303,6 +317,55 @@ unsafe impl<F: ?Sized> GpuFuture for Box<F>
                           -> Result<Option<(PipelineStages, AccessFlagBits)>, AccessCheckError> {
         (**self).check_image_access(image, layout, exclusive, queue)
     }
+
+    #[inline]
+    fn with_ownership_releases(&self, f: &mut FnMut(&[OwnershipTransfer])) {
+        (**self).with_ownership_releases(f)
+    }
+}
+
+/// Transfer of the ownership of a resource from a queue family to another.
+///
+/// A transfer is made of a release operation submitted to a queue of the source family, followed
+/// with an acquire operation submitted to a queue of the destination family.
+#[derive(Copy, Clone)]
+pub struct OwnershipTransfer<'a> {
+    /// The resource whose ownership is transferred.
+    pub resource: OwnershipTransferResource<'a>,
+    /// Id of the queue family that releases the resource.
+    pub source_family: u32,
+    /// Id of the queue family that acquires the resource.
+    pub destination_family: u32,
+}
+
+/// Resource whose ownership is transferred by an `OwnershipTransfer`.
+#[derive(Copy, Clone)]
+pub enum OwnershipTransferResource<'a> {
+    /// A buffer.
+    Buffer(&'a BufferAccess),
+    /// An image, with the layout it is in during the transfer.
+    Image(&'a ImageAccess, ImageLayout),
+}
+
+impl<'a> OwnershipTransferResource<'a> {
+    /// Returns true if the two resources overlap.
+    #[inline]
+    pub fn conflicts(&self, other: &OwnershipTransferResource) -> bool {
+        match (*self, *other) {
+            (OwnershipTransferResource::Buffer(a), OwnershipTransferResource::Buffer(b)) => {
+                a.conflicts_buffer(b)
+            },
+            (OwnershipTransferResource::Buffer(a), OwnershipTransferResource::Image(b, _)) => {
+                a.conflicts_image(b)
+            },
+            (OwnershipTransferResource::Image(a, _), OwnershipTransferResource::Buffer(b)) => {
+                a.conflicts_buffer(b)
+            },
+            (OwnershipTransferResource::Image(a, _), OwnershipTransferResource::Image(b, _)) => {
+                a.conflicts_image(b)
+            },
+        }
+    }
 }
 
 /// Access to a resource was denied.
@@ -331,6 +394,12 @@ pub enum AccessError {
 
     /// Trying to use a swapchain image without depending on a corresponding acquire image future.
     SwapchainImageAcquireOnly,
+
+    /// The ownership of the resource has been released to another queue family.
+    OwnershipReleased {
+        /// Id of the queue family the resource has been released to.
+        destination_family: u32,
+    },
 }
 
 impl error::Error for AccessError {
@@ -357,6 +426,9 @@ impl error::Error for AccessError {
                 "trying to use a swapchain image without depending on a corresponding acquire \
                  image future"
             },
+            AccessError::OwnershipReleased { .. } => {
+                "the ownership of the resource has been released to another queue family"
+            },
         }
     }
 }
@@ -25,6 +25,7 @@ use sync::AccessCheckError;
 use sync::AccessFlagBits;
 use sync::FlushError;
 use sync::GpuFuture;
+use sync::OwnershipTransfer;
 use sync::PipelineStages;
 use sync::Semaphore;
 
@@ -158,6 +159,11 @@ unsafe impl<F> GpuFuture for SemaphoreSignalFuture<F>
             .check_image_access(image, layout, exclusive, queue)
             .map(|_| None)
     }
+
+    #[inline]
+    fn with_ownership_releases(&self, f: &mut FnMut(&[OwnershipTransfer])) {
+        self.previous.with_ownership_releases(f)
+    }
 }
 
 unsafe impl<F> DeviceOwned for SemaphoreSignalFuture<F>
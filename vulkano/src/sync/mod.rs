@@ -116,6 +116,8 @@ pub use self::future::FlushError;
 pub use self::future::GpuFuture;
 pub use self::future::JoinFuture;
 pub use self::future::NowFuture;
+pub use self::future::OwnershipTransfer;
+pub use self::future::OwnershipTransferResource;
 pub use self::future::SemaphoreSignalFuture;
 pub use self::future::now;
 pub use self::pipeline::AccessFlagBits;
@@ -30,6 +30,15 @@ macro_rules! pipeline_stages {
                 }
             }
 
+            // Returns the names of the stages that are set, for display purposes.
+            pub(crate) fn names(&self) -> Vec<&'static str> {
+                let mut names = Vec::new();
+                $(
+                    if self.$elem { names.push(stringify!($elem)) }
+                )+
+                names
+            }
+
             #[inline]
             pub(crate) fn into_vulkan_bits(self) -> vk::PipelineStageFlagBits {
                 let mut result = 0;
@@ -86,7 +95,7 @@ pipeline_stages!{
 
 macro_rules! access_flags {
     ($($elem:ident => $val:expr,)+) => (
-        #[derive(Debug, Copy, Clone)]
+        #[derive(Debug, Copy, Clone, PartialEq, Eq)]
         #[allow(missing_docs)]
         pub struct AccessFlagBits {
             $(
@@ -113,6 +122,15 @@ macro_rules! access_flags {
                 }
             }
 
+            // Returns the names of the accesses that are set, for display purposes.
+            pub(crate) fn names(&self) -> Vec<&'static str> {
+                let mut names = Vec::new();
+                $(
+                    if self.$elem { names.push(stringify!($elem)) }
+                )+
+                names
+            }
+
             #[inline]
             pub(crate) fn into_vulkan_bits(self) -> vk::AccessFlagBits {
                 let mut result = 0;
@@ -223,4 +241,19 @@ impl AccessFlagBits {
 
         true
     }
+
+    // Returns the write accesses of `self`. Only writes need to be made available by a memory
+    // barrier, as reads don't modify the memory.
+    #[inline]
+    pub(crate) fn writes(&self) -> AccessFlagBits {
+        AccessFlagBits {
+            shader_write: self.shader_write,
+            color_attachment_write: self.color_attachment_write,
+            depth_stencil_attachment_write: self.depth_stencil_attachment_write,
+            transfer_write: self.transfer_write,
+            host_write: self.host_write,
+            memory_write: self.memory_write,
+            ..AccessFlagBits::none()
+        }
+    }
 }
@@ -66,6 +66,47 @@ macro_rules! gfx_dev_and_queue {
     });
 }
 
+/// Creates a device with a queue in a family that supports graphics operations, and a queue in
+/// another family. Returns if the physical device only has one queue family.
+macro_rules! gfx_dev_and_two_queues {
+    () => ({
+        use instance;
+        use device::Device;
+        use device::DeviceExtensions;
+        use features::Features;
+
+        let instance = instance!();
+
+        let physical = match instance::PhysicalDevice::enumerate(&instance).next() {
+            Some(p) => p,
+            None => return
+        };
+
+        let gfx_family = match physical.queue_families().find(|q| q.supports_graphics()) {
+            Some(q) => q,
+            None => return
+        };
+
+        let other_family = match physical.queue_families().find(|q| q.id() != gfx_family.id()) {
+            Some(q) => q,
+            None => return
+        };
+
+        let (device, mut queues) = match Device::new(physical, &Features::none(),
+                                                     &DeviceExtensions::none(),
+                                                     [(gfx_family, 0.5), (other_family, 0.5)]
+                                                         .iter().cloned())
+        {
+            Ok(r) => r,
+            Err(_) => return
+        };
+
+        let gfx_queue = queues.next().unwrap();
+        let other_queue = queues.next().unwrap();
+        (device, gfx_queue, other_queue)
+    });
+}
+
 macro_rules! assert_should_panic {
     ($msg:expr, $code:block) => ({
         let res = ::std::panic::catch_unwind(|| {
@@ -97,3 +138,78 @@ macro_rules! assert_should_panic {
         }
     });
 }
+
+/// Builds a graphics pipeline that runs empty shaders on the first subpass of `$render_pass`.
+///
+/// The optional closure-like argument receives the builder and can change its fixed-function
+/// state before the pipeline is built. Evaluates to the result of `build`.
+macro_rules! empty_gfx_pipeline {
+    ($device:expr, $render_pass:expr) => (
+        empty_gfx_pipeline!($device, $render_pass, |builder| builder)
+    );
+
+    ($device:expr, $render_pass:expr, |$builder:ident| $customize:expr) => ({
+        use std::ffi::CStr;
+        use descriptor::pipeline_layout::EmptyPipelineDesc;
+        use framebuffer::Subpass;
+        use pipeline::GraphicsPipeline;
+        use pipeline::shader::EmptyShaderInterfaceDef;
+        use pipeline::shader::GraphicsShaderType;
+        use pipeline::shader::ShaderModule;
+        use pipeline::viewport::Viewport;
+
+        let vs_module = unsafe { ShaderModule::new($device.clone(), &::tests::EMPTY_VS).unwrap() };
+        let fs_module = unsafe { ShaderModule::new($device.clone(), &::tests::EMPTY_FS).unwrap() };
+        let name = CStr::from_bytes_with_nul(b"main\0").unwrap();
+        let vs = unsafe {
+            vs_module.graphics_entry_point::<(), _, _, _>(name, EmptyShaderInterfaceDef,
+                                                          EmptyShaderInterfaceDef,
+                                                          EmptyPipelineDesc,
+                                                          GraphicsShaderType::Vertex)
+        };
+        let fs = unsafe {
+            fs_module.graphics_entry_point::<(), _, _, _>(name, EmptyShaderInterfaceDef,
+                                                          EmptyShaderInterfaceDef,
+                                                          EmptyPipelineDesc,
+                                                          GraphicsShaderType::Fragment)
+        };
+
+        let $builder = GraphicsPipeline::start()
+            .vertex_shader(vs, ())
+            .viewports(Some(Viewport {
+                               origin: [0.0, 0.0],
+                               dimensions: [64.0, 64.0],
+                               depth_range: 0.0 .. 1.0,
+                           }))
+            .fragment_shader(fs, ())
+            .render_pass(Subpass::from($render_pass, 0).unwrap());
+
+        $customize.build($device.clone())
+    });
+}
+
+/// SPIR-V of a vertex shader whose `main` entry point does nothing.
+pub static EMPTY_VS: [u8; 116] = [
+    3, 2, 35, 7, 0, 0, 1, 0, 0, 0, 0, 0, 5, 0, 0, 0, 0, 0, 0, 0, 17, 0, 2, 0, 1, 0, 0, 0, 14, 0,
+    3, 0, 0, 0, 0, 0, 1, 0, 0, 0, 15, 0, 5, 0, 0, 0, 0, 0, 1, 0, 0, 0, 109, 97, 105, 110, 0, 0,
+    0, 0, 19, 0, 2, 0, 2, 0, 0, 0, 33, 0, 3, 0, 3, 0, 0, 0, 2, 0, 0, 0, 54, 0, 5, 0, 2, 0, 0, 0,
+    1, 0, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0, 248, 0, 2, 0, 4, 0, 0, 0, 253, 0, 1, 0, 56, 0, 1, 0,
+];
+
+/// SPIR-V of a fragment shader whose `main` entry point does nothing.
+pub static EMPTY_FS: [u8; 128] = [
+    3, 2, 35, 7, 0, 0, 1, 0, 0, 0, 0, 0, 5, 0, 0, 0, 0, 0, 0, 0, 17, 0, 2, 0, 1, 0, 0, 0, 14, 0,
+    3, 0, 0, 0, 0, 0, 1, 0, 0, 0, 15, 0, 5, 0, 4, 0, 0, 0, 1, 0, 0, 0, 109, 97, 105, 110, 0, 0,
+    0, 0, 16, 0, 3, 0, 1, 0, 0, 0, 7, 0, 0, 0, 19, 0, 2, 0, 2, 0, 0, 0, 33, 0, 3, 0, 3, 0, 0, 0,
+    2, 0, 0, 0, 54, 0, 5, 0, 2, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0, 248, 0, 2, 0, 4, 0,
+    0, 0, 253, 0, 1, 0, 56, 0, 1, 0,
+];
+
+/// SPIR-V of a compute shader whose `main` entry point does nothing.
+pub static EMPTY_CS: [u8; 140] = [
+    3, 2, 35, 7, 0, 0, 1, 0, 0, 0, 0, 0, 5, 0, 0, 0, 0, 0, 0, 0, 17, 0, 2, 0, 1, 0, 0, 0, 14, 0, 3,
+    0, 0, 0, 0, 0, 1, 0, 0, 0, 15, 0, 5, 0, 5, 0, 0, 0, 1, 0, 0, 0, 109, 97, 105, 110, 0, 0, 0, 0,
+    16, 0, 6, 0, 1, 0, 0, 0, 17, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 19, 0, 2, 0, 2, 0, 0,
+    0, 33, 0, 3, 0, 3, 0, 0, 0, 2, 0, 0, 0, 54, 0, 5, 0, 2, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 3, 0,
+    0, 0, 248, 0, 2, 0, 4, 0, 0, 0, 253, 0, 1, 0, 56, 0, 1, 0,
+];